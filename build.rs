@@ -1,3 +1,94 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Directory holding the web dashboard sources
+const WEB_DIR: &str = "web";
+
 fn main() {
-    embuild::espidf::sysenv::output();
-} 
\ No newline at end of file
+    // Host builds only compile the library for `cargo test`
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("espidf") {
+        embuild::espidf::sysenv::output();
+    }
+    // Hex Ed25519 public key trusted for OTA images, see `tools/ota-sign`
+    println!("cargo:rerun-if-env-changed=OTA_PUBLIC_KEY");
+    embed_web_assets();
+}
+
+/// Gzip every file under `web/` and generate `web_assets.rs` listing them
+fn embed_web_assets() {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let web_dir = manifest_dir.join(WEB_DIR);
+    println!("cargo:rerun-if-changed={}", web_dir.display());
+
+    let mut files = Vec::new();
+    if web_dir.is_dir() {
+        collect_files(&web_dir, &mut files);
+    }
+    files.sort();
+
+    let mut generated = String::from("pub static WEB_ASSETS: &[WebAsset] = &[\n");
+    for file in files {
+        println!("cargo:rerun-if-changed={}", file.display());
+
+        let relative = file.strip_prefix(&web_dir).unwrap();
+        let url_path = format!("/{}", relative.to_string_lossy().replace('\\', "/"));
+        let content = std::fs::read(&file).unwrap();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&content).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let gz_path = out_dir.join(format!("web{}.gz", url_path.replace('/', "_")));
+        std::fs::write(&gz_path, &gzipped).unwrap();
+
+        writeln!(
+            generated,
+            "    WebAsset {{ path: {:?}, content_type: {:?}, etag: \"\\\"{:016x}\\\"\", body: include_bytes!({:?}) }},",
+            url_path,
+            content_type(&file),
+            fnv1a(&content),
+            gz_path.display().to_string(),
+        )
+        .unwrap();
+    }
+    generated.push_str("];\n");
+
+    std::fs::write(out_dir.join("web_assets.rs"), generated).unwrap();
+}
+
+/// Recursively list the files in a directory
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// Guess the MIME type from a file extension
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "application/javascript",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 64-bit FNV-1a hash used as the asset ETag
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
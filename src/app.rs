@@ -0,0 +1,1116 @@
+use esp_idf_hal::delay::FreeRtos;
+use esp_idf_hal::gpio::{AnyIOPin, AnyOutputPin};
+use esp_idf_hal::peripherals::Peripherals;
+use esp_idf_hal::sys::link_patches;
+use esp_idf_svc::eventloop::EspSystemEventLoop;
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
+use esp_idf_svc::sys::EspError;
+use log::{debug, info, warn, error};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex};
+
+use esp32_template::{error, peripherals, tasks, utils};
+
+use peripherals::led::{LedController, LED_COUNT};
+use peripherals::button::{ButtonController, ButtonEvent};
+use tasks::console::{format_crashes, parse_arg, register_config_command, register_log_command, register_logs_command};
+use tasks::dashboard::register_dashboard_routes;
+use tasks::http_api::{register_device_routes, DeviceApi, DeviceStatus, WifiInfo};
+use tasks::ha_discovery::{
+    button_event_payload, led_state_payload, parse_led_state, DeviceInfo, DiscoveryRegistry,
+    LedKind,
+};
+use tasks::mqtt_commands::{Command, CommandDispatcher};
+use tasks::mqtt_task::QoS;
+use tasks::ota::SelfTestConfig;
+use tasks::ota_task::{load_public_key, spawn_self_test, store_public_key, validate_boot};
+use tasks::provisioning_task::{request_provisioning, take_provisioning_request};
+use tasks::sntp_task::log_sync;
+use tasks::web_assets::WEB_ASSETS;
+use tasks::wifi_scan::{filter_visible, sort_by_rssi};
+use tasks::device_config::{PinConfig, NVS_NAMESPACE as CONFIG_NAMESPACE};
+use tasks::{network_config, provisioning_task, wifi_credentials};
+use tasks::{
+    ApiRouter, ConfigChange, ConfigSection, ConfigStore, Console, ConsoleTask, CredentialStore,
+    EspMqttTransport, HttpServerTask, MdnsTask, MqttConfig, MqttTask,
+    OtaPublicKey, OtaStatus, OtaTask, ProvisioningTask, SensorReadings, SensorTask, SharedStreamHub,
+    SntpTask, StreamEvent, StreamHub, WifiNetwork, WifiTask,
+};
+use error::Error;
+use utils::error_handler::{handle_error, CircuitBreaker, CircuitBreakerConfig};
+use utils::crash_log::CrashEntry;
+use utils::crash_reporter::{install_panic_hook, reset_reason, CrashReporter};
+use utils::esp_nvs_storage::{erase_namespace, EspNvsStorage};
+use utils::log_filter::{validate_module, LogFilter, LogLevel};
+use utils::log_ring::{LogPage, LogQuery, SharedLogRing};
+use utils::serial_logger::init_logging;
+use utils::retry::{Jitter, RetryPolicy};
+use utils::time_utils::{format_uptime, get_uptime_ms, Timer};
+use utils::watchdog::{Watchdog, WatchdogHandle};
+use utils::watchdog_supervisor::{spawn_supervisor, DEFAULT_CHECK_INTERVAL_MS};
+
+/// SSID of the SoftAP started for WiFi provisioning
+const PROVISIONING_AP_SSID: &str = "ESP32-Setup";
+
+/// Device name and model announced to Home Assistant
+const DEVICE_NAME: &str = "ESP32 Template";
+const DEVICE_MODEL: &str = "ESP32-S3";
+
+/// How often the telemetry task checks for remote commands
+const COMMAND_POLL_MS: u32 = 200;
+
+/// How often queued WebSocket frames are sent when no new events arrive
+const STREAM_FLUSH_MS: u64 = 50;
+
+/// How long each task may go without feeding the watchdog
+const MAIN_LOOP_WATCHDOG_MS: u32 = 2000;
+const STREAM_WATCHDOG_MS: u32 = 5000;
+const TELEMETRY_WATCHDOG_MS: u32 = 30_000;
+
+/// Watchdog tasks a new OTA image must run before it is marked valid
+const SELF_TEST_TASKS: [&str; 3] = ["main", "ws_stream", "telemetry"];
+
+/// How long to wait before retrying a failed MQTT client setup
+const MQTT_RETRY_MS: u32 = 30_000;
+
+/// Time given to background tasks before a requested reboot
+const REBOOT_GRACE_MS: u32 = 500;
+
+/// NVS namespaces erased by a factory reset besides the device configuration;
+/// the OTA signing key and the crash log survive
+const FACTORY_RESET_NAMESPACES: [&str; 3] = [
+    wifi_credentials::NVS_NAMESPACE,
+    network_config::NVS_NAMESPACE,
+    provisioning_task::NVS_NAMESPACE,
+];
+
+/// Main application entry point
+pub fn main() -> Result<()> {
+    // Setup ESP-IDF internals
+    link_patches();
+    // Levels come from the stored configuration once it is loaded
+    let log_filter = LogFilter::new(LogLevel::Info);
+    let log_ring = init_logging(log_filter.clone())?;
+    install_panic_hook();
+
+    info!("ESP32 Template Application Starting...");
+
+    // Initialize peripherals
+    let peripherals = match Peripherals::take() {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to acquire peripherals: {:?}", e);
+            return Err(anyhow::anyhow!("Failed to acquire peripherals"));
+        }
+    };
+
+    let nvs = match EspDefaultNvsPartition::take() {
+        Ok(nvs) => nvs,
+        Err(e) => {
+            error!("Failed to acquire NVS partition: {:?}", e);
+            return Err(anyhow::anyhow!("NVS partition acquisition failed"));
+        }
+    };
+
+    // Record the evidence left by a crash in the previous run
+    let crash_reporter = CrashReporter::on_boot(nvs.clone())
+        .map(|reporter| Arc::new(Mutex::new(reporter)))
+        .map_err(|e| warn!("Crash log unavailable: {:?}", e))
+        .ok();
+
+    let mut config_store = ConfigStore::load(EspNvsStorage::open(nvs.clone(), CONFIG_NAMESPACE)?);
+    let config = config_store.config().clone();
+    log_filter.apply(config.logging.level, &config.logging.modules);
+    let main_config_changes = config_store.subscribe();
+    let telemetry_config_changes = config_store.subscribe();
+    let config_store: SharedConfigStore = Arc::new(Mutex::new(config_store));
+
+    // Initialize the LED (shared with the remote command handlers) and button
+    // controllers, keeping the console and HTTP API reachable to repair bad pins
+    let defaults = PinConfig::default();
+    let (led_controller, mut button_controller) = match init_io(&config.pins) {
+        Ok(io) => io,
+        Err(e) if config.pins != defaults => {
+            error!("Configured pins {:?} unusable, falling back to {:?}: {:?}", config.pins, defaults, e);
+            init_io(&defaults).map_err(|e| {
+                error!("Failed to initialize LED and button controllers: {:?}", e);
+                anyhow::anyhow!("LED and button controller initialization failed")
+            })?
+        }
+        Err(e) => {
+            error!("Failed to initialize LED and button controllers: {:?}", e);
+            return Err(anyhow::anyhow!("LED and button controller initialization failed"));
+        }
+    };
+    info!("LED and button controllers initialized successfully");
+    let led_controller = Arc::new(Mutex::new(led_controller));
+
+    button_controller.set_debounce_time(config.button.debounce_ms);
+    let mut provisioning_hold_ms = config.button.provisioning_hold_ms;
+
+    // Initialize WiFi from stored credentials
+    let sysloop = match EspSystemEventLoop::take() {
+        Ok(sysloop) => sysloop,
+        Err(e) => {
+            error!("Failed to acquire system event loop: {:?}", e);
+            return Err(anyhow::anyhow!("System event loop acquisition failed"));
+        }
+    };
+
+    // Unreadable credentials are erased so provisioning can save new ones
+    let credentials = CredentialStore::load(nvs.clone()).or_else(|e| {
+        warn!("Failed to load WiFi credentials, erasing them: {:?}", e);
+        erase_namespace(wifi_credentials::NVS_NAMESPACE)?;
+        CredentialStore::load(nvs.clone())
+    });
+    let credentials_persistent = credentials.is_ok();
+    let credentials = credentials.unwrap_or_else(|e| {
+        error!("WiFi credential storage unavailable, starting empty: {:?}", e);
+        CredentialStore::new()
+    });
+
+    let mut wifi_task = WifiTask::with_credentials(credentials);
+    wifi_task.set_network_config(config.network.clone())?;
+    wifi_task.init_with_modem(peripherals.modem, sysloop, nvs.clone())?;
+
+    let provisioning_requested = take_provisioning_request(nvs.clone()).unwrap_or_else(|e| {
+        warn!("Failed to read provisioning request: {:?}", e);
+        false
+    });
+
+    if (provisioning_requested || !wifi_task.has_networks()) && !credentials_persistent {
+        // Credentials entered in the portal would be lost on the restart
+        error!("Not starting WiFi provisioning, credentials cannot be saved");
+    } else if provisioning_requested || !wifi_task.has_networks() {
+        // The restart after provisioning would roll back an unconfirmed OTA
+        // image; without credentials, a started WiFi driver is the self-test
+        if let Err(e) = validate_boot(true) {
+            warn!("OTA boot validation failed: {:?}", e);
+        }
+        // Restarts the device once credentials have been saved
+        ProvisioningTask::new(wifi_task, PROVISIONING_AP_SSID.to_string()).run()?;
+        return Ok(());
+    }
+
+    // Access points often come up after the device when power returns
+    let wifi_retry = RetryPolicy::exponential(config.wifi.retry_delay_ms)
+        .max_attempts(config.wifi.connect_attempts)
+        .max_delay_ms(config.wifi.retry_max_delay_ms)
+        .jitter(Jitter::Full);
+    if let Err(e) = wifi_retry.run(|| wifi_task.connect()) {
+        warn!("WiFi unavailable, continuing offline: {:?}", e);
+    }
+
+    // Measurements switch from uptime to UTC timestamps after the first sync
+    let _sntp = SntpTask::start(&config.sntp, log_sync)
+        .map_err(|e| warn!("SNTP unavailable: {:?}", e))
+        .ok();
+
+    // Announce `<hostname>.local` plus the dashboard and node discovery services
+    let _mdns = config.mdns.enabled
+        .then(|| {
+            MdnsTask::start(
+                &config.mdns,
+                &wifi_task.network_config().hostname,
+                &config.mqtt.client_id,
+                env!("CARGO_PKG_VERSION"),
+            )
+            .map_err(|e| warn!("mDNS unavailable: {:?}", e))
+            .ok()
+        })
+        .flatten();
+
+    let ota_public_key = load_public_key(nvs.clone()).unwrap_or_else(|e| {
+        warn!("Failed to load OTA public key: {:?}", e);
+        None
+    });
+    if ota_public_key.is_none() {
+        warn!("No OTA public key configured, remote updates will be refused");
+    }
+
+    let wifi = Arc::new(Mutex::new(wifi_task));
+    let latest_readings = Arc::new(Mutex::new(None));
+    let reboot_requested = Arc::new(AtomicBool::new(false));
+    let ota = OtaUpdater {
+        status: Arc::new(Mutex::new(OtaStatus::new())),
+        public_key: ota_public_key,
+        breaker: Arc::new(CircuitBreaker::new("OTA server", CircuitBreakerConfig::default())?),
+    };
+
+    // Report stalled tasks before the hardware task watchdog resets the chip
+    let watchdog = Watchdog::new();
+    if let Err(e) = spawn_supervisor(watchdog.clone(), DEFAULT_CHECK_INTERVAL_MS) {
+        warn!("Watchdog supervisor unavailable: {:?}", e);
+    }
+
+    // Serve the REST API and web dashboard on the local network
+    let mut router = ApiRouter::new();
+    register_device_routes(&mut router, Arc::new(AppDevice {
+        leds: led_controller.clone(),
+        wifi: wifi.clone(),
+        readings: latest_readings.clone(),
+        reboot_requested: reboot_requested.clone(),
+        ota: ota.clone(),
+        crash_reporter: crash_reporter.clone(),
+        config: config_store.clone(),
+        log_ring: log_ring.clone(),
+    }));
+    register_dashboard_routes(&mut router, WEB_ASSETS);
+    let stream_hub: SharedStreamHub = Arc::new(Mutex::new(StreamHub::new()));
+    let _http_server = HttpServerTask::start_with_stream(router, stream_hub.clone())
+        .map_err(|e| warn!("HTTP API unavailable: {:?}", e))
+        .ok();
+
+    // Fan sensor readings and button events out to WebSocket clients
+    let (stream_events, stream_event_rx) = mpsc::channel();
+    let button_pressed = Arc::new(AtomicBool::new(false));
+    let stream_watchdog = watchdog.register("ws_stream", STREAM_WATCHDOG_MS)?;
+    std::thread::Builder::new()
+        .name("ws_stream".to_string())
+        .stack_size(6144)
+        .spawn(move || run_stream(stream_hub, stream_event_rx, stream_watchdog))?;
+
+    // Interactive shell on the serial console
+    let mut console = Console::new();
+    register_console_commands(&mut console, ConsoleShared {
+        leds: led_controller.clone(),
+        button_pressed: button_pressed.clone(),
+        readings: latest_readings.clone(),
+        wifi: wifi.clone(),
+        config: config_store.clone(),
+        watchdog: watchdog.clone(),
+        reboot_requested: reboot_requested.clone(),
+        log_ring: log_ring.clone(),
+        nvs: nvs.clone(),
+        crash_reporter,
+    });
+    if let Err(e) = ConsoleTask::spawn(console) {
+        warn!("Serial console unavailable: {:?}", e);
+    }
+
+    // Publish sensor telemetry in the background; messages buffer while offline
+    let mut mqtt_config = MqttConfig::new(&config.mqtt.broker_url, &config.mqtt.client_id);
+    mqtt_config.username = config.mqtt.username.clone();
+    mqtt_config.password = config.mqtt.password.clone();
+    mqtt_config.topic_prefix = config.mqtt.topic_prefix.clone();
+    mqtt_config.qos = QoS::from_level(config.mqtt.qos).unwrap_or(QoS::AtLeastOnce);
+    mqtt_config.retain = config.mqtt.retain;
+
+    let telemetry = TelemetryShared {
+        leds: led_controller.clone(),
+        readings: latest_readings,
+        reboot_requested: reboot_requested.clone(),
+        ota,
+        stream_events: stream_events.clone(),
+        watchdog: watchdog.clone(),
+        config: config_store.clone(),
+        config_changes: telemetry_config_changes,
+        log_ring,
+    };
+    let (button_events, button_event_rx) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("telemetry".to_string())
+        .stack_size(8192)
+        .spawn(move || {
+            if let Err(e) = run_telemetry(mqtt_config, telemetry, button_event_rx) {
+                error!("Telemetry task stopped: {:?}", e);
+            }
+        })?;
+
+    // Application state
+    let mut led_state = false;
+    let mut last_button_state = false;
+    let mut last_raw_pressed = false;
+
+    let main_watchdog = watchdog.register("main", MAIN_LOOP_WATCHDOG_MS)?;
+
+    // A freshly installed OTA image is confirmed once its tasks run and it
+    // reaches the network, and rolled back if they stall or it never does
+    let self_test_config = SelfTestConfig {
+        tasks: SELF_TEST_TASKS.iter().map(|name| name.to_string()).collect(),
+        ..SelfTestConfig::default()
+    };
+    let self_test_wifi = wifi.clone();
+    if let Err(e) = spawn_self_test(self_test_config, watchdog.clone(), move || {
+        self_test_wifi.lock().unwrap().is_connected()
+    }) {
+        warn!("OTA self-test unavailable: {:?}", e);
+    }
+
+    info!("Application initialized successfully. Starting main loop...");
+
+    // Main application loop
+    loop {
+        main_watchdog.feed();
+
+        // Set by the REST API, MQTT commands, the console and OTA updates
+        if reboot_requested.load(Ordering::Relaxed) {
+            info!("Rebooting on request...");
+            // Lets the telemetry task flush queued MQTT messages first
+            FreeRtos::delay_ms(REBOOT_GRACE_MS);
+            esp_idf_hal::reset::restart();
+        }
+
+        for change in main_config_changes.try_iter() {
+            if change.affects(ConfigSection::Logging) {
+                log_filter.apply(change.config.logging.level, &change.config.logging.modules);
+                info!("Log level set to {}", change.config.logging.level);
+            }
+            if change.affects(ConfigSection::Button) {
+                button_controller.set_debounce_time(change.config.button.debounce_ms);
+                provisioning_hold_ms = change.config.button.provisioning_hold_ms;
+            }
+            let after_reboot = [
+                ConfigSection::Pins,
+                ConfigSection::Wifi,
+                ConfigSection::Network,
+                ConfigSection::Mqtt,
+                ConfigSection::Mdns,
+                ConfigSection::Sntp,
+            ];
+            if after_reboot.iter().any(|&s| change.affects(s)) {
+                info!("Pin, WiFi, network, MQTT, mDNS and SNTP settings take effect after a reboot");
+            }
+        }
+
+        // Read button state
+        let button_pressed = match button_controller.is_pressed() {
+            Ok(pressed) => pressed,
+            Err(e) => {
+                warn!("Failed to read button state: {:?}", e);
+                false
+            }
+        };
+
+        // Handle button press (rising edge detection)
+        if button_pressed && !last_button_state {
+            led_state = !led_state;
+            
+            match led_controller.lock().unwrap().set_state(led_state) {
+                Ok(_) => {
+                    if led_state {
+                        info!("LED turned ON");
+                    } else {
+                        info!("LED turned OFF");
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to set LED state: {:?}", e);
+                }
+            }
+        }
+
+        last_button_state = button_pressed;
+
+        // Report raw press/release transitions to remote consumers
+        if let Ok(raw_pressed) = button_controller.is_pressed_raw() {
+            if raw_pressed != last_raw_pressed {
+                let event = if raw_pressed { ButtonEvent::Pressed } else { ButtonEvent::Released };
+                let _ = button_events.send(event);
+                let _ = stream_events.send(StreamEvent::Button { id: 1, event, uptime_ms: get_uptime_ms() });
+                button_pressed.store(raw_pressed, Ordering::Relaxed);
+                last_raw_pressed = raw_pressed;
+            }
+        }
+
+        // Long press re-enters WiFi provisioning
+        if button_controller.is_held_for(provisioning_hold_ms).unwrap_or(false) {
+            info!("Button held, restarting into WiFi provisioning...");
+            if let Err(e) = request_provisioning(nvs.clone()) {
+                error!("Failed to request provisioning: {:?}", e);
+            } else {
+                esp_idf_hal::reset::restart();
+            }
+        }
+
+        // Small delay to prevent busy waiting
+        FreeRtos::delay_ms(50);
+    }
+
+    #[allow(unreachable_code)]
+    Ok(())
+}
+
+/// Device state shared with the REST API
+struct AppDevice {
+    leds: Arc<Mutex<LedController>>,
+    wifi: Arc<Mutex<WifiTask>>,
+    readings: Arc<Mutex<Option<SensorReadings>>>,
+    reboot_requested: Arc<AtomicBool>,
+    ota: OtaUpdater,
+    crash_reporter: Option<Arc<Mutex<CrashReporter>>>,
+    config: SharedConfigStore,
+    log_ring: SharedLogRing,
+}
+
+impl AppDevice {
+    fn crash_reporter(&self) -> Result<&Arc<Mutex<CrashReporter>>> {
+        self.crash_reporter.as_ref().ok_or_else(|| anyhow::anyhow!("Crash log unavailable"))
+    }
+}
+
+impl DeviceApi for AppDevice {
+    fn status(&self) -> Result<DeviceStatus> {
+        let wifi = self.wifi.lock().unwrap();
+
+        Ok(DeviceStatus {
+            uptime: format_uptime(),
+            uptime_ms: get_uptime_ms(),
+            free_heap: unsafe { esp_idf_svc::sys::esp_get_free_heap_size() },
+            min_free_heap: unsafe { esp_idf_svc::sys::esp_get_minimum_free_heap_size() },
+            wifi: WifiInfo {
+                connected: wifi.is_connected(),
+                ssid: wifi.connected_ssid(),
+                ip: wifi.station_ip().map(|ip| ip.to_string()),
+                rssi: wifi.rssi(),
+                access_point_active: wifi.is_access_point_active(),
+            },
+            reset_reason: reset_reason(),
+            crash_count: self.crash_reporter.as_ref()
+                .map(|reporter| reporter.lock().unwrap().total_crashes())
+                .unwrap_or(0),
+        })
+    }
+
+    fn sensors(&self) -> Result<Option<SensorReadings>> {
+        Ok(*self.readings.lock().unwrap())
+    }
+
+    fn led_count(&self) -> u8 {
+        LED_COUNT
+    }
+
+    fn led_state(&self, id: u8) -> Result<bool> {
+        Ok(self.leds.lock().unwrap().get_led_state(id)?)
+    }
+
+    fn set_led(&self, id: u8, state: bool) -> Result<()> {
+        Ok(self.leds.lock().unwrap().set_led(id, state)?)
+    }
+
+    fn reboot(&self) -> Result<()> {
+        self.reboot_requested.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn ota_status(&self) -> OtaStatus {
+        self.ota.status.lock().unwrap().clone()
+    }
+
+    fn start_ota(&self, manifest_url: &str) -> Result<()> {
+        self.ota.spawn(manifest_url.to_string(), self.reboot_requested.clone())
+    }
+
+    fn crash_log(&self) -> Result<Vec<CrashEntry>> {
+        Ok(self.crash_reporter()?.lock().unwrap().entries())
+    }
+
+    fn clear_crash_log(&self) -> Result<()> {
+        Ok(self.crash_reporter()?.lock().unwrap().clear()?)
+    }
+
+    fn config(&self) -> Result<Value> {
+        Ok(self.config.lock().unwrap().export())
+    }
+
+    fn import_config(&self, patch: &Value) -> Result<Vec<ConfigSection>> {
+        Ok(self.config.lock().unwrap().import(patch)?)
+    }
+
+    fn factory_reset(&self) -> Result<()> {
+        factory_reset(&self.config)?;
+        self.reboot_requested.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn logs(&self, query: &LogQuery) -> Result<LogPage> {
+        Ok(self.log_ring.lock().unwrap().query(query))
+    }
+}
+
+/// Create the LED and button controllers on the given pins
+fn init_io(pins: &PinConfig) -> Result<(LedController, ButtonController), Error> {
+    // SAFETY: these GPIOs are driven only by the LED and button controllers; a
+    // failed attempt drops its drivers before the next one takes the pins
+    let (led1_pin, led2_pin, button_pin) = unsafe {
+        (
+            AnyOutputPin::new(pins.led1 as i32),
+            AnyOutputPin::new(pins.led2 as i32),
+            AnyIOPin::new(pins.button as i32),
+        )
+    };
+    let leds = LedController::new(led1_pin, led2_pin)?;
+    let button = ButtonController::new(button_pin)?;
+    Ok((leds, button))
+}
+
+/// Erase the stored settings so the next boot starts as shipped
+fn factory_reset(config: &SharedConfigStore) -> Result<(), Error> {
+    config.lock().unwrap().factory_reset()?;
+    for namespace in FACTORY_RESET_NAMESPACES {
+        erase_namespace(namespace)?;
+    }
+    warn!("Factory reset complete, rebooting");
+    Ok(())
+}
+
+/// State shared with the serial console commands
+struct ConsoleShared {
+    leds: Arc<Mutex<LedController>>,
+    button_pressed: Arc<AtomicBool>,
+    readings: Arc<Mutex<Option<SensorReadings>>>,
+    wifi: Arc<Mutex<WifiTask>>,
+    config: SharedConfigStore,
+    watchdog: Watchdog,
+    reboot_requested: Arc<AtomicBool>,
+    log_ring: SharedLogRing,
+    nvs: EspDefaultNvsPartition,
+    crash_reporter: Option<Arc<Mutex<CrashReporter>>>,
+}
+
+/// Register the built-in console commands
+fn register_console_commands(console: &mut Console, shared: ConsoleShared) {
+    let ConsoleShared {
+        leds, button_pressed, readings, wifi, config, watchdog, reboot_requested, log_ring, nvs, crash_reporter,
+    } = shared;
+    let on_off = |state: bool| if state { "on" } else { "off" };
+
+    console.register_with_subcommands("led", "led [<id> on|off|toggle]", &["1", "2"], move |args| {
+        let mut leds = leds.lock().unwrap();
+        if args.is_empty() {
+            let states = (1..=LED_COUNT)
+                .map(|id| Ok(format!("LED{}: {}", id, on_off(leds.get_led_state(id)?))))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(states.join("\n"));
+        }
+
+        let id: u8 = parse_arg(args, 0, "LED id")?;
+        match args.get(1).map(String::as_str) {
+            Some("on") => leds.set_led(id, true)?,
+            Some("off") => leds.set_led(id, false)?,
+            Some("toggle") => leds.toggle_led(id)?,
+            Some(other) => return Err(anyhow::anyhow!("Unknown LED action '{}'", other)),
+            None => {}
+        }
+        Ok(format!("LED{}: {}", id, on_off(leds.get_led_state(id)?)))
+    });
+
+    let button_config = config.clone();
+    console.register("button", "Show the button state and timing", move |_| {
+        let state = if button_pressed.load(Ordering::Relaxed) { "pressed" } else { "released" };
+        let button = button_config.lock().unwrap().config().button.clone();
+        Ok(format!(
+            "Button: {}\nDebounce: {} ms\nProvisioning hold: {} ms",
+            state, button.debounce_ms, button.provisioning_hold_ms
+        ))
+    });
+
+    console.register("sensors", "Show the latest sensor readings", move |_| {
+        Ok(match *readings.lock().unwrap() {
+            Some(r) => format!(
+                "Temperature: {:.1} C\nHumidity: {:.1} %\nPressure: {:.1} hPa\nTaken at: {} ms uptime",
+                r.temperature, r.humidity, r.pressure, r.uptime_ms
+            ),
+            None => "No readings yet".to_string(),
+        })
+    });
+
+    console.register_with_subcommands(
+        "wifi",
+        "wifi status | scan | connect <ssid> [password]",
+        &["status", "scan", "connect"],
+        move |args| {
+            let mut wifi = wifi.lock().unwrap();
+            match args.first().map(String::as_str).unwrap_or("status") {
+                "status" => Ok(format!(
+                    "Connected: {}\nSSID: {}\nIP: {}\nRSSI: {}\nAccess point: {}",
+                    wifi.is_connected(),
+                    wifi.connected_ssid().unwrap_or_else(|| "-".to_string()),
+                    wifi.station_ip().map_or("-".to_string(), |ip| ip.to_string()),
+                    wifi.rssi().map_or("-".to_string(), |rssi| format!("{} dBm", rssi)),
+                    wifi.is_access_point_active(),
+                )),
+                "scan" => {
+                    let mut results = filter_visible(wifi.scan()?);
+                    sort_by_rssi(&mut results);
+                    if results.is_empty() {
+                        return Ok("No networks found".to_string());
+                    }
+                    let lines: Vec<String> = results.iter()
+                        .map(|r| format!("{:>4} dBm  ch{:<2}  {:<15}  {}", r.rssi, r.channel, r.auth_mode.to_string(), r.ssid))
+                        .collect();
+                    Ok(lines.join("\n"))
+                }
+                "connect" => {
+                    let ssid: String = parse_arg(args, 1, "SSID")?;
+                    let password = args.get(2).cloned().unwrap_or_default();
+                    let network = WifiNetwork::new(ssid.clone(), password.clone(), 0);
+                    wifi.connect_network(&network)?;
+                    // Saved only once it has worked
+                    wifi.add_network(ssid.clone(), password, 0)?;
+                    Ok(format!("Connected to {}", ssid))
+                }
+                other => Err(anyhow::anyhow!("Unknown subcommand '{}'", other)),
+            }
+        },
+    );
+
+    register_log_command(console, config.clone());
+    register_logs_command(console, log_ring);
+
+    let reset_config = config.clone();
+    let reset_flag = reboot_requested.clone();
+    register_config_command(console, config, move || {
+        factory_reset(&reset_config)?;
+        reset_flag.store(true, Ordering::Relaxed);
+        Ok(())
+    });
+
+    console.register("uptime", "Show the time since boot", |_| Ok(format_uptime()));
+
+    console.register("ota-key", "ota-key [<hex>]", move |args| {
+        let Some(hex) = args.first() else {
+            return Ok(match load_public_key(nvs.clone())? {
+                Some(key) => format!("OTA public key: {}", key.to_hex()),
+                None => "No OTA public key configured".to_string(),
+            });
+        };
+        if OtaPublicKey::builtin()?.is_some() {
+            return Err(anyhow::anyhow!("The firmware has a built-in OTA public key"));
+        }
+        let key = OtaPublicKey::from_hex(hex)?;
+        store_public_key(nvs.clone(), &key)?;
+        Ok("OTA public key stored, takes effect after a reboot".to_string())
+    });
+
+    console.register("reboot", "Restart the device", move |_| {
+        reboot_requested.store(true, Ordering::Relaxed);
+        Ok("Rebooting...".to_string())
+    });
+
+    console.register("tasks", "List FreeRTOS tasks and watchdog health", move |_| {
+        // About 40 bytes per task
+        let mut buf = vec![0u8; 2048];
+        // SAFETY: the buffer is zeroed and large enough for the task list
+        unsafe { esp_idf_svc::sys::vTaskList(buf.as_mut_ptr().cast()) };
+        let list = CStr::from_bytes_until_nul(&buf)
+            .map(|list| list.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut text = format!("Name\t\tState\tPrio\tStack\tNum\tCore\n{}", list.trim_end());
+        text.push_str("\n\nWatchdog:");
+        for task in watchdog.health() {
+            text.push_str(&format!(
+                "\n{:<16} {:>6} ms since feed, timeout {} ms{}",
+                task.name,
+                task.since_feed_ms,
+                task.timeout_ms,
+                if task.stalled { "  STALLED" } else { "" }
+            ));
+        }
+        Ok(text)
+    });
+
+    console.register("heap", "Show free heap memory", |_| {
+        // SAFETY: read-only heap statistics
+        let (free, min_free, largest) = unsafe {
+            (
+                esp_idf_svc::sys::esp_get_free_heap_size(),
+                esp_idf_svc::sys::esp_get_minimum_free_heap_size(),
+                esp_idf_svc::sys::heap_caps_get_largest_free_block(esp_idf_svc::sys::MALLOC_CAP_8BIT),
+            )
+        };
+        Ok(format!("Free: {} bytes\nMinimum free: {} bytes\nLargest block: {} bytes", free, min_free, largest))
+    });
+
+    console.register_with_subcommands("crashes", "crashes [clear]", &["clear"], move |args| {
+        let reporter = crash_reporter.as_ref().ok_or_else(|| anyhow::anyhow!("Crash log unavailable"))?;
+        let mut reporter = reporter.lock().unwrap();
+        match args.first().map(String::as_str) {
+            None => Ok(format_crashes(&reporter.entries(), reporter.total_crashes())),
+            Some("clear") => {
+                reporter.clear()?;
+                Ok("Crash log cleared".to_string())
+            }
+            Some(other) => Err(anyhow::anyhow!("Unknown subcommand '{}'", other)),
+        }
+    });
+}
+
+/// Starts OTA updates for the REST API and remote commands
+#[derive(Clone)]
+struct OtaUpdater {
+    status: Arc<Mutex<OtaStatus>>,
+    public_key: Option<OtaPublicKey>,
+    /// Shared by all updates so an unreachable server is left alone for a while
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl OtaUpdater {
+    /// Install an update in the background and reboot into it once written
+    fn spawn(&self, manifest_url: String, reboot_requested: Arc<AtomicBool>) -> Result<()> {
+        if self.status.lock().unwrap().is_busy() {
+            return Err(anyhow::anyhow!("An update is already in progress"));
+        }
+
+        let updater = self.clone();
+        std::thread::Builder::new()
+            .name("ota".to_string())
+            .stack_size(10240)
+            .spawn(move || {
+                let mut ota_task = OtaTask::new(updater.status);
+                if let Some(key) = updater.public_key {
+                    ota_task.set_public_key(key);
+                }
+                ota_task.set_circuit_breaker(updater.breaker);
+                if let Ok(true) = ota_task.update(&manifest_url) {
+                    reboot_requested.store(true, Ordering::Relaxed);
+                }
+            })?;
+        Ok(())
+    }
+}
+
+/// State shared between the telemetry task and the rest of the application
+struct TelemetryShared {
+    leds: Arc<Mutex<LedController>>,
+    readings: Arc<Mutex<Option<SensorReadings>>>,
+    reboot_requested: Arc<AtomicBool>,
+    ota: OtaUpdater,
+    stream_events: Sender<StreamEvent>,
+    watchdog: Watchdog,
+    config: SharedConfigStore,
+    config_changes: Receiver<ConfigChange>,
+    log_ring: SharedLogRing,
+}
+
+/// Device configuration shared between tasks
+type SharedConfigStore = Arc<Mutex<ConfigStore<EspNvsStorage>>>;
+
+/// Forward stream events to WebSocket clients until all senders are gone
+fn run_stream(hub: SharedStreamHub, events: Receiver<StreamEvent>, watchdog: WatchdogHandle) {
+    loop {
+        watchdog.feed();
+        match events.recv_timeout(std::time::Duration::from_millis(STREAM_FLUSH_MS)) {
+            Ok(event) => {
+                if let Err(e) = hub.lock().unwrap().publish(&event, get_uptime_ms()) {
+                    warn!("Failed to publish stream event: {:?}", e);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        // Sending waits for the HTTP server task, whose WebSocket handler also
+        // locks the hub, so frames are sent with the hub unlocked
+        let mut outgoing = hub.lock().unwrap().take_outgoing();
+        if !outgoing.is_empty() {
+            outgoing.send();
+            hub.lock().unwrap().finish(outgoing);
+        }
+    }
+}
+
+/// Read sensors periodically, publish the readings and serve remote commands over MQTT
+fn run_telemetry(
+    mqtt_config: MqttConfig,
+    shared: TelemetryShared,
+    button_events: Receiver<ButtonEvent>,
+) -> Result<()> {
+    let TelemetryShared {
+        leds,
+        readings,
+        reboot_requested,
+        ota,
+        stream_events,
+        watchdog,
+        config,
+        config_changes,
+        log_ring,
+    } = shared;
+    let (mut sample_interval_ms, thresholds) = {
+        let config = config.lock().unwrap();
+        (config.config().telemetry.sample_interval_ms, config.config().thresholds)
+    };
+
+    let telemetry_watchdog = watchdog.register("telemetry", TELEMETRY_WATCHDOG_MS)?;
+
+    let mut sensor_task = SensorTask::new();
+    if let Err(e) = sensor_task.start() {
+        warn!("Failed to start sensors: {:?}", e);
+    }
+    sensor_task.set_thresholds(thresholds);
+    // Stop polling a sensor that keeps failing instead of flooding the log
+    let sensor_breaker = CircuitBreaker::new("Sensors", CircuitBreakerConfig::default())?;
+
+    let mut dispatcher = CommandDispatcher::new();
+    register_commands(
+        &mut dispatcher,
+        leds.clone(),
+        config,
+        reboot_requested,
+        ota,
+        log_ring,
+    );
+
+    // Announce LEDs, the button and sensor measurements to Home Assistant
+    let mut discovery = DiscoveryRegistry::new(DeviceInfo::new(DEVICE_NAME, DEVICE_MODEL));
+    discovery
+        .add_led(1, "LED 1", LedKind::Light)
+        .add_led(2, "LED 2", LedKind::Light)
+        .add_button(1, "Button")
+        .add_sensor_task_measurements();
+
+    // Sensors keep running while the MQTT client can't be created
+    let mut mqtt = None;
+    let mut mqtt_retry_timer = Timer::new(0);
+    let mut sample_timer = Timer::new(0);
+    let mut last_led_states = [None; LED_COUNT as usize];
+
+    loop {
+        telemetry_watchdog.feed();
+
+        for change in config_changes.try_iter() {
+            if change.affects(ConfigSection::Telemetry) {
+                sample_interval_ms = change.config.telemetry.sample_interval_ms;
+                sample_timer.reset_with_duration(sample_interval_ms);
+            }
+            if change.affects(ConfigSection::Thresholds) {
+                sensor_task.set_thresholds(change.config.thresholds);
+            }
+        }
+
+        if mqtt.is_none() && mqtt_retry_timer.has_expired() {
+            match start_mqtt(&mqtt_config, &dispatcher, &discovery) {
+                Ok(mqtt_task) => mqtt = Some(mqtt_task),
+                Err(e) => {
+                    warn!("MQTT unavailable, retrying in {} ms: {:?}", MQTT_RETRY_MS, e);
+                    mqtt_retry_timer.reset_with_duration(MQTT_RETRY_MS);
+                }
+            }
+        }
+
+        match &mut mqtt {
+            Some(mqtt_task) => {
+                let served = serve_mqtt(
+                    mqtt_task,
+                    &mut dispatcher,
+                    &discovery,
+                    &button_events,
+                    &leds,
+                    &mut last_led_states,
+                );
+                if let Err(e) = served {
+                    warn!("MQTT processing failed: {:?}", e);
+                }
+            }
+            // Nobody to report button events to
+            None => button_events.try_iter().for_each(drop),
+        }
+
+        if sample_timer.has_expired() {
+            match sensor_breaker.call(|| sensor_task.read_snapshot()) {
+                Ok(snapshot) => {
+                    sensor_task.check_thresholds(snapshot.temperature, snapshot.humidity);
+                    *readings.lock().unwrap() = Some(snapshot);
+                    let _ = stream_events.send(StreamEvent::Sensors(snapshot));
+                    if let Some(mqtt_task) = &mut mqtt {
+                        if let Err(e) = mqtt_task.publish_readings(&snapshot) {
+                            warn!("Failed to publish readings: {:?}", e);
+                        }
+                    }
+                }
+                Err(Error::CircuitOpen { retry_in_ms, .. }) => {
+                    debug!("Skipping sensor read, retrying in {} ms", retry_in_ms)
+                }
+                Err(e) => warn!("Failed to read sensors: {:?}", e),
+            }
+            sample_timer.reset_with_duration(sample_interval_ms);
+        }
+
+        FreeRtos::delay_ms(COMMAND_POLL_MS);
+    }
+}
+
+/// Create the MQTT client and announce the device
+///
+/// Subscriptions and discovery configs made while the broker is unreachable
+/// are sent once it connects.
+fn start_mqtt(
+    config: &MqttConfig,
+    dispatcher: &CommandDispatcher,
+    discovery: &DiscoveryRegistry,
+) -> Result<MqttTask<EspMqttTransport>> {
+    let transport = EspMqttTransport::new(config)?;
+    let mut mqtt_task = MqttTask::new(transport, config.clone());
+    // Buffer telemetry instead of retrying a broker that keeps refusing it
+    mqtt_task.set_circuit_breaker(CircuitBreaker::new("MQTT", CircuitBreakerConfig::default())?);
+
+    if let Err(e) = dispatcher.subscribe(&mut mqtt_task) {
+        warn!("Failed to subscribe to remote commands: {:?}", e);
+    }
+    if let Err(e) = discovery.subscribe(&mut mqtt_task) {
+        warn!("Failed to subscribe to Home Assistant status: {:?}", e);
+    }
+    if let Err(e) = discovery.publish(&mut mqtt_task) {
+        warn!("Failed to publish Home Assistant discovery: {:?}", e);
+    }
+    Ok(mqtt_task)
+}
+
+/// Send buffered messages, handle received commands and publish button and LED changes
+fn serve_mqtt(
+    mqtt_task: &mut MqttTask<EspMqttTransport>,
+    dispatcher: &mut CommandDispatcher,
+    discovery: &DiscoveryRegistry,
+    button_events: &Receiver<ButtonEvent>,
+    leds: &Mutex<LedController>,
+    last_led_states: &mut [Option<bool>],
+) -> Result<()> {
+    mqtt_task.flush()?;
+    let messages = mqtt_task.poll_messages();
+    dispatcher.process_messages(mqtt_task, &messages)?;
+    discovery.process_messages(mqtt_task, &messages)?;
+
+    for event in button_events.try_iter() {
+        let topic = mqtt_task.config().topic("buttons/1");
+        mqtt_task.publish(&topic, button_event_payload(event).as_bytes())?;
+    }
+
+    // Publish retained LED states whenever they change
+    for id in 1..=LED_COUNT {
+        let state = leds.lock().unwrap().get_led_state(id).ok();
+        let last_state = &mut last_led_states[id as usize - 1];
+        if let Some(state) = state.filter(|s| Some(*s) != *last_state) {
+            let topic = mqtt_task.config().topic(&format!("leds/{}", id));
+            mqtt_task.publish_with(&topic, led_state_payload(state).as_bytes(), QoS::AtLeastOnce, true)?;
+            *last_state = Some(state);
+        }
+    }
+    Ok(())
+}
+
+/// Register handlers for the built-in remote commands
+fn register_commands(
+    dispatcher: &mut CommandDispatcher,
+    leds: Arc<Mutex<LedController>>,
+    config: SharedConfigStore,
+    reboot_requested: Arc<AtomicBool>,
+    ota: OtaUpdater,
+    log_ring: SharedLogRing,
+) {
+    let set_leds = leds.clone();
+    dispatcher.register("set_led", move |command| match command {
+        Command::SetLed { led, state } => {
+            set_leds.lock().unwrap().set_led(*led, *state)?;
+            Ok(Some(json!({ "led": led, "state": state })))
+        }
+        _ => Err(anyhow::anyhow!("Unexpected command")),
+    });
+
+    let toggle_leds = leds.clone();
+    dispatcher.register("toggle_led", move |command| match command {
+        Command::ToggleLed { led } => {
+            let mut leds = toggle_leds.lock().unwrap();
+            leds.toggle_led(*led)?;
+            Ok(Some(json!({ "led": led, "state": leds.get_led_state(*led)? })))
+        }
+        _ => Err(anyhow::anyhow!("Unexpected command")),
+    });
+
+    let interval_config = config.clone();
+    dispatcher.register("set_sample_interval", move |command| match command {
+        Command::SetSampleInterval { interval_ms } => {
+            interval_config.lock().unwrap().modify(|config| config.telemetry.sample_interval_ms = *interval_ms)?;
+            info!("Sample interval set to {}ms", interval_ms);
+            Ok(Some(json!({ "interval_ms": interval_ms })))
+        }
+        _ => Err(anyhow::anyhow!("Unexpected command")),
+    });
+
+    let get_config = config.clone();
+    dispatcher.register("get_config", move |_| Ok(Some(get_config.lock().unwrap().export())));
+
+    // `{"config": {...}}` merges a partial configuration as exported by `get_config`
+    let set_config = config.clone();
+    dispatcher.register("set_config", move |command| match command {
+        Command::Custom { params, .. } => {
+            let patch = params.get("config").ok_or_else(|| anyhow::anyhow!("Missing 'config'"))?;
+            let changed = set_config.lock().unwrap().import(patch)?;
+            Ok(Some(json!({ "changed": changed })))
+        }
+        _ => Err(anyhow::anyhow!("Unexpected command")),
+    });
+
+    // `{"level": "debug"}`, or `{"module": "tasks::wifi_task", "level": null}` to clear an override
+    let log_config = config.clone();
+    dispatcher.register("set_log_level", move |command| match command {
+        Command::Custom { params, .. } => {
+            let level = params.get("level").cloned().unwrap_or(Value::Null);
+            let patch = match params.get("module").and_then(Value::as_str) {
+                Some(module) => {
+                    validate_module(module)?;
+                    json!({ "logging": { "modules": { module: level } } })
+                }
+                None if level.is_null() => return Err(anyhow::anyhow!("Missing 'level'")),
+                None => json!({ "logging": { "level": level } }),
+            };
+            let mut config = log_config.lock().unwrap();
+            config.import(&patch)?;
+            Ok(Some(serde_json::to_value(&config.config().logging)?))
+        }
+        _ => Err(anyhow::anyhow!("Unexpected command")),
+    });
+
+    // `{"since": 120, "level": "warn", "module": "tasks::wifi_task", "limit": 20}`, all optional;
+    // `{"previous_boot": true}` reads the records retained from before the last reset
+    dispatcher.register("get_logs", move |command| match command {
+        Command::Custom { params, .. } => {
+            let query: LogQuery = serde_json::from_value(params.clone())?;
+            Ok(Some(serde_json::to_value(log_ring.lock().unwrap().query(&query))?))
+        }
+        _ => Err(anyhow::anyhow!("Unexpected command")),
+    });
+
+    let reset_flag = reboot_requested.clone();
+    dispatcher.register("factory_reset", move |_| {
+        factory_reset(&config)?;
+        reset_flag.store(true, Ordering::Relaxed);
+        Ok(Some(json!({ "resetting": true })))
+    });
+
+    let reboot_flag = reboot_requested.clone();
+    dispatcher.register("reboot", move |_| {
+        reboot_flag.store(true, Ordering::Relaxed);
+        Ok(None)
+    });
+
+    // `{"url": "https://.../manifest.json"}` starts an OTA update
+    dispatcher.register("ota_update", move |command| match command {
+        Command::Custom { params, .. } => {
+            let url = params.get("url")
+                .and_then(serde_json::Value::as_str)
+                .ok_or_else(|| anyhow::anyhow!("Missing 'url'"))?;
+            ota.spawn(url.to_string(), reboot_requested.clone())?;
+            Ok(Some(json!({ "started": true })))
+        }
+        _ => Err(anyhow::anyhow!("Unexpected command")),
+    });
+
+    // Home Assistant sends `{"state": "ON"}` to `cmd/led<n>`
+    for id in 1..=LED_COUNT {
+        let ha_leds = leds.clone();
+        dispatcher.register(&format!("led{}", id), move |command| match command {
+            Command::Custom { params, .. } => {
+                let state = parse_led_state(params)?;
+                ha_leds.lock().unwrap().set_led(id, state)?;
+                Ok(Some(json!({ "led": id, "state": state })))
+            }
+            _ => Err(anyhow::anyhow!("Unexpected command")),
+        });
+    }
+}
@@ -0,0 +1,296 @@
+use serde::Serialize;
+use std::fmt;
+use std::io;
+
+/// Result type used by the drivers and tasks
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An ESP-IDF `esp_err_t` code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EspCode(pub i32);
+
+impl EspCode {
+    pub const FAIL: Self = Self(-1);
+    pub const NO_MEM: Self = Self(0x101);
+    pub const INVALID_ARG: Self = Self(0x102);
+    pub const INVALID_STATE: Self = Self(0x103);
+    pub const INVALID_SIZE: Self = Self(0x104);
+    pub const NOT_FOUND: Self = Self(0x105);
+    pub const NOT_SUPPORTED: Self = Self(0x106);
+    pub const TIMEOUT: Self = Self(0x107);
+    pub const NOT_FINISHED: Self = Self(0x10c);
+    pub const NVS_NOT_INITIALIZED: Self = Self(0x1101);
+    pub const NVS_NOT_FOUND: Self = Self(0x1102);
+    pub const NVS_NOT_ENOUGH_SPACE: Self = Self(0x1105);
+    pub const NVS_INVALID_LENGTH: Self = Self(0x110c);
+    pub const WIFI_NOT_INIT: Self = Self(0x3001);
+    pub const WIFI_NOT_STARTED: Self = Self(0x3002);
+    pub const WIFI_CONN: Self = Self(0x3007);
+    pub const WIFI_SSID: Self = Self(0x300a);
+    pub const WIFI_PASSWORD: Self = Self(0x300b);
+    pub const WIFI_TIMEOUT: Self = Self(0x300c);
+    pub const WIFI_WOULD_BLOCK: Self = Self(0x300e);
+    pub const WIFI_NOT_CONNECT: Self = Self(0x300f);
+
+    /// Get the raw `esp_err_t` value
+    pub fn code(&self) -> i32 {
+        self.0
+    }
+
+    /// Get the ESP-IDF name of common codes
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match *self {
+            Self::FAIL => "ESP_FAIL",
+            Self::NO_MEM => "ESP_ERR_NO_MEM",
+            Self::INVALID_ARG => "ESP_ERR_INVALID_ARG",
+            Self::INVALID_STATE => "ESP_ERR_INVALID_STATE",
+            Self::INVALID_SIZE => "ESP_ERR_INVALID_SIZE",
+            Self::NOT_FOUND => "ESP_ERR_NOT_FOUND",
+            Self::NOT_SUPPORTED => "ESP_ERR_NOT_SUPPORTED",
+            Self::TIMEOUT => "ESP_ERR_TIMEOUT",
+            Self::NOT_FINISHED => "ESP_ERR_NOT_FINISHED",
+            Self::NVS_NOT_INITIALIZED => "ESP_ERR_NVS_NOT_INITIALIZED",
+            Self::NVS_NOT_FOUND => "ESP_ERR_NVS_NOT_FOUND",
+            Self::NVS_NOT_ENOUGH_SPACE => "ESP_ERR_NVS_NOT_ENOUGH_SPACE",
+            Self::NVS_INVALID_LENGTH => "ESP_ERR_NVS_INVALID_LENGTH",
+            Self::WIFI_NOT_INIT => "ESP_ERR_WIFI_NOT_INIT",
+            Self::WIFI_NOT_STARTED => "ESP_ERR_WIFI_NOT_STARTED",
+            Self::WIFI_CONN => "ESP_ERR_WIFI_CONN",
+            Self::WIFI_SSID => "ESP_ERR_WIFI_SSID",
+            Self::WIFI_PASSWORD => "ESP_ERR_WIFI_PASSWORD",
+            Self::WIFI_TIMEOUT => "ESP_ERR_WIFI_TIMEOUT",
+            Self::WIFI_WOULD_BLOCK => "ESP_ERR_WIFI_WOULD_BLOCK",
+            Self::WIFI_NOT_CONNECT => "ESP_ERR_WIFI_NOT_CONNECT",
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// Check if the code reports a condition that may clear up on its own
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            *self,
+            Self::NO_MEM
+                | Self::TIMEOUT
+                | Self::NOT_FINISHED
+                | Self::WIFI_CONN
+                | Self::WIFI_TIMEOUT
+                | Self::WIFI_WOULD_BLOCK
+                | Self::WIFI_NOT_CONNECT
+        )
+    }
+}
+
+impl fmt::Display for EspCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} (0x{:x})", name, self.0),
+            None => write!(f, "esp_err_t 0x{:x}", self.0),
+        }
+    }
+}
+
+impl std::error::Error for EspCode {}
+
+#[cfg(target_os = "espidf")]
+impl From<esp_idf_svc::sys::EspError> for EspCode {
+    fn from(e: esp_idf_svc::sys::EspError) -> Self {
+        Self(e.code())
+    }
+}
+
+/// A setting rejected by validation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// Dotted path of the setting, e.g. `telemetry.sample_interval_ms`
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+/// Errors reported by the drivers and tasks
+#[derive(Debug)]
+pub enum Error {
+    /// GPIO configuration or access failed
+    Gpio { context: String, code: EspCode },
+    /// An I2C transaction failed
+    I2c { context: String, code: EspCode },
+    /// A WiFi driver call failed
+    Wifi { context: String, code: EspCode },
+    /// Reading or writing NVS failed
+    Nvs { context: String, code: EspCode },
+    /// A UART driver call failed
+    Uart { context: String, code: EspCode },
+    /// An operation did not complete in time
+    Timeout { operation: String, timeout_ms: u32 },
+    /// A component was used before being initialized or started
+    NotInitialized(&'static str),
+    /// The operation is not possible in the current state
+    InvalidState(String),
+    /// An argument or reading is outside its valid range
+    OutOfRange { name: String, value: f64, min: f64, max: f64 },
+    /// A configuration value was rejected
+    InvalidArgument(String),
+    /// A circuit breaker is rejecting calls to a failing dependency
+    CircuitOpen { name: String, retry_in_ms: u64 },
+    /// Settings were rejected, one entry per field
+    InvalidFields(Vec<FieldError>),
+    Io(io::Error),
+}
+
+impl Error {
+    /// GPIO failure with the ESP-IDF code
+    pub fn gpio(context: impl Into<String>, code: impl Into<EspCode>) -> Self {
+        Self::Gpio { context: context.into(), code: code.into() }
+    }
+
+    /// I2C failure with the ESP-IDF code
+    pub fn i2c(context: impl Into<String>, code: impl Into<EspCode>) -> Self {
+        Self::I2c { context: context.into(), code: code.into() }
+    }
+
+    /// WiFi failure with the ESP-IDF code
+    pub fn wifi(context: impl Into<String>, code: impl Into<EspCode>) -> Self {
+        Self::Wifi { context: context.into(), code: code.into() }
+    }
+
+    /// NVS failure with the ESP-IDF code
+    pub fn nvs(context: impl Into<String>, code: impl Into<EspCode>) -> Self {
+        Self::Nvs { context: context.into(), code: code.into() }
+    }
+
+    /// UART failure with the ESP-IDF code
+    pub fn uart(context: impl Into<String>, code: impl Into<EspCode>) -> Self {
+        Self::Uart { context: context.into(), code: code.into() }
+    }
+
+    /// Timeout of a named operation
+    pub fn timeout(operation: impl Into<String>, timeout_ms: u32) -> Self {
+        Self::Timeout { operation: operation.into(), timeout_ms }
+    }
+
+    /// Value outside `[min, max]`
+    pub fn out_of_range(name: impl Into<String>, value: impl Into<f64>, min: impl Into<f64>, max: impl Into<f64>) -> Self {
+        Self::OutOfRange { name: name.into(), value: value.into(), min: min.into(), max: max.into() }
+    }
+
+    /// Rejected configuration, keeping the validation message
+    pub fn invalid_argument(e: impl fmt::Display) -> Self {
+        Self::InvalidArgument(e.to_string())
+    }
+
+    /// Get the ESP-IDF code of driver errors
+    pub fn esp_code(&self) -> Option<EspCode> {
+        match self {
+            Self::Gpio { code, .. }
+            | Self::I2c { code, .. }
+            | Self::Wifi { code, .. }
+            | Self::Nvs { code, .. }
+            | Self::Uart { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Check if retrying the operation might succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Timeout { .. } => true,
+            // A NACK or a busy bus is usually a glitch on the wire
+            Self::I2c { code, .. } => code.is_transient() || matches!(*code, EspCode::FAIL | EspCode::INVALID_STATE),
+            Self::Gpio { code, .. } | Self::Wifi { code, .. } | Self::Nvs { code, .. } | Self::Uart { code, .. } => {
+                code.is_transient()
+            }
+            Self::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted
+                    | io::ErrorKind::WouldBlock
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+            ),
+            // Retrying before the cool-down ends would only be rejected again
+            Self::CircuitOpen { .. } => false,
+            Self::NotInitialized(_)
+            | Self::InvalidState(_)
+            | Self::OutOfRange { .. }
+            | Self::InvalidArgument(_)
+            | Self::InvalidFields(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gpio { context, .. }
+            | Self::I2c { context, .. }
+            | Self::Wifi { context, .. }
+            | Self::Nvs { context, .. }
+            | Self::Uart { context, .. } => write!(f, "{} failed", context),
+            Self::Timeout { operation, timeout_ms } => write!(f, "{} timed out after {} ms", operation, timeout_ms),
+            Self::NotInitialized(component) => write!(f, "{} not initialized", component),
+            Self::InvalidState(message) | Self::InvalidArgument(message) => write!(f, "{}", message),
+            Self::OutOfRange { name, value, min, max } => {
+                write!(f, "{} value {} is out of range [{}, {}]", name, value, min, max)
+            }
+            Self::CircuitOpen { name, retry_in_ms } => {
+                write!(f, "{} circuit open, retry in {} ms", name, retry_in_ms)
+            }
+            Self::InvalidFields(fields) => {
+                write!(f, "Invalid settings: ")?;
+                for (i, field) in fields.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "; " };
+                    write!(f, "{}{}: {}", separator, field.field, field.message)?;
+                }
+                Ok(())
+            }
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Gpio { code, .. }
+            | Self::I2c { code, .. }
+            | Self::Wifi { code, .. }
+            | Self::Nvs { code, .. }
+            | Self::Uart { code, .. } => Some(code),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Errors that can say whether a retry might succeed
+pub trait Retryable {
+    fn is_retryable(&self) -> bool;
+}
+
+impl Retryable for Error {
+    fn is_retryable(&self) -> bool {
+        Error::is_retryable(self)
+    }
+}
+
+/// Classified by the first typed `Error` in the chain; untyped errors are retried
+impl Retryable for anyhow::Error {
+    fn is_retryable(&self) -> bool {
+        match self.chain().find_map(|cause| cause.downcast_ref::<Error>()) {
+            Some(e) => e.is_retryable(),
+            None => true,
+        }
+    }
+}
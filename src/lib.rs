@@ -0,0 +1,9 @@
+//! Drivers, tasks and utilities for the ESP32 template firmware
+//!
+//! Modules that talk to ESP-IDF only build for `target_os = "espidf"`; the rest
+//! are plain Rust and run in the host tests under `tests/`.
+
+pub mod error;
+pub mod peripherals;
+pub mod tasks;
+pub mod utils;
@@ -1,29 +1,45 @@
-use esp_idf_hal::gpio::{Gpio5, PinDriver, Pull};
-use anyhow::Result;
-use log::error;
+use serde::Serialize;
+
+#[cfg(target_os = "espidf")]
+use {
+    esp_idf_hal::gpio::{AnyIOPin, PinDriver, Pull},
+    log::error,
+    crate::error::{Error, Result},
+};
+
+/// Button state change reported to remote consumers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ButtonEvent {
+    Pressed,
+    Released,
+}
 
 /// Button Controller with debouncing
+#[cfg(target_os = "espidf")]
 pub struct ButtonController {
-    button: PinDriver<'static, Gpio5, esp_idf_hal::gpio::Input>,
+    button: PinDriver<'static, AnyIOPin, esp_idf_hal::gpio::Input>,
     last_state: bool,
     debounce_time: u32,
     last_press_time: u32,
+    held_since_ms: Option<u64>,
 }
 
+#[cfg(target_os = "espidf")]
 impl ButtonController {
     /// Create a new button controller
-    pub fn new(button_pin: Gpio5) -> Result<Self> {
+    pub fn new(button_pin: AnyIOPin) -> Result<Self> {
         let mut button = PinDriver::input(button_pin)
             .map_err(|e| {
                 error!("Failed to configure button pin: {:?}", e);
-                anyhow::anyhow!("Button pin configuration failed")
+                Error::gpio("Button pin configuration", e)
             })?;
 
         // Enable pull-up resistor
         button.set_pull(Pull::Up)
             .map_err(|e| {
                 error!("Failed to enable pull-up on button: {:?}", e);
-                anyhow::anyhow!("Button pull-up configuration failed")
+                Error::gpio("Button pull-up configuration", e)
             })?;
 
         Ok(Self {
@@ -31,6 +47,7 @@ impl ButtonController {
             last_state: false,
             debounce_time: 50, // 50ms debounce
             last_press_time: 0,
+            held_since_ms: None,
         })
     }
 
@@ -39,7 +56,7 @@ impl ButtonController {
         let current_state = self.button.is_low()
             .map_err(|e| {
                 error!("Failed to read button state: {:?}", e);
-                anyhow::anyhow!("Button state reading failed")
+                Error::gpio("Button state reading", e)
             })?;
 
         // Simple debouncing logic
@@ -57,7 +74,7 @@ impl ButtonController {
         self.button.is_low()
             .map_err(|e| {
                 error!("Failed to read button state: {:?}", e);
-                anyhow::anyhow!("Button state reading failed")
+                Error::gpio("Button state reading", e)
             })
     }
 
@@ -79,6 +96,19 @@ impl ButtonController {
         }
     }
 
+    /// Check if button has been held down continuously for at least `duration_ms`
+    pub fn is_held_for(&mut self, duration_ms: u32) -> Result<bool> {
+        let now_ms = esp_idf_hal::sys::esp_timer_get_time() as u64 / 1000;
+
+        if !self.is_pressed_raw()? {
+            self.held_since_ms = None;
+            return Ok(false);
+        }
+
+        let held_since = *self.held_since_ms.get_or_insert(now_ms);
+        Ok(now_ms - held_since >= duration_ms as u64)
+    }
+
     /// Set debounce time in milliseconds
     pub fn set_debounce_time(&mut self, time_ms: u32) {
         self.debounce_time = time_ms;
@@ -1,29 +1,33 @@
-use esp_idf_hal::gpio::{Gpio2, Gpio4, PinDriver};
-use anyhow::Result;
+use esp_idf_hal::gpio::{AnyOutputPin, PinDriver};
 use log::error;
 
+use crate::error::{Error, Result};
+
+/// Number of LEDs managed by the controller
+pub const LED_COUNT: u8 = 2;
+
 /// LED Controller for managing multiple LEDs
 pub struct LedController {
-    led1: PinDriver<'static, Gpio2, esp_idf_hal::gpio::Output>,
-    led2: PinDriver<'static, Gpio4, esp_idf_hal::gpio::Output>,
+    led1: PinDriver<'static, AnyOutputPin, esp_idf_hal::gpio::Output>,
+    led2: PinDriver<'static, AnyOutputPin, esp_idf_hal::gpio::Output>,
 }
 
 impl LedController {
     /// Create a new LED controller with two LEDs
     pub fn new(
-        led1_pin: Gpio2,
-        led2_pin: Gpio4,
+        led1_pin: AnyOutputPin,
+        led2_pin: AnyOutputPin,
     ) -> Result<Self> {
         let led1 = PinDriver::output(led1_pin)
             .map_err(|e| {
                 error!("Failed to configure LED1 pin: {:?}", e);
-                anyhow::anyhow!("LED1 pin configuration failed")
+                Error::gpio("LED1 pin configuration", e)
             })?;
 
         let led2 = PinDriver::output(led2_pin)
             .map_err(|e| {
                 error!("Failed to configure LED2 pin: {:?}", e);
-                anyhow::anyhow!("LED2 pin configuration failed")
+                Error::gpio("LED2 pin configuration", e)
             })?;
 
         Ok(Self { led1, led2 })
@@ -37,7 +41,7 @@ impl LedController {
         }
         .map_err(|e| {
             error!("Failed to set LED1 state: {:?}", e);
-            anyhow::anyhow!("LED1 state setting failed")
+            Error::gpio("LED1 state setting", e)
         })
     }
 
@@ -49,7 +53,7 @@ impl LedController {
         }
         .map_err(|e| {
             error!("Failed to set LED2 state: {:?}", e);
-            anyhow::anyhow!("LED2 state setting failed")
+            Error::gpio("LED2 state setting", e)
         })
     }
 
@@ -65,7 +69,7 @@ impl LedController {
         self.led1.toggle()
             .map_err(|e| {
                 error!("Failed to toggle LED1: {:?}", e);
-                anyhow::anyhow!("LED1 toggle failed")
+                Error::gpio("LED1 toggle", e)
             })
     }
 
@@ -74,7 +78,7 @@ impl LedController {
         self.led2.toggle()
             .map_err(|e| {
                 error!("Failed to toggle LED2: {:?}", e);
-                anyhow::anyhow!("LED2 toggle failed")
+                Error::gpio("LED2 toggle", e)
             })
     }
 
@@ -83,7 +87,7 @@ impl LedController {
         self.led1.is_high()
             .map_err(|e| {
                 error!("Failed to read LED1 state: {:?}", e);
-                anyhow::anyhow!("LED1 state reading failed")
+                Error::gpio("LED1 state reading", e)
             })
     }
 
@@ -92,7 +96,34 @@ impl LedController {
         self.led2.is_high()
             .map_err(|e| {
                 error!("Failed to read LED2 state: {:?}", e);
-                anyhow::anyhow!("LED2 state reading failed")
+                Error::gpio("LED2 state reading", e)
             })
     }
-} 
\ No newline at end of file
+
+    /// Set the state of an LED by its 1-based index
+    pub fn set_led(&mut self, id: u8, state: bool) -> Result<()> {
+        match id {
+            1 => self.set_led1(state),
+            2 => self.set_led2(state),
+            _ => Err(Error::out_of_range("LED", id, 1, LED_COUNT)),
+        }
+    }
+
+    /// Toggle an LED by its 1-based index
+    pub fn toggle_led(&mut self, id: u8) -> Result<()> {
+        match id {
+            1 => self.toggle_led1(),
+            2 => self.toggle_led2(),
+            _ => Err(Error::out_of_range("LED", id, 1, LED_COUNT)),
+        }
+    }
+
+    /// Get the state of an LED by its 1-based index
+    pub fn get_led_state(&self, id: u8) -> Result<bool> {
+        match id {
+            1 => self.get_led1_state(),
+            2 => self.get_led2_state(),
+            _ => Err(Error::out_of_range("LED", id, 1, LED_COUNT)),
+        }
+    }
+}
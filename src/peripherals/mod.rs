@@ -1,7 +1,11 @@
 // Peripheral drivers module
+#[cfg(target_os = "espidf")]
 pub mod led;
 pub mod button;
 
 // Re-export commonly used peripherals
+#[cfg(target_os = "espidf")]
 pub use led::LedController;
+pub use button::ButtonEvent;
+#[cfg(target_os = "espidf")]
 pub use button::ButtonController; 
\ No newline at end of file
@@ -0,0 +1,620 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use super::device_config::{field_patch, ConfigSection, ConfigStore};
+use crate::utils::crash_log::CrashEntry;
+use crate::utils::log_filter::{validate_module, LogLevel};
+use crate::utils::log_ring::{LogQuery, SharedLogRing};
+use crate::utils::nvs_storage::NvsStorage;
+
+/// Prompt printed before each command line
+pub const PROMPT: &str = "esp32> ";
+
+/// Longest command line accepted by the editor
+pub const MAX_LINE_LEN: usize = 256;
+
+/// Number of command lines kept in the history
+pub const HISTORY_LEN: usize = 16;
+
+/// Handler invoked with the arguments after the command name; returns the text to print
+pub type ConsoleHandler = Box<dyn FnMut(&[String]) -> Result<String> + Send>;
+
+struct ConsoleCommand {
+    help: String,
+    subcommands: Vec<String>,
+    handler: ConsoleHandler,
+}
+
+/// Parses command lines and dispatches them to registered commands
+///
+/// `help` is built in and lists the registered commands.
+pub struct Console {
+    commands: BTreeMap<String, ConsoleCommand>,
+}
+
+impl Console {
+    /// Create a console with only the `help` command
+    pub fn new() -> Self {
+        Self { commands: BTreeMap::new() }
+    }
+
+    /// Register a command, replacing any existing one with the same name
+    pub fn register<F>(&mut self, name: &str, help: &str, handler: F) -> &mut Self
+    where
+        F: FnMut(&[String]) -> Result<String> + Send + 'static,
+    {
+        self.register_with_subcommands(name, help, &[], handler)
+    }
+
+    /// Register a command whose first argument is one of `subcommands`, offered by tab completion
+    pub fn register_with_subcommands<F>(&mut self, name: &str, help: &str, subcommands: &[&str], handler: F) -> &mut Self
+    where
+        F: FnMut(&[String]) -> Result<String> + Send + 'static,
+    {
+        self.commands.insert(name.to_string(), ConsoleCommand {
+            help: help.to_string(),
+            subcommands: subcommands.iter().map(|s| s.to_string()).collect(),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Check if a command is registered
+    pub fn has_command(&self, name: &str) -> bool {
+        name == "help" || self.commands.contains_key(name)
+    }
+
+    /// Run a command line, returning the text to print
+    pub fn execute(&mut self, line: &str) -> Result<String> {
+        let args = tokenize(line)?;
+        let Some((name, args)) = args.split_first() else {
+            return Ok(String::new());
+        };
+
+        if name == "help" {
+            return self.help(args.first().map(String::as_str));
+        }
+        let command = self.commands.get_mut(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown command '{}', try 'help'", name))?;
+        (command.handler)(args)
+    }
+
+    /// Get the completions of the last word of `line`, in order
+    pub fn complete(&self, line: &str) -> Vec<String> {
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if line.is_empty() || line.ends_with(char::is_whitespace) {
+            words.push("");
+        }
+        let Some((word, previous)) = words.split_last() else {
+            return Vec::new();
+        };
+
+        let candidates: Vec<&str> = match previous {
+            [] => std::iter::once("help").chain(self.commands.keys().map(String::as_str)).collect(),
+            [command] => match self.commands.get(*command) {
+                Some(command) => command.subcommands.iter().map(String::as_str).collect(),
+                None if *command == "help" => self.commands.keys().map(String::as_str).collect(),
+                None => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+
+        let mut matches: Vec<String> = candidates.into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .map(str::to_string)
+            .collect();
+        matches.sort();
+        matches.dedup();
+        matches
+    }
+
+    fn help(&self, name: Option<&str>) -> Result<String> {
+        if let Some(name) = name {
+            let command = self.commands.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Unknown command '{}'", name))?;
+            return Ok(format!("{:<10} {}", name, command.help));
+        }
+
+        let mut text = format!("{:<10} {}", "help", "List commands, or describe one: help [command]");
+        for (name, command) in &self.commands {
+            text.push('\n');
+            text.push_str(&format!("{:<10} {}", name, command.help));
+        }
+        Ok(text)
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Split a command line into arguments
+///
+/// Arguments are separated by whitespace. Single or double quotes group words,
+/// and a backslash escapes the next character outside single quotes.
+pub fn tokenize(line: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                let escaped = chars.next().ok_or_else(|| anyhow::anyhow!("Trailing backslash"))?;
+                current.push(escaped);
+                in_word = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        return Err(anyhow::anyhow!("Unterminated quote"));
+    }
+    if in_word {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// Parse the argument at `index`, naming it `name` in errors
+pub fn parse_arg<T: FromStr>(args: &[String], index: usize, name: &str) -> Result<T> {
+    let raw = args.get(index).ok_or_else(|| anyhow::anyhow!("Missing {}", name))?;
+    raw.parse().map_err(|_| anyhow::anyhow!("Invalid {} '{}'", name, raw))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Escape {
+    None,
+    /// After ESC
+    Start,
+    /// Inside a control sequence, collecting its parameter
+    Sequence(String),
+}
+
+/// Line editor for a serial terminal
+///
+/// Bytes typed by the user are fed in one at a time; the echo and redraw
+/// sequences to send back are collected in an output buffer. Supports cursor
+/// movement, history (up/down), Ctrl-A/E/C/U and tab completion.
+pub struct LineEditor {
+    line: String,
+    cursor: usize,
+    history: VecDeque<String>,
+    history_index: Option<usize>,
+    /// The line being typed before browsing the history
+    draft: String,
+    escape: Escape,
+    last_was_cr: bool,
+    output: Vec<u8>,
+}
+
+impl LineEditor {
+    /// Create an editor with an empty history
+    pub fn new() -> Self {
+        Self {
+            line: String::new(),
+            cursor: 0,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            history_index: None,
+            draft: String::new(),
+            escape: Escape::None,
+            last_was_cr: false,
+            output: Vec::new(),
+        }
+    }
+
+    /// Get the line being edited
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Get the cursor position within the line
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Get the entered lines, oldest first
+    pub fn history(&self) -> &VecDeque<String> {
+        &self.history
+    }
+
+    /// Queue the prompt for output
+    pub fn prompt(&mut self) {
+        self.output.extend_from_slice(PROMPT.as_bytes());
+    }
+
+    /// Take the bytes to send to the terminal
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Process one input byte, returning the line once Enter is pressed
+    pub fn feed(&mut self, byte: u8, console: &Console) -> Option<String> {
+        let after_cr = std::mem::replace(&mut self.last_was_cr, byte == b'\r');
+
+        match std::mem::replace(&mut self.escape, Escape::None) {
+            Escape::Start => {
+                if byte == b'[' || byte == b'O' {
+                    self.escape = Escape::Sequence(String::new());
+                }
+                return None;
+            }
+            Escape::Sequence(mut param) => {
+                if byte.is_ascii_digit() || byte == b';' {
+                    param.push(byte as char);
+                    self.escape = Escape::Sequence(param);
+                } else {
+                    self.handle_sequence(&param, byte);
+                }
+                return None;
+            }
+            Escape::None => {}
+        }
+
+        match byte {
+            b'\r' | b'\n' => {
+                if byte == b'\n' && after_cr {
+                    return None;
+                }
+                return Some(self.submit());
+            }
+            0x1b => self.escape = Escape::Start,
+            0x01 => self.move_to(0),
+            0x05 => self.move_to(self.line.len()),
+            0x03 => {
+                self.output.extend_from_slice(b"^C\r\n");
+                self.reset();
+                self.prompt();
+            }
+            0x15 => {
+                self.line.clear();
+                self.cursor = 0;
+                self.refresh();
+            }
+            0x08 | 0x7f if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.line.remove(self.cursor);
+                self.refresh();
+            }
+            b'\t' => self.complete(console),
+            0x20..=0x7e => self.insert(&(byte as char).to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_sequence(&mut self, param: &str, byte: u8) {
+        match (byte, param) {
+            (b'A', _) => self.history_previous(),
+            (b'B', _) => self.history_next(),
+            (b'C', _) => self.move_to((self.cursor + 1).min(self.line.len())),
+            (b'D', _) => self.move_to(self.cursor.saturating_sub(1)),
+            (b'H', _) | (b'~', "1" | "7") => self.move_to(0),
+            (b'F', _) | (b'~', "4" | "8") => self.move_to(self.line.len()),
+            (b'~', "3") if self.cursor < self.line.len() => {
+                self.line.remove(self.cursor);
+                self.refresh();
+            }
+            _ => {}
+        }
+    }
+
+    fn submit(&mut self) -> String {
+        self.output.extend_from_slice(b"\r\n");
+        let line = std::mem::take(&mut self.line);
+        if !line.trim().is_empty() && self.history.back() != Some(&line) {
+            if self.history.len() == HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(line.clone());
+        }
+        self.reset();
+        line
+    }
+
+    fn reset(&mut self) {
+        self.line.clear();
+        self.cursor = 0;
+        self.history_index = None;
+        self.draft.clear();
+    }
+
+    fn insert(&mut self, text: &str) {
+        if self.line.len() + text.len() > MAX_LINE_LEN {
+            self.output.push(0x07);
+            return;
+        }
+        let at_end = self.cursor == self.line.len();
+        self.line.insert_str(self.cursor, text);
+        self.cursor += text.len();
+        if at_end {
+            self.output.extend_from_slice(text.as_bytes());
+        } else {
+            self.refresh();
+        }
+    }
+
+    fn move_to(&mut self, cursor: usize) {
+        if cursor != self.cursor {
+            self.cursor = cursor;
+            self.refresh();
+        }
+    }
+
+    fn history_previous(&mut self) {
+        let index = match self.history_index {
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.line.clone();
+                self.history.len() - 1
+            }
+            Some(index) => index.saturating_sub(1),
+        };
+        self.show_history(Some(index));
+    }
+
+    fn history_next(&mut self) {
+        match self.history_index {
+            None => {}
+            Some(index) if index + 1 < self.history.len() => self.show_history(Some(index + 1)),
+            Some(_) => self.show_history(None),
+        }
+    }
+
+    fn show_history(&mut self, index: Option<usize>) {
+        self.history_index = index;
+        self.line = match index {
+            Some(index) => self.history[index].clone(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.cursor = self.line.len();
+        self.refresh();
+    }
+
+    fn complete(&mut self, console: &Console) {
+        // Only the word before the cursor at the end of the line is completed
+        if self.cursor != self.line.len() {
+            self.output.push(0x07);
+            return;
+        }
+        let word_start = self.line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &self.line[word_start..];
+        let candidates = console.complete(&self.line);
+
+        match candidates.as_slice() {
+            [] => self.output.push(0x07),
+            [only] => {
+                let rest = format!("{} ", &only[word.len()..]);
+                self.insert(&rest);
+            }
+            _ => {
+                let prefix = common_prefix(&candidates);
+                if prefix.len() > word.len() {
+                    let rest = prefix[word.len()..].to_string();
+                    self.insert(&rest);
+                } else {
+                    self.output.extend_from_slice(b"\r\n");
+                    self.output.extend_from_slice(candidates.join("  ").as_bytes());
+                    self.output.extend_from_slice(b"\r\n");
+                    self.refresh();
+                }
+            }
+        }
+    }
+
+    /// Redraw the prompt and line, then put the cursor back
+    fn refresh(&mut self) {
+        self.output.push(b'\r');
+        self.output.extend_from_slice(PROMPT.as_bytes());
+        self.output.extend_from_slice(self.line.as_bytes());
+        self.output.extend_from_slice(b"\x1b[K");
+        let back = self.line.len() - self.cursor;
+        if back > 0 {
+            self.output.extend_from_slice(format!("\x1b[{}D", back).as_bytes());
+        }
+    }
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the longest prefix shared by all candidates
+fn common_prefix(candidates: &[String]) -> &str {
+    let first = &candidates[0];
+    let len = candidates[1..].iter().fold(first.len(), |len, candidate| {
+        first.bytes().zip(candidate.bytes()).take(len).take_while(|(a, b)| a == b).count()
+    });
+    &first[..len]
+}
+
+/// Build a configuration patch setting one dotted field, e.g. `telemetry.sample_interval_ms`
+///
+/// The value is read as JSON when possible and as a plain string otherwise.
+pub fn config_field_patch(field: &str, raw: &str) -> Value {
+    let value = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+    field_patch(field, value)
+}
+
+/// Register the `config` command over a shared configuration store
+///
+/// `reset` performs the device's factory reset for `config reset confirm`.
+pub fn register_config_command<S, F>(console: &mut Console, store: Arc<Mutex<ConfigStore<S>>>, mut reset: F)
+where
+    S: NvsStorage + 'static,
+    F: FnMut() -> Result<()> + Send + 'static,
+{
+    const USAGE: &str = "config get [field] | set <field> <value> | import <json> | reset confirm";
+
+    console.register_with_subcommands("config", USAGE, &["get", "set", "import", "reset"], move |args| {
+        let subcommand = args.first().map(String::as_str).unwrap_or("get");
+        match subcommand {
+            "get" => {
+                let exported = store.lock().unwrap().export();
+                let value = match args.get(1) {
+                    Some(field) => exported.pointer(&format!("/{}", field.replace('.', "/")))
+                        .cloned()
+                        .ok_or_else(|| anyhow::anyhow!("Unknown setting '{}'", field))?,
+                    None => exported,
+                };
+                Ok(serde_json::to_string_pretty(&value)?)
+            }
+            "set" => {
+                let field: String = parse_arg(args, 1, "field")?;
+                let raw: String = parse_arg(args, 2, "value")?;
+                let changed = store.lock().unwrap().import(&config_field_patch(&field, &raw))?;
+                Ok(describe_changes(&changed))
+            }
+            "import" => {
+                let raw: String = parse_arg(args, 1, "JSON document")?;
+                let patch: Value = serde_json::from_str(&raw)
+                    .map_err(|e| anyhow::anyhow!("Invalid JSON: {}", e))?;
+                let changed = store.lock().unwrap().import(&patch)?;
+                Ok(describe_changes(&changed))
+            }
+            "reset" if args.get(1).map(String::as_str) == Some("confirm") => {
+                reset()?;
+                Ok("Settings erased, rebooting".to_string())
+            }
+            "reset" => Ok("This erases all settings; run 'config reset confirm' to proceed".to_string()),
+            other => Err(anyhow::anyhow!("Unknown subcommand '{}', usage: {}", other, USAGE)),
+        }
+    });
+}
+
+/// Register the `log` command, changing levels through the configuration store
+///
+/// Levels are persisted; the application applies them when notified of
+/// `ConfigSection::Logging` changes.
+pub fn register_log_command<S: NvsStorage + 'static>(console: &mut Console, store: Arc<Mutex<ConfigStore<S>>>) {
+    const USAGE: &str = "log [show] | level <level> | level <module> <level|default>";
+
+    console.register_with_subcommands("log", USAGE, &["show", "level"], move |args| {
+        match args.first().map(String::as_str).unwrap_or("show") {
+            "show" => {
+                let logging = store.lock().unwrap().config().logging.clone();
+                let mut text = format!("default: {}", logging.level);
+                for (module, level) in &logging.modules {
+                    text.push_str(&format!("\n{}: {}", module, level));
+                }
+                Ok(text)
+            }
+            "level" => {
+                let patch = match args.len() {
+                    2 => field_patch("logging.level", Value::String(parse_arg::<LogLevel>(args, 1, "level")?.to_string())),
+                    3 => {
+                        let module = &args[1];
+                        validate_module(module)?;
+                        let level = match args[2].as_str() {
+                            "default" => Value::Null,
+                            _ => Value::String(parse_arg::<LogLevel>(args, 2, "level")?.to_string()),
+                        };
+                        field_patch(&format!("logging.modules.{}", module), level)
+                    }
+                    _ => return Err(anyhow::anyhow!("Usage: {}", USAGE)),
+                };
+                let changed = store.lock().unwrap().import(&patch)?;
+                Ok(describe_changes(&changed))
+            }
+            other => Err(anyhow::anyhow!("Unknown subcommand '{}', usage: {}", other, USAGE)),
+        }
+    });
+}
+
+/// Register the `logs` command, printing records from the log ring
+///
+/// Filters are `key=value` arguments as accepted by `LogQuery::from_pairs`.
+pub fn register_logs_command(console: &mut Console, ring: SharedLogRing) {
+    const USAGE: &str = "logs [since=<seq>] [level=<level>] [module=<path>] [limit=<n>] [previous_boot=true] | clear";
+
+    console.register_with_subcommands("logs", USAGE, &["clear"], move |args| {
+        if args.first().map(String::as_str) == Some("clear") {
+            ring.lock().unwrap().clear();
+            return Ok("Log cleared".to_string());
+        }
+
+        let pairs = args.iter()
+            .map(|arg| arg.split_once('=').ok_or_else(|| anyhow::anyhow!("Expected key=value, usage: {}", USAGE)))
+            .collect::<Result<Vec<_>>>()?;
+        let query = LogQuery::from_pairs(pairs)?;
+        let page = ring.lock().unwrap().query(&query);
+        if page.entries.is_empty() {
+            return Ok("No log records".to_string());
+        }
+
+        let lines: Vec<String> = page.entries.iter()
+            .map(|entry| format!("{} {} ({}) {}: {}", entry.seq, entry.level, entry.uptime_ms, entry.target, entry.message))
+            .collect();
+        Ok(lines.join("\n"))
+    });
+}
+
+/// Format crash entries for the `crashes` command, newest first as given
+pub fn format_crashes(entries: &[CrashEntry], total_crashes: u32) -> String {
+    if entries.is_empty() {
+        return format!("No crashes stored ({} recorded in total)", total_crashes);
+    }
+
+    let mut lines = Vec::new();
+    for entry in entries {
+        let reason = serde_json::to_value(entry.reset_reason)
+            .ok()
+            .and_then(|name| name.as_str().map(str::to_string))
+            .unwrap_or_default();
+        lines.push(format!("#{} {}, firmware {}", entry.sequence, reason, entry.firmware_version));
+        if let Some(panic) = &entry.panic {
+            lines.push(format!(
+                "  panicked at {}:{}:{} in task '{}' after {} ms: {}",
+                panic.file, panic.line, panic.column, panic.task, panic.uptime_ms, panic.message
+            ));
+        }
+        if let Some(core_dump) = &entry.core_dump {
+            let backtrace: Vec<String> = core_dump.backtrace.iter().map(|pc| format!("0x{:08x}", pc)).collect();
+            lines.push(format!(
+                "  exception in task '{}' at 0x{:08x}, backtrace: {}{}",
+                core_dump.task,
+                core_dump.program_counter,
+                backtrace.join(" "),
+                if core_dump.backtrace_corrupted { " (corrupted)" } else { "" }
+            ));
+        }
+    }
+    lines.push(format!("{} crash(es) recorded in total", total_crashes));
+    lines.join("\n")
+}
+
+fn describe_changes(changed: &[ConfigSection]) -> String {
+    if changed.is_empty() {
+        return "No changes".to_string();
+    }
+    let names: Vec<String> = changed.iter()
+        .filter_map(|section| serde_json::to_value(section).ok())
+        .filter_map(|name| name.as_str().map(str::to_string))
+        .collect();
+    format!("Changed: {}", names.join(", "))
+}
@@ -0,0 +1,90 @@
+use esp_idf_svc::sys::{self, esp};
+use log::{info, warn};
+use std::thread::JoinHandle;
+
+use super::console::{Console, LineEditor};
+use crate::error::{Error, Result};
+use crate::utils::time_utils::ms_to_ticks;
+
+/// UART shared by the console and the log output
+const CONSOLE_UART: sys::uart_port_t = sys::CONFIG_ESP_CONSOLE_UART_NUM as sys::uart_port_t;
+
+/// Size of the UART receive buffer
+const RX_BUFFER_LEN: i32 = 512;
+
+/// How long a read waits for input
+const READ_TIMEOUT_MS: u32 = 100;
+
+/// Interactive command shell on the console UART
+pub struct ConsoleTask;
+
+impl ConsoleTask {
+    /// Install the UART driver and serve `console` on a background thread
+    pub fn spawn(mut console: Console) -> Result<JoinHandle<()>> {
+        // SAFETY: the console UART is only read by this task
+        esp!(unsafe {
+            sys::uart_driver_install(CONSOLE_UART, RX_BUFFER_LEN, 0, 0, std::ptr::null_mut(), 0)
+        })
+        .map_err(|e| Error::uart("Console UART driver install", e))?;
+
+        let handle = std::thread::Builder::new()
+            .name("console".to_string())
+            .stack_size(8192)
+            .spawn(move || {
+                let mut editor = LineEditor::new();
+                editor.prompt();
+                let mut buf = [0u8; 64];
+
+                loop {
+                    write(&editor.take_output());
+
+                    // SAFETY: `buf` outlives the call and its length is passed along
+                    let read = unsafe {
+                        sys::uart_read_bytes(
+                            CONSOLE_UART,
+                            buf.as_mut_ptr().cast(),
+                            buf.len() as u32,
+                            ms_to_ticks(READ_TIMEOUT_MS),
+                        )
+                    };
+                    if read <= 0 {
+                        continue;
+                    }
+
+                    for &byte in &buf[..read as usize] {
+                        let Some(line) = editor.feed(byte, &console) else {
+                            continue;
+                        };
+                        write(&editor.take_output());
+
+                        let output = match console.execute(&line) {
+                            Ok(output) => output,
+                            Err(e) => {
+                                warn!("Console command '{}' failed: {:?}", line, e);
+                                format!("error: {}", e)
+                            }
+                        };
+                        if !output.is_empty() {
+                            write(output.replace('\n', "\r\n").as_bytes());
+                            write(b"\r\n");
+                        }
+                        editor.prompt();
+                    }
+                }
+            })?;
+
+        info!("Console ready on UART{}", CONSOLE_UART);
+        Ok(handle)
+    }
+}
+
+/// Write to the console UART, blocking until queued
+fn write(bytes: &[u8]) {
+    if bytes.is_empty() {
+        return;
+    }
+    // SAFETY: the driver copies `bytes` before returning
+    unsafe {
+        sys::uart_write_bytes(CONSOLE_UART, bytes.as_ptr().cast(), bytes.len());
+    }
+}
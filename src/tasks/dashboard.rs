@@ -0,0 +1,89 @@
+use log::info;
+use std::borrow::Cow;
+
+use super::http_api::{ApiRouter, HttpMethod, HttpResponse, RouteRequest};
+
+/// A gzipped static file embedded at build time from `web/`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebAsset {
+    /// URL path, e.g. `/app.js`
+    pub path: &'static str,
+    pub content_type: &'static str,
+    /// Quoted ETag derived from the uncompressed content
+    pub etag: &'static str,
+    /// Gzip-compressed content
+    pub body: &'static [u8],
+}
+
+/// Page served for `/`
+pub const INDEX_PATH: &str = "/index.html";
+
+/// Browsers revalidate with the ETag before reusing a cached asset
+const CACHE_CONTROL: &str = "no-cache";
+
+/// Find the asset for a URL path, mapping `/` to the index page
+pub fn find_asset<'a>(assets: &'a [WebAsset], path: &str) -> Option<&'a WebAsset> {
+    let path = if path == "/" { INDEX_PATH } else { path };
+    assets.iter().find(|asset| asset.path == path)
+}
+
+/// Check if an `If-None-Match` header matches an ETag
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Check if an `Accept-Encoding` header allows gzip
+fn accepts_gzip(accept_encoding: &str) -> bool {
+    accept_encoding.split(',').any(|coding| {
+        let mut parts = coding.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        let rejected = parts.any(|param| {
+            param.trim().strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()) == Some(0.0)
+        });
+        (name.eq_ignore_ascii_case("gzip") || name == "*") && !rejected
+    })
+}
+
+/// Serve an asset, honouring `If-None-Match` and `Accept-Encoding`
+pub fn serve_asset(asset: &WebAsset, request: &RouteRequest) -> HttpResponse {
+    if request.header("If-None-Match").is_some_and(|tags| etag_matches(tags, asset.etag)) {
+        return HttpResponse {
+            status: 304,
+            content_type: asset.content_type,
+            headers: Vec::new(),
+            body: Cow::Borrowed(&[]),
+        }
+        .with_header("ETag", asset.etag)
+        .with_header("Cache-Control", CACHE_CONTROL);
+    }
+
+    // Assets are only stored compressed
+    if !request.header("Accept-Encoding").is_some_and(accepts_gzip) {
+        return HttpResponse::error(406, "Client must accept gzip encoding");
+    }
+
+    HttpResponse {
+        status: 200,
+        content_type: asset.content_type,
+        headers: Vec::new(),
+        body: Cow::Borrowed(asset.body),
+    }
+    .with_header("Content-Encoding", "gzip")
+    .with_header("ETag", asset.etag)
+    .with_header("Cache-Control", CACHE_CONTROL)
+}
+
+/// Register a GET route for each asset, plus `/` for the index page
+pub fn register_dashboard_routes(router: &mut ApiRouter, assets: &'static [WebAsset]) {
+    for asset in assets {
+        router.route(HttpMethod::Get, asset.path, move |request| Ok(serve_asset(asset, request)));
+    }
+
+    if let Some(index) = find_asset(assets, "/") {
+        router.route(HttpMethod::Get, "/", move |request| Ok(serve_asset(index, request)));
+    }
+
+    info!("Dashboard serving {} embedded asset(s)", assets.len());
+}
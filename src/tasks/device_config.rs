@@ -0,0 +1,750 @@
+use log::{info, warn, error};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::error::{Error, FieldError, Result};
+use crate::tasks::mdns_config::MdnsConfig;
+use crate::tasks::network_config::{validate_hostname, NetworkConfig};
+use crate::tasks::mqtt_commands::{MAX_SAMPLE_INTERVAL_MS, MIN_SAMPLE_INTERVAL_MS};
+use crate::tasks::mqtt_task::QoS;
+use crate::tasks::sntp_config::{SntpConfig, MAX_SNTP_SERVERS, MAX_SYNC_INTERVAL_MS, MIN_SYNC_INTERVAL_MS};
+use crate::utils::error_handler::validate_range;
+use crate::utils::log_filter::{validate_module, LogLevel};
+use crate::utils::nvs_storage::NvsStorage;
+use crate::utils::wall_clock::PosixTz;
+
+/// NVS namespace of the device configuration
+pub const NVS_NAMESPACE: &str = "device_cfg";
+
+/// NVS key of the configuration document
+const NVS_CONFIG: &str = "config";
+
+/// Current configuration schema version
+pub const CONFIG_VERSION: u32 = 1;
+
+pub use chip::MAX_GPIO;
+
+/// GPIO layout of the chip selected by the `esp32*` feature (ESP32 by default)
+#[cfg(not(any(feature = "esp32s2", feature = "esp32s3", feature = "esp32c3")))]
+mod chip {
+    /// Highest GPIO number
+    pub const MAX_GPIO: u8 = 39;
+    /// Pads without a GPIO
+    pub const MISSING: &[u8] = &[20, 24, 28, 29, 30, 31];
+    /// SPI flash pins (6-11) and the UART0 console (1, 3)
+    pub const RESERVED: &[u8] = &[1, 3, 6, 7, 8, 9, 10, 11];
+    /// Pins without an output driver
+    pub const INPUT_ONLY: &[u8] = &[34, 35, 36, 37, 38, 39];
+}
+
+#[cfg(any(feature = "esp32s2", feature = "esp32s3"))]
+mod chip {
+    pub const MAX_GPIO: u8 = if cfg!(feature = "esp32s3") { 48 } else { 46 };
+    pub const MISSING: &[u8] = &[22, 23, 24, 25];
+    /// SPI flash and PSRAM pins (26-32) and the UART0 console (43, 44)
+    pub const RESERVED: &[u8] = &[26, 27, 28, 29, 30, 31, 32, 43, 44];
+    pub const INPUT_ONLY: &[u8] = if cfg!(feature = "esp32s3") { &[] } else { &[46] };
+}
+
+#[cfg(feature = "esp32c3")]
+mod chip {
+    pub const MAX_GPIO: u8 = 21;
+    pub const MISSING: &[u8] = &[];
+    /// SPI flash pins (12-17) and the UART0 console (20, 21)
+    pub const RESERVED: &[u8] = &[12, 13, 14, 15, 16, 17, 20, 21];
+    pub const INPUT_ONLY: &[u8] = &[];
+}
+
+/// Longest MQTT client ID every broker must accept
+const MAX_CLIENT_ID_LEN: usize = 23;
+
+/// Placeholder replacing secrets in exported configurations
+pub const REDACTED: &str = "********";
+
+/// Settings replaced by `REDACTED` on export
+pub const SECRET_FIELDS: &[&str] = &["mqtt.password"];
+
+/// Settings holding a map with arbitrary keys
+const MAP_FIELDS: &[&str] = &["logging.modules"];
+
+/// Upgrades a stored configuration document by one schema version
+pub type Migration = fn(&mut Value) -> Result<()>;
+
+/// Migrations in order, the first upgrading from version 1
+///
+/// When changing the schema, bump `CONFIG_VERSION` and append the step that
+/// rewrites the previous layout. Fields added with a default need no migration.
+pub const MIGRATIONS: &[Migration] = &[];
+
+const _: () = assert!(MIGRATIONS.len() as u32 + 1 == CONFIG_VERSION);
+
+/// GPIO assignments
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PinConfig {
+    pub led1: u8,
+    pub led2: u8,
+    pub button: u8,
+}
+
+impl Default for PinConfig {
+    fn default() -> Self {
+        Self { led1: 2, led2: 4, button: 5 }
+    }
+}
+
+/// Record an integer setting outside `min..=max`, compared without conversion to f32
+fn range<T: PartialOrd + Into<f64>>(errors: &mut Vec<(&'static str, Error)>, value: T, min: T, max: T, name: &'static str) {
+    if value < min || value > max {
+        errors.push((name, Error::out_of_range(name, value, min, max)));
+    }
+}
+
+/// Check that a GPIO exists and is free to drive (`output`) or read on this chip
+pub fn validate_pin(pin: u8, output: bool) -> Result<()> {
+    validate_range(pin as f32, 0.0, MAX_GPIO as f32, "GPIO")?;
+    if chip::MISSING.contains(&pin) {
+        return Err(Error::InvalidArgument(format!("GPIO{} does not exist on this chip", pin)));
+    }
+    if chip::RESERVED.contains(&pin) {
+        return Err(Error::InvalidArgument(format!("GPIO{} is reserved for flash or the serial console", pin)));
+    }
+    if output && chip::INPUT_ONLY.contains(&pin) {
+        return Err(Error::InvalidArgument(format!("GPIO{} is input-only", pin)));
+    }
+    Ok(())
+}
+
+/// Button timing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ButtonConfig {
+    pub debounce_ms: u32,
+    /// How long the button must be held to enter provisioning mode
+    pub provisioning_hold_ms: u32,
+}
+
+impl Default for ButtonConfig {
+    fn default() -> Self {
+        Self { debounce_ms: 50, provisioning_hold_ms: 5000 }
+    }
+}
+
+/// Boot-time WiFi connection attempts and backoff; networks live in `CredentialStore`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WifiConfig {
+    pub connect_attempts: u32,
+    pub retry_delay_ms: u32,
+    pub retry_max_delay_ms: u32,
+}
+
+impl Default for WifiConfig {
+    fn default() -> Self {
+        Self { connect_attempts: 4, retry_delay_ms: 2000, retry_max_delay_ms: 10_000 }
+    }
+}
+
+/// MQTT broker connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub broker_url: String,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Topics are `<topic_prefix>/<client_id>/...`
+    pub topic_prefix: String,
+    /// QoS level (0-2) of telemetry and button messages
+    pub qos: u8,
+    /// Publish telemetry as retained messages
+    pub retain: bool,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            broker_url: "mqtt://broker.local:1883".to_string(),
+            client_id: "esp32-template".to_string(),
+            username: None,
+            password: None,
+            topic_prefix: "devices".to_string(),
+            qos: 1,
+            retain: false,
+        }
+    }
+}
+
+/// Sensor sampling
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub sample_interval_ms: u32,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self { sample_interval_ms: 10_000 }
+    }
+}
+
+/// Sensor levels that are logged as warnings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    pub high_temperature_c: f32,
+    pub low_humidity_pct: f32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self { high_temperature_c: 30.0, low_humidity_pct: 20.0 }
+    }
+}
+
+/// Log verbosity, applied at runtime
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub level: LogLevel,
+    /// Overrides keyed by module path, e.g. `tasks::wifi_task`
+    pub modules: BTreeMap<String, LogLevel>,
+}
+
+/// Top-level section of the device configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSection {
+    Pins,
+    Button,
+    Wifi,
+    Network,
+    Mqtt,
+    Telemetry,
+    Thresholds,
+    Logging,
+    Mdns,
+    Sntp,
+}
+
+/// Runtime settings of the device, persisted in NVS
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceConfig {
+    pub pins: PinConfig,
+    pub button: ButtonConfig,
+    pub wifi: WifiConfig,
+    pub network: NetworkConfig,
+    pub mqtt: MqttSettings,
+    pub telemetry: TelemetryConfig,
+    pub thresholds: Thresholds,
+    pub logging: LoggingConfig,
+    pub mdns: MdnsConfig,
+    pub sntp: SntpConfig,
+}
+
+impl DeviceConfig {
+    /// Check every setting against its allowed range, reporting the first problem
+    pub fn validate(&self) -> Result<()> {
+        match self.check().into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Check every setting, reporting each rejected field
+    pub fn field_errors(&self) -> Vec<FieldError> {
+        self.check()
+            .into_iter()
+            .map(|(field, e)| FieldError::new(field, e.to_string()))
+            .collect()
+    }
+
+    fn check(&self) -> Vec<(&'static str, Error)> {
+        let mut errors = Vec::new();
+
+        range(&mut errors, self.button.debounce_ms, 0, 1000, "button.debounce_ms");
+        range(&mut errors, self.button.provisioning_hold_ms, 1000, 30_000, "button.provisioning_hold_ms");
+
+        range(&mut errors, self.wifi.connect_attempts, 1, 20, "wifi.connect_attempts");
+        range(&mut errors, self.wifi.retry_delay_ms, 100, 60_000, "wifi.retry_delay_ms");
+        range(&mut errors, self.wifi.retry_max_delay_ms, self.wifi.retry_delay_ms, 300_000, "wifi.retry_max_delay_ms");
+        range(&mut errors, self.network.dhcp_timeout_ms, 1000, 120_000, "network.dhcp_timeout_ms");
+
+        range(
+            &mut errors,
+            self.telemetry.sample_interval_ms,
+            MIN_SAMPLE_INTERVAL_MS,
+            MAX_SAMPLE_INTERVAL_MS,
+            "telemetry.sample_interval_ms",
+        );
+
+        let thresholds = [
+            (self.thresholds.high_temperature_c, -40.0, 125.0, "thresholds.high_temperature_c"),
+            (self.thresholds.low_humidity_pct, 0.0, 100.0, "thresholds.low_humidity_pct"),
+        ];
+        for (value, min, max, name) in thresholds {
+            if let Err(e) = validate_range(value, min, max, name) {
+                errors.push((name, e));
+            }
+        }
+
+        range(&mut errors, self.sntp.sync_interval_ms, MIN_SYNC_INTERVAL_MS, MAX_SYNC_INTERVAL_MS, "sntp.sync_interval_ms");
+
+        let pins = [("pins.led1", self.pins.led1), ("pins.led2", self.pins.led2), ("pins.button", self.pins.button)];
+        for (name, pin) in pins {
+            if let Err(e) = validate_pin(pin, name != "pins.button") {
+                errors.push((name, e));
+            }
+        }
+        for (i, (name, pin)) in pins.iter().enumerate() {
+            if let Some((other, _)) = pins[i + 1..].iter().find(|(_, p)| p == pin) {
+                let message = format!("{} and {} both use GPIO{}", name, other, pin);
+                errors.push((*other, Error::InvalidArgument(message)));
+            }
+        }
+
+        if let Err(e) = validate_hostname(&self.network.hostname) {
+            errors.push(("network.hostname", Error::invalid_argument(e)));
+        }
+        if let Some(settings) = self.network.static_settings() {
+            if let Err(e) = settings.validate() {
+                errors.push(("network", Error::invalid_argument(e)));
+            }
+        }
+
+        let url = &self.mqtt.broker_url;
+        let host = url.strip_prefix("mqtt://").or_else(|| url.strip_prefix("mqtts://"));
+        if !matches!(host, Some(host) if !host.is_empty()) {
+            let message = format!("mqtt.broker_url '{}' must start with mqtt:// or mqtts://", url);
+            errors.push(("mqtt.broker_url", Error::InvalidArgument(message)));
+        }
+        let client_id = &self.mqtt.client_id;
+        if client_id.is_empty() || client_id.len() > MAX_CLIENT_ID_LEN || !client_id.is_ascii() {
+            let message = format!("mqtt.client_id must be 1-{} ASCII characters", MAX_CLIENT_ID_LEN);
+            errors.push(("mqtt.client_id", Error::InvalidArgument(message)));
+        }
+        let prefix = &self.mqtt.topic_prefix;
+        if prefix.is_empty() || prefix.starts_with('/') || prefix.ends_with('/') || prefix.contains(['+', '#']) {
+            let message = format!("mqtt.topic_prefix '{}' must be a topic without wildcards or outer '/'", prefix);
+            errors.push(("mqtt.topic_prefix", Error::InvalidArgument(message)));
+        }
+        if QoS::from_level(self.mqtt.qos).is_none() {
+            errors.push(("mqtt.qos", Error::out_of_range("mqtt.qos", self.mqtt.qos, 0, 2)));
+        }
+
+        for module in self.logging.modules.keys() {
+            if let Err(e) = validate_module(module) {
+                errors.push(("logging.modules", e));
+            }
+        }
+
+        if let Err(e) = self.mdns.validate() {
+            errors.push(("mdns", Error::invalid_argument(e)));
+        }
+
+        if !self.sntp.servers_valid() {
+            let message = format!("sntp.servers must list 1-{} server names", MAX_SNTP_SERVERS);
+            errors.push(("sntp.servers", Error::InvalidArgument(message)));
+        }
+        if let Err(e) = PosixTz::parse(&self.sntp.time_zone) {
+            errors.push(("sntp.time_zone", Error::invalid_argument(e)));
+        }
+
+        errors
+    }
+
+    /// Get the sections that differ from `other`
+    pub fn changed_sections(&self, other: &DeviceConfig) -> Vec<ConfigSection> {
+        let mut sections = Vec::new();
+        if self.pins != other.pins {
+            sections.push(ConfigSection::Pins);
+        }
+        if self.button != other.button {
+            sections.push(ConfigSection::Button);
+        }
+        if self.wifi != other.wifi {
+            sections.push(ConfigSection::Wifi);
+        }
+        if self.network != other.network {
+            sections.push(ConfigSection::Network);
+        }
+        if self.mqtt != other.mqtt {
+            sections.push(ConfigSection::Mqtt);
+        }
+        if self.telemetry != other.telemetry {
+            sections.push(ConfigSection::Telemetry);
+        }
+        if self.thresholds != other.thresholds {
+            sections.push(ConfigSection::Thresholds);
+        }
+        if self.logging != other.logging {
+            sections.push(ConfigSection::Logging);
+        }
+        if self.mdns != other.mdns {
+            sections.push(ConfigSection::Mdns);
+        }
+        if self.sntp != other.sntp {
+            sections.push(ConfigSection::Sntp);
+        }
+        sections
+    }
+
+    /// Serialize into a versioned document as stored in NVS
+    pub fn to_document(&self) -> Value {
+        let mut document = serde_json::to_value(self).expect("DeviceConfig serializes to JSON");
+        document["version"] = CONFIG_VERSION.into();
+        document
+    }
+
+    /// Parse a stored document, upgrading it to the current schema first
+    ///
+    /// Documents from newer firmware are read as far as this schema allows.
+    pub fn from_document(mut document: Value) -> Result<Self> {
+        migrate(&mut document, MIGRATIONS)?;
+        if let Value::Object(fields) = &mut document {
+            fields.remove("version");
+        }
+        serde_json::from_value(document)
+            .map_err(|e| Error::InvalidArgument(format!("Malformed configuration: {}", e)))
+    }
+
+    /// Serialize into a versioned document for other devices, with secrets redacted
+    pub fn export(&self) -> Value {
+        let mut document = self.to_document();
+        for field in SECRET_FIELDS {
+            if let Some(value) = document.pointer_mut(&pointer(field)) {
+                if !value.is_null() {
+                    *value = REDACTED.into();
+                }
+            }
+        }
+        document
+    }
+
+    /// Apply a partial document on top of this configuration
+    ///
+    /// Objects merge recursively and `null` restores a setting's default.
+    /// Secrets given as `REDACTED` keep their current value, so an export can
+    /// be imported as-is. Every rejected setting is reported in
+    /// `Error::InvalidFields`.
+    pub fn merged(&self, patch: &Value) -> Result<DeviceConfig> {
+        if !patch.is_object() {
+            return Err(Error::InvalidFields(vec![FieldError::new("", "expected a JSON object")]));
+        }
+        let mut patch = patch.clone();
+        if patch.get("version").is_some() {
+            migrate(&mut patch, MIGRATIONS)
+                .map_err(|e| Error::InvalidFields(vec![FieldError::new("version", e.to_string())]))?;
+            if let Value::Object(fields) = &mut patch {
+                fields.remove("version");
+            }
+        }
+        for field in SECRET_FIELDS {
+            let pointer = pointer(field);
+            if patch.pointer(&pointer).and_then(Value::as_str) == Some(REDACTED) {
+                let (section, key) = pointer.rsplit_once('/').unwrap_or_default();
+                if let Some(Value::Object(fields)) = patch.pointer_mut(section) {
+                    fields.remove(key);
+                }
+            }
+        }
+
+        let current = serde_json::to_value(self).expect("DeviceConfig serializes to JSON");
+        let mut errors = Vec::new();
+        check_shape(&current, &patch, "", &mut errors);
+        if !errors.is_empty() {
+            return Err(Error::InvalidFields(errors));
+        }
+
+        let mut document = current.clone();
+        merge_patch(&mut document, &patch);
+        match serde_json::from_value::<DeviceConfig>(document) {
+            Ok(config) => {
+                let errors = config.field_errors();
+                if errors.is_empty() {
+                    Ok(config)
+                } else {
+                    Err(Error::InvalidFields(errors))
+                }
+            }
+            Err(_) => {
+                // Apply the settings one at a time to find those with the wrong type,
+                // then validate the rest
+                let mut leaves = Vec::new();
+                collect_leaves(&patch, "", &mut leaves);
+                let mut document = current.clone();
+                for (field, value) in leaves.into_iter().filter(|(_, value)| !value.is_null()) {
+                    let mut single = document.clone();
+                    merge_patch(&mut single, &field_patch(&field, value));
+                    match serde_json::from_value::<DeviceConfig>(single.clone()) {
+                        Ok(_) => document = single,
+                        Err(e) => errors.push(FieldError::new(field, e.to_string())),
+                    }
+                }
+                if let Ok(config) = serde_json::from_value::<DeviceConfig>(document) {
+                    errors.extend(config.field_errors());
+                }
+                Err(Error::InvalidFields(errors))
+            }
+        }
+    }
+}
+
+/// Merge `patch` into `target` as a JSON merge patch (RFC 7396)
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch_fields) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(fields) = target {
+        for (key, value) in patch_fields {
+            if value.is_null() {
+                fields.remove(key);
+            } else {
+                merge_patch(fields.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Build a patch setting one dotted field, e.g. `telemetry.sample_interval_ms`
+pub fn field_patch(field: &str, value: Value) -> Value {
+    field.rsplit('.').fold(value, |value, key| {
+        let mut object = serde_json::Map::new();
+        object.insert(key.to_string(), value);
+        Value::Object(object)
+    })
+}
+
+/// Convert a dotted setting name into a JSON pointer
+fn pointer(field: &str) -> String {
+    format!("/{}", field.replace('.', "/"))
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Report patch keys that are not settings, or that replace a section with a value
+fn check_shape(current: &Value, patch: &Value, prefix: &str, errors: &mut Vec<FieldError>) {
+    let Value::Object(fields) = patch else {
+        return;
+    };
+    for (key, value) in fields {
+        let field = join(prefix, key);
+        match current.get(key) {
+            None => errors.push(FieldError::new(field, "unknown setting")),
+            Some(Value::Object(_)) if MAP_FIELDS.contains(&field.as_str()) => {
+                if !value.is_object() && !value.is_null() {
+                    errors.push(FieldError::new(field, "expected an object"));
+                }
+            }
+            Some(section @ Value::Object(_)) => {
+                if value.is_object() {
+                    check_shape(section, value, &field, errors);
+                } else if !value.is_null() {
+                    errors.push(FieldError::new(field, "expected an object"));
+                }
+            }
+            Some(_) => {
+                if value.is_object() {
+                    errors.push(FieldError::new(field, "expected a value, not an object"));
+                }
+            }
+        }
+    }
+}
+
+/// Flatten a patch into its dotted setting names and values
+fn collect_leaves(patch: &Value, prefix: &str, leaves: &mut Vec<(String, Value)>) {
+    let Value::Object(fields) = patch else {
+        return;
+    };
+    for (key, value) in fields {
+        let field = join(prefix, key);
+        if value.is_object() {
+            collect_leaves(value, &field, leaves);
+        } else {
+            leaves.push((field, value.clone()));
+        }
+    }
+}
+
+/// Get the schema version of a configuration document
+pub fn document_version(document: &Value) -> Result<u32> {
+    document.get("version")
+        .and_then(Value::as_u64)
+        .filter(|&version| version >= 1 && version <= u32::MAX as u64)
+        .map(|version| version as u32)
+        .ok_or_else(|| Error::InvalidArgument("Configuration has no valid 'version'".to_string()))
+}
+
+/// Apply the migrations a document needs, returning its resulting version
+///
+/// `migrations[0]` upgrades version 1 to 2, and so on. Documents at or above
+/// the last version are left untouched.
+pub fn migrate(document: &mut Value, migrations: &[Migration]) -> Result<u32> {
+    let target = migrations.len() as u32 + 1;
+    let mut version = document_version(document)?;
+
+    while version < target {
+        migrations[version as usize - 1](document).map_err(|e| {
+            error!("Configuration migration from version {} failed: {:?}", version, e);
+            e
+        })?;
+        version += 1;
+        document["version"] = version.into();
+        info!("Migrated configuration to version {}", version);
+    }
+
+    Ok(version)
+}
+
+/// A configuration update sent to subscribers
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub config: DeviceConfig,
+    pub sections: Vec<ConfigSection>,
+}
+
+impl ConfigChange {
+    /// Check if the update touched `section`
+    pub fn affects(&self, section: ConfigSection) -> bool {
+        self.sections.contains(&section)
+    }
+}
+
+/// Device configuration persisted through `storage`, notifying subscribers of changes
+pub struct ConfigStore<S: NvsStorage> {
+    storage: S,
+    config: DeviceConfig,
+    subscribers: Vec<Sender<ConfigChange>>,
+}
+
+impl<S: NvsStorage> ConfigStore<S> {
+    /// Load the stored configuration, falling back to defaults if it is missing or unusable
+    ///
+    /// A configuration from an older schema is migrated and written back.
+    pub fn load(mut storage: S) -> Self {
+        let config = match storage.get_blob(NVS_CONFIG) {
+            Ok(Some(bytes)) => match Self::parse(&bytes) {
+                Ok((config, stored_version)) => {
+                    if stored_version < CONFIG_VERSION {
+                        if let Err(e) = storage.set_blob(NVS_CONFIG, &Self::encode(&config)) {
+                            warn!("Failed to store migrated configuration: {:?}", e);
+                        }
+                    } else if stored_version > CONFIG_VERSION {
+                        warn!(
+                            "Configuration version {} is newer than {}, unknown settings are ignored",
+                            stored_version, CONFIG_VERSION
+                        );
+                    }
+                    config
+                }
+                Err(e) => {
+                    warn!("Discarding unusable configuration: {:?}", e);
+                    DeviceConfig::default()
+                }
+            },
+            Ok(None) => {
+                info!("No stored configuration, using defaults");
+                DeviceConfig::default()
+            }
+            Err(e) => {
+                warn!("Failed to read configuration, using defaults: {:?}", e);
+                DeviceConfig::default()
+            }
+        };
+
+        Self { storage, config, subscribers: Vec::new() }
+    }
+
+    /// Get the current configuration
+    pub fn config(&self) -> &DeviceConfig {
+        &self.config
+    }
+
+    /// Validate, persist and publish a new configuration, returning the changed sections
+    pub fn update(&mut self, config: DeviceConfig) -> Result<Vec<ConfigSection>> {
+        config.validate()?;
+
+        let sections = config.changed_sections(&self.config);
+        if sections.is_empty() {
+            return Ok(sections);
+        }
+
+        self.storage.set_blob(NVS_CONFIG, &Self::encode(&config))?;
+        self.config = config;
+        info!("Configuration updated: {:?}", sections);
+
+        self.notify(&sections);
+        Ok(sections)
+    }
+
+    /// Apply a partial JSON document, see `DeviceConfig::merged`
+    pub fn import(&mut self, patch: &Value) -> Result<Vec<ConfigSection>> {
+        let config = self.config.merged(patch)?;
+        self.update(config)
+    }
+
+    /// Export the configuration with secrets redacted
+    pub fn export(&self) -> Value {
+        self.config.export()
+    }
+
+    /// Erase the stored configuration and return to defaults
+    pub fn factory_reset(&mut self) -> Result<()> {
+        self.storage.erase_all()?;
+        let previous = std::mem::take(&mut self.config);
+        warn!("Configuration reset to factory defaults");
+
+        let sections = self.config.changed_sections(&previous);
+        if !sections.is_empty() {
+            self.notify(&sections);
+        }
+        Ok(())
+    }
+
+    /// Change the configuration in place, then validate, persist and publish it
+    pub fn modify<F: FnOnce(&mut DeviceConfig)>(&mut self, change: F) -> Result<Vec<ConfigSection>> {
+        let mut config = self.config.clone();
+        change(&mut config);
+        self.update(config)
+    }
+
+    /// Receive every configuration change from now on
+    pub fn subscribe(&mut self) -> Receiver<ConfigChange> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    fn notify(&mut self, sections: &[ConfigSection]) {
+        let change = ConfigChange { config: self.config.clone(), sections: sections.to_vec() };
+        // Subscribers that went away are dropped
+        self.subscribers.retain(|subscriber| subscriber.send(change.clone()).is_ok());
+    }
+
+    fn parse(bytes: &[u8]) -> Result<(DeviceConfig, u32)> {
+        let document: Value = serde_json::from_slice(bytes)
+            .map_err(|e| Error::InvalidArgument(format!("Configuration is not JSON: {}", e)))?;
+        let version = document_version(&document)?;
+        let config = DeviceConfig::from_document(document)?;
+        config.validate()?;
+        Ok((config, version))
+    }
+
+    fn encode(config: &DeviceConfig) -> Vec<u8> {
+        config.to_document().to_string().into_bytes()
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::Result;
+use log::info;
+use serde_json::{json, Value};
+
+use crate::peripherals::button::ButtonEvent;
+
+use super::mqtt_task::{MqttConfig, MqttTask, MqttTransport, QoS, ReceivedMessage};
+
+/// Default Home Assistant discovery prefix
+pub const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// Device metadata shown in Home Assistant
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub manufacturer: String,
+    pub model: String,
+    pub sw_version: String,
+}
+
+impl DeviceInfo {
+    /// Create device metadata with this firmware's version
+    pub fn new(name: &str, model: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            manufacturer: "Espressif".to_string(),
+            model: model.to_string(),
+            sw_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// How an LED output is exposed to Home Assistant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedKind {
+    Light,
+    Switch,
+}
+
+/// A device component announced via discovery
+#[derive(Debug, Clone, PartialEq)]
+pub enum Component {
+    Led { id: u8, name: String, kind: LedKind },
+    Button { id: u8, name: String },
+    Sensor {
+        key: String,
+        name: String,
+        device_class: Option<String>,
+        unit: Option<String>,
+    },
+}
+
+/// A retained discovery config message
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveryMessage {
+    pub topic: String,
+    pub payload: String,
+}
+
+/// Registry of components announced to Home Assistant
+pub struct DiscoveryRegistry {
+    device: DeviceInfo,
+    discovery_prefix: String,
+    components: Vec<Component>,
+}
+
+impl DiscoveryRegistry {
+    /// Create an empty registry for a device
+    pub fn new(device: DeviceInfo) -> Self {
+        Self {
+            device,
+            discovery_prefix: DEFAULT_DISCOVERY_PREFIX.to_string(),
+            components: Vec::new(),
+        }
+    }
+
+    /// Use a custom discovery prefix
+    pub fn set_discovery_prefix(&mut self, prefix: &str) {
+        self.discovery_prefix = prefix.to_string();
+    }
+
+    /// Get the topic Home Assistant publishes `online` to after it starts
+    pub fn status_topic(&self) -> String {
+        format!("{}/status", self.discovery_prefix)
+    }
+
+    /// Register an LED output
+    pub fn add_led(&mut self, id: u8, name: &str, kind: LedKind) -> &mut Self {
+        self.components.push(Component::Led {
+            id,
+            name: name.to_string(),
+            kind,
+        });
+        self
+    }
+
+    /// Register a push button
+    pub fn add_button(&mut self, id: u8, name: &str) -> &mut Self {
+        self.components.push(Component::Button {
+            id,
+            name: name.to_string(),
+        });
+        self
+    }
+
+    /// Register a sensor measurement published on `sensors/<key>`
+    pub fn add_sensor(&mut self, key: &str, name: &str, device_class: Option<&str>, unit: Option<&str>) -> &mut Self {
+        self.components.push(Component::Sensor {
+            key: key.to_string(),
+            name: name.to_string(),
+            device_class: device_class.map(str::to_string),
+            unit: unit.map(str::to_string),
+        });
+        self
+    }
+
+    /// Register the measurements published by `SensorTask`
+    pub fn add_sensor_task_measurements(&mut self) -> &mut Self {
+        self.add_sensor("temperature", "Temperature", Some("temperature"), Some("°C"))
+            .add_sensor("humidity", "Humidity", Some("humidity"), Some("%"))
+            .add_sensor("pressure", "Pressure", Some("atmospheric_pressure"), Some("hPa"))
+    }
+
+    /// Get the registered components
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    /// Build the discovery messages for all registered components
+    pub fn messages(&self, mqtt: &MqttConfig) -> Vec<DiscoveryMessage> {
+        let mut messages = Vec::new();
+
+        for component in &self.components {
+            match component {
+                Component::Led { id, name, kind } => {
+                    let object_id = format!("led{}", id);
+                    let mut config = self.base_config(mqtt, &object_id, name);
+                    config["command_topic"] = json!(mqtt.topic(&format!("cmd/led{}", id)));
+                    config["state_topic"] = json!(mqtt.topic(&format!("leds/{}", id)));
+
+                    let platform = match kind {
+                        LedKind::Light => {
+                            config["schema"] = json!("json");
+                            "light"
+                        }
+                        LedKind::Switch => {
+                            config["payload_on"] = json!(r#"{"state":"ON"}"#);
+                            config["payload_off"] = json!(r#"{"state":"OFF"}"#);
+                            config["value_template"] = json!("{{ value_json.state }}");
+                            config["state_on"] = json!("ON");
+                            config["state_off"] = json!("OFF");
+                            "switch"
+                        }
+                    };
+                    messages.push(self.message(mqtt, platform, &object_id, config));
+                }
+                Component::Button { id, name } => {
+                    let object_id = format!("button{}", id);
+                    let state_topic = mqtt.topic(&format!("buttons/{}", id));
+
+                    let mut config = self.base_config(mqtt, &object_id, name);
+                    config["state_topic"] = json!(state_topic);
+                    config["value_template"] = json!("{{ value_json.event }}");
+                    config["payload_on"] = json!("pressed");
+                    config["payload_off"] = json!("released");
+                    messages.push(self.message(mqtt, "binary_sensor", &object_id, config));
+
+                    let trigger = json!({
+                        "automation_type": "trigger",
+                        "topic": state_topic,
+                        "value_template": "{{ value_json.event }}",
+                        "payload": "pressed",
+                        "type": "button_short_press",
+                        "subtype": format!("button_{}", id),
+                        "device": self.device_config(mqtt),
+                    });
+                    messages.push(self.message(mqtt, "device_automation", &format!("{}_press", object_id), trigger));
+                }
+                Component::Sensor { key, name, device_class, unit } => {
+                    let mut config = self.base_config(mqtt, key, name);
+                    config["state_topic"] = json!(mqtt.topic(&format!("sensors/{}", key)));
+                    config["state_class"] = json!("measurement");
+                    if let Some(device_class) = device_class {
+                        config["device_class"] = json!(device_class);
+                    }
+                    if let Some(unit) = unit {
+                        config["unit_of_measurement"] = json!(unit);
+                    }
+                    messages.push(self.message(mqtt, "sensor", key, config));
+                }
+            }
+        }
+
+        messages
+    }
+
+    /// Publish all discovery configs as retained messages
+    pub fn publish<T: MqttTransport>(&self, mqtt: &mut MqttTask<T>) -> Result<()> {
+        let messages = self.messages(mqtt.config());
+        for message in &messages {
+            mqtt.publish_with(&message.topic, message.payload.as_bytes(), QoS::AtLeastOnce, true)?;
+        }
+
+        info!("Published {} Home Assistant discovery config(s)", messages.len());
+        Ok(())
+    }
+
+    /// Subscribe to Home Assistant restarts so discovery can be republished
+    pub fn subscribe<T: MqttTransport>(&self, mqtt: &mut MqttTask<T>) -> Result<()> {
+        mqtt.subscribe(&self.status_topic(), QoS::AtLeastOnce)
+    }
+
+    /// Republish discovery if Home Assistant announced it came online
+    pub fn process_messages<T: MqttTransport>(
+        &self,
+        mqtt: &mut MqttTask<T>,
+        messages: &[ReceivedMessage],
+    ) -> Result<()> {
+        let status_topic = self.status_topic();
+        let ha_restarted = messages.iter()
+            .any(|m| m.topic == status_topic && m.payload == b"online");
+
+        if ha_restarted {
+            info!("Home Assistant came online, republishing discovery");
+            self.publish(mqtt)?;
+        }
+        Ok(())
+    }
+
+    /// Build fields shared by every entity config
+    fn base_config(&self, mqtt: &MqttConfig, object_id: &str, name: &str) -> Value {
+        json!({
+            "name": name,
+            "unique_id": format!("{}_{}", mqtt.client_id, object_id),
+            "availability_topic": mqtt.status_topic(),
+            "payload_available": "online",
+            "payload_not_available": "offline",
+            "device": self.device_config(mqtt),
+        })
+    }
+
+    /// Build the device block linking entities together
+    fn device_config(&self, mqtt: &MqttConfig) -> Value {
+        json!({
+            "identifiers": [mqtt.client_id],
+            "name": self.device.name,
+            "manufacturer": self.device.manufacturer,
+            "model": self.device.model,
+            "sw_version": self.device.sw_version,
+        })
+    }
+
+    /// Wrap a config into a message on `<prefix>/<platform>/<client_id>/<object>/config`
+    fn message(&self, mqtt: &MqttConfig, platform: &str, object_id: &str, config: Value) -> DiscoveryMessage {
+        DiscoveryMessage {
+            topic: format!(
+                "{}/{}/{}/{}/config",
+                self.discovery_prefix, platform, mqtt.client_id, object_id
+            ),
+            payload: config.to_string(),
+        }
+    }
+}
+
+/// Build the JSON state payload for an LED
+pub fn led_state_payload(state: bool) -> String {
+    json!({ "state": if state { "ON" } else { "OFF" } }).to_string()
+}
+
+/// Parse an `ON`/`OFF` state payload sent by Home Assistant
+pub fn parse_led_state(params: &Value) -> Result<bool> {
+    match params.get("state").and_then(Value::as_str) {
+        Some("ON") => Ok(true),
+        Some("OFF") => Ok(false),
+        _ => Err(anyhow::anyhow!("Expected state \"ON\" or \"OFF\"")),
+    }
+}
+
+/// Build the JSON state payload for a button event
+pub fn button_event_payload(event: ButtonEvent) -> String {
+    json!({ "event": event }).to_string()
+}
@@ -0,0 +1,508 @@
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use super::device_config::ConfigSection;
+use super::ota::OtaStatus;
+use super::sensor_task::SensorReadings;
+use crate::error::Error;
+use crate::utils::crash_log::{CrashEntry, ResetReason};
+use crate::utils::log_ring::{LogPage, LogQuery};
+
+/// Largest request body accepted by the API
+pub const MAX_BODY_LEN: usize = 1024;
+
+/// Request headers made available to route handlers
+pub const ROUTED_HEADERS: &[&str] = &["Accept-Encoding", "If-None-Match"];
+
+/// HTTP request method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+/// A request as seen by the router, independent of the HTTP server
+pub trait HttpRequest {
+    /// Get the request method
+    fn method(&self) -> HttpMethod;
+
+    /// Get the request URI, including any query string
+    fn uri(&self) -> &str;
+
+    /// Get a request header by name
+    fn header(&self, name: &str) -> Option<&str>;
+
+    /// Read the request body, failing with `Error::OutOfRange` if it exceeds `max_len` bytes
+    fn read_body(&mut self, max_len: usize) -> Result<Vec<u8>>;
+}
+
+/// A response produced by the router
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    /// Embedded assets are borrowed instead of copied per request
+    pub body: Cow<'static, [u8]>,
+}
+
+impl HttpResponse {
+    /// Build a JSON response
+    pub fn json(status: u16, body: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: Cow::Owned(body.to_string().into_bytes()),
+        }
+    }
+
+    /// Build a JSON error response `{"error": message}`
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &json!({ "error": message }))
+    }
+
+    /// Add an extra response header
+    pub fn with_header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+
+    /// Get the standard reason phrase for the status code
+    pub fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            204 => "No Content",
+            304 => "Not Modified",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            503 => "Service Unavailable",
+            _ if self.status >= 500 => "Internal Server Error",
+            _ => "",
+        }
+    }
+}
+
+/// A matched request passed to route handlers
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteRequest {
+    pub method: HttpMethod,
+    pub path: String,
+    pub query: Option<String>,
+    pub params: Vec<(String, String)>,
+    /// Headers listed in `ROUTED_HEADERS` that were present
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl RouteRequest {
+    /// Get a path parameter captured by a `{name}` segment
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get a routed request header, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get a query string parameter
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query.as_deref()?
+            .split('&')
+            .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Parse the body as JSON
+    pub fn json<T: for<'de> Deserialize<'de>>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| anyhow::anyhow!("Invalid JSON body: {}", e))
+    }
+}
+
+/// Handler invoked for a matched route
+pub type RouteHandler = Box<dyn Fn(&RouteRequest) -> Result<HttpResponse> + Send + Sync>;
+
+struct Route {
+    method: HttpMethod,
+    segments: Vec<String>,
+    handler: RouteHandler,
+}
+
+/// Routes requests to handlers by method and path pattern
+pub struct ApiRouter {
+    routes: Vec<Route>,
+}
+
+impl ApiRouter {
+    /// Create a router with no routes
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// Register a handler for a path pattern such as `/api/leds/{id}`
+    pub fn route<F>(&mut self, method: HttpMethod, pattern: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&RouteRequest) -> Result<HttpResponse> + Send + Sync + 'static,
+    {
+        self.routes.push(Route {
+            method,
+            segments: split_path(pattern).map(str::to_string).collect(),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Get the methods with at least one registered route
+    pub fn methods(&self) -> Vec<HttpMethod> {
+        let mut methods = Vec::new();
+        for route in &self.routes {
+            if !methods.contains(&route.method) {
+                methods.push(route.method);
+            }
+        }
+        methods
+    }
+
+    /// Route a request and produce its response
+    pub fn handle<R: HttpRequest>(&self, request: &mut R) -> HttpResponse {
+        let method = request.method();
+        let (path, query) = match request.uri().split_once('?') {
+            Some((path, query)) => (path.to_string(), Some(query.to_string())),
+            None => (request.uri().to_string(), None),
+        };
+
+        let mut path_matched = false;
+        for route in &self.routes {
+            let Some(params) = match_path(&route.segments, &path) else {
+                continue;
+            };
+            path_matched = true;
+            if route.method != method {
+                continue;
+            }
+
+            let body = match method {
+                HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch => match request.read_body(MAX_BODY_LEN) {
+                    Ok(body) => body,
+                    Err(e) => {
+                        let status = match e.downcast_ref::<Error>() {
+                            Some(Error::OutOfRange { .. }) => 413,
+                            _ => 400,
+                        };
+                        return HttpResponse::error(status, &e.to_string());
+                    }
+                },
+                _ => Vec::new(),
+            };
+
+            let headers = ROUTED_HEADERS.iter()
+                .filter_map(|name| request.header(name).map(|value| (name.to_string(), value.to_string())))
+                .collect();
+
+            let route_request = RouteRequest {
+                method,
+                path,
+                query,
+                params,
+                headers,
+                body,
+            };
+
+            debug!("{:?} {}", method, route_request.path);
+            return (route.handler)(&route_request).unwrap_or_else(|e| {
+                warn!("Handler for {:?} {} failed: {:?}", method, route_request.path, e);
+                HttpResponse::error(500, &e.to_string())
+            });
+        }
+
+        if path_matched {
+            HttpResponse::error(405, "Method not allowed")
+        } else {
+            HttpResponse::error(404, "Not found")
+        }
+    }
+}
+
+impl Default for ApiRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Split a path into its non-empty segments
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Match a path against pattern segments, capturing `{name}` parameters
+fn match_path(pattern: &[String], path: &str) -> Option<Vec<(String, String)>> {
+    let segments: Vec<&str> = split_path(path).collect();
+    if segments.len() != pattern.len() {
+        return None;
+    }
+
+    let mut params = Vec::new();
+    for (expected, actual) in pattern.iter().zip(segments) {
+        match expected.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+            Some(name) => params.push((name.to_string(), actual.to_string())),
+            None if expected == actual => {}
+            None => return None,
+        }
+    }
+    Some(params)
+}
+
+/// WiFi details reported by `/api/status`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WifiInfo {
+    pub connected: bool,
+    pub ssid: Option<String>,
+    pub ip: Option<String>,
+    pub rssi: Option<i8>,
+    pub access_point_active: bool,
+}
+
+/// Device summary reported by `/api/status`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceStatus {
+    pub uptime: String,
+    pub uptime_ms: u64,
+    pub free_heap: u32,
+    pub min_free_heap: u32,
+    pub wifi: WifiInfo,
+    pub reset_reason: ResetReason,
+    /// Crashes recorded since the crash log was created
+    pub crash_count: u32,
+}
+
+/// Device state and actions exposed over the REST API
+pub trait DeviceApi: Send + Sync {
+    /// Get the current device status
+    fn status(&self) -> Result<DeviceStatus>;
+
+    /// Get the latest sensor readings, if any have been taken yet
+    fn sensors(&self) -> Result<Option<SensorReadings>>;
+
+    /// Get the number of LEDs, numbered from 1
+    fn led_count(&self) -> u8;
+
+    /// Get the state of an LED
+    fn led_state(&self, id: u8) -> Result<bool>;
+
+    /// Set the state of an LED
+    fn set_led(&self, id: u8, state: bool) -> Result<()>;
+
+    /// Schedule a reboot once the response has been sent
+    fn reboot(&self) -> Result<()>;
+
+    /// Get the progress of the current or last OTA update
+    fn ota_status(&self) -> OtaStatus;
+
+    /// Start an OTA update from a manifest URL in the background
+    fn start_ota(&self, manifest_url: &str) -> Result<()>;
+
+    /// Get the recorded crashes, newest first
+    fn crash_log(&self) -> Result<Vec<CrashEntry>>;
+
+    /// Forget the recorded crashes
+    fn clear_crash_log(&self) -> Result<()>;
+
+    /// Export the device configuration with secrets redacted
+    fn config(&self) -> Result<Value>;
+
+    /// Merge a partial configuration document, returning the changed sections
+    fn import_config(&self, patch: &Value) -> Result<Vec<ConfigSection>>;
+
+    /// Erase stored settings and reboot with defaults once the response has been sent
+    fn factory_reset(&self) -> Result<()>;
+
+    /// Get recent log records
+    fn logs(&self, query: &LogQuery) -> Result<LogPage>;
+}
+
+#[derive(Deserialize)]
+struct LedStateBody {
+    state: bool,
+}
+
+#[derive(Deserialize)]
+struct OtaRequestBody {
+    url: String,
+}
+
+/// Register the built-in `/api/...` routes
+pub fn register_device_routes(router: &mut ApiRouter, device: Arc<dyn DeviceApi>) {
+    let status_device = device.clone();
+    router.route(HttpMethod::Get, "/api/status", move |_| {
+        let status = status_device.status()?;
+        Ok(HttpResponse::json(200, &serde_json::to_value(status)?))
+    });
+
+    let sensor_device = device.clone();
+    router.route(HttpMethod::Get, "/api/sensors", move |_| {
+        Ok(match sensor_device.sensors()? {
+            Some(readings) => HttpResponse::json(200, &serde_json::to_value(readings)?),
+            None => HttpResponse::error(503, "No sensor readings yet"),
+        })
+    });
+
+    let get_led_device = device.clone();
+    router.route(HttpMethod::Get, "/api/leds/{id}", move |request| {
+        let id = match parse_led_id(request, get_led_device.led_count()) {
+            Ok(id) => id,
+            Err(response) => return Ok(response),
+        };
+        let state = get_led_device.led_state(id)?;
+        Ok(HttpResponse::json(200, &json!({ "id": id, "state": state })))
+    });
+
+    let put_led_device = device.clone();
+    router.route(HttpMethod::Put, "/api/leds/{id}", move |request| {
+        let id = match parse_led_id(request, put_led_device.led_count()) {
+            Ok(id) => id,
+            Err(response) => return Ok(response),
+        };
+        let body: LedStateBody = match request.json() {
+            Ok(body) => body,
+            Err(e) => return Ok(HttpResponse::error(400, &e.to_string())),
+        };
+        put_led_device.set_led(id, body.state)?;
+        Ok(HttpResponse::json(200, &json!({ "id": id, "state": body.state })))
+    });
+
+    let reboot_device = device.clone();
+    router.route(HttpMethod::Post, "/api/reboot", move |_| {
+        reboot_device.reboot()?;
+        Ok(HttpResponse::json(202, &json!({ "rebooting": true })))
+    });
+
+    let ota_status_device = device.clone();
+    router.route(HttpMethod::Get, "/api/ota", move |_| {
+        Ok(HttpResponse::json(200, &serde_json::to_value(ota_status_device.ota_status())?))
+    });
+
+    let crash_device = device.clone();
+    router.route(HttpMethod::Get, "/api/crashes", move |_| {
+        Ok(HttpResponse::json(200, &serde_json::to_value(crash_device.crash_log()?)?))
+    });
+
+    let clear_crash_device = device.clone();
+    router.route(HttpMethod::Delete, "/api/crashes", move |_| {
+        clear_crash_device.clear_crash_log()?;
+        Ok(HttpResponse::json(200, &json!({ "cleared": true })))
+    });
+
+    let config_device = device.clone();
+    router.route(HttpMethod::Get, "/api/config", move |_| {
+        Ok(HttpResponse::json(200, &config_device.config()?))
+    });
+
+    let import_device = device.clone();
+    router.route(HttpMethod::Patch, "/api/config", move |request| {
+        let patch: Value = match request.json() {
+            Ok(patch) => patch,
+            Err(e) => return Ok(HttpResponse::error(400, &e.to_string())),
+        };
+        let changed = match import_config(import_device.as_ref(), &patch)? {
+            Ok(changed) => changed,
+            Err(response) => return Ok(response),
+        };
+        Ok(HttpResponse::json(200, &json!({
+            "changed": changed,
+            "config": import_device.config()?,
+        })))
+    });
+
+    let logging_device = device.clone();
+    router.route(HttpMethod::Get, "/api/logging", move |_| {
+        Ok(HttpResponse::json(200, &logging_device.config()?["logging"]))
+    });
+
+    let set_logging_device = device.clone();
+    router.route(HttpMethod::Patch, "/api/logging", move |request| {
+        let patch: Value = match request.json() {
+            Ok(patch) => patch,
+            Err(e) => return Ok(HttpResponse::error(400, &e.to_string())),
+        };
+        if let Err(response) = import_config(set_logging_device.as_ref(), &json!({ "logging": patch }))? {
+            return Ok(response);
+        }
+        Ok(HttpResponse::json(200, &set_logging_device.config()?["logging"]))
+    });
+
+    let logs_device = device.clone();
+    router.route(HttpMethod::Get, "/api/logs", move |request| {
+        let pairs = request.query.as_deref().unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")));
+        let query = match LogQuery::from_pairs(pairs) {
+            Ok(query) => query,
+            Err(e) => return Ok(HttpResponse::error(400, &e.to_string())),
+        };
+        Ok(HttpResponse::json(200, &serde_json::to_value(logs_device.logs(&query)?)?))
+    });
+
+    let reset_device = device.clone();
+    router.route(HttpMethod::Post, "/api/factory-reset", move |_| {
+        reset_device.factory_reset()?;
+        Ok(HttpResponse::json(202, &json!({ "resetting": true })))
+    });
+
+    router.route(HttpMethod::Post, "/api/ota", move |request| {
+        let body: OtaRequestBody = match request.json() {
+            Ok(body) => body,
+            Err(e) => return Ok(HttpResponse::error(400, &e.to_string())),
+        };
+        if device.ota_status().is_busy() {
+            return Ok(HttpResponse::error(409, "An update is already in progress"));
+        }
+        device.start_ota(&body.url)?;
+        Ok(HttpResponse::json(202, &serde_json::to_value(device.ota_status())?))
+    });
+}
+
+/// Apply a configuration patch, producing a 400 response listing rejected fields
+fn import_config(device: &dyn DeviceApi, patch: &Value) -> Result<std::result::Result<Vec<ConfigSection>, HttpResponse>> {
+    match device.import_config(patch) {
+        Ok(changed) => Ok(Ok(changed)),
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::InvalidFields(fields)) => Ok(Err(HttpResponse::json(400, &json!({
+                "error": e.to_string(),
+                "fields": fields,
+            })))),
+            _ => Err(e),
+        },
+    }
+}
+
+/// Parse the `{id}` parameter, producing a 400/404 response on failure
+fn parse_led_id(request: &RouteRequest, led_count: u8) -> std::result::Result<u8, HttpResponse> {
+    let raw = request.param("id").unwrap_or_default();
+    let id: u8 = raw.parse()
+        .map_err(|_| HttpResponse::error(400, &format!("Invalid LED id '{}'", raw)))?;
+
+    if id == 0 || id > led_count {
+        return Err(HttpResponse::error(404, &format!("LED {} does not exist", id)));
+    }
+    Ok(id)
+}
@@ -0,0 +1,201 @@
+use esp_idf_svc::http::server::ws::EspHttpWsDetachedSender;
+use esp_idf_svc::http::server::{
+    Configuration as HttpConfiguration, EspHttpConnection, EspHttpServer, Request,
+};
+use esp_idf_svc::http::{Headers, Method};
+use esp_idf_svc::io::{Read, Write};
+use esp_idf_svc::sys::{EspError, ESP_ERR_INVALID_SIZE};
+use esp_idf_svc::ws::FrameType;
+use anyhow::Result;
+use log::{info, warn, error};
+use std::sync::{Arc, Mutex};
+
+use super::http_api::{ApiRouter, HttpMethod, HttpRequest};
+use super::ws_stream::{FrameSink, StreamHub};
+use crate::error::Error;
+
+/// Largest control message accepted from a stream client
+const MAX_WS_MESSAGE_LEN: usize = 256;
+
+/// Stack size for HTTP handlers; JSON serialization needs more than the default
+const HTTP_STACK_SIZE: usize = 10240;
+
+/// Stream hub shared between the WebSocket handler and the stream task
+pub type SharedStreamHub = Arc<Mutex<StreamHub<EspWsSink>>>;
+
+/// HTTP Server Task serving an `ApiRouter` with `EspHttpServer`
+pub struct HttpServerTask {
+    _server: EspHttpServer<'static>,
+}
+
+impl HttpServerTask {
+    /// Start the server on port 80 and route every request through `router`
+    pub fn start(router: ApiRouter) -> Result<Self> {
+        let mut server = create_server()?;
+        register_router(&mut server, router)?;
+
+        info!("HTTP server started");
+        Ok(Self { _server: server })
+    }
+
+    /// Start the server with a WebSocket event stream on `/ws`
+    pub fn start_with_stream(router: ApiRouter, hub: SharedStreamHub) -> Result<Self> {
+        let mut server = create_server()?;
+
+        // Must be registered before the router's `/*` wildcard handlers
+        server.ws_handler("/ws", move |ws| {
+            let session = ws.session();
+
+            if ws.is_new() {
+                let sender = ws.create_detached_sender()?;
+                let sink = EspWsSink { sender: Arc::new(Mutex::new(sender)) };
+                hub.lock().unwrap().add_client(session, sink);
+                return Ok(());
+            }
+            if ws.is_closed() {
+                hub.lock().unwrap().remove_client(session);
+                return Ok(());
+            }
+
+            let (frame_type, len) = ws.recv(&mut [])?;
+            if len > MAX_WS_MESSAGE_LEN {
+                // The unread payload would be parsed as the next frame, so close
+                // the session instead; failing the handler makes the server drop it
+                warn!("Closing stream client {} after a {} byte message", session, len);
+                hub.lock().unwrap().remove_client(session);
+                return Err(EspError::from_infallible::<ESP_ERR_INVALID_SIZE>());
+            }
+
+            let mut buf = vec![0u8; len];
+            ws.recv(&mut buf)?;
+            if let FrameType::Text(_) = frame_type {
+                let text = std::str::from_utf8(&buf).unwrap_or_default().trim_end_matches('\0');
+                if let Err(e) = hub.lock().unwrap().handle_client_message(session, text) {
+                    warn!("Stream client {} sent a bad control message: {:?}", session, e);
+                }
+            }
+            Ok::<(), EspError>(())
+        })
+        .map_err(|e| {
+            error!("Failed to register WebSocket handler: {:?}", e);
+            anyhow::anyhow!("WebSocket handler registration failed")
+        })?;
+
+        register_router(&mut server, router)?;
+
+        info!("HTTP server started with event stream on /ws");
+        Ok(Self { _server: server })
+    }
+}
+
+/// Create the underlying ESP-IDF server
+fn create_server() -> Result<EspHttpServer<'static>> {
+    EspHttpServer::new(&HttpConfiguration {
+        uri_match_wildcard: true,
+        stack_size: HTTP_STACK_SIZE,
+        ..Default::default()
+    })
+    .map_err(|e| {
+        error!("Failed to start HTTP server: {:?}", e);
+        anyhow::anyhow!("HTTP server start failed")
+    })
+}
+
+/// Route every request for the router's methods through `router`
+fn register_router(server: &mut EspHttpServer<'static>, router: ApiRouter) -> Result<()> {
+    let router = Arc::new(router);
+    for method in router.methods() {
+        let router = router.clone();
+        server.fn_handler("/*", to_esp_method(method), move |request| {
+            let mut request = EspRequest { request, method };
+            let response = router.handle(&mut request);
+
+            let mut headers = vec![("Content-Type", response.content_type)];
+            headers.extend(response.headers.iter().map(|(name, value)| (*name, value.as_str())));
+
+            request.request
+                .into_response(response.status, Some(response.reason()), &headers)?
+                .write_all(&response.body)?;
+            Ok::<(), anyhow::Error>(())
+        })
+        .map_err(|e| {
+            error!("Failed to register HTTP handler for {:?}: {:?}", method, e);
+            anyhow::anyhow!("HTTP handler registration failed")
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Frame sink sending to a WebSocket session outside its handler
+///
+/// Sends block until the server task has written the frame; clones share the
+/// session so frames can be sent without holding the stream hub.
+#[derive(Clone)]
+pub struct EspWsSink {
+    sender: Arc<Mutex<EspHttpWsDetachedSender>>,
+}
+
+impl FrameSink for EspWsSink {
+    fn send_text(&mut self, frame: &str) -> Result<()> {
+        self.sender.lock().unwrap().send(FrameType::Text(false), frame.as_bytes())
+            .map_err(|e| anyhow::anyhow!("WebSocket send failed: {:?}", e))
+    }
+
+    fn close(&mut self) {
+        if let Err(e) = self.sender.lock().unwrap().send(FrameType::Close, &[]) {
+            warn!("Failed to close WebSocket session: {:?}", e);
+        }
+    }
+}
+
+/// Adapts an ESP-IDF request to the router's request trait
+struct EspRequest<'r, 'c> {
+    request: Request<&'r mut EspHttpConnection<'c>>,
+    method: HttpMethod,
+}
+
+impl HttpRequest for EspRequest<'_, '_> {
+    fn method(&self) -> HttpMethod {
+        self.method
+    }
+
+    fn uri(&self) -> &str {
+        self.request.uri()
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.request.header(name)
+    }
+
+    fn read_body(&mut self, max_len: usize) -> Result<Vec<u8>> {
+        let content_len = self.request.content_len().unwrap_or(0) as usize;
+        if content_len > max_len {
+            return Err(Error::out_of_range("Request body length", content_len as f64, 0, max_len as f64).into());
+        }
+
+        let mut body = vec![0u8; content_len];
+        let mut len = 0;
+        while len < body.len() {
+            let read = self.request.read(&mut body[len..])
+                .map_err(|e| anyhow::anyhow!("Failed to read request body: {:?}", e))?;
+            if read == 0 {
+                break;
+            }
+            len += read;
+        }
+        body.truncate(len);
+        Ok(body)
+    }
+}
+
+/// Convert a router method into the ESP-IDF server method
+fn to_esp_method(method: HttpMethod) -> Method {
+    match method {
+        HttpMethod::Get => Method::Get,
+        HttpMethod::Post => Method::Post,
+        HttpMethod::Put => Method::Put,
+        HttpMethod::Patch => Method::Patch,
+        HttpMethod::Delete => Method::Delete,
+    }
+}
@@ -0,0 +1,250 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Service type advertised by every node running this firmware
+pub const APP_SERVICE_TYPE: &str = "_esp32-template";
+
+/// TXT keys of the application service
+pub const TXT_DEVICE_ID: &str = "id";
+pub const TXT_FIRMWARE_VERSION: &str = "fw";
+
+/// Longest DNS label, used for host and instance names
+const MAX_LABEL_LEN: usize = 63;
+
+/// Longest service name after the leading underscore (RFC 6335)
+const MAX_SERVICE_NAME_LEN: usize = 15;
+
+/// Longest `key=value` TXT entry
+const MAX_TXT_ENTRY_LEN: usize = 255;
+
+/// Transport protocol of a DNS-SD service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceProtocol {
+    Tcp,
+    Udp,
+}
+
+impl ServiceProtocol {
+    /// Label used in service names, e.g. `_tcp`
+    pub fn label(&self) -> &'static str {
+        match self {
+            ServiceProtocol::Tcp => "_tcp",
+            ServiceProtocol::Udp => "_udp",
+        }
+    }
+}
+
+/// A DNS-SD service announced over mDNS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceAdvert {
+    /// Instance name, defaults to the mDNS instance name
+    pub instance_name: Option<String>,
+    /// Service type such as `_http`
+    pub service_type: String,
+    pub protocol: ServiceProtocol,
+    pub port: u16,
+    pub txt: Vec<(String, String)>,
+}
+
+impl ServiceAdvert {
+    /// Create an advert without TXT records
+    pub fn new(service_type: &str, protocol: ServiceProtocol, port: u16) -> Self {
+        Self {
+            instance_name: None,
+            service_type: service_type.to_string(),
+            protocol,
+            port,
+            txt: Vec::new(),
+        }
+    }
+
+    /// Add a TXT record
+    pub fn with_txt(mut self, key: &str, value: &str) -> Self {
+        self.txt.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Check the service type, port and TXT records
+    pub fn validate(&self) -> Result<()> {
+        validate_service_type(&self.service_type)?;
+        if let Some(name) = &self.instance_name {
+            validate_instance_name(name)?;
+        }
+        if self.port == 0 {
+            return Err(anyhow::anyhow!("Service {} needs a port", self.service_type));
+        }
+        for (key, value) in &self.txt {
+            validate_txt(key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// mDNS hostname announcement and advertised services
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MdnsConfig {
+    pub enabled: bool,
+    /// Announced as `<hostname>.local`; the network hostname is used if unset
+    pub hostname: Option<String>,
+    /// Human readable name shown by service browsers
+    pub instance_name: String,
+    /// Advertise the web dashboard and REST API as `_http._tcp`
+    pub http_port: Option<u16>,
+    /// Advertise an MQTT broker on this node as `_mqtt._tcp`
+    pub mqtt_port: Option<u16>,
+    /// Advertise the application service used for node discovery
+    pub advertise_app: bool,
+}
+
+impl Default for MdnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hostname: None,
+            instance_name: "ESP32 Template".to_string(),
+            http_port: Some(80),
+            mqtt_port: None,
+            advertise_app: true,
+        }
+    }
+}
+
+impl MdnsConfig {
+    /// Validate names and ports
+    pub fn validate(&self) -> Result<()> {
+        if let Some(hostname) = &self.hostname {
+            validate_hostname(hostname)?;
+        }
+        validate_instance_name(&self.instance_name)?;
+        if self.http_port == Some(0) || self.mqtt_port == Some(0) {
+            return Err(anyhow::anyhow!("Advertised ports must be non-zero"));
+        }
+        Ok(())
+    }
+
+    /// Get the hostname to announce, falling back to the network hostname
+    pub fn hostname<'a>(&'a self, network_hostname: &'a str) -> &'a str {
+        self.hostname.as_deref().unwrap_or(network_hostname)
+    }
+
+    /// Build the services to advertise for a device
+    pub fn services(&self, device_id: &str, firmware_version: &str) -> Vec<ServiceAdvert> {
+        let mut services = Vec::new();
+
+        if let Some(port) = self.http_port {
+            services.push(ServiceAdvert::new("_http", ServiceProtocol::Tcp, port).with_txt("path", "/"));
+        }
+        if let Some(port) = self.mqtt_port {
+            services.push(ServiceAdvert::new("_mqtt", ServiceProtocol::Tcp, port));
+        }
+        if self.advertise_app {
+            // Discovery only needs the TXT records, so point at the HTTP port
+            let port = self.http_port.unwrap_or(80);
+            services.push(
+                ServiceAdvert::new(APP_SERVICE_TYPE, ServiceProtocol::Tcp, port)
+                    .with_txt(TXT_DEVICE_ID, device_id)
+                    .with_txt(TXT_FIRMWARE_VERSION, firmware_version),
+            );
+        }
+
+        services
+    }
+}
+
+/// A service instance found by an mDNS query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredService {
+    pub instance_name: Option<String>,
+    pub hostname: Option<String>,
+    pub port: u16,
+    pub addresses: Vec<IpAddr>,
+    pub txt: Vec<(String, String)>,
+}
+
+impl DiscoveredService {
+    /// Get a TXT value by key, ignoring case as DNS-SD requires
+    pub fn txt_value(&self, key: &str) -> Option<&str> {
+        self.txt.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the device ID of a node running this firmware
+    pub fn device_id(&self) -> Option<&str> {
+        self.txt_value(TXT_DEVICE_ID)
+    }
+
+    /// Get the firmware version of a node running this firmware
+    pub fn firmware_version(&self) -> Option<&str> {
+        self.txt_value(TXT_FIRMWARE_VERSION)
+    }
+}
+
+/// Keep one entry per device ID, dropping ourselves and nodes without an ID
+pub fn unique_nodes(services: Vec<DiscoveredService>, own_device_id: &str) -> Vec<DiscoveredService> {
+    let mut nodes: Vec<DiscoveredService> = Vec::new();
+    for service in services {
+        let Some(id) = service.device_id() else {
+            continue;
+        };
+        if id == own_device_id || nodes.iter().any(|node| node.device_id() == Some(id)) {
+            continue;
+        }
+        nodes.push(service);
+    }
+    nodes
+}
+
+/// Check a `.local` hostname label
+pub fn validate_hostname(hostname: &str) -> Result<()> {
+    let valid = !hostname.is_empty()
+        && hostname.len() <= MAX_LABEL_LEN
+        && hostname.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-')
+        && !hostname.starts_with('-')
+        && !hostname.ends_with('-');
+
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid mDNS hostname '{}'", hostname));
+    }
+    Ok(())
+}
+
+/// Check a service instance name
+pub fn validate_instance_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > MAX_LABEL_LEN || name.chars().any(char::is_control) {
+        return Err(anyhow::anyhow!("Invalid mDNS instance name '{}'", name));
+    }
+    Ok(())
+}
+
+/// Check a service type such as `_http`
+pub fn validate_service_type(service_type: &str) -> Result<()> {
+    let name = service_type.strip_prefix('_').unwrap_or_default();
+    let valid = !name.is_empty()
+        && name.len() <= MAX_SERVICE_NAME_LEN
+        && name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-')
+        && name.bytes().any(|c| c.is_ascii_alphabetic())
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && !name.contains("--");
+
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid service type '{}'", service_type));
+    }
+    Ok(())
+}
+
+/// Check a TXT record key and value
+pub fn validate_txt(key: &str, value: &str) -> Result<()> {
+    let key_valid = !key.is_empty() && key.bytes().all(|c| (0x20..=0x7e).contains(&c) && c != b'=');
+    if !key_valid {
+        return Err(anyhow::anyhow!("Invalid TXT key '{}'", key));
+    }
+    if key.len() + 1 + value.len() > MAX_TXT_ENTRY_LEN {
+        return Err(anyhow::anyhow!("TXT record '{}' exceeds {} bytes", key, MAX_TXT_ENTRY_LEN));
+    }
+    Ok(())
+}
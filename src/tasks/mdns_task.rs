@@ -0,0 +1,133 @@
+use esp_idf_svc::mdns::{EspMdns, Interface, Protocol, QueryResult};
+use anyhow::Result;
+use log::{info, error};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use super::mdns_config::{
+    unique_nodes, DiscoveredService, MdnsConfig, ServiceAdvert, ServiceProtocol, APP_SERVICE_TYPE,
+};
+
+/// Most results collected from a single query
+const MAX_QUERY_RESULTS: usize = 16;
+
+/// mDNS Task announcing `<hostname>.local` and the configured services
+pub struct MdnsTask {
+    mdns: EspMdns,
+    device_id: String,
+}
+
+impl MdnsTask {
+    /// Start the responder and advertise the services in `config`
+    pub fn start(config: &MdnsConfig, network_hostname: &str, device_id: &str, firmware_version: &str) -> Result<Self> {
+        config.validate()?;
+        let hostname = config.hostname(network_hostname);
+
+        let mut mdns = EspMdns::take()
+            .map_err(|e| {
+                error!("Failed to start mDNS: {:?}", e);
+                anyhow::anyhow!("mDNS initialization failed")
+            })?;
+
+        mdns.set_hostname(hostname)
+            .and_then(|_| mdns.set_instance_name(&config.instance_name))
+            .map_err(|e| {
+                error!("Failed to set mDNS hostname: {:?}", e);
+                anyhow::anyhow!("mDNS hostname configuration failed")
+            })?;
+
+        let mut task = Self {
+            mdns,
+            device_id: device_id.to_string(),
+        };
+        for service in config.services(device_id, firmware_version) {
+            task.add_service(&service)?;
+        }
+
+        info!("mDNS announcing {}.local", hostname);
+        Ok(task)
+    }
+
+    /// Advertise an additional service
+    pub fn add_service(&mut self, service: &ServiceAdvert) -> Result<()> {
+        service.validate()?;
+        let txt: Vec<(&str, &str)> = service.txt.iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+
+        self.mdns.add_service(
+            service.instance_name.as_deref(),
+            &service.service_type,
+            service.protocol.label(),
+            service.port,
+            &txt,
+        )
+        .map_err(|e| {
+            error!("Failed to advertise {}: {:?}", service.service_type, e);
+            anyhow::anyhow!("mDNS service registration failed")
+        })?;
+
+        info!("Advertising {}.{} on port {}", service.service_type, service.protocol.label(), service.port);
+        Ok(())
+    }
+
+    /// Stop advertising a service
+    pub fn remove_service(&mut self, service_type: &str, protocol: ServiceProtocol) -> Result<()> {
+        self.mdns.remove_service(service_type, protocol.label())
+            .map_err(|e| {
+                error!("Failed to remove {}: {:?}", service_type, e);
+                anyhow::anyhow!("mDNS service removal failed")
+            })
+    }
+
+    /// Find instances of a service on the local network
+    pub fn query(&self, service_type: &str, protocol: ServiceProtocol, timeout: Duration) -> Result<Vec<DiscoveredService>> {
+        let mut results: Vec<QueryResult> = (0..MAX_QUERY_RESULTS).map(|_| empty_result()).collect();
+
+        let count = self.mdns.query_ptr(service_type, protocol.label(), timeout, MAX_QUERY_RESULTS, &mut results)
+            .map_err(|e| {
+                error!("mDNS query for {} failed: {:?}", service_type, e);
+                anyhow::anyhow!("mDNS query failed")
+            })?;
+
+        Ok(results.into_iter()
+            .take(count)
+            .map(|result| DiscoveredService {
+                instance_name: result.instance_name,
+                hostname: result.hostname,
+                port: result.port,
+                addresses: result.addr,
+                txt: result.txt,
+            })
+            .collect())
+    }
+
+    /// Find other nodes running this firmware, one entry per device
+    pub fn discover_nodes(&self, timeout: Duration) -> Result<Vec<DiscoveredService>> {
+        let services = self.query(APP_SERVICE_TYPE, ServiceProtocol::Tcp, timeout)?;
+        Ok(unique_nodes(services, &self.device_id))
+    }
+
+    /// Resolve `<hostname>.local` to an IPv4 address
+    pub fn resolve_host(&self, hostname: &str, timeout: Duration) -> Result<Ipv4Addr> {
+        let hostname = hostname.trim_end_matches(".local");
+        self.mdns.query_a(hostname, timeout)
+            .map_err(|e| {
+                error!("Failed to resolve {}.local: {:?}", hostname, e);
+                anyhow::anyhow!("mDNS host lookup failed")
+            })
+    }
+}
+
+/// Placeholder filled in by `query_ptr`
+fn empty_result() -> QueryResult {
+    QueryResult {
+        instance_name: None,
+        hostname: None,
+        port: 0,
+        txt: Vec::new(),
+        addr: Vec::new(),
+        interface: Interface::STA,
+        ip_protocol: Protocol::V4,
+    }
+}
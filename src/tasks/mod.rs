@@ -1,7 +1,75 @@
 // FreeRTOS tasks and async code module
+#[cfg(target_os = "espidf")]
 pub mod wifi_task;
 pub mod sensor_task;
+pub mod wifi_credentials;
+pub mod wifi_scan;
+pub mod network_config;
+pub mod device_config;
+pub mod wifi_modes;
+pub mod sntp_config;
+#[cfg(target_os = "espidf")]
+pub mod sntp_task;
+pub mod mdns_config;
+#[cfg(target_os = "espidf")]
+pub mod mdns_task;
+pub mod mqtt_task;
+#[cfg(target_os = "espidf")]
+pub mod mqtt_transport;
+pub mod mqtt_commands;
+pub mod ha_discovery;
+pub mod http_api;
+#[cfg(target_os = "espidf")]
+pub mod http_server;
+pub mod ws_stream;
+pub mod dashboard;
+pub mod web_assets;
+pub mod ota;
+pub mod ota_signature;
+#[cfg(target_os = "espidf")]
+pub mod ota_task;
+pub mod provisioning_portal;
+#[cfg(target_os = "espidf")]
+pub mod provisioning_task;
+pub mod console;
+#[cfg(target_os = "espidf")]
+pub mod console_task;
 
 // Re-export commonly used tasks
+#[cfg(target_os = "espidf")]
 pub use wifi_task::WifiTask;
-pub use sensor_task::SensorTask; 
\ No newline at end of file
+pub use sensor_task::SensorReadings;
+#[cfg(target_os = "espidf")]
+pub use sensor_task::SensorTask;
+pub use wifi_credentials::WifiNetwork;
+#[cfg(target_os = "espidf")]
+pub use wifi_credentials::CredentialStore;
+pub use wifi_scan::{AuthMode, ScanResult};
+pub use network_config::{IpMode, NetworkConfig, StaticIpConfig};
+pub use device_config::{ConfigChange, ConfigSection, ConfigStore, DeviceConfig};
+pub use wifi_modes::{AccessPointSettings, PowerSaveMode};
+pub use sntp_config::SntpConfig;
+#[cfg(target_os = "espidf")]
+pub use sntp_task::SntpTask;
+pub use mdns_config::{DiscoveredService, MdnsConfig, ServiceAdvert};
+#[cfg(target_os = "espidf")]
+pub use mdns_task::MdnsTask;
+pub use mqtt_task::{MqttConfig, MqttTask, MqttTransport};
+#[cfg(target_os = "espidf")]
+pub use mqtt_transport::EspMqttTransport;
+pub use mqtt_commands::{Command, CommandDispatcher};
+pub use ha_discovery::DiscoveryRegistry;
+pub use http_api::{ApiRouter, DeviceApi, HttpResponse};
+#[cfg(target_os = "espidf")]
+pub use http_server::{HttpServerTask, SharedStreamHub};
+pub use ws_stream::{Outgoing, StreamEvent, StreamHub};
+pub use dashboard::WebAsset;
+pub use ota::{FirmwareVersion, OtaManifest, OtaStatus};
+pub use ota_signature::OtaPublicKey;
+#[cfg(target_os = "espidf")]
+pub use ota_task::OtaTask;
+#[cfg(target_os = "espidf")]
+pub use provisioning_task::ProvisioningTask;
+pub use console::{Console, LineEditor};
+#[cfg(target_os = "espidf")]
+pub use console_task::ConsoleTask; 
\ No newline at end of file
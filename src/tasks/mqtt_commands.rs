@@ -0,0 +1,260 @@
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::mqtt_task::{MqttTask, MqttTransport, QoS, ReceivedMessage};
+
+/// Shortest sample interval accepted remotely
+pub const MIN_SAMPLE_INTERVAL_MS: u32 = 1_000;
+
+/// Longest sample interval accepted remotely
+pub const MAX_SAMPLE_INTERVAL_MS: u32 = 3_600_000;
+
+/// A remote command received on `<prefix>/<device-id>/cmd/<name>`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    SetLed { led: u8, state: bool },
+    ToggleLed { led: u8 },
+    Reboot,
+    SetSampleInterval { interval_ms: u32 },
+    /// Application-defined command with raw JSON parameters
+    Custom { name: String, params: Value },
+}
+
+impl Command {
+    /// Get the command name used in topics and handler registration
+    pub fn name(&self) -> &str {
+        match self {
+            Command::SetLed { .. } => "set_led",
+            Command::ToggleLed { .. } => "toggle_led",
+            Command::Reboot => "reboot",
+            Command::SetSampleInterval { .. } => "set_sample_interval",
+            Command::Custom { name, .. } => name,
+        }
+    }
+}
+
+/// A parsed command with its correlation ID
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandRequest {
+    pub correlation_id: Option<String>,
+    pub command: Command,
+}
+
+/// Outcome reported in an acknowledgement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AckStatus {
+    Ok,
+    Error,
+}
+
+/// Acknowledgement published to `<prefix>/<device-id>/ack`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandAck {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    pub command: String,
+    pub status: AckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SetLedParams {
+    led: u8,
+    state: bool,
+}
+
+#[derive(Deserialize)]
+struct ToggleLedParams {
+    led: u8,
+}
+
+#[derive(Deserialize)]
+struct SampleIntervalParams {
+    interval_ms: u32,
+}
+
+/// Parse a command name and JSON payload into a typed command
+pub fn parse_command(name: &str, payload: &[u8]) -> Result<CommandRequest> {
+    let params: Value = if payload.iter().all(u8::is_ascii_whitespace) {
+        Value::Object(Default::default())
+    } else {
+        serde_json::from_slice(payload)
+            .map_err(|e| anyhow::anyhow!("Invalid JSON payload: {}", e))?
+    };
+
+    if !params.is_object() {
+        return Err(anyhow::anyhow!("Command payload must be a JSON object"));
+    }
+
+    let correlation_id = correlation_id(&params);
+    let command = match name {
+        "set_led" => {
+            let p: SetLedParams = serde_json::from_value(params)?;
+            Command::SetLed { led: p.led, state: p.state }
+        }
+        "toggle_led" => {
+            let p: ToggleLedParams = serde_json::from_value(params)?;
+            Command::ToggleLed { led: p.led }
+        }
+        "reboot" => Command::Reboot,
+        "set_sample_interval" => {
+            let p: SampleIntervalParams = serde_json::from_value(params)?;
+            if !(MIN_SAMPLE_INTERVAL_MS..=MAX_SAMPLE_INTERVAL_MS).contains(&p.interval_ms) {
+                return Err(anyhow::anyhow!(
+                    "interval_ms {} is out of range [{}, {}]",
+                    p.interval_ms,
+                    MIN_SAMPLE_INTERVAL_MS,
+                    MAX_SAMPLE_INTERVAL_MS
+                ));
+            }
+            Command::SetSampleInterval { interval_ms: p.interval_ms }
+        }
+        "" => return Err(anyhow::anyhow!("Missing command name")),
+        _ => Command::Custom {
+            name: name.to_string(),
+            params,
+        },
+    };
+
+    Ok(CommandRequest {
+        correlation_id,
+        command,
+    })
+}
+
+/// Extract the correlation ID from a JSON payload, if present
+fn correlation_id(params: &Value) -> Option<String> {
+    params.get("correlation_id")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// Handler invoked for a command; may return a JSON result for the acknowledgement
+pub type CommandHandler = Box<dyn FnMut(&Command) -> Result<Option<Value>> + Send>;
+
+/// Dispatches parsed commands to registered handlers
+pub struct CommandDispatcher {
+    handlers: HashMap<String, CommandHandler>,
+}
+
+impl CommandDispatcher {
+    /// Create a dispatcher with no handlers
+    pub fn new() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Register a handler for a command name, replacing any existing one
+    pub fn register<F>(&mut self, name: &str, handler: F)
+    where
+        F: FnMut(&Command) -> Result<Option<Value>> + Send + 'static,
+    {
+        self.handlers.insert(name.to_string(), Box::new(handler));
+    }
+
+    /// Check if a handler is registered for a command name
+    pub fn has_handler(&self, name: &str) -> bool {
+        self.handlers.contains_key(name)
+    }
+
+    /// Parse and dispatch a single command, producing its acknowledgement
+    pub fn handle(&mut self, name: &str, payload: &[u8]) -> CommandAck {
+        let request = match parse_command(name, payload) {
+            Ok(request) => request,
+            Err(e) => {
+                // Still echo the correlation ID if the payload was valid JSON
+                let correlation_id = serde_json::from_slice::<Value>(payload)
+                    .ok()
+                    .as_ref()
+                    .and_then(correlation_id);
+                return error_ack(correlation_id, name, e.to_string());
+            }
+        };
+
+        let Some(handler) = self.handlers.get_mut(name) else {
+            return error_ack(request.correlation_id, name, format!("Unknown command '{}'", name));
+        };
+
+        match handler(&request.command) {
+            Ok(result) => CommandAck {
+                correlation_id: request.correlation_id,
+                command: name.to_string(),
+                status: AckStatus::Ok,
+                result,
+                error: None,
+            },
+            Err(e) => error_ack(request.correlation_id, name, e.to_string()),
+        }
+    }
+
+    /// Subscribe the MQTT task to this device's command topics
+    pub fn subscribe<T: MqttTransport>(&self, mqtt: &mut MqttTask<T>) -> Result<()> {
+        let filter = mqtt.config().topic("cmd/#");
+        mqtt.subscribe(&filter, QoS::AtLeastOnce)
+    }
+
+    /// Handle all pending command messages and publish their acknowledgements
+    pub fn process<T: MqttTransport>(&mut self, mqtt: &mut MqttTask<T>) -> Result<usize> {
+        let messages = mqtt.poll_messages();
+        self.process_messages(mqtt, &messages)
+    }
+
+    /// Handle command messages among `messages`, ignoring other topics
+    pub fn process_messages<T: MqttTransport>(
+        &mut self,
+        mqtt: &mut MqttTask<T>,
+        messages: &[ReceivedMessage],
+    ) -> Result<usize> {
+        let command_prefix = mqtt.config().topic("cmd/");
+        let ack_topic = mqtt.config().topic("ack");
+        let mut handled = 0;
+
+        for message in messages {
+            let Some(name) = message.topic.strip_prefix(&command_prefix) else {
+                continue;
+            };
+
+            let ack = if name.contains('/') {
+                error_ack(None, name, "Nested command topics are not supported".to_string())
+            } else {
+                self.handle(name, &message.payload)
+            };
+
+            match ack.status {
+                AckStatus::Ok => info!("Command '{}' succeeded", ack.command),
+                AckStatus::Error => warn!("Command '{}' failed: {:?}", ack.command, ack.error),
+            }
+
+            let payload = serde_json::to_vec(&ack)?;
+            mqtt.publish_with(&ack_topic, &payload, QoS::AtLeastOnce, false)?;
+            handled += 1;
+        }
+
+        Ok(handled)
+    }
+}
+
+impl Default for CommandDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Build an error acknowledgement
+fn error_ack(correlation_id: Option<String>, name: &str, error: String) -> CommandAck {
+    CommandAck {
+        correlation_id,
+        command: name.to_string(),
+        status: AckStatus::Error,
+        result: None,
+        error: Some(error),
+    }
+}
@@ -0,0 +1,346 @@
+use anyhow::Result;
+use log::{info, warn, debug};
+use std::collections::VecDeque;
+
+use super::sensor_task::SensorReadings;
+use crate::utils::error_handler::{CircuitBreaker, CircuitState};
+
+/// Default number of messages kept while the broker is unreachable
+pub const DEFAULT_OFFLINE_BUFFER: usize = 64;
+
+/// MQTT delivery guarantee
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QoS {
+    AtMostOnce,
+    AtLeastOnce,
+    ExactlyOnce,
+}
+
+impl QoS {
+    /// Get the QoS for an MQTT level number (0, 1 or 2)
+    pub fn from_level(level: u8) -> Option<Self> {
+        match level {
+            0 => Some(QoS::AtMostOnce),
+            1 => Some(QoS::AtLeastOnce),
+            2 => Some(QoS::ExactlyOnce),
+            _ => None,
+        }
+    }
+}
+
+/// Message published by the broker if the device disconnects uncleanly
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastWill {
+    pub topic: String,
+    pub payload: String,
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+/// MQTT connection and publishing settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttConfig {
+    /// Broker URL, e.g. `mqtt://broker.local:1883` or `mqtts://broker.example.com:8883`
+    pub broker_url: String,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// PEM CA certificate (NUL-terminated) for `mqtts://` brokers; without
+    /// one, brokers are checked against the ESP-IDF certificate bundle
+    pub ca_certificate: Option<&'static str>,
+    /// Topic prefix; topics are `<prefix>/<client_id>/...`
+    pub topic_prefix: String,
+    pub qos: QoS,
+    pub retain: bool,
+    pub keep_alive_secs: u16,
+    pub offline_buffer_size: usize,
+    /// Publish `offline` on the status topic as last will
+    pub use_last_will: bool,
+}
+
+impl MqttConfig {
+    /// Create a configuration with sensible defaults
+    pub fn new(broker_url: &str, client_id: &str) -> Self {
+        Self {
+            broker_url: broker_url.to_string(),
+            client_id: client_id.to_string(),
+            username: None,
+            password: None,
+            ca_certificate: None,
+            topic_prefix: "devices".to_string(),
+            qos: QoS::AtLeastOnce,
+            retain: false,
+            keep_alive_secs: 30,
+            offline_buffer_size: DEFAULT_OFFLINE_BUFFER,
+            use_last_will: true,
+        }
+    }
+
+    /// Check if the broker URL requires TLS
+    pub fn uses_tls(&self) -> bool {
+        self.broker_url.starts_with("mqtts://") || self.broker_url.starts_with("wss://")
+    }
+
+    /// Build a topic under this device's namespace
+    pub fn topic(&self, suffix: &str) -> String {
+        format!("{}/{}/{}", self.topic_prefix, self.client_id, suffix)
+    }
+
+    /// Get the availability topic
+    pub fn status_topic(&self) -> String {
+        self.topic("status")
+    }
+
+    /// Get the last will published when the device drops off
+    pub fn last_will(&self) -> Option<LastWill> {
+        self.use_last_will.then(|| LastWill {
+            topic: self.status_topic(),
+            payload: "offline".to_string(),
+            qos: QoS::AtLeastOnce,
+            retain: true,
+        })
+    }
+
+    /// Validate URL, client ID, buffer settings and CA certificate
+    pub fn validate(&self) -> Result<()> {
+        let scheme_ok = ["mqtt://", "mqtts://", "ws://", "wss://"]
+            .iter()
+            .any(|scheme| self.broker_url.starts_with(scheme));
+        if !scheme_ok {
+            return Err(anyhow::anyhow!("Unsupported broker URL '{}'", self.broker_url));
+        }
+        if self.client_id.is_empty() || self.client_id.contains(['/', '+', '#']) {
+            return Err(anyhow::anyhow!("Client ID must be non-empty and free of '/', '+' and '#'"));
+        }
+        if self.offline_buffer_size == 0 {
+            return Err(anyhow::anyhow!("Offline buffer size must be at least 1"));
+        }
+        // The ESP-IDF client reads the PEM up to its terminating NUL
+        if self.ca_certificate.is_some_and(|pem| !pem.ends_with('\0')) {
+            return Err(anyhow::anyhow!("CA certificate must end with a NUL byte"));
+        }
+        Ok(())
+    }
+}
+
+/// Transport used by the MQTT task to reach a broker
+pub trait MqttTransport {
+    /// Publish a message; fails if the broker is unreachable
+    fn publish(&mut self, topic: &str, qos: QoS, retain: bool, payload: &[u8]) -> Result<()>;
+
+    /// Subscribe to a topic filter
+    fn subscribe(&mut self, topic: &str, qos: QoS) -> Result<()>;
+
+    /// Take the next received message, if any
+    fn poll_message(&mut self) -> Option<ReceivedMessage>;
+
+    /// Check if the transport is currently connected to the broker
+    fn is_connected(&self) -> bool;
+}
+
+/// A message received on a subscribed topic
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+/// A message waiting to be published
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+/// MQTT Task publishing telemetry, buffering messages while offline
+pub struct MqttTask<T: MqttTransport> {
+    transport: T,
+    config: MqttConfig,
+    pending: VecDeque<PendingMessage>,
+    subscriptions: Vec<(String, QoS)>,
+    was_connected: bool,
+    dropped: u32,
+    breaker: Option<CircuitBreaker>,
+}
+
+impl<T: MqttTransport> MqttTask<T> {
+    /// Create a new MQTT task over the given transport
+    pub fn new(transport: T, config: MqttConfig) -> Self {
+        Self {
+            transport,
+            config,
+            pending: VecDeque::new(),
+            subscriptions: Vec::new(),
+            was_connected: false,
+            dropped: 0,
+            breaker: None,
+        }
+    }
+
+    /// Buffer messages instead of publishing while `breaker` is open
+    ///
+    /// Publishes, including buffered ones, count as calls of the breaker.
+    pub fn set_circuit_breaker(&mut self, breaker: CircuitBreaker) {
+        self.breaker = Some(breaker);
+    }
+
+    /// Publish a message with the configured QoS and retain flag
+    pub fn publish(&mut self, topic: &str, payload: &[u8]) -> Result<()> {
+        self.publish_with(topic, payload, self.config.qos, self.config.retain)
+    }
+
+    /// Publish a message, buffering it if the broker is unreachable
+    pub fn publish_with(&mut self, topic: &str, payload: &[u8], qos: QoS, retain: bool) -> Result<()> {
+        let message = PendingMessage {
+            topic: topic.to_string(),
+            payload: payload.to_vec(),
+            qos,
+            retain,
+        };
+
+        self.flush()?;
+        if !self.pending.is_empty() || !self.transport.is_connected() || self.circuit_open() {
+            self.enqueue(message);
+            return Ok(());
+        }
+
+        if let Err(e) = self.send(&message) {
+            warn!("MQTT publish to {} failed, buffering: {:?}", message.topic, e);
+            self.enqueue(message);
+        }
+        Ok(())
+    }
+
+    /// Publish sensor readings as individual values and a combined JSON document
+    pub fn publish_readings(&mut self, readings: &SensorReadings) -> Result<()> {
+        let values = [
+            ("temperature", readings.temperature),
+            ("humidity", readings.humidity),
+            ("pressure", readings.pressure),
+        ];
+        for (name, value) in values {
+            let topic = self.config.topic(&format!("sensors/{}", name));
+            self.publish(&topic, format!("{:.2}", value).as_bytes())?;
+        }
+
+        let payload = serde_json::to_vec(readings)?;
+        let topic = self.config.topic("telemetry");
+        self.publish(&topic, &payload)
+    }
+
+    /// Subscribe to a topic filter, restoring the subscription after reconnects
+    pub fn subscribe(&mut self, topic: &str, qos: QoS) -> Result<()> {
+        if !self.subscriptions.iter().any(|(t, _)| t == topic) {
+            self.subscriptions.push((topic.to_string(), qos));
+        }
+
+        if self.transport.is_connected() {
+            self.transport.subscribe(topic, qos)?;
+        }
+        Ok(())
+    }
+
+    /// Take all messages received since the last call
+    pub fn poll_messages(&mut self) -> Vec<ReceivedMessage> {
+        std::iter::from_fn(|| self.transport.poll_message()).collect()
+    }
+
+    /// Send buffered messages if the broker is reachable.
+    ///
+    /// Also announces `online` on the status topic and restores subscriptions
+    /// after each (re)connect.
+    pub fn flush(&mut self) -> Result<()> {
+        let connected = self.transport.is_connected();
+        if connected && !self.was_connected {
+            info!("MQTT connected to {}", self.config.broker_url);
+            for (topic, qos) in &self.subscriptions {
+                if let Err(e) = self.transport.subscribe(topic, *qos) {
+                    warn!("Failed to subscribe to {}: {:?}", topic, e);
+                }
+            }
+            if self.config.use_last_will {
+                let topic = self.config.status_topic();
+                if let Err(e) = self.transport.publish(&topic, QoS::AtLeastOnce, true, b"online") {
+                    warn!("Failed to publish MQTT availability: {:?}", e);
+                }
+            }
+        } else if !connected && self.was_connected {
+            warn!("MQTT disconnected, buffering messages");
+        }
+        self.was_connected = connected;
+
+        if !connected || self.circuit_open() {
+            return Ok(());
+        }
+
+        while let Some(message) = self.pending.pop_front() {
+            match self.send(&message) {
+                Ok(()) => debug!("Flushed buffered MQTT message to {}", message.topic),
+                Err(e) => {
+                    self.pending.push_front(message);
+                    warn!("MQTT flush failed, {} message(s) still buffered: {:?}", self.pending.len(), e);
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the number of buffered messages
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Get the number of messages dropped because the buffer was full
+    pub fn dropped_count(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Get the MQTT configuration
+    pub fn config(&self) -> &MqttConfig {
+        &self.config
+    }
+
+    /// Get the underlying transport
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Get the underlying transport mutably
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Get the circuit breaker guarding publishes, if any
+    pub fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        self.breaker.as_ref()
+    }
+
+    /// Check if the circuit breaker is rejecting publishes
+    fn circuit_open(&self) -> bool {
+        self.breaker.as_ref().is_some_and(|breaker| breaker.state() == CircuitState::Open)
+    }
+
+    /// Publish through the transport, recording the outcome in the circuit breaker
+    fn send(&mut self, message: &PendingMessage) -> Result<()> {
+        let transport = &mut self.transport;
+        let mut publish = || transport.publish(&message.topic, message.qos, message.retain, &message.payload);
+        match &self.breaker {
+            Some(breaker) => breaker.call(publish),
+            None => publish(),
+        }
+    }
+
+    /// Buffer a message, dropping the oldest one if the buffer is full
+    fn enqueue(&mut self, message: PendingMessage) {
+        if self.pending.len() >= self.config.offline_buffer_size {
+            self.pending.pop_front();
+            self.dropped += 1;
+            warn!("MQTT offline buffer full, dropped oldest message");
+        }
+        self.pending.push_back(message);
+    }
+}
@@ -0,0 +1,118 @@
+use esp_idf_svc::mqtt::client::{
+    EspMqttClient, EventPayload, LwtConfiguration, MqttClientConfiguration, QoS as EspQoS,
+};
+use esp_idf_svc::sys;
+use esp_idf_svc::tls::X509;
+use anyhow::Result;
+use log::{info, warn, error};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::mqtt_task::{MqttConfig, MqttTransport, QoS, ReceivedMessage};
+
+/// Maximum number of received messages waiting to be polled
+const MAX_INBOX_LEN: usize = 16;
+
+/// MQTT transport backed by the ESP-IDF MQTT client
+pub struct EspMqttTransport {
+    client: EspMqttClient<'static>,
+    connected: Arc<AtomicBool>,
+    inbox: Arc<Mutex<VecDeque<ReceivedMessage>>>,
+}
+
+impl EspMqttTransport {
+    /// Connect to the broker described by `config`
+    pub fn new(config: &MqttConfig) -> Result<Self> {
+        config.validate()?;
+
+        let last_will = config.last_will();
+        let client_config = MqttClientConfiguration {
+            client_id: Some(&config.client_id),
+            username: config.username.as_deref(),
+            password: config.password.as_deref(),
+            keep_alive_interval: Some(Duration::from_secs(config.keep_alive_secs as u64)),
+            lwt: last_will.as_ref().map(|will| LwtConfiguration {
+                topic: &will.topic,
+                payload: will.payload.as_bytes(),
+                qos: to_esp_qos(will.qos),
+                retain: will.retain,
+            }),
+            server_certificate: config.ca_certificate
+                .filter(|_| config.uses_tls())
+                .map(|pem| X509::pem_until_nul(pem.as_bytes())),
+            // Public brokers are verified against the bundled common CAs
+            crt_bundle_attach: (config.uses_tls() && config.ca_certificate.is_none())
+                .then_some(sys::esp_crt_bundle_attach),
+            ..Default::default()
+        };
+
+        let connected = Arc::new(AtomicBool::new(false));
+        let event_connected = connected.clone();
+        let inbox = Arc::new(Mutex::new(VecDeque::new()));
+        let event_inbox = inbox.clone();
+
+        let client = EspMqttClient::new_cb(&config.broker_url, &client_config, move |event| {
+            match event.payload() {
+                EventPayload::Connected(_) => event_connected.store(true, Ordering::Relaxed),
+                EventPayload::Disconnected => event_connected.store(false, Ordering::Relaxed),
+                EventPayload::Received { topic: Some(topic), data, .. } => {
+                    let mut inbox = event_inbox.lock().unwrap();
+                    if inbox.len() >= MAX_INBOX_LEN {
+                        warn!("MQTT inbox full, dropping message on {}", topic);
+                    } else {
+                        inbox.push_back(ReceivedMessage {
+                            topic: topic.to_string(),
+                            payload: data.to_vec(),
+                        });
+                    }
+                }
+                EventPayload::Error(e) => warn!("MQTT client error: {:?}", e),
+                _ => {}
+            }
+        })
+        .map_err(|e| {
+            error!("Failed to create MQTT client: {:?}", e);
+            anyhow::anyhow!("MQTT client creation failed")
+        })?;
+
+        info!("MQTT client created for {}", config.broker_url);
+        Ok(Self {
+            client,
+            connected,
+            inbox,
+        })
+    }
+}
+
+impl MqttTransport for EspMqttTransport {
+    fn publish(&mut self, topic: &str, qos: QoS, retain: bool, payload: &[u8]) -> Result<()> {
+        self.client.enqueue(topic, to_esp_qos(qos), retain, payload)
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!("MQTT publish failed: {:?}", e))
+    }
+
+    fn subscribe(&mut self, topic: &str, qos: QoS) -> Result<()> {
+        self.client.subscribe(topic, to_esp_qos(qos))
+            .map(|_| info!("Subscribed to {}", topic))
+            .map_err(|e| anyhow::anyhow!("MQTT subscribe to {} failed: {:?}", topic, e))
+    }
+
+    fn poll_message(&mut self) -> Option<ReceivedMessage> {
+        self.inbox.lock().unwrap().pop_front()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+}
+
+/// Convert the task QoS into the ESP-IDF client QoS
+fn to_esp_qos(qos: QoS) -> EspQoS {
+    match qos {
+        QoS::AtMostOnce => EspQoS::AtMostOnce,
+        QoS::AtLeastOnce => EspQoS::AtLeastOnce,
+        QoS::ExactlyOnce => EspQoS::ExactlyOnce,
+    }
+}
@@ -0,0 +1,225 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+/// NVS namespace that held station network settings before they moved into
+/// the device configuration; still erased by a factory reset
+pub const NVS_NAMESPACE: &str = "net_cfg";
+
+/// Maximum hostname length accepted by the ESP-IDF DHCP client
+const MAX_HOSTNAME_LEN: usize = 30;
+
+/// Default hostname advertised via DHCP
+pub const DEFAULT_HOSTNAME: &str = "esp32-device";
+
+/// Default time to wait for a DHCP lease before falling back
+pub const DEFAULT_DHCP_TIMEOUT_MS: u32 = 10_000;
+
+/// Static IPv4 settings for the station interface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StaticIpConfig {
+    pub address: Ipv4Addr,
+    pub prefix_len: u8,
+    pub gateway: Ipv4Addr,
+    pub dns: Option<Ipv4Addr>,
+    pub secondary_dns: Option<Ipv4Addr>,
+}
+
+impl StaticIpConfig {
+    /// Get the subnet mask as a dotted address
+    pub fn netmask(&self) -> Ipv4Addr {
+        prefix_to_netmask(self.prefix_len)
+    }
+
+    /// Validate address, mask and gateway consistency
+    pub fn validate(&self) -> Result<()> {
+        if self.prefix_len == 0 || self.prefix_len > 32 {
+            return Err(anyhow::anyhow!("Prefix length {} is out of range [1, 32]", self.prefix_len));
+        }
+        if self.address.is_unspecified() || self.address.is_broadcast() || self.address.is_multicast() {
+            return Err(anyhow::anyhow!("Invalid static address {}", self.address));
+        }
+
+        let mask = u32::from(self.netmask());
+        if u32::from(self.address) & mask != u32::from(self.gateway) & mask {
+            return Err(anyhow::anyhow!(
+                "Gateway {} is not in subnet {}/{}",
+                self.gateway,
+                self.address,
+                self.prefix_len
+            ));
+        }
+        if self.gateway == self.address {
+            return Err(anyhow::anyhow!("Gateway {} is the static address itself", self.gateway));
+        }
+
+        // /31 and /32 subnets have no network or broadcast address
+        if self.prefix_len <= 30 {
+            let network = u32::from(self.address) & mask;
+            let broadcast = network | !mask;
+            for (name, addr) in [("address", self.address), ("gateway", self.gateway)] {
+                let addr = u32::from(addr);
+                if addr == network || addr == broadcast {
+                    return Err(anyhow::anyhow!(
+                        "Static {} {} is the network or broadcast address of {}/{}",
+                        name,
+                        Ipv4Addr::from(addr),
+                        Ipv4Addr::from(network),
+                        self.prefix_len
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// How the station interface obtains its IPv4 address
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IpMode {
+    /// Always use DHCP
+    Dhcp,
+    /// Always use the given static settings
+    Static(StaticIpConfig),
+    /// Use DHCP, switching to the static settings if no lease arrives in time
+    DhcpWithFallback(StaticIpConfig),
+}
+
+/// Station interface network configuration
+///
+/// Stored in the `network` section of the device configuration as flat
+/// settings, see [`NetworkSettings`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "NetworkSettings", into = "NetworkSettings")]
+pub struct NetworkConfig {
+    pub hostname: String,
+    pub ip_mode: IpMode,
+    pub dhcp_timeout_ms: u32,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            hostname: DEFAULT_HOSTNAME.to_string(),
+            ip_mode: IpMode::Dhcp,
+            dhcp_timeout_ms: DEFAULT_DHCP_TIMEOUT_MS,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Validate hostname and static settings
+    pub fn validate(&self) -> Result<()> {
+        validate_hostname(&self.hostname)?;
+
+        match &self.ip_mode {
+            IpMode::Dhcp => Ok(()),
+            IpMode::Static(settings) | IpMode::DhcpWithFallback(settings) => settings.validate(),
+        }
+    }
+
+    /// Get the static settings, if the mode uses any
+    pub fn static_settings(&self) -> Option<&StaticIpConfig> {
+        match &self.ip_mode {
+            IpMode::Dhcp => None,
+            IpMode::Static(settings) | IpMode::DhcpWithFallback(settings) => Some(settings),
+        }
+    }
+}
+
+/// How the station interface obtains its IPv4 address, as stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpModeKind {
+    Dhcp,
+    Static,
+    DhcpWithFallback,
+}
+
+/// Stored form of [`NetworkConfig`]
+///
+/// Every setting is always present so patches can change the mode and the
+/// static settings independently; the static settings are ignored with DHCP.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    pub hostname: String,
+    pub mode: IpModeKind,
+    pub dhcp_timeout_ms: u32,
+    pub address: Ipv4Addr,
+    pub prefix_len: u8,
+    pub gateway: Ipv4Addr,
+    pub dns: Option<Ipv4Addr>,
+    pub secondary_dns: Option<Ipv4Addr>,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkConfig::default().into()
+    }
+}
+
+impl From<NetworkConfig> for NetworkSettings {
+    fn from(config: NetworkConfig) -> Self {
+        let (mode, settings) = match config.ip_mode {
+            IpMode::Dhcp => (IpModeKind::Dhcp, None),
+            IpMode::Static(settings) => (IpModeKind::Static, Some(settings)),
+            IpMode::DhcpWithFallback(settings) => (IpModeKind::DhcpWithFallback, Some(settings)),
+        };
+        Self {
+            hostname: config.hostname,
+            mode,
+            dhcp_timeout_ms: config.dhcp_timeout_ms,
+            address: settings.map_or(Ipv4Addr::UNSPECIFIED, |s| s.address),
+            prefix_len: settings.map_or(24, |s| s.prefix_len),
+            gateway: settings.map_or(Ipv4Addr::UNSPECIFIED, |s| s.gateway),
+            dns: settings.and_then(|s| s.dns),
+            secondary_dns: settings.and_then(|s| s.secondary_dns),
+        }
+    }
+}
+
+impl From<NetworkSettings> for NetworkConfig {
+    fn from(settings: NetworkSettings) -> Self {
+        let static_ip = StaticIpConfig {
+            address: settings.address,
+            prefix_len: settings.prefix_len,
+            gateway: settings.gateway,
+            dns: settings.dns,
+            secondary_dns: settings.secondary_dns,
+        };
+        Self {
+            hostname: settings.hostname,
+            ip_mode: match settings.mode {
+                IpModeKind::Dhcp => IpMode::Dhcp,
+                IpModeKind::Static => IpMode::Static(static_ip),
+                IpModeKind::DhcpWithFallback => IpMode::DhcpWithFallback(static_ip),
+            },
+            dhcp_timeout_ms: settings.dhcp_timeout_ms,
+        }
+    }
+}
+
+/// Convert a prefix length into a dotted subnet mask
+pub fn prefix_to_netmask(prefix_len: u8) -> Ipv4Addr {
+    match prefix_len {
+        0 => Ipv4Addr::UNSPECIFIED,
+        len if len >= 32 => Ipv4Addr::BROADCAST,
+        len => Ipv4Addr::from(u32::MAX << (32 - len)),
+    }
+}
+
+/// Validate a hostname against RFC 1123 label rules
+pub fn validate_hostname(hostname: &str) -> Result<()> {
+    if hostname.is_empty() || hostname.len() > MAX_HOSTNAME_LEN {
+        return Err(anyhow::anyhow!("Hostname must be 1-{} characters", MAX_HOSTNAME_LEN));
+    }
+    if hostname.starts_with('-') || hostname.ends_with('-') {
+        return Err(anyhow::anyhow!("Hostname cannot start or end with '-'"));
+    }
+    if !hostname.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(anyhow::anyhow!("Hostname may only contain letters, digits and '-'"));
+    }
+    Ok(())
+}
@@ -0,0 +1,376 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::utils::watchdog::TaskHealth;
+
+/// Firmware version of the running image
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Semantic firmware version `major.minor.patch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FirmwareVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl FirmwareVersion {
+    /// Parse a version such as `1.2.3` or `v1.2.3`
+    pub fn parse(version: &str) -> Result<Self> {
+        let trimmed = version.trim().trim_start_matches('v');
+        // Ignore pre-release and build metadata suffixes
+        let core = trimmed.split(['-', '+']).next().unwrap_or_default();
+
+        let parts: Vec<&str> = core.split('.').collect();
+        if parts.len() != 3 {
+            return Err(anyhow::anyhow!("Invalid firmware version '{}'", version));
+        }
+
+        let parse_part = |part: &str| {
+            part.parse::<u16>()
+                .map_err(|_| anyhow::anyhow!("Invalid firmware version '{}'", version))
+        };
+
+        Ok(Self {
+            major: parse_part(parts[0])?,
+            minor: parse_part(parts[1])?,
+            patch: parse_part(parts[2])?,
+        })
+    }
+
+    /// Get the version of the running firmware
+    pub fn current() -> Self {
+        Self::parse(CURRENT_VERSION).expect("CARGO_PKG_VERSION is a valid version")
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Update manifest published next to a firmware image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OtaManifest {
+    pub version: String,
+    /// HTTPS URL of the firmware image
+    pub url: String,
+    /// Hex-encoded SHA-256 of the image
+    pub sha256: String,
+    /// Image size in bytes
+    pub size: u32,
+    /// Hex-encoded Ed25519 signature over the version, size and SHA-256 digest
+    pub signature: String,
+}
+
+impl OtaManifest {
+    /// Parse and validate a JSON manifest
+    pub fn parse(json: &[u8]) -> Result<Self> {
+        let manifest: Self = serde_json::from_slice(json)
+            .map_err(|e| anyhow::anyhow!("Invalid OTA manifest: {}", e))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Check the version, URL scheme, hash, size and signature format
+    pub fn validate(&self) -> Result<()> {
+        FirmwareVersion::parse(&self.version)?;
+        if !self.url.starts_with("https://") {
+            return Err(anyhow::anyhow!("Firmware URL must use HTTPS"));
+        }
+        parse_sha256(&self.sha256)?;
+        if self.size == 0 {
+            return Err(anyhow::anyhow!("Firmware size must be non-zero"));
+        }
+        parse_hex::<64>(&self.signature)
+            .map_err(|_| anyhow::anyhow!("Signature must be 128 hex characters"))?;
+        Ok(())
+    }
+
+    /// Get the manifest's firmware version
+    pub fn firmware_version(&self) -> Result<FirmwareVersion> {
+        FirmwareVersion::parse(&self.version)
+    }
+}
+
+/// Parse a hex-encoded SHA-256 digest
+pub fn parse_sha256(hex: &str) -> Result<[u8; 32]> {
+    parse_hex(hex).map_err(|_| anyhow::anyhow!("SHA-256 must be 64 hex characters"))
+}
+
+/// Parse exactly `N` hex-encoded bytes
+pub fn parse_hex<const N: usize>(hex: &str) -> Result<[u8; N]> {
+    let hex = hex.trim();
+    if hex.len() != N * 2 || !hex.is_ascii() {
+        return Err(anyhow::anyhow!("Expected {} hex characters", N * 2));
+    }
+
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| anyhow::anyhow!("Invalid hex character"))?;
+    }
+    Ok(bytes)
+}
+
+/// Encode bytes as lowercase hex
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Result of comparing the running firmware with a manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateDecision {
+    /// The running firmware is the same or newer
+    UpToDate,
+    /// A newer version is available
+    Update(FirmwareVersion),
+    /// The manifest is older and downgrades were explicitly allowed
+    Downgrade(FirmwareVersion),
+}
+
+/// Decide whether a manifest should be installed
+pub fn check_update(current: FirmwareVersion, manifest: &OtaManifest, allow_downgrade: bool) -> Result<UpdateDecision> {
+    let available = manifest.firmware_version()?;
+
+    Ok(match available.cmp(&current) {
+        Ordering::Greater => UpdateDecision::Update(available),
+        Ordering::Less if allow_downgrade => UpdateDecision::Downgrade(available),
+        _ => UpdateDecision::UpToDate,
+    })
+}
+
+/// Hashes and size-checks an image while it is being written
+pub struct ImageVerifier {
+    hasher: Sha256,
+    received: u32,
+    expected_size: u32,
+    expected_sha256: [u8; 32],
+}
+
+impl ImageVerifier {
+    /// Create a verifier for the image described by a manifest
+    pub fn new(manifest: &OtaManifest) -> Result<Self> {
+        Ok(Self {
+            hasher: Sha256::new(),
+            received: 0,
+            expected_size: manifest.size,
+            expected_sha256: parse_sha256(&manifest.sha256)?,
+        })
+    }
+
+    /// Feed the next chunk, failing if the image grows past its declared size
+    pub fn update(&mut self, chunk: &[u8]) -> Result<()> {
+        let received = self.received as u64 + chunk.len() as u64;
+        if received > self.expected_size as u64 {
+            return Err(anyhow::anyhow!("Image exceeds declared size of {} bytes", self.expected_size));
+        }
+
+        self.hasher.update(chunk);
+        self.received = received as u32;
+        Ok(())
+    }
+
+    /// Get the number of bytes received so far
+    pub fn received(&self) -> u32 {
+        self.received
+    }
+
+    /// Check the final size and hash, returning the digest
+    pub fn finish(self) -> Result<[u8; 32]> {
+        if self.received != self.expected_size {
+            return Err(anyhow::anyhow!(
+                "Image is {} bytes, expected {}",
+                self.received,
+                self.expected_size
+            ));
+        }
+
+        let digest: [u8; 32] = self.hasher.finalize().into();
+        if digest != self.expected_sha256 {
+            return Err(anyhow::anyhow!("Image SHA-256 mismatch"));
+        }
+        Ok(digest)
+    }
+}
+
+/// Stage of an OTA update
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OtaState {
+    Idle,
+    Checking,
+    Downloading,
+    Verifying,
+    /// Image written and selected for the next boot
+    ReadyToRestart,
+    UpToDate,
+    Failed,
+}
+
+/// Progress of the current or last OTA update
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OtaStatus {
+    pub state: OtaState,
+    pub current_version: String,
+    pub target_version: Option<String>,
+    pub downloaded: u32,
+    pub total: Option<u32>,
+    pub error: Option<String>,
+}
+
+impl OtaStatus {
+    /// Create an idle status for the running firmware
+    pub fn new() -> Self {
+        Self {
+            state: OtaState::Idle,
+            current_version: CURRENT_VERSION.to_string(),
+            target_version: None,
+            downloaded: 0,
+            total: None,
+            error: None,
+        }
+    }
+
+    /// Check if an update is in progress
+    pub fn is_busy(&self) -> bool {
+        matches!(self.state, OtaState::Checking | OtaState::Downloading | OtaState::Verifying)
+    }
+
+    /// Get the download progress in percent, if the size is known
+    pub fn percent(&self) -> Option<u8> {
+        let total = self.total.filter(|total| *total > 0)?;
+        Some((self.downloaded as u64 * 100 / total as u64).min(100) as u8)
+    }
+}
+
+impl Default for OtaStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Boot state of the running image, as reported by the OTA data partition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageState {
+    /// First boot of a new image; must be confirmed or it is rolled back
+    PendingVerify,
+    Valid,
+    /// Factory image or state not tracked
+    Untracked,
+}
+
+/// What to do with the running image after the boot self-test
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootAction {
+    /// Nothing to confirm
+    None,
+    /// Confirm the new image so it keeps booting
+    MarkValid,
+    /// Mark the new image invalid and reboot into the previous one
+    Rollback,
+}
+
+/// Decide how to finish booting a possibly unconfirmed image
+pub fn boot_action(state: ImageState, self_test_passed: bool) -> BootAction {
+    match (state, self_test_passed) {
+        (ImageState::PendingVerify, true) => BootAction::MarkValid,
+        (ImageState::PendingVerify, false) => BootAction::Rollback,
+        _ => BootAction::None,
+    }
+}
+
+/// Self-test thresholds for the first boot of a new image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestConfig {
+    /// Watchdog task names that must all start and keep running
+    pub tasks: Vec<String>,
+    /// How long the tasks must run without stalling once all have started
+    pub healthy_ms: u64,
+    /// How long to wait for network connectivity; `None` skips the check
+    pub connectivity_timeout_ms: Option<u64>,
+}
+
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        Self {
+            tasks: Vec::new(),
+            healthy_ms: 60_000,
+            connectivity_timeout_ms: Some(600_000),
+        }
+    }
+}
+
+/// Outcome of the boot self-test so far
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelfTestVerdict {
+    /// Keep checking
+    Pending,
+    Passed,
+    Failed(String),
+}
+
+/// Decides whether a new image works from its watchdog health and connectivity
+///
+/// The image passes once every listed task has run without stalling for
+/// `healthy_ms` and the network was reached at least once. A stalled or
+/// stopped task fails it at once, and so does a task that hasn't started
+/// within `healthy_ms`. Missing connectivity only fails it after
+/// `connectivity_timeout_ms`, so an access point that comes up late
+/// doesn't cause a rollback.
+#[derive(Debug, Clone)]
+pub struct SelfTest {
+    config: SelfTestConfig,
+    started_ms: u64,
+    tasks_started_ms: Option<u64>,
+    connected: bool,
+}
+
+impl SelfTest {
+    /// Start a self-test at `now_ms`
+    pub fn new(config: SelfTestConfig, now_ms: u64) -> Self {
+        Self {
+            config,
+            started_ms: now_ms,
+            tasks_started_ms: None,
+            connected: false,
+        }
+    }
+
+    /// Record the watchdog state and connectivity at `now_ms` and get the verdict
+    pub fn update(&mut self, now_ms: u64, tasks: &[TaskHealth], connected: bool) -> SelfTestVerdict {
+        if let Some(task) = tasks.iter().find(|task| task.stalled) {
+            return SelfTestVerdict::Failed(format!("Task '{}' stalled", task.name));
+        }
+
+        let elapsed_ms = now_ms.saturating_sub(self.started_ms);
+        let missing = self.config.tasks.iter().find(|name| !tasks.iter().any(|task| &task.name == *name));
+        match (missing, self.tasks_started_ms) {
+            (Some(name), Some(_)) => return SelfTestVerdict::Failed(format!("Task '{}' stopped", name)),
+            (Some(name), None) if elapsed_ms >= self.config.healthy_ms => {
+                return SelfTestVerdict::Failed(format!("Task '{}' did not start", name));
+            }
+            (Some(_), None) | (None, Some(_)) => {}
+            (None, None) => self.tasks_started_ms = Some(now_ms),
+        }
+
+        self.connected |= connected;
+        let healthy = self.tasks_started_ms
+            .is_some_and(|started_ms| now_ms.saturating_sub(started_ms) >= self.config.healthy_ms);
+        let connectivity_ok = self.connected || self.config.connectivity_timeout_ms.is_none();
+        if healthy && connectivity_ok {
+            return SelfTestVerdict::Passed;
+        }
+
+        match self.config.connectivity_timeout_ms {
+            Some(timeout_ms) if !self.connected && elapsed_ms >= timeout_ms => {
+                SelfTestVerdict::Failed(format!("No network connectivity within {} ms", timeout_ms))
+            }
+            _ => SelfTestVerdict::Pending,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+use super::ota::{parse_hex, to_hex, OtaManifest};
+
+/// Public key compiled into the firmware from the `OTA_PUBLIC_KEY` build variable (hex)
+pub const BUILTIN_PUBLIC_KEY: Option<&str> = option_env!("OTA_PUBLIC_KEY");
+
+/// Prefix of every signed message, so signatures can't be reused for anything else
+const SIGNATURE_CONTEXT: &[u8] = b"esp32-template-ota-v1\0";
+
+/// Build the message signed for an image
+///
+/// Covers the manifest version and size as well as the digest, so a signed
+/// image can't be offered again under a newer version to bypass the
+/// downgrade check. Layout: context, version length (`u16` LE), version,
+/// size (`u32` LE), SHA-256 digest.
+pub fn signed_message(version: &str, size: u32, digest: &[u8; 32]) -> Vec<u8> {
+    let version_len = u16::try_from(version.len()).unwrap_or(u16::MAX);
+    let mut message = Vec::with_capacity(SIGNATURE_CONTEXT.len() + 2 + version.len() + 4 + digest.len());
+    message.extend_from_slice(SIGNATURE_CONTEXT);
+    message.extend_from_slice(&version_len.to_le_bytes());
+    message.extend_from_slice(&version.as_bytes()[..version_len as usize]);
+    message.extend_from_slice(&size.to_le_bytes());
+    message.extend_from_slice(digest);
+    message
+}
+
+/// Ed25519 key trusted to sign firmware images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OtaPublicKey(VerifyingKey);
+
+impl OtaPublicKey {
+    /// Create a key from its 32 raw bytes
+    pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self> {
+        VerifyingKey::from_bytes(bytes)
+            .map(Self)
+            .map_err(|_| anyhow::anyhow!("Invalid Ed25519 public key"))
+    }
+
+    /// Create a key from 64 hex characters
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = parse_hex::<32>(hex)
+            .map_err(|_| anyhow::anyhow!("Public key must be 64 hex characters"))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Get the key compiled into the firmware, if any
+    pub fn builtin() -> Result<Option<Self>> {
+        BUILTIN_PUBLIC_KEY.map(Self::from_hex).transpose()
+    }
+
+    /// Get the raw key bytes
+    pub fn to_bytes(self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// Encode the key as hex
+    pub fn to_hex(self) -> String {
+        to_hex(self.0.as_bytes())
+    }
+
+    /// Check a hex signature over a message
+    pub fn verify(&self, message: &[u8], signature_hex: &str) -> Result<()> {
+        let signature = parse_hex::<64>(signature_hex)
+            .map_err(|_| anyhow::anyhow!("Signature must be 128 hex characters"))?;
+
+        self.0.verify_strict(message, &Signature::from_bytes(&signature))
+            .map_err(|_| anyhow::anyhow!("Image signature is invalid"))
+    }
+
+    /// Check the manifest signature against its version and size and the
+    /// digest of the downloaded image
+    pub fn verify_image(&self, manifest: &OtaManifest, digest: &[u8; 32]) -> Result<()> {
+        self.verify(&signed_message(&manifest.version, manifest.size, digest), &manifest.signature)
+    }
+}
+
+/// Ed25519 secret key used by the host signing tool
+pub struct OtaSigningKey(SigningKey);
+
+impl OtaSigningKey {
+    /// Create a key from its 32-byte seed
+    pub fn from_bytes(seed: &[u8; 32]) -> Self {
+        Self(SigningKey::from_bytes(seed))
+    }
+
+    /// Create a key from a 64 hex character seed
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let seed = parse_hex::<32>(hex)
+            .map_err(|_| anyhow::anyhow!("Secret key must be 64 hex characters"))?;
+        Ok(Self::from_bytes(&seed))
+    }
+
+    /// Encode the seed as hex
+    pub fn to_hex(&self) -> String {
+        to_hex(self.0.as_bytes())
+    }
+
+    /// Get the matching public key
+    pub fn public_key(&self) -> OtaPublicKey {
+        OtaPublicKey(self.0.verifying_key())
+    }
+
+    /// Sign a message, returning the hex signature
+    pub fn sign(&self, message: &[u8]) -> String {
+        to_hex(&self.0.sign(message).to_bytes())
+    }
+
+    /// Sign an image for the manifest with this version and size
+    pub fn sign_image(&self, version: &str, size: u32, digest: &[u8; 32]) -> String {
+        self.sign(&signed_message(version, size, digest))
+    }
+}
@@ -0,0 +1,396 @@
+use esp_idf_svc::http::client::{Client, Configuration as HttpClientConfiguration, EspHttpConnection};
+use esp_idf_svc::io::{Read, Write};
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs};
+use esp_idf_svc::ota::{EspOta, SlotState};
+use esp_idf_svc::sys::{self as sys, esp};
+use anyhow::Result;
+use log::{info, warn, error};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::ota::{
+    boot_action, check_update, BootAction, FirmwareVersion, ImageState, ImageVerifier, OtaManifest,
+    OtaState, OtaStatus, SelfTest, SelfTestConfig, SelfTestVerdict, UpdateDecision,
+};
+use super::ota_signature::OtaPublicKey;
+use crate::utils::error_handler::CircuitBreaker;
+use crate::utils::retry::{Clock, MonotonicClock};
+use crate::utils::watchdog::Watchdog;
+
+/// NVS namespace and key of a provisioned OTA public key
+const NVS_NAMESPACE: &str = "ota";
+const NVS_PUBLIC_KEY: &str = "public_key";
+
+/// Largest manifest accepted
+const MAX_MANIFEST_LEN: usize = 2048;
+
+/// Size of each chunk streamed into the OTA partition
+const CHUNK_SIZE: usize = 4096;
+
+/// HTTP timeout for manifest and image requests
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often the boot self-test checks the watchdog and connectivity
+const SELF_TEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// OTA Task downloading firmware over HTTPS into the inactive slot
+pub struct OtaTask {
+    status: Arc<Mutex<OtaStatus>>,
+    ca_certificate: Option<&'static str>,
+    public_key: Option<OtaPublicKey>,
+    allow_downgrade: bool,
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl OtaTask {
+    /// Create an OTA task reporting progress into `status`
+    ///
+    /// Updates are refused until a signing public key is set.
+    pub fn new(status: Arc<Mutex<OtaStatus>>) -> Self {
+        Self {
+            status,
+            ca_certificate: None,
+            public_key: None,
+            allow_downgrade: false,
+            breaker: None,
+        }
+    }
+
+    /// Trust images signed by the matching Ed25519 secret key
+    pub fn set_public_key(&mut self, key: OtaPublicKey) {
+        self.public_key = Some(key);
+    }
+
+    /// Trust a PEM CA certificate (NUL-terminated) for the update server
+    ///
+    /// Without one, servers are checked against the ESP-IDF certificate bundle
+    /// of common public CAs, so only private CAs need setting here.
+    pub fn set_ca_certificate(&mut self, pem: &'static str) {
+        self.ca_certificate = Some(pem);
+    }
+
+    /// Allow installing manifests older than the running firmware
+    pub fn set_allow_downgrade(&mut self, allow: bool) {
+        self.allow_downgrade = allow;
+    }
+
+    /// Stop contacting the update server while `breaker` is open
+    ///
+    /// The manifest fetch and the image download each count as one call.
+    pub fn set_circuit_breaker(&mut self, breaker: Arc<CircuitBreaker>) {
+        self.breaker = Some(breaker);
+    }
+
+    /// Get a copy of the current progress
+    pub fn status(&self) -> OtaStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Check the manifest and install a newer image.
+    ///
+    /// Returns true if a new image was installed and a restart is needed.
+    pub fn update(&self, manifest_url: &str) -> Result<bool> {
+        let result = self.run_update(manifest_url);
+        if let Err(e) = &result {
+            error!("OTA update failed: {:?}", e);
+            self.set_status(|status| {
+                status.state = OtaState::Failed;
+                status.error = Some(e.to_string());
+            });
+        }
+        result
+    }
+
+    fn run_update(&self, manifest_url: &str) -> Result<bool> {
+        let public_key = self.public_key
+            .ok_or_else(|| anyhow::anyhow!("No OTA public key configured"))?;
+
+        self.set_status(|status| {
+            *status = OtaStatus::new();
+            status.state = OtaState::Checking;
+        });
+
+        let manifest = self.guarded(|| self.fetch_manifest(manifest_url))?;
+        let target = match check_update(FirmwareVersion::current(), &manifest, self.allow_downgrade)? {
+            UpdateDecision::UpToDate => {
+                info!("Firmware {} is up to date (manifest has {})", FirmwareVersion::current(), manifest.version);
+                self.set_status(|status| status.state = OtaState::UpToDate);
+                return Ok(false);
+            }
+            UpdateDecision::Update(version) => version,
+            UpdateDecision::Downgrade(version) => {
+                warn!("Downgrading firmware to {}", version);
+                version
+            }
+        };
+
+        info!("Installing firmware {} from {}", target, manifest.url);
+        self.set_status(|status| {
+            status.state = OtaState::Downloading;
+            status.target_version = Some(target.to_string());
+            status.total = Some(manifest.size);
+        });
+
+        self.guarded(|| self.download_image(&manifest, &public_key))?;
+
+        self.set_status(|status| status.state = OtaState::ReadyToRestart);
+        info!("Firmware {} installed, restart to boot it", target);
+        Ok(true)
+    }
+
+    /// Download and parse the update manifest
+    fn fetch_manifest(&self, url: &str) -> Result<OtaManifest> {
+        if !url.starts_with("https://") {
+            return Err(anyhow::anyhow!("Manifest URL must use HTTPS"));
+        }
+
+        let mut client = self.http_client()?;
+        let mut response = client.get(url)?.submit()?;
+        if response.status() != 200 {
+            return Err(anyhow::anyhow!("Manifest request returned HTTP {}", response.status()));
+        }
+
+        let mut body = vec![0u8; MAX_MANIFEST_LEN];
+        let mut len = 0;
+        while len < body.len() {
+            let read = response.read(&mut body[len..])?;
+            if read == 0 {
+                break;
+            }
+            len += read;
+        }
+        if len == body.len() {
+            return Err(anyhow::anyhow!("Manifest exceeds {} bytes", MAX_MANIFEST_LEN));
+        }
+
+        OtaManifest::parse(&body[..len])
+    }
+
+    /// Stream the image into the inactive slot, verifying it before activation
+    fn download_image(&self, manifest: &OtaManifest, public_key: &OtaPublicKey) -> Result<()> {
+        let mut verifier = ImageVerifier::new(manifest)?;
+
+        let mut client = self.http_client()?;
+        let mut response = client.get(&manifest.url)?.submit()?;
+        if response.status() != 200 {
+            return Err(anyhow::anyhow!("Image request returned HTTP {}", response.status()));
+        }
+
+        let mut ota = EspOta::new()
+            .map_err(|e| {
+                error!("Failed to open OTA partitions: {:?}", e);
+                anyhow::anyhow!("OTA initialization failed")
+            })?;
+        let mut update = ota.initiate_update()
+            .map_err(|e| {
+                error!("Failed to start OTA update: {:?}", e);
+                anyhow::anyhow!("OTA update start failed")
+            })?;
+
+        let mut chunk = vec![0u8; CHUNK_SIZE];
+        let mut last_percent = 0;
+        let written = loop {
+            let read = match response.read(&mut chunk) {
+                Ok(0) => break Ok(()),
+                Ok(read) => read,
+                Err(e) => break Err(anyhow::anyhow!("Image download failed: {:?}", e)),
+            };
+
+            if let Err(e) = verifier.update(&chunk[..read]) {
+                break Err(e);
+            }
+            if let Err(e) = update.write_all(&chunk[..read]) {
+                break Err(anyhow::anyhow!("Writing OTA partition failed: {:?}", e));
+            }
+
+            let downloaded = verifier.received();
+            self.set_status(|status| status.downloaded = downloaded);
+
+            let percent = self.status().percent().unwrap_or(0);
+            if percent >= last_percent + 10 {
+                info!("OTA download {}% ({} bytes)", percent, downloaded);
+                last_percent = percent;
+            }
+        };
+
+        self.set_status(|status| status.state = OtaState::Verifying);
+        let verified = written
+            .and_then(|_| verifier.finish())
+            .and_then(|digest| public_key.verify_image(manifest, &digest));
+        if let Err(e) = verified {
+            if let Err(abort_error) = update.abort() {
+                warn!("Failed to abort OTA update: {:?}", abort_error);
+            }
+            return Err(e);
+        }
+
+        // Completing the update selects the new slot for the next boot
+        update.complete()
+            .map_err(|e| {
+                error!("Failed to activate OTA image: {:?}", e);
+                anyhow::anyhow!("OTA image activation failed")
+            })
+    }
+
+    /// Create an HTTPS client trusting the configured CA, or else the certificate bundle
+    fn http_client(&self) -> Result<Client<EspHttpConnection>> {
+        if let Some(pem) = self.ca_certificate {
+            esp!(unsafe { sys::esp_tls_set_global_ca_store(pem.as_ptr(), pem.len() as u32) })
+                .map_err(|e| {
+                    error!("Failed to install OTA CA certificate: {:?}", e);
+                    anyhow::anyhow!("OTA CA certificate installation failed")
+                })?;
+        }
+
+        let connection = EspHttpConnection::new(&HttpClientConfiguration {
+            timeout: Some(HTTP_TIMEOUT),
+            use_global_ca_store: self.ca_certificate.is_some(),
+            crt_bundle_attach: self.ca_certificate.is_none().then_some(sys::esp_crt_bundle_attach),
+            ..Default::default()
+        })
+        .map_err(|e| {
+            error!("Failed to create HTTP client: {:?}", e);
+            anyhow::anyhow!("HTTP client creation failed")
+        })?;
+
+        Ok(Client::wrap(connection))
+    }
+
+    /// Run a request to the update server through the circuit breaker, if any
+    fn guarded<T>(&self, request: impl FnOnce() -> Result<T>) -> Result<T> {
+        match &self.breaker {
+            Some(breaker) => breaker.call(request),
+            None => request(),
+        }
+    }
+
+    fn set_status(&self, update: impl FnOnce(&mut OtaStatus)) {
+        update(&mut self.status.lock().unwrap());
+    }
+}
+
+/// Get the OTA signing key, preferring the one compiled into the firmware
+///
+/// A key stored in NVS is only used when none was compiled in.
+pub fn load_public_key(partition: EspDefaultNvsPartition) -> Result<Option<OtaPublicKey>> {
+    if let Some(key) = OtaPublicKey::builtin()? {
+        return Ok(Some(key));
+    }
+
+    let nvs = EspNvs::new(partition, NVS_NAMESPACE, true)
+        .map_err(|e| {
+            error!("Failed to open OTA NVS namespace: {:?}", e);
+            anyhow::anyhow!("OTA NVS access failed")
+        })?;
+
+    let mut buf = [0u8; 32];
+    match nvs.get_blob(NVS_PUBLIC_KEY, &mut buf)? {
+        Some(bytes) if bytes.len() == buf.len() => Ok(Some(OtaPublicKey::from_bytes(&buf)?)),
+        Some(_) => Err(anyhow::anyhow!("Stored OTA public key has the wrong length")),
+        None => Ok(None),
+    }
+}
+
+/// Store an OTA signing key in NVS for firmware built without one
+pub fn store_public_key(partition: EspDefaultNvsPartition, key: &OtaPublicKey) -> Result<()> {
+    let mut nvs = EspNvs::new(partition, NVS_NAMESPACE, true)
+        .map_err(|e| {
+            error!("Failed to open OTA NVS namespace: {:?}", e);
+            anyhow::anyhow!("OTA NVS access failed")
+        })?;
+
+    nvs.set_blob(NVS_PUBLIC_KEY, &key.to_bytes())
+        .map_err(|e| {
+            error!("Failed to store OTA public key: {:?}", e);
+            anyhow::anyhow!("OTA public key storage failed")
+        })
+}
+
+fn open_ota() -> Result<EspOta> {
+    EspOta::new()
+        .map_err(|e| {
+            error!("Failed to open OTA partitions: {:?}", e);
+            anyhow::anyhow!("OTA initialization failed")
+        })
+}
+
+fn image_state(ota: &EspOta) -> Result<ImageState> {
+    let slot = ota.get_running_slot()
+        .map_err(|e| {
+            error!("Failed to read running OTA slot: {:?}", e);
+            anyhow::anyhow!("OTA slot reading failed")
+        })?;
+
+    Ok(match slot.state {
+        SlotState::Unverified => ImageState::PendingVerify,
+        SlotState::Valid => ImageState::Valid,
+        _ => ImageState::Untracked,
+    })
+}
+
+/// Get the boot state of the running image
+pub fn running_image_state() -> Result<ImageState> {
+    image_state(&open_ota()?)
+}
+
+/// Run the boot self-test in the background if the running image is unconfirmed
+///
+/// Call once the watched tasks have been spawned. The image is marked valid
+/// when the test passes and rolled back when it fails; see `SelfTest`.
+pub fn spawn_self_test<F>(config: SelfTestConfig, watchdog: Watchdog, connected: F) -> Result<()>
+where
+    F: Fn() -> bool + Send + 'static,
+{
+    if running_image_state()? != ImageState::PendingVerify {
+        return Ok(());
+    }
+
+    info!("New firmware pending verification, running self-test...");
+    std::thread::Builder::new()
+        .name("ota_self_test".to_string())
+        .stack_size(4096)
+        .spawn(move || {
+            let clock = MonotonicClock::new();
+            let mut test = SelfTest::new(config, clock.now_ms());
+            let passed = loop {
+                match test.update(clock.now_ms(), &watchdog.health(), connected()) {
+                    SelfTestVerdict::Pending => std::thread::sleep(SELF_TEST_INTERVAL),
+                    SelfTestVerdict::Passed => break true,
+                    SelfTestVerdict::Failed(reason) => {
+                        error!("Firmware self-test failed: {}", reason);
+                        break false;
+                    }
+                }
+            };
+            if let Err(e) = validate_boot(passed) {
+                error!("OTA boot validation failed: {:?}", e);
+            }
+        })?;
+
+    Ok(())
+}
+
+/// Confirm or roll back a freshly installed image after the boot self-test
+pub fn validate_boot(self_test_passed: bool) -> Result<BootAction> {
+    let mut ota = open_ota()?;
+    let action = boot_action(image_state(&ota)?, self_test_passed);
+    match action {
+        BootAction::None => {}
+        BootAction::MarkValid => {
+            ota.mark_running_slot_valid()
+                .map_err(|e| {
+                    error!("Failed to mark firmware valid: {:?}", e);
+                    anyhow::anyhow!("Marking firmware valid failed")
+                })?;
+            info!("New firmware passed self-test and was marked valid");
+        }
+        BootAction::Rollback => {
+            error!("New firmware failed self-test, rolling back...");
+            let e = ota.mark_running_slot_invalid_and_reboot();
+            error!("Rollback failed: {:?}", e);
+            return Err(anyhow::anyhow!("OTA rollback failed"));
+        }
+    }
+
+    Ok(action)
+}
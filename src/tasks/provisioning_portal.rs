@@ -0,0 +1,187 @@
+use anyhow::Result;
+use std::net::Ipv4Addr;
+
+use super::wifi_credentials::WifiNetwork;
+use super::wifi_scan::ScanResult;
+
+/// Priority assigned to networks added through the portal when none is given
+pub const DEFAULT_PRIORITY: u8 = 10;
+
+/// Decode an `application/x-www-form-urlencoded` component
+pub fn url_decode(input: &str) -> Result<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = input.get(i + 1..i + 3)
+                    .ok_or_else(|| anyhow::anyhow!("Truncated percent escape"))?;
+                // `from_str_radix` alone would accept a sign such as "%+f"
+                if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    anyhow::bail!("Invalid percent escape '%{}'", hex);
+                }
+                let byte = u8::from_str_radix(hex, 16)?;
+                decoded.push(byte);
+                i += 2;
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+
+    String::from_utf8(decoded).map_err(|_| anyhow::anyhow!("Form value is not valid UTF-8"))
+}
+
+/// Parse a URL-encoded form body into key/value pairs
+pub fn parse_form(body: &str) -> Result<Vec<(String, String)>> {
+    body.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((url_decode(key)?, url_decode(value)?))
+        })
+        .collect()
+}
+
+/// Parse and validate the credentials form submitted by the portal page
+pub fn parse_credentials_form(body: &str) -> Result<WifiNetwork> {
+    let fields = parse_form(body)?;
+    let field = |name: &str| {
+        fields.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let ssid = field("ssid").unwrap_or("").trim();
+    if ssid.is_empty() || ssid.len() > 32 {
+        return Err(anyhow::anyhow!("SSID must be 1-32 bytes"));
+    }
+
+    let password = field("password").unwrap_or("");
+    if !password.is_empty() && !(8..=64).contains(&password.len()) {
+        return Err(anyhow::anyhow!("Password must be empty or 8-64 characters"));
+    }
+
+    let priority = match field("priority").map(str::trim) {
+        None | Some("") => DEFAULT_PRIORITY,
+        Some(value) => value.parse::<u8>()
+            .map_err(|_| anyhow::anyhow!("Priority must be a number between 0 and 255"))?,
+    };
+
+    Ok(WifiNetwork::new(ssid.to_string(), password.to_string(), priority))
+}
+
+/// Escape text for safe inclusion in HTML
+pub fn html_escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render the portal page listing scanned networks and the credentials form
+pub fn render_portal_page(networks: &[ScanResult], message: Option<&str>) -> String {
+    let mut options = String::new();
+    for network in networks.iter().filter(|n| !n.hidden) {
+        let ssid = html_escape(&network.ssid);
+        options.push_str(&format!(
+            "<option value=\"{}\">{} ({} dBm, {})</option>",
+            ssid, ssid, network.rssi, network.auth_mode
+        ));
+    }
+
+    let banner = message
+        .map(|m| format!("<p class=\"msg\">{}</p>", html_escape(m)))
+        .unwrap_or_default();
+
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width,initial-scale=1\">\
+         <title>Device WiFi Setup</title>\
+         <style>body{{font-family:sans-serif;max-width:24em;margin:2em auto}}\
+         input,select,button{{width:100%;margin:.3em 0;padding:.4em}}.msg{{color:#b00}}</style>\
+         </head><body><h1>WiFi Setup</h1>{}\
+         <form method=\"post\" action=\"/connect\">\
+         <label>Network</label><select onchange=\"ssid.value=this.value\">\
+         <option value=\"\">-- select --</option>{}</select>\
+         <label>SSID</label><input name=\"ssid\" id=\"ssid\" maxlength=\"32\" required>\
+         <label>Password</label><input name=\"password\" type=\"password\" maxlength=\"64\">\
+         <button type=\"submit\">Connect</button></form></body></html>",
+        banner, options
+    )
+}
+
+/// Render the page shown after credentials were validated and saved
+pub fn render_success_page(ssid: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Device WiFi Setup</title></head>\
+         <body><h1>Connected</h1><p>Saved network '{}'. The device is restarting.</p></body></html>",
+        html_escape(ssid)
+    )
+}
+
+/// Build a DNS response resolving every A query to the portal address.
+///
+/// Returns `None` for packets that are not standard single-question queries,
+/// which the caller should ignore.
+pub fn build_dns_response(query: &[u8], address: Ipv4Addr) -> Option<Vec<u8>> {
+    const HEADER_LEN: usize = 12;
+
+    if query.len() < HEADER_LEN {
+        return None;
+    }
+
+    let flags = u16::from_be_bytes([query[2], query[3]]);
+    let question_count = u16::from_be_bytes([query[4], query[5]]);
+    let is_query = flags & 0x8000 == 0;
+    let opcode = (flags >> 11) & 0x0f;
+    if !is_query || opcode != 0 || question_count != 1 {
+        return None;
+    }
+
+    // Walk the question name to find where QTYPE/QCLASS start
+    let mut pos = HEADER_LEN;
+    loop {
+        let label_len = *query.get(pos)? as usize;
+        pos += 1;
+        if label_len == 0 {
+            break;
+        }
+        pos += label_len;
+    }
+    let question_end = pos + 4;
+    if question_end > query.len() {
+        return None;
+    }
+    let qtype = u16::from_be_bytes([query[pos], query[pos + 1]]);
+
+    let mut response = Vec::with_capacity(question_end + 16);
+    response.extend_from_slice(&query[0..2]); // transaction ID
+    response.extend_from_slice(&[0x81, 0x80]); // response, recursion available
+    response.extend_from_slice(&[0x00, 0x01]); // one question
+    let answer_count: u16 = if qtype == 1 { 1 } else { 0 };
+    response.extend_from_slice(&answer_count.to_be_bytes());
+    response.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // no authority/additional
+    response.extend_from_slice(&query[HEADER_LEN..question_end]);
+
+    if answer_count == 1 {
+        response.extend_from_slice(&[0xc0, 0x0c]); // pointer to question name
+        response.extend_from_slice(&[0x00, 0x01, 0x00, 0x01]); // type A, class IN
+        response.extend_from_slice(&60u32.to_be_bytes()); // TTL
+        response.extend_from_slice(&[0x00, 0x04]);
+        response.extend_from_slice(&address.octets());
+    }
+
+    Some(response)
+}
@@ -0,0 +1,211 @@
+use esp_idf_svc::http::server::{Configuration as HttpConfiguration, EspHttpServer};
+use esp_idf_svc::http::Method;
+use esp_idf_svc::io::{Read, Write};
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs};
+use anyhow::Result;
+use log::{info, warn, error};
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::provisioning_portal::{
+    build_dns_response, parse_credentials_form, render_portal_page, render_success_page,
+};
+use super::wifi_scan::{dedup_by_ssid, ScanResult};
+use super::wifi_task::WifiTask;
+
+/// NVS namespace holding the provisioning request flag
+pub const NVS_NAMESPACE: &str = "provisioning";
+
+/// Largest form body accepted by the portal
+const MAX_FORM_LEN: usize = 512;
+
+/// Delay before restarting so the success page reaches the browser
+const RESTART_DELAY_MS: u32 = 2000;
+
+/// Provisioning Task serving a captive portal over a SoftAP
+pub struct ProvisioningTask {
+    wifi: Arc<Mutex<WifiTask>>,
+    ap_ssid: String,
+}
+
+impl ProvisioningTask {
+    /// Create a new provisioning task around an initialized WiFi task
+    pub fn new(wifi: WifiTask, ap_ssid: String) -> Self {
+        Self {
+            wifi: Arc::new(Mutex::new(wifi)),
+            ap_ssid,
+        }
+    }
+
+    /// Run the captive portal until credentials are saved, then restart
+    pub fn run(self) -> Result<()> {
+        info!("Starting WiFi provisioning on '{}'...", self.ap_ssid);
+
+        let (networks, portal_ip) = {
+            let mut wifi = self.wifi.lock().unwrap();
+            wifi.start_access_point(&self.ap_ssid)?;
+
+            // Scan once up front; scanning while serving clients disrupts the AP
+            let networks = match wifi.scan() {
+                Ok(results) => dedup_by_ssid(results),
+                Err(e) => {
+                    warn!("Provisioning scan failed: {:?}", e);
+                    Vec::new()
+                }
+            };
+
+            (networks, wifi.access_point_ip()?)
+        };
+
+        let done = Arc::new(AtomicBool::new(false));
+
+        let dns_done = done.clone();
+        std::thread::Builder::new()
+            .name("dns_portal".to_string())
+            .stack_size(4096)
+            .spawn(move || {
+                if let Err(e) = run_dns_server(portal_ip, &dns_done) {
+                    error!("Captive portal DNS server stopped: {:?}", e);
+                }
+            })?;
+
+        let _server = self.start_http_server(networks, portal_ip, done.clone())?;
+        info!("Captive portal running at http://{}/", portal_ip);
+
+        while !done.load(Ordering::Relaxed) {
+            esp_idf_hal::delay::FreeRtos::delay_ms(100);
+        }
+
+        info!("WiFi credentials saved, restarting into station mode...");
+        esp_idf_hal::delay::FreeRtos::delay_ms(RESTART_DELAY_MS);
+        esp_idf_hal::reset::restart();
+    }
+
+    /// Register the portal pages and the catch-all redirect
+    fn start_http_server(
+        &self,
+        networks: Vec<ScanResult>,
+        portal_ip: Ipv4Addr,
+        done: Arc<AtomicBool>,
+    ) -> Result<EspHttpServer<'static>> {
+        let mut server = EspHttpServer::new(&HttpConfiguration {
+            uri_match_wildcard: true,
+            ..Default::default()
+        })
+        .map_err(|e| {
+            error!("Failed to start provisioning HTTP server: {:?}", e);
+            anyhow::anyhow!("Provisioning HTTP server start failed")
+        })?;
+
+        let networks = Arc::new(networks);
+
+        let page_networks = networks.clone();
+        server.fn_handler("/", Method::Get, move |req| {
+            let page = render_portal_page(&page_networks, None);
+            req.into_response(200, Some("OK"), &[("Content-Type", "text/html")])?
+                .write_all(page.as_bytes())?;
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        let wifi = self.wifi.clone();
+        server.fn_handler("/connect", Method::Post, move |mut req| {
+            let content_len = req.content_len().unwrap_or(0) as usize;
+            if content_len > MAX_FORM_LEN {
+                warn!("Rejecting {} byte provisioning form", content_len);
+                req.into_response(413, Some("Payload Too Large"), &[("Content-Type", "text/plain")])?
+                    .write_all(b"Form too large")?;
+                return Ok(());
+            }
+
+            let mut body = [0u8; MAX_FORM_LEN];
+            let mut len = 0;
+            while len < body.len() {
+                let read = req.read(&mut body[len..])?;
+                if read == 0 {
+                    break;
+                }
+                len += read;
+            }
+
+            let page = match std::str::from_utf8(&body[..len])
+                .map_err(|_| anyhow::anyhow!("Form body is not valid UTF-8"))
+                .and_then(parse_credentials_form)
+            {
+                Err(e) => render_portal_page(&networks, Some(&e.to_string())),
+                Ok(network) => {
+                    let mut wifi = wifi.lock().unwrap();
+                    match wifi.connect_network(&network) {
+                        Ok(()) => {
+                            wifi.add_network(network.ssid.clone(), network.password.clone(), network.priority)?;
+                            done.store(true, Ordering::Relaxed);
+                            render_success_page(&network.ssid)
+                        }
+                        Err(e) => {
+                            warn!("Provisioning test connection failed: {:?}", e);
+                            render_portal_page(
+                                &networks,
+                                Some(&format!("Could not connect to '{}'", network.ssid)),
+                            )
+                        }
+                    }
+                }
+            };
+
+            req.into_response(200, Some("OK"), &[("Content-Type", "text/html")])?
+                .write_all(page.as_bytes())?;
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        // Redirect everything else (OS connectivity checks) to the portal page
+        let location = format!("http://{}/", portal_ip);
+        server.fn_handler("/*", Method::Get, move |req| {
+            req.into_response(302, Some("Found"), &[("Location", location.as_str())])?;
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        Ok(server)
+    }
+}
+
+/// Answer every DNS query with the portal address so clients open the portal
+fn run_dns_server(portal_ip: Ipv4Addr, done: &AtomicBool) -> Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:53")?;
+    socket.set_read_timeout(Some(Duration::from_millis(500)))?;
+
+    let mut buf = [0u8; 512];
+    while !done.load(Ordering::Relaxed) {
+        let (len, peer) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(response) = build_dns_response(&buf[..len], portal_ip) {
+            if let Err(e) = socket.send_to(&response, peer) {
+                warn!("Failed to send DNS response: {:?}", e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask for provisioning mode on the next boot
+pub fn request_provisioning(partition: EspDefaultNvsPartition) -> Result<()> {
+    let mut nvs = EspNvs::new(partition, NVS_NAMESPACE, true)?;
+    nvs.set_u8("requested", 1)?;
+    info!("Provisioning requested for next boot");
+    Ok(())
+}
+
+/// Check and clear a pending provisioning request
+pub fn take_provisioning_request(partition: EspDefaultNvsPartition) -> Result<bool> {
+    let mut nvs = EspNvs::new(partition, NVS_NAMESPACE, true)?;
+    let requested = nvs.get_u8("requested")?.unwrap_or(0) != 0;
+    if requested {
+        nvs.remove("requested")?;
+    }
+    Ok(requested)
+}
@@ -1,15 +1,48 @@
-use anyhow::Result;
-use log::{info, warn, error};
-use esp_idf_hal::delay::FreeRtos;
+use serde::Serialize;
+
+use crate::utils::wall_clock::Timestamp;
+
+// The simulated sensors need the ESP-IDF RNG and timers; the readings are plain data
+#[cfg(target_os = "espidf")]
+use {
+    esp_idf_hal::delay::FreeRtos,
+    log::{info, warn, error},
+    crate::error::{Error, Result},
+    crate::tasks::device_config::Thresholds,
+    crate::utils::time_utils::{get_uptime_ms, timestamp_now},
+    crate::utils::watchdog::WatchdogHandle,
+};
+
+/// Time between readings in `run_loop`
+#[cfg(target_os = "espidf")]
+const READ_INTERVAL_MS: u32 = 5000;
+
+/// Longest sleep between watchdog feeds in `run_loop`
+#[cfg(target_os = "espidf")]
+const FEED_INTERVAL_MS: u32 = 1000;
+
+/// A timestamped set of sensor readings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SensorReadings {
+    pub temperature: f32,
+    pub humidity: f32,
+    pub pressure: f32,
+    pub uptime_ms: u64,
+    /// Uptime until the clock is synchronized, then UTC
+    pub timestamp: Timestamp,
+}
 
 /// Sensor Task for handling sensor operations in background
+#[cfg(target_os = "espidf")]
 pub struct SensorTask {
     temperature: f32,
     humidity: f32,
     pressure: f32,
     is_active: bool,
+    thresholds: Thresholds,
 }
 
+#[cfg(target_os = "espidf")]
 impl SensorTask {
     /// Create a new sensor task
     pub fn new() -> Self {
@@ -18,6 +51,7 @@ impl SensorTask {
             humidity: 0.0,
             pressure: 0.0,
             is_active: false,
+            thresholds: Thresholds::default(),
         }
     }
 
@@ -46,7 +80,7 @@ impl SensorTask {
     /// Read temperature sensor
     pub fn read_temperature(&mut self) -> Result<f32> {
         if !self.is_active {
-            return Err(anyhow::anyhow!("Sensor task not active"));
+            return Err(Error::NotInitialized("Sensor task"));
         }
 
         // Simulate sensor reading with some noise
@@ -60,7 +94,7 @@ impl SensorTask {
     /// Read humidity sensor
     pub fn read_humidity(&mut self) -> Result<f32> {
         if !self.is_active {
-            return Err(anyhow::anyhow!("Sensor task not active"));
+            return Err(Error::NotInitialized("Sensor task"));
         }
 
         // Simulate sensor reading with some noise
@@ -74,7 +108,7 @@ impl SensorTask {
     /// Read pressure sensor
     pub fn read_pressure(&mut self) -> Result<f32> {
         if !self.is_active {
-            return Err(anyhow::anyhow!("Sensor task not active"));
+            return Err(Error::NotInitialized("Sensor task"));
         }
 
         // Simulate sensor reading with some noise
@@ -94,41 +128,67 @@ impl SensorTask {
         Ok((temp, humidity, pressure))
     }
 
+    /// Read all sensors into a timestamped snapshot
+    pub fn read_snapshot(&mut self) -> Result<SensorReadings> {
+        let (temperature, humidity, pressure) = self.read_all_sensors()?;
+
+        Ok(SensorReadings {
+            temperature,
+            humidity,
+            pressure,
+            uptime_ms: get_uptime_ms(),
+            timestamp: timestamp_now(),
+        })
+    }
+
     /// Get current sensor values (without reading from hardware)
     pub fn get_current_values(&self) -> (f32, f32, f32) {
         (self.temperature, self.humidity, self.pressure)
     }
 
+    /// Set the levels that are logged as warnings
+    pub fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    /// Log a warning for readings beyond the thresholds
+    pub fn check_thresholds(&self, temperature: f32, humidity: f32) {
+        if temperature > self.thresholds.high_temperature_c {
+            warn!("High temperature detected: {:.1}°C", temperature);
+        }
+
+        if humidity < self.thresholds.low_humidity_pct {
+            warn!("Low humidity detected: {:.1}%", humidity);
+        }
+    }
+
     /// Check if sensor task is active
     pub fn is_active(&self) -> bool {
         self.is_active
     }
 
-    /// Run sensor task loop (for background operation)
-    pub fn run_loop(&mut self) -> Result<()> {
+    /// Run sensor task loop (for background operation), feeding `watchdog` while waiting
+    pub fn run_loop(&mut self, watchdog: &WatchdogHandle) -> Result<()> {
         if !self.is_active {
-            return Err(anyhow::anyhow!("Sensor task not active"));
+            return Err(Error::NotInitialized("Sensor task"));
         }
 
         loop {
             match self.read_all_sensors() {
-                Ok((temp, humidity, pressure)) => {
+                Ok((temp, humidity, _pressure)) => {
                     // Process sensor data here
-                    if temp > 30.0 {
-                        warn!("High temperature detected: {:.1}°C", temp);
-                    }
-                    
-                    if humidity < 20.0 {
-                        warn!("Low humidity detected: {:.1}%", humidity);
-                    }
+                    self.check_thresholds(temp, humidity);
                 }
                 Err(e) => {
                     error!("Failed to read sensors: {:?}", e);
                 }
             }
 
-            // Wait before next reading
-            FreeRtos::delay_ms(5000); // 5 seconds
+            // Wait before next reading in steps short enough for the watchdog
+            for _ in 0..READ_INTERVAL_MS / FEED_INTERVAL_MS {
+                watchdog.feed();
+                FreeRtos::delay_ms(FEED_INTERVAL_MS);
+            }
         }
     }
 } 
\ No newline at end of file
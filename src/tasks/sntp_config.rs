@@ -0,0 +1,58 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::wall_clock::PosixTz;
+
+/// Shortest resync interval accepted by the SNTP client
+pub const MIN_SYNC_INTERVAL_MS: u32 = 15_000;
+
+/// Longest resync interval, one day
+pub const MAX_SYNC_INTERVAL_MS: u32 = 86_400_000;
+
+/// Number of SNTP servers ESP-IDF is built for (`CONFIG_LWIP_SNTP_MAX_SERVERS`)
+pub const MAX_SNTP_SERVERS: usize = 3;
+
+/// SNTP servers, resync interval and local time zone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SntpConfig {
+    /// Servers in order of preference
+    pub servers: Vec<String>,
+    pub sync_interval_ms: u32,
+    /// POSIX TZ string used by `now_local`, e.g. `CET-1CEST,M3.5.0,M10.5.0/3`
+    pub time_zone: String,
+}
+
+impl Default for SntpConfig {
+    fn default() -> Self {
+        Self {
+            servers: vec!["pool.ntp.org".to_string(), "time.google.com".to_string()],
+            sync_interval_ms: 3_600_000,
+            time_zone: "UTC0".to_string(),
+        }
+    }
+}
+
+impl SntpConfig {
+    /// Check the servers, the resync interval and the time zone
+    pub fn validate(&self) -> Result<()> {
+        if !self.servers_valid() {
+            return Err(anyhow::anyhow!("SNTP needs 1-{} server names", MAX_SNTP_SERVERS));
+        }
+        if !(MIN_SYNC_INTERVAL_MS..=MAX_SYNC_INTERVAL_MS).contains(&self.sync_interval_ms) {
+            return Err(anyhow::anyhow!(
+                "SNTP sync interval must be {}-{} ms",
+                MIN_SYNC_INTERVAL_MS,
+                MAX_SYNC_INTERVAL_MS
+            ));
+        }
+        PosixTz::parse(&self.time_zone)?;
+        Ok(())
+    }
+
+    /// Check that 1 to `MAX_SNTP_SERVERS` non-empty server names are set
+    pub fn servers_valid(&self) -> bool {
+        let servers = &self.servers;
+        !servers.is_empty() && servers.len() <= MAX_SNTP_SERVERS && servers.iter().all(|s| !s.trim().is_empty())
+    }
+}
@@ -0,0 +1,66 @@
+use esp_idf_svc::sntp::{EspSntp, OperatingMode, SntpConf, SyncMode, SyncStatus};
+use esp_idf_svc::sys;
+use anyhow::Result;
+use log::{info, warn, error};
+use std::time::Duration;
+
+use super::sntp_config::SntpConfig;
+use crate::utils::time_utils::{now_local, record_time_sync, set_time_zone};
+use crate::utils::wall_clock::SyncReport;
+
+/// SNTP Task keeping the wall clock in `time_utils` synchronized
+pub struct SntpTask {
+    sntp: EspSntp<'static>,
+}
+
+impl SntpTask {
+    /// Start synchronizing, calling `on_sync` after every successful sync
+    pub fn start<F>(config: &SntpConfig, mut on_sync: F) -> Result<Self>
+    where
+        F: FnMut(&SyncReport) + Send + 'static,
+    {
+        config.validate()?;
+        set_time_zone(&config.time_zone)?;
+
+        let mut conf = SntpConf {
+            operating_mode: OperatingMode::Poll,
+            sync_mode: SyncMode::Immediate,
+            ..Default::default()
+        };
+        if config.servers.len() > conf.servers.len() {
+            warn!("Only the first {} SNTP server(s) are used", conf.servers.len());
+        }
+        for (slot, server) in conf.servers.iter_mut().zip(&config.servers) {
+            *slot = server.as_str();
+        }
+
+        // Applies from the first sync when set before the client starts
+        unsafe { sys::sntp_set_sync_interval(config.sync_interval_ms) };
+
+        let sntp = EspSntp::new_with_callback(&conf, move |since_epoch: Duration| {
+            let report = record_time_sync(since_epoch.as_millis() as i64);
+            on_sync(&report);
+        })
+        .map_err(|e| {
+            error!("Failed to start SNTP: {:?}", e);
+            anyhow::anyhow!("SNTP initialization failed")
+        })?;
+
+        info!("SNTP started with {} server(s), resync every {} s", config.servers.len(), config.sync_interval_ms / 1000);
+        Ok(Self { sntp })
+    }
+
+    /// Check if the last sync attempt completed
+    pub fn is_synced(&self) -> bool {
+        self.sntp.get_sync_status() == SyncStatus::Completed
+    }
+}
+
+/// Log a sync, including the clock correction after the first one
+pub fn log_sync(report: &SyncReport) {
+    let local = now_local().map(|time| time.to_string()).unwrap_or_default();
+    match report.correction_ms {
+        None => info!("Clock synchronized: {}", local),
+        Some(correction_ms) => info!("Clock resynchronized: {} (corrected by {} ms)", local, correction_ms),
+    }
+}
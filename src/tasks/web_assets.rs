@@ -0,0 +1,4 @@
+use super::dashboard::WebAsset;
+
+// Generated by build.rs from the files in `web/`
+include!(concat!(env!("OUT_DIR"), "/web_assets.rs"));
@@ -0,0 +1,245 @@
+use super::wifi_scan::ScanResult;
+use crate::error::{Error, Result};
+
+// The store persists to ESP-IDF NVS; `WifiNetwork` and the ordering are plain data
+#[cfg(target_os = "espidf")]
+use {
+    esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault},
+    log::{info, warn, error},
+};
+
+/// NVS namespace used for stored WiFi credentials
+pub const NVS_NAMESPACE: &str = "wifi_creds";
+
+/// Maximum number of networks kept in the credential store
+pub const MAX_NETWORKS: usize = 8;
+
+/// Maximum SSID length allowed by 802.11
+pub const MAX_SSID_LEN: usize = 32;
+
+/// Maximum WPA2 passphrase length
+pub const MAX_PASSWORD_LEN: usize = 64;
+
+/// A stored WiFi network with its connection priority
+#[derive(Debug, Clone, PartialEq)]
+pub struct WifiNetwork {
+    pub ssid: String,
+    pub password: String,
+    pub priority: u8,
+}
+
+impl WifiNetwork {
+    /// Create a new network entry
+    pub fn new(ssid: String, password: String, priority: u8) -> Self {
+        Self {
+            ssid,
+            password,
+            priority,
+        }
+    }
+}
+
+/// Add a network, replacing any existing entry with the same SSID
+pub fn upsert_network(networks: &mut Vec<WifiNetwork>, network: WifiNetwork) -> Result<()> {
+    if network.ssid.is_empty() || network.ssid.len() > MAX_SSID_LEN {
+        return Err(Error::out_of_range("SSID length", network.ssid.len() as f64, 1, MAX_SSID_LEN as f64));
+    }
+    if network.password.len() > MAX_PASSWORD_LEN {
+        return Err(Error::out_of_range("Password length", network.password.len() as f64, 0, MAX_PASSWORD_LEN as f64));
+    }
+
+    if let Some(existing) = networks.iter_mut().find(|n| n.ssid == network.ssid) {
+        *existing = network;
+    } else {
+        if networks.len() >= MAX_NETWORKS {
+            return Err(Error::InvalidState(format!("Credential store is full ({} networks)", MAX_NETWORKS)));
+        }
+        networks.push(network);
+    }
+    Ok(())
+}
+
+/// Order stored networks for connection attempts.
+///
+/// Visible networks come first by priority, then by best RSSI; networks
+/// not seen in the scan (e.g. hidden SSIDs) follow in priority order.
+pub fn connection_order(networks: &[WifiNetwork], visible: &[ScanResult]) -> Vec<WifiNetwork> {
+    let best_rssi = |ssid: &str| {
+        visible.iter()
+            .filter(|r| r.ssid == ssid)
+            .map(|r| r.rssi)
+            .max()
+    };
+
+    let mut candidates: Vec<(WifiNetwork, Option<i8>)> = networks.iter()
+        .map(|n| (n.clone(), best_rssi(&n.ssid)))
+        .collect();
+
+    candidates.sort_by(|(a, a_rssi), (b, b_rssi)| {
+        b_rssi.is_some().cmp(&a_rssi.is_some())
+            .then(b.priority.cmp(&a.priority))
+            .then(b_rssi.cmp(a_rssi))
+    });
+
+    candidates.into_iter().map(|(n, _)| n).collect()
+}
+
+/// Credential store holding multiple WiFi networks, optionally persisted in NVS
+#[cfg(target_os = "espidf")]
+pub struct CredentialStore {
+    networks: Vec<WifiNetwork>,
+    nvs: Option<EspNvs<NvsDefault>>,
+}
+
+#[cfg(target_os = "espidf")]
+impl CredentialStore {
+    /// Create an empty in-memory credential store
+    pub fn new() -> Self {
+        Self {
+            networks: Vec::new(),
+            nvs: None,
+        }
+    }
+
+    /// Load the credential store from NVS
+    pub fn load(partition: EspDefaultNvsPartition) -> Result<Self> {
+        let nvs = EspNvs::new(partition, NVS_NAMESPACE, true)
+            .map_err(|e| {
+                error!("Failed to open WiFi credential namespace: {:?}", e);
+                Error::nvs("WiFi credential namespace open", e)
+            })?;
+
+        let count = nvs.get_u8("count")
+            .map_err(|e| {
+                error!("Failed to read WiFi credential count: {:?}", e);
+                Error::nvs("WiFi credential count read", e)
+            })?
+            .unwrap_or(0) as usize;
+
+        let mut networks = Vec::with_capacity(count);
+        for index in 0..count.min(MAX_NETWORKS) {
+            match Self::read_network(&nvs, index) {
+                Ok(Some(network)) => networks.push(network),
+                Ok(None) => warn!("WiFi credential slot {} is incomplete, skipping", index),
+                Err(e) => warn!("Failed to read WiFi credential slot {}: {:?}", index, e),
+            }
+        }
+
+        info!("Loaded {} WiFi network(s) from NVS", networks.len());
+        Ok(Self {
+            networks,
+            nvs: Some(nvs),
+        })
+    }
+
+    /// Add a network, replacing any existing entry with the same SSID
+    pub fn add_network(&mut self, network: WifiNetwork) -> Result<()> {
+        upsert_network(&mut self.networks, network)?;
+        self.persist()
+    }
+
+    /// Remove a network by SSID, returning whether it was present
+    pub fn remove_network(&mut self, ssid: &str) -> Result<bool> {
+        let len_before = self.networks.len();
+        self.networks.retain(|n| n.ssid != ssid);
+
+        if self.networks.len() == len_before {
+            return Ok(false);
+        }
+
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// List stored networks, highest priority first
+    pub fn list_networks(&self) -> Vec<WifiNetwork> {
+        let mut networks = self.networks.clone();
+        networks.sort_by(|a, b| b.priority.cmp(&a.priority));
+        networks
+    }
+
+    /// Remove all stored networks
+    pub fn clear(&mut self) -> Result<()> {
+        self.networks.clear();
+        self.persist()
+    }
+
+    /// Check if the store has no networks
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+    }
+
+    /// Get the number of stored networks
+    pub fn len(&self) -> usize {
+        self.networks.len()
+    }
+
+    /// Order stored networks for connection attempts, see [`connection_order`]
+    pub fn connection_order(&self, visible: &[ScanResult]) -> Vec<WifiNetwork> {
+        connection_order(&self.networks, visible)
+    }
+
+    /// Write the current network list to NVS (no-op for in-memory stores)
+    fn persist(&mut self) -> Result<()> {
+        let Some(nvs) = &mut self.nvs else {
+            return Ok(());
+        };
+
+        for (index, network) in self.networks.iter().enumerate() {
+            nvs.set_str(&format!("ssid{}", index), &network.ssid)
+                .and_then(|_| nvs.set_str(&format!("pass{}", index), &network.password))
+                .and_then(|_| nvs.set_u8(&format!("prio{}", index), network.priority))
+                .map_err(|e| {
+                    error!("Failed to write WiFi credential slot {}: {:?}", index, e);
+                    Error::nvs(format!("WiFi credential slot {} write", index), e)
+                })?;
+        }
+
+        // Drop slots left over from a previously longer list
+        for index in self.networks.len()..MAX_NETWORKS {
+            for key in ["ssid", "pass", "prio"] {
+                nvs.remove(&format!("{}{}", key, index))
+                    .map_err(|e| {
+                        error!("Failed to clear WiFi credential slot {}: {:?}", index, e);
+                        Error::nvs(format!("WiFi credential slot {} removal", index), e)
+                    })?;
+            }
+        }
+
+        nvs.set_u8("count", self.networks.len() as u8)
+            .map_err(|e| {
+                error!("Failed to write WiFi credential count: {:?}", e);
+                Error::nvs("WiFi credential count write", e)
+            })?;
+
+        info!("Saved {} WiFi network(s) to NVS", self.networks.len());
+        Ok(())
+    }
+
+    /// Read a single network slot from NVS
+    fn read_network(nvs: &EspNvs<NvsDefault>, index: usize) -> Result<Option<WifiNetwork>> {
+        let mut ssid_buf = [0u8; MAX_SSID_LEN + 1];
+        let mut pass_buf = [0u8; MAX_PASSWORD_LEN + 1];
+        let read_error = |e| Error::nvs(format!("WiFi credential slot {} read", index), e);
+
+        let ssid = nvs.get_str(&format!("ssid{}", index), &mut ssid_buf).map_err(read_error)?;
+        let password = nvs.get_str(&format!("pass{}", index), &mut pass_buf).map_err(read_error)?;
+        let priority = nvs.get_u8(&format!("prio{}", index)).map_err(read_error)?;
+
+        Ok(match (ssid, password) {
+            (Some(ssid), Some(password)) => Some(WifiNetwork::new(
+                ssid.to_string(),
+                password.to_string(),
+                priority.unwrap_or(0),
+            )),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(target_os = "espidf")]
+impl Default for CredentialStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+
+/// Lowest TX power accepted by the WiFi driver, in dBm
+pub const MIN_TX_POWER_DBM: f32 = 2.0;
+
+/// Highest TX power accepted by the WiFi driver, in dBm
+pub const MAX_TX_POWER_DBM: f32 = 20.0;
+
+/// Settings for the SoftAP run alongside the station interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessPointSettings {
+    pub ssid: String,
+    /// Empty for an open network, otherwise 8-64 characters (WPA2)
+    pub password: String,
+    pub channel: u8,
+    pub max_connections: u16,
+    pub hidden: bool,
+}
+
+impl AccessPointSettings {
+    /// Create settings for an open access point
+    pub fn open(ssid: &str) -> Self {
+        Self {
+            ssid: ssid.to_string(),
+            password: String::new(),
+            channel: 1,
+            max_connections: 4,
+            hidden: false,
+        }
+    }
+
+    /// Create settings for a WPA2-protected access point
+    pub fn secured(ssid: &str, password: &str) -> Self {
+        Self {
+            password: password.to_string(),
+            ..Self::open(ssid)
+        }
+    }
+
+    /// Validate SSID, password and channel
+    pub fn validate(&self) -> Result<()> {
+        if self.ssid.is_empty() || self.ssid.len() > 32 {
+            return Err(anyhow::anyhow!("Access point SSID must be 1-32 bytes"));
+        }
+        if !self.password.is_empty() && !(8..=64).contains(&self.password.len()) {
+            return Err(anyhow::anyhow!("Access point password must be empty or 8-64 characters"));
+        }
+        if !(1..=13).contains(&self.channel) {
+            return Err(anyhow::anyhow!("Access point channel {} is out of range [1, 13]", self.channel));
+        }
+        if self.max_connections == 0 || self.max_connections > 10 {
+            return Err(anyhow::anyhow!("Access point max connections must be 1-10"));
+        }
+        Ok(())
+    }
+
+    /// Check if the access point requires a password
+    pub fn is_secured(&self) -> bool {
+        !self.password.is_empty()
+    }
+}
+
+/// WiFi modem power-save profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PowerSaveMode {
+    /// Radio always on; lowest latency, highest current
+    None,
+    /// Wake for every DTIM beacon
+    #[default]
+    MinModem,
+    /// Wake every `listen_interval` beacons; lowest current, highest latency
+    MaxModem { listen_interval: u16 },
+}
+
+impl PowerSaveMode {
+    /// Validate the listen interval for max modem mode
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            PowerSaveMode::MaxModem { listen_interval } if *listen_interval == 0 => {
+                Err(anyhow::anyhow!("Listen interval must be at least 1 beacon"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Convert a TX power in dBm into the driver's 0.25 dBm units
+pub fn tx_power_to_quarter_dbm(dbm: f32) -> Result<i8> {
+    if !(MIN_TX_POWER_DBM..=MAX_TX_POWER_DBM).contains(&dbm) {
+        return Err(anyhow::anyhow!(
+            "TX power {} dBm is out of range [{}, {}]",
+            dbm,
+            MIN_TX_POWER_DBM,
+            MAX_TX_POWER_DBM
+        ));
+    }
+    Ok((dbm * 4.0).round() as i8)
+}
+
+/// Convert the driver's 0.25 dBm units into dBm
+pub fn quarter_dbm_to_tx_power(quarter_dbm: i8) -> f32 {
+    quarter_dbm as f32 / 4.0
+}
@@ -0,0 +1,105 @@
+use std::cmp::Reverse;
+use std::fmt;
+
+/// Authentication mode advertised by an access point
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMode {
+    Open,
+    Wep,
+    Wpa,
+    Wpa2Personal,
+    WpaWpa2Personal,
+    Wpa2Enterprise,
+    Wpa3Personal,
+    Wpa2Wpa3Personal,
+    Wapi,
+    Unknown,
+}
+
+impl AuthMode {
+    /// Check if the network requires a password
+    pub fn requires_password(&self) -> bool {
+        !matches!(self, AuthMode::Open)
+    }
+}
+
+impl fmt::Display for AuthMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AuthMode::Open => "open",
+            AuthMode::Wep => "WEP",
+            AuthMode::Wpa => "WPA",
+            AuthMode::Wpa2Personal => "WPA2",
+            AuthMode::WpaWpa2Personal => "WPA/WPA2",
+            AuthMode::Wpa2Enterprise => "WPA2-Enterprise",
+            AuthMode::Wpa3Personal => "WPA3",
+            AuthMode::Wpa2Wpa3Personal => "WPA2/WPA3",
+            AuthMode::Wapi => "WAPI",
+            AuthMode::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A single access point found during a WiFi scan
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanResult {
+    pub ssid: String,
+    pub bssid: [u8; 6],
+    pub channel: u8,
+    pub rssi: i8,
+    pub auth_mode: AuthMode,
+    pub hidden: bool,
+}
+
+impl ScanResult {
+    /// Format the BSSID as a colon-separated MAC address
+    pub fn bssid_string(&self) -> String {
+        self.bssid.iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+}
+
+/// Keep only access points at or above the given signal strength
+pub fn filter_min_rssi(results: Vec<ScanResult>, min_rssi: i8) -> Vec<ScanResult> {
+    results.into_iter().filter(|r| r.rssi >= min_rssi).collect()
+}
+
+/// Remove hidden access points (those broadcasting an empty SSID)
+pub fn filter_visible(results: Vec<ScanResult>) -> Vec<ScanResult> {
+    results.into_iter().filter(|r| !r.hidden).collect()
+}
+
+/// Sort access points by signal strength, strongest first
+pub fn sort_by_rssi(results: &mut [ScanResult]) {
+    results.sort_by_key(|r| Reverse(r.rssi));
+}
+
+/// Sort access points alphabetically by SSID
+pub fn sort_by_ssid(results: &mut [ScanResult]) {
+    results.sort_by(|a, b| a.ssid.cmp(&b.ssid));
+}
+
+/// Collapse mesh access points sharing an SSID into the strongest one.
+///
+/// Hidden access points are kept as-is since their SSIDs are unknown.
+/// The result is sorted by signal strength, strongest first.
+pub fn dedup_by_ssid(mut results: Vec<ScanResult>) -> Vec<ScanResult> {
+    sort_by_rssi(&mut results);
+
+    let mut seen: Vec<String> = Vec::new();
+    results.retain(|r| {
+        if r.hidden {
+            return true;
+        }
+        if seen.contains(&r.ssid) {
+            return false;
+        }
+        seen.push(r.ssid.clone());
+        true
+    });
+
+    results
+}
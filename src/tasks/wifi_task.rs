@@ -1,110 +1,622 @@
-use esp_idf_svc::wifi::{EspWifi, WifiConfiguration};
+use esp_idf_svc::wifi::{
+    AccessPointConfiguration, AccessPointInfo, AuthMethod, ClientConfiguration, EspWifi,
+    WifiConfiguration,
+};
+use esp_idf_svc::wifi::config::ScanConfig;
+use esp_idf_svc::ipv4;
+use esp_idf_svc::netif::{EspNetif, NetifConfiguration};
 use esp_idf_svc::nvs::EspDefaultNvsPartition;
 use esp_idf_svc::eventloop::EspSystemEventLoop;
-use anyhow::Result;
+use esp_idf_svc::sys::{self as sys, esp};
+use esp_idf_hal::modem::Modem;
 use log::{info, warn, error};
 
+use crate::error::{Error, EspCode, Result};
+
+use super::network_config::{IpMode, NetworkConfig};
+use super::wifi_modes::{quarter_dbm_to_tx_power, tx_power_to_quarter_dbm, AccessPointSettings, PowerSaveMode};
+use super::wifi_credentials::{CredentialStore, WifiNetwork};
+use super::wifi_scan::{AuthMode, ScanResult};
+
+/// How long to wait for a single network to connect
+const CONNECT_TIMEOUT_MS: u32 = 15_000;
+
 /// WiFi Task for handling WiFi operations in background
 pub struct WifiTask {
     wifi: Option<EspWifi<'static>>,
-    ssid: String,
-    password: String,
+    credentials: CredentialStore,
+    access_point: Option<AccessPointConfiguration>,
+    network_config: NetworkConfig,
+    power_save: PowerSaveMode,
 }
 
 impl WifiTask {
-    /// Create a new WiFi task
+    /// Create a new WiFi task for a single network
     pub fn new(ssid: String, password: String) -> Self {
+        let mut credentials = CredentialStore::new();
+        if let Err(e) = credentials.add_network(WifiNetwork::new(ssid, password, 0)) {
+            warn!("Ignoring invalid WiFi credentials: {:?}", e);
+        }
+
+        Self::with_credentials(credentials)
+    }
+
+    /// Create a new WiFi task backed by a credential store
+    pub fn with_credentials(credentials: CredentialStore) -> Self {
         Self {
             wifi: None,
-            ssid,
-            password,
+            credentials,
+            access_point: None,
+            network_config: NetworkConfig::default(),
+            power_save: PowerSaveMode::default(),
         }
     }
 
+    /// Set the station IP, hostname and DNS configuration.
+    ///
+    /// Takes effect immediately if WiFi is already initialized.
+    pub fn set_network_config(&mut self, config: NetworkConfig) -> Result<()> {
+        config.validate().map_err(Error::invalid_argument)?;
+        self.network_config = config;
+
+        if self.wifi.is_some() {
+            self.apply_ip_settings(false)?;
+        }
+        Ok(())
+    }
+
+    /// Get the current station network configuration
+    pub fn network_config(&self) -> &NetworkConfig {
+        &self.network_config
+    }
+
     /// Initialize WiFi
     pub fn init(&mut self) -> Result<()> {
         let nvs = EspDefaultNvsPartition::take()
             .map_err(|e| {
                 error!("Failed to get NVS partition: {:?}", e);
-                anyhow::anyhow!("NVS partition acquisition failed")
+                Error::nvs("NVS partition acquisition", e)
             })?;
 
         let sysloop = EspSystemEventLoop::take()
             .map_err(|e| {
                 error!("Failed to get system event loop: {:?}", e);
-                anyhow::anyhow!("System event loop acquisition failed")
+                Error::wifi("System event loop acquisition", e)
             })?;
 
-        let wifi = EspWifi::new(
-            esp_idf_hal::peripherals::Peripherals::take()
-                .map_err(|e| {
-                    error!("Failed to acquire peripherals: {:?}", e);
-                    anyhow::anyhow!("Peripheral acquisition failed")
-                })?
-                .modem,
-            sysloop.clone(),
-            Some(nvs),
-        )
-        .map_err(|e| {
-            error!("Failed to create WiFi instance: {:?}", e);
-            anyhow::anyhow!("WiFi instance creation failed")
-        })?;
+        let modem = esp_idf_hal::peripherals::Peripherals::take()
+            .map_err(|e| {
+                error!("Failed to acquire peripherals: {:?}", e);
+                Error::wifi("Peripheral acquisition", e)
+            })?
+            .modem;
+
+        self.init_with_modem(modem, sysloop, nvs)
+    }
+
+    /// Initialize WiFi with resources already acquired by the caller
+    pub fn init_with_modem(
+        &mut self,
+        modem: Modem,
+        sysloop: EspSystemEventLoop,
+        nvs: EspDefaultNvsPartition,
+    ) -> Result<()> {
+        let wifi = EspWifi::new(modem, sysloop, Some(nvs))
+            .map_err(|e| {
+                error!("Failed to create WiFi instance: {:?}", e);
+                Error::wifi("WiFi instance creation", e)
+            })?;
 
         self.wifi = Some(wifi);
+        self.apply_ip_settings(false)?;
+        self.apply_power_save()?;
         info!("WiFi initialized successfully");
         Ok(())
     }
 
-    /// Connect to WiFi network
+    /// Rebuild the station interface for the configured IP mode.
+    ///
+    /// With `use_fallback` set, `DhcpWithFallback` switches to its static settings.
+    fn apply_ip_settings(&mut self, use_fallback: bool) -> Result<()> {
+        let static_settings = match self.network_config.ip_mode {
+            IpMode::Static(settings) => Some(settings),
+            IpMode::DhcpWithFallback(settings) if use_fallback => Some(settings),
+            _ => None,
+        };
+
+        let ip_configuration = match static_settings {
+            Some(settings) => ipv4::ClientConfiguration::Fixed(ipv4::ClientSettings {
+                ip: settings.address,
+                subnet: ipv4::Subnet {
+                    gateway: settings.gateway,
+                    mask: ipv4::Mask(settings.prefix_len),
+                },
+                dns: settings.dns,
+                secondary_dns: settings.secondary_dns,
+            }),
+            None => ipv4::ClientConfiguration::DHCP(ipv4::DHCPClientSettings {
+                hostname: Some(self.network_config.hostname.as_str().try_into()
+                    .map_err(|_| Error::invalid_argument("Hostname too long"))?),
+            }),
+        };
+
+        let mut netif = EspNetif::new_with_conf(&NetifConfiguration {
+            ip_configuration: Some(ipv4::Configuration::Client(ip_configuration)),
+            ..NetifConfiguration::wifi_default_client()
+        })
+        .map_err(|e| {
+            error!("Failed to create station interface: {:?}", e);
+            Error::wifi("Station interface creation", e)
+        })?;
+
+        netif.set_hostname(&self.network_config.hostname)
+            .map_err(|e| {
+                error!("Failed to set hostname: {:?}", e);
+                Error::wifi("Hostname configuration", e)
+            })?;
+
+        let Some(wifi) = &mut self.wifi else {
+            return Err(Error::NotInitialized("WiFi"));
+        };
+
+        wifi.swap_netif_sta(netif)
+            .map_err(|e| {
+                error!("Failed to attach station interface: {:?}", e);
+                Error::wifi("Station interface attach", e)
+            })?;
+
+        match static_settings {
+            Some(settings) => info!(
+                "Station using static IP {}/{} via {}",
+                settings.address, settings.prefix_len, settings.gateway
+            ),
+            None => info!("Station using DHCP as '{}'", self.network_config.hostname),
+        }
+        Ok(())
+    }
+
+    /// Wait for the station interface to obtain an IPv4 address
+    fn wait_for_ip(&self, timeout_ms: u32) -> Result<bool> {
+        let Some(wifi) = &self.wifi else {
+            return Err(Error::NotInitialized("WiFi"));
+        };
+
+        let mut waited_ms = 0;
+        while waited_ms < timeout_ms {
+            let has_ip = wifi.sta_netif().get_ip_info()
+                .map(|info| !info.ip.is_unspecified())
+                .unwrap_or(false);
+            if has_ip {
+                return Ok(true);
+            }
+
+            esp_idf_hal::delay::FreeRtos::delay_ms(100);
+            waited_ms += 100;
+        }
+
+        Ok(false)
+    }
+
+    /// Connect to the best available stored network.
+    ///
+    /// Scans for visible networks, then tries stored networks by priority and
+    /// signal strength, falling back to the next one if a connection fails.
     pub fn connect(&mut self) -> Result<()> {
+        if self.credentials.is_empty() {
+            return Err(Error::InvalidState("No WiFi networks configured".to_string()));
+        }
+
+        let visible = match self.scan() {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("WiFi scan failed, trying stored networks blindly: {:?}", e);
+                Vec::new()
+            }
+        };
+
+        for network in self.credentials.connection_order(&visible) {
+            match self.connect_network(&network) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    warn!("Failed to connect to '{}': {:?}, trying next network", network.ssid, e);
+                    if let Some(wifi) = &mut self.wifi {
+                        let _ = wifi.disconnect();
+                    }
+                }
+            }
+        }
+
+        error!("Could not connect to any stored WiFi network");
+        Err(Error::wifi("WiFi connection", EspCode::WIFI_CONN))
+    }
+
+    /// Connect to a single network and wait for the result.
+    ///
+    /// Keeps the access point running if one was started, so this can be used
+    /// to validate credentials during provisioning.
+    pub fn connect_network(&mut self, network: &WifiNetwork) -> Result<()> {
+        let client = ClientConfiguration {
+            ssid: network.ssid.as_str().try_into()
+                .map_err(|_| Error::invalid_argument("SSID too long"))?,
+            password: network.password.as_str().try_into()
+                .map_err(|_| Error::invalid_argument("Password too long"))?,
+            ..Default::default()
+        };
+        let wifi_configuration = self.station_configuration(client);
+
+        let Some(wifi) = &mut self.wifi else {
+            return Err(Error::NotInitialized("WiFi"));
+        };
+
+        wifi.set_configuration(&wifi_configuration)
+            .map_err(|e| {
+                error!("Failed to set WiFi configuration: {:?}", e);
+                Error::wifi("WiFi configuration", e)
+            })?;
+
+        if !wifi.is_started().unwrap_or(false) {
+            wifi.start()
+                .map_err(|e| {
+                    error!("Failed to start WiFi: {:?}", e);
+                    Error::wifi("WiFi start", e)
+                })?;
+        }
+
+        // Setting the client configuration resets the listen interval, and the
+        // AP only learns it from the association request
+        self.apply_power_save()?;
+
+        let Some(wifi) = &mut self.wifi else {
+            return Err(Error::NotInitialized("WiFi"));
+        };
+
+        wifi.connect()
+            .map_err(|e| {
+                error!("Failed to start WiFi connection: {:?}", e);
+                Error::wifi("WiFi connect", e)
+            })?;
+
+        info!("Attempting to connect to '{}'...", network.ssid);
+
+        // Wait for connection
+        let mut waited_ms = 0;
+        loop {
+            let status = wifi.get_status();
+            match status {
+                esp_idf_svc::wifi::WifiStatus::Started => {
+                    info!("WiFi started, waiting for connection...");
+                }
+                esp_idf_svc::wifi::WifiStatus::Connected => {
+                    info!("WiFi connected to '{}' successfully!", network.ssid);
+                    break;
+                }
+                esp_idf_svc::wifi::WifiStatus::Failed => {
+                    error!("WiFi connection to '{}' failed", network.ssid);
+                    return Err(Error::wifi("WiFi connection", EspCode::WIFI_CONN));
+                }
+                _ => {
+                    warn!("WiFi status: {:?}", status);
+                }
+            }
+
+            if waited_ms >= CONNECT_TIMEOUT_MS {
+                error!("Timed out connecting to '{}'", network.ssid);
+                return Err(Error::timeout("WiFi connection", CONNECT_TIMEOUT_MS));
+            }
+
+            esp_idf_hal::delay::FreeRtos::delay_ms(100);
+            waited_ms += 100;
+        }
+
+        match self.network_config.ip_mode {
+            IpMode::Static(_) => Ok(()),
+            IpMode::Dhcp => {
+                if !self.wait_for_ip(self.network_config.dhcp_timeout_ms)? {
+                    warn!("No DHCP lease yet from '{}'", network.ssid);
+                }
+                Ok(())
+            }
+            IpMode::DhcpWithFallback(_) => {
+                if !self.wait_for_ip(self.network_config.dhcp_timeout_ms)? {
+                    warn!("No DHCP lease from '{}', falling back to static IP", network.ssid);
+                    self.apply_ip_settings(true)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Start an open SoftAP alongside the station interface
+    pub fn start_access_point(&mut self, ssid: &str) -> Result<()> {
+        self.start_access_point_with(&AccessPointSettings::open(ssid))
+    }
+
+    /// Start a SoftAP alongside the station interface (AP+STA mode).
+    ///
+    /// An existing station connection is kept, so this can be used for local
+    /// maintenance access while connected upstream.
+    pub fn start_access_point_with(&mut self, settings: &AccessPointSettings) -> Result<()> {
+        settings.validate().map_err(Error::invalid_argument)?;
+
+        let access_point = AccessPointConfiguration {
+            ssid: settings.ssid.as_str().try_into()
+                .map_err(|_| Error::invalid_argument("Access point SSID too long"))?,
+            password: settings.password.as_str().try_into()
+                .map_err(|_| Error::invalid_argument("Access point password too long"))?,
+            auth_method: if settings.is_secured() {
+                AuthMethod::WPA2Personal
+            } else {
+                AuthMethod::None
+            },
+            channel: settings.channel,
+            max_connections: settings.max_connections,
+            ssid_hidden: settings.hidden,
+            ..Default::default()
+        };
+        self.access_point = Some(access_point);
+
+        let wifi_configuration = self.station_configuration(self.current_client_configuration());
+        let Some(wifi) = &mut self.wifi else {
+            return Err(Error::NotInitialized("WiFi"));
+        };
+
+        wifi.set_configuration(&wifi_configuration)
+            .map_err(|e| {
+                error!("Failed to set access point configuration: {:?}", e);
+                Error::wifi("Access point configuration", e)
+            })?;
+
+        if !wifi.is_started().unwrap_or(false) {
+            wifi.start()
+                .map_err(|e| {
+                    error!("Failed to start access point: {:?}", e);
+                    Error::wifi("Access point start", e)
+                })?;
+        }
+
+        info!("Access point '{}' started", settings.ssid);
+        Ok(())
+    }
+
+    /// Stop the SoftAP, keeping the station interface running
+    pub fn stop_access_point(&mut self) -> Result<()> {
+        if self.access_point.take().is_none() {
+            return Ok(());
+        }
+
+        let wifi_configuration = self.station_configuration(self.current_client_configuration());
+        let Some(wifi) = &mut self.wifi else {
+            return Err(Error::NotInitialized("WiFi"));
+        };
+
+        wifi.set_configuration(&wifi_configuration)
+            .map_err(|e| {
+                error!("Failed to disable access point: {:?}", e);
+                Error::wifi("Access point stop", e)
+            })?;
+
+        info!("Access point stopped");
+        Ok(())
+    }
+
+    /// Select a modem power-save profile
+    ///
+    /// A new listen interval takes effect from the next association.
+    pub fn set_power_save(&mut self, mode: PowerSaveMode) -> Result<()> {
+        mode.validate().map_err(Error::invalid_argument)?;
+        self.power_save = mode;
+
+        if self.wifi.is_some() {
+            self.apply_power_save()?;
+        }
+        Ok(())
+    }
+
+    /// Get the selected modem power-save profile
+    pub fn power_save(&self) -> PowerSaveMode {
+        self.power_save
+    }
+
+    /// Limit the maximum TX power, in dBm
+    ///
+    /// The driver only accepts this once started.
+    pub fn set_max_tx_power(&mut self, dbm: f32) -> Result<()> {
+        let started = self.wifi.as_ref()
+            .map(|wifi| wifi.is_started().unwrap_or(false))
+            .unwrap_or(false);
+        if !started {
+            return Err(Error::NotInitialized("WiFi driver"));
+        }
+
+        let quarter_dbm = tx_power_to_quarter_dbm(dbm).map_err(Error::invalid_argument)?;
+        esp!(unsafe { sys::esp_wifi_set_max_tx_power(quarter_dbm) })
+            .map_err(|e| {
+                error!("Failed to set TX power: {:?}", e);
+                Error::wifi("TX power configuration", e)
+            })?;
+
+        info!("WiFi TX power limited to {:.2} dBm", quarter_dbm_to_tx_power(quarter_dbm));
+        Ok(())
+    }
+
+    /// Get the current maximum TX power, in dBm
+    pub fn max_tx_power(&self) -> Result<f32> {
+        if self.wifi.is_none() {
+            return Err(Error::NotInitialized("WiFi"));
+        }
+
+        let mut quarter_dbm: i8 = 0;
+        esp!(unsafe { sys::esp_wifi_get_max_tx_power(&mut quarter_dbm) })
+            .map_err(|e| {
+                error!("Failed to read TX power: {:?}", e);
+                Error::wifi("TX power reading", e)
+            })?;
+
+        Ok(quarter_dbm_to_tx_power(quarter_dbm))
+    }
+
+    /// Push the selected power-save profile to the driver
+    fn apply_power_save(&mut self) -> Result<()> {
+        let ps_type = match self.power_save {
+            PowerSaveMode::None => sys::wifi_ps_type_t_WIFI_PS_NONE,
+            PowerSaveMode::MinModem => sys::wifi_ps_type_t_WIFI_PS_MIN_MODEM,
+            PowerSaveMode::MaxModem { listen_interval } => {
+                // The listen interval lives in the raw station config
+                let mut config = sys::wifi_config_t::default();
+                esp!(unsafe { sys::esp_wifi_get_config(sys::wifi_interface_t_WIFI_IF_STA, &mut config) })
+                    .and_then(|_| {
+                        unsafe { config.sta.listen_interval = listen_interval };
+                        esp!(unsafe { sys::esp_wifi_set_config(sys::wifi_interface_t_WIFI_IF_STA, &mut config) })
+                    })
+                    .map_err(|e| {
+                        error!("Failed to set listen interval: {:?}", e);
+                        Error::wifi("Listen interval configuration", e)
+                    })?;
+
+                sys::wifi_ps_type_t_WIFI_PS_MAX_MODEM
+            }
+        };
+
+        esp!(unsafe { sys::esp_wifi_set_ps(ps_type) })
+            .map_err(|e| {
+                error!("Failed to set power-save mode: {:?}", e);
+                Error::wifi("Power-save configuration", e)
+            })?;
+
+        info!("WiFi power save: {:?}", self.power_save);
+        Ok(())
+    }
+
+    /// Get the station part of the active configuration, if any
+    fn current_client_configuration(&self) -> ClientConfiguration {
+        self.wifi.as_ref()
+            .and_then(|wifi| wifi.get_configuration().ok())
+            .and_then(|config| match config {
+                WifiConfiguration::Client(client) | WifiConfiguration::Mixed(client, _) => Some(client),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Check if the SoftAP is enabled
+    pub fn is_access_point_active(&self) -> bool {
+        self.access_point.is_some()
+    }
+
+    /// Get the address clients reach the device at over the access point
+    pub fn access_point_ip(&self) -> Result<std::net::Ipv4Addr> {
+        if let Some(wifi) = &self.wifi {
+            wifi.ap_netif().get_ip_info()
+                .map(|info| info.ip)
+                .map_err(|e| {
+                    error!("Failed to read access point IP info: {:?}", e);
+                    Error::wifi("Access point IP info reading", e)
+                })
+        } else {
+            Err(Error::NotInitialized("WiFi"))
+        }
+    }
+
+    /// Check if any networks are stored
+    pub fn has_networks(&self) -> bool {
+        !self.credentials.is_empty()
+    }
+
+    /// Wrap a station configuration with the access point configuration if enabled
+    fn station_configuration(&self, client: ClientConfiguration) -> WifiConfiguration {
+        match &self.access_point {
+            Some(access_point) => WifiConfiguration::Mixed(client, access_point.clone()),
+            None => WifiConfiguration::Client(client),
+        }
+    }
+
+    /// Scan for nearby networks, blocking until the scan completes
+    pub fn scan(&mut self) -> Result<Vec<ScanResult>> {
+        self.start_scan_inner(true)?;
+        self.get_scan_results()
+    }
+
+    /// Start a scan in the background; poll `is_scan_done` for completion
+    pub fn start_scan(&mut self) -> Result<()> {
+        self.start_scan_inner(false)
+    }
+
+    /// Check if a background scan has finished
+    pub fn is_scan_done(&self) -> Result<bool> {
+        if let Some(wifi) = &self.wifi {
+            wifi.is_scan_done()
+                .map_err(|e| {
+                    error!("Failed to query WiFi scan state: {:?}", e);
+                    Error::wifi("WiFi scan state query", e)
+                })
+        } else {
+            Err(Error::NotInitialized("WiFi"))
+        }
+    }
+
+    /// Fetch the results of the last completed scan
+    pub fn get_scan_results(&mut self) -> Result<Vec<ScanResult>> {
         if let Some(wifi) = &mut self.wifi {
-            let wifi_configuration = WifiConfiguration::Client(esp_idf_svc::wifi::ClientConfiguration {
-                ssid: self.ssid.clone().into(),
-                password: self.password.clone().into(),
-                ..Default::default()
-            });
+            let access_points = wifi.get_scan_result()
+                .map_err(|e| {
+                    error!("Failed to fetch WiFi scan results: {:?}", e);
+                    Error::wifi("WiFi scan result retrieval", e)
+                })?;
+
+            info!("WiFi scan found {} access point(s)", access_points.len());
+            Ok(access_points.iter().map(to_scan_result).collect())
+        } else {
+            Err(Error::NotInitialized("WiFi"))
+        }
+    }
 
+    /// Start the driver if needed and kick off a scan
+    fn start_scan_inner(&mut self, blocking: bool) -> Result<()> {
+        let wifi_configuration = self.station_configuration(Default::default());
+        let Some(wifi) = &mut self.wifi else {
+            return Err(Error::NotInitialized("WiFi"));
+        };
+
+        // Scanning requires the driver to be started in station mode
+        if !wifi.is_started().unwrap_or(false) {
             wifi.set_configuration(&wifi_configuration)
                 .map_err(|e| {
                     error!("Failed to set WiFi configuration: {:?}", e);
-                    anyhow::anyhow!("WiFi configuration failed")
+                    Error::wifi("WiFi configuration", e)
                 })?;
 
             wifi.start()
                 .map_err(|e| {
                     error!("Failed to start WiFi: {:?}", e);
-                    anyhow::anyhow!("WiFi start failed")
+                    Error::wifi("WiFi start", e)
                 })?;
+        }
 
-            info!("WiFi started, attempting to connect...");
+        let scan_config = ScanConfig {
+            show_hidden: true,
+            ..Default::default()
+        };
 
-            // Wait for connection
-            loop {
-                let status = wifi.get_status();
-                match status {
-                    esp_idf_svc::wifi::WifiStatus::Started => {
-                        info!("WiFi started, waiting for connection...");
-                    }
-                    esp_idf_svc::wifi::WifiStatus::Connected => {
-                        info!("WiFi connected successfully!");
-                        break;
-                    }
-                    esp_idf_svc::wifi::WifiStatus::Failed => {
-                        error!("WiFi connection failed");
-                        return Err(anyhow::anyhow!("WiFi connection failed"));
-                    }
-                    _ => {
-                        warn!("WiFi status: {:?}", status);
-                    }
-                }
+        wifi.start_scan(&scan_config, blocking)
+            .map_err(|e| {
+                error!("Failed to start WiFi scan: {:?}", e);
+                Error::wifi("WiFi scan", e)
+            })
+    }
 
-                esp_idf_hal::delay::FreeRtos::delay_ms(100);
-            }
+    /// Add or update a stored network
+    pub fn add_network(&mut self, ssid: String, password: String, priority: u8) -> Result<()> {
+        self.credentials.add_network(WifiNetwork::new(ssid, password, priority))
+    }
 
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("WiFi not initialized"))
-        }
+    /// Remove a stored network by SSID
+    pub fn remove_network(&mut self, ssid: &str) -> Result<bool> {
+        self.credentials.remove_network(ssid)
+    }
+
+    /// List stored networks, highest priority first
+    pub fn list_networks(&self) -> Vec<WifiNetwork> {
+        self.credentials.list_networks()
     }
 
     /// Disconnect from WiFi
@@ -113,13 +625,13 @@ impl WifiTask {
             wifi.stop()
                 .map_err(|e| {
                     error!("Failed to stop WiFi: {:?}", e);
-                    anyhow::anyhow!("WiFi stop failed")
+                    Error::wifi("WiFi stop", e)
                 })?;
 
             info!("WiFi disconnected");
             Ok(())
         } else {
-            Err(anyhow::anyhow!("WiFi not initialized"))
+            Err(Error::NotInitialized("WiFi"))
         }
     }
 
@@ -128,7 +640,7 @@ impl WifiTask {
         if let Some(wifi) = &self.wifi {
             Ok(wifi.get_status())
         } else {
-            Err(anyhow::anyhow!("WiFi not initialized"))
+            Err(Error::NotInitialized("WiFi"))
         }
     }
 
@@ -137,7 +649,66 @@ impl WifiTask {
         if let Some(wifi) = &self.wifi {
             Ok(wifi.get_ip_info().map(|info| info.subnet.gateway))
         } else {
-            Err(anyhow::anyhow!("WiFi not initialized"))
+            Err(Error::NotInitialized("WiFi"))
+        }
+    }
+
+    /// Check if the station is connected to an access point
+    pub fn is_connected(&self) -> bool {
+        self.wifi.as_ref()
+            .and_then(|wifi| wifi.is_connected().ok())
+            .unwrap_or(false)
+    }
+
+    /// Get the SSID of the configured station network
+    pub fn connected_ssid(&self) -> Option<String> {
+        if !self.is_connected() {
+            return None;
         }
+        Some(self.current_client_configuration().ssid.to_string())
+    }
+
+    /// Get the station's own IPv4 address
+    pub fn station_ip(&self) -> Option<std::net::Ipv4Addr> {
+        self.wifi.as_ref()
+            .and_then(|wifi| wifi.sta_netif().get_ip_info().ok())
+            .map(|info| info.ip)
+            .filter(|ip| !ip.is_unspecified())
     }
-} 
\ No newline at end of file
+
+    /// Get the signal strength of the connected access point
+    pub fn rssi(&self) -> Option<i8> {
+        if !self.is_connected() {
+            return None;
+        }
+
+        let mut ap_info = sys::wifi_ap_record_t::default();
+        esp!(unsafe { sys::esp_wifi_sta_get_ap_info(&mut ap_info) }).ok()?;
+        Some(ap_info.rssi)
+    }
+}
+
+/// Convert a driver access point record into a scan result
+fn to_scan_result(ap: &AccessPointInfo) -> ScanResult {
+    let auth_mode = match ap.auth_method {
+        Some(AuthMethod::None) => AuthMode::Open,
+        Some(AuthMethod::WEP) => AuthMode::Wep,
+        Some(AuthMethod::WPA) => AuthMode::Wpa,
+        Some(AuthMethod::WPA2Personal) => AuthMode::Wpa2Personal,
+        Some(AuthMethod::WPAWPA2Personal) => AuthMode::WpaWpa2Personal,
+        Some(AuthMethod::WPA2Enterprise) => AuthMode::Wpa2Enterprise,
+        Some(AuthMethod::WPA3Personal) => AuthMode::Wpa3Personal,
+        Some(AuthMethod::WPA2WPA3Personal) => AuthMode::Wpa2Wpa3Personal,
+        Some(AuthMethod::WAPIPersonal) => AuthMode::Wapi,
+        _ => AuthMode::Unknown,
+    };
+
+    ScanResult {
+        ssid: ap.ssid.to_string(),
+        bssid: ap.bssid,
+        channel: ap.channel,
+        rssi: ap.signal_strength,
+        auth_mode,
+        hidden: ap.ssid.is_empty(),
+    }
+}
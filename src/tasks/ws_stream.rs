@@ -0,0 +1,350 @@
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::peripherals::button::ButtonEvent;
+
+use super::sensor_task::SensorReadings;
+
+/// Default number of frames queued per client before it is dropped as too slow
+pub const DEFAULT_QUEUE_CAPACITY: usize = 16;
+
+/// Default number of frames sent to each client per flush
+pub const DEFAULT_FRAMES_PER_FLUSH: usize = 4;
+
+/// An event streamed to WebSocket clients as a JSON frame
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Sensors(SensorReadings),
+    Button { id: u8, event: ButtonEvent, uptime_ms: u64 },
+}
+
+impl StreamEvent {
+    /// Get the topic clients subscribe to for this event
+    pub fn topic(&self) -> StreamTopic {
+        match self {
+            StreamEvent::Sensors(_) => StreamTopic::Sensors,
+            StreamEvent::Button { .. } => StreamTopic::Buttons,
+        }
+    }
+}
+
+/// Topics a client can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamTopic {
+    Sensors,
+    Buttons,
+}
+
+/// Per-client subscription filter and rate limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientFilter {
+    pub sensors: bool,
+    pub buttons: bool,
+    /// Minimum time between sensor frames; button events are never rate limited
+    pub min_interval_ms: u32,
+}
+
+impl ClientFilter {
+    /// Check if the filter accepts a topic
+    pub fn accepts(&self, topic: StreamTopic) -> bool {
+        match topic {
+            StreamTopic::Sensors => self.sensors,
+            StreamTopic::Buttons => self.buttons,
+        }
+    }
+}
+
+impl Default for ClientFilter {
+    fn default() -> Self {
+        Self {
+            sensors: true,
+            buttons: true,
+            min_interval_ms: 0,
+        }
+    }
+}
+
+/// Control message sent by a client, e.g. `{"subscribe": ["sensors"], "min_interval_ms": 1000}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ClientMessage {
+    pub subscribe: Option<Vec<StreamTopic>>,
+    pub min_interval_ms: Option<u32>,
+}
+
+/// Destination for frames sent to one client
+pub trait FrameSink {
+    /// Send a text frame; an error drops the client
+    fn send_text(&mut self, frame: &str) -> Result<()>;
+
+    /// Close the connection after the client has been dropped
+    fn close(&mut self) {}
+}
+
+/// Frames and closes taken out of a hub by `StreamHub::take_outgoing`
+pub struct Outgoing<S: FrameSink> {
+    batches: Vec<(i32, S, Vec<String>)>,
+    closing: Vec<S>,
+    sent: u32,
+    failed: Vec<i32>,
+}
+
+impl<S: FrameSink> Outgoing<S> {
+    /// Send each client's frames in order and close dropped clients
+    pub fn send(&mut self) {
+        for (id, sink, frames) in &mut self.batches {
+            for frame in frames.iter() {
+                if let Err(e) = sink.send_text(frame) {
+                    warn!("Failed to send to stream client {}: {:?}", id, e);
+                    self.failed.push(*id);
+                    break;
+                }
+                self.sent += 1;
+            }
+        }
+        for sink in &mut self.closing {
+            sink.close();
+        }
+    }
+
+    /// Get the number of frames taken out
+    pub fn len(&self) -> usize {
+        self.batches.iter().map(|(_, _, frames)| frames.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0 && self.closing.is_empty()
+    }
+}
+
+/// Hub counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamStats {
+    pub frames_sent: u32,
+    pub frames_rate_limited: u32,
+    pub clients_dropped: u32,
+}
+
+struct Client<S: FrameSink> {
+    sink: S,
+    filter: ClientFilter,
+    queue: VecDeque<String>,
+    last_sensor_ms: Option<u64>,
+}
+
+/// Fans out stream events to WebSocket clients with per-client queues
+pub struct StreamHub<S: FrameSink> {
+    clients: BTreeMap<i32, Client<S>>,
+    queue_capacity: usize,
+    frames_per_flush: usize,
+    stats: StreamStats,
+    /// Sinks of dropped clients, closed on the next flush
+    closing: Vec<S>,
+}
+
+impl<S: FrameSink> StreamHub<S> {
+    /// Create a hub with default queue limits
+    pub fn new() -> Self {
+        Self::with_limits(DEFAULT_QUEUE_CAPACITY, DEFAULT_FRAMES_PER_FLUSH)
+    }
+
+    /// Create a hub with custom queue capacity and flush batch size
+    pub fn with_limits(queue_capacity: usize, frames_per_flush: usize) -> Self {
+        Self {
+            clients: BTreeMap::new(),
+            queue_capacity: queue_capacity.max(1),
+            frames_per_flush: frames_per_flush.max(1),
+            stats: StreamStats::default(),
+            closing: Vec::new(),
+        }
+    }
+
+    /// Add a client subscribed to everything
+    pub fn add_client(&mut self, id: i32, sink: S) {
+        self.clients.insert(id, Client {
+            sink,
+            filter: ClientFilter::default(),
+            queue: VecDeque::new(),
+            last_sensor_ms: None,
+        });
+        info!("Stream client {} connected ({} total)", id, self.clients.len());
+    }
+
+    /// Remove a client, returning true if it was connected
+    pub fn remove_client(&mut self, id: i32) -> bool {
+        let removed = self.clients.remove(&id).is_some();
+        if removed {
+            info!("Stream client {} disconnected", id);
+        }
+        removed
+    }
+
+    /// Apply a JSON control message from a client
+    pub fn handle_client_message(&mut self, id: i32, text: &str) -> Result<()> {
+        let message: ClientMessage = serde_json::from_str(text)
+            .map_err(|e| anyhow::anyhow!("Invalid control message: {}", e))?;
+        let client = self.clients.get_mut(&id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown stream client {}", id))?;
+
+        if let Some(topics) = message.subscribe {
+            client.filter.sensors = topics.contains(&StreamTopic::Sensors);
+            client.filter.buttons = topics.contains(&StreamTopic::Buttons);
+        }
+        if let Some(min_interval_ms) = message.min_interval_ms {
+            client.filter.min_interval_ms = min_interval_ms;
+        }
+        Ok(())
+    }
+
+    /// Get a client's filter
+    pub fn filter(&self, id: i32) -> Option<&ClientFilter> {
+        self.clients.get(&id).map(|client| &client.filter)
+    }
+
+    /// Queue an event for every subscribed client.
+    ///
+    /// Clients whose queue is already full are dropped as too slow.
+    pub fn publish(&mut self, event: &StreamEvent, now_ms: u64) -> Result<()> {
+        if self.clients.is_empty() {
+            return Ok(());
+        }
+
+        let frame = serde_json::to_string(event)?;
+        let topic = event.topic();
+        let mut slow = Vec::new();
+
+        for (id, client) in self.clients.iter_mut() {
+            if !client.filter.accepts(topic) {
+                continue;
+            }
+
+            if topic == StreamTopic::Sensors {
+                let due = match client.last_sensor_ms {
+                    Some(last) => now_ms.saturating_sub(last) >= client.filter.min_interval_ms as u64,
+                    None => true,
+                };
+                if !due {
+                    self.stats.frames_rate_limited += 1;
+                    continue;
+                }
+                client.last_sensor_ms = Some(now_ms);
+            }
+
+            if client.queue.len() >= self.queue_capacity {
+                slow.push(*id);
+            } else {
+                client.queue.push_back(frame.clone());
+            }
+        }
+
+        for id in slow {
+            warn!("Stream client {} is too slow, dropping it", id);
+            self.drop_client(id);
+        }
+        Ok(())
+    }
+
+    /// Send up to one batch of queued frames to each client.
+    ///
+    /// Clients whose sink fails are dropped. A hub shared with the code that
+    /// drives the sinks should use `take_outgoing` instead, so nothing is sent
+    /// while it is locked.
+    pub fn flush(&mut self) {
+        let mut failed = Vec::new();
+
+        for (id, client) in self.clients.iter_mut() {
+            for _ in 0..self.frames_per_flush {
+                let Some(frame) = client.queue.front() else {
+                    break;
+                };
+                if let Err(e) = client.sink.send_text(frame) {
+                    warn!("Failed to send to stream client {}: {:?}", id, e);
+                    failed.push(*id);
+                    break;
+                }
+                client.queue.pop_front();
+                self.stats.frames_sent += 1;
+            }
+        }
+
+        for id in failed {
+            self.drop_client(id);
+        }
+        for mut sink in self.closing.drain(..) {
+            sink.close();
+        }
+    }
+
+    /// Count the frames sent from `take_outgoing` and drop clients whose sink failed
+    pub fn finish(&mut self, outgoing: Outgoing<S>) {
+        self.stats.frames_sent += outgoing.sent;
+        for id in outgoing.failed {
+            self.drop_client(id);
+        }
+    }
+
+    /// Get the number of frames waiting for a client
+    pub fn queued(&self, id: i32) -> usize {
+        self.clients.get(&id).map_or(0, |client| client.queue.len())
+    }
+
+    /// Get the connected client IDs
+    pub fn client_ids(&self) -> Vec<i32> {
+        self.clients.keys().copied().collect()
+    }
+
+    /// Get the hub counters
+    pub fn stats(&self) -> StreamStats {
+        self.stats
+    }
+
+    /// Get a client's sink
+    pub fn sink(&self, id: i32) -> Option<&S> {
+        self.clients.get(&id).map(|client| &client.sink)
+    }
+
+    /// Get a client's sink mutably
+    pub fn sink_mut(&mut self, id: i32) -> Option<&mut S> {
+        self.clients.get_mut(&id).map(|client| &mut client.sink)
+    }
+
+    /// Remove a misbehaving client; its connection is closed on the next flush
+    fn drop_client(&mut self, id: i32) {
+        if let Some(client) = self.clients.remove(&id) {
+            self.closing.push(client.sink);
+            self.stats.clients_dropped += 1;
+        }
+    }
+}
+
+impl<S: FrameSink + Clone> StreamHub<S> {
+    /// Take up to one batch of queued frames per client, plus the dropped
+    /// clients to close, so they can be sent after releasing the hub.
+    ///
+    /// Report the result with `finish`.
+    pub fn take_outgoing(&mut self) -> Outgoing<S> {
+        let batches = self.clients.iter_mut()
+            .filter(|(_, client)| !client.queue.is_empty())
+            .map(|(id, client)| {
+                let len = client.queue.len().min(self.frames_per_flush);
+                (*id, client.sink.clone(), client.queue.drain(..len).collect())
+            })
+            .collect();
+
+        Outgoing {
+            batches,
+            closing: std::mem::take(&mut self.closing),
+            sent: 0,
+            failed: Vec::new(),
+        }
+    }
+}
+
+impl<S: FrameSink> Default for StreamHub<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,515 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+
+use crate::error::{Error, Result};
+
+/// Format version of encoded panic records and crash rings
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Longest stored panic message, in bytes
+pub const MAX_MESSAGE_LEN: usize = 192;
+
+/// Longest stored source file path; longer paths keep their end
+pub const MAX_FILE_LEN: usize = 64;
+
+/// Longest FreeRTOS task name
+pub const MAX_TASK_LEN: usize = 16;
+
+/// Most backtrace addresses kept from a core dump
+pub const MAX_BACKTRACE_LEN: usize = 16;
+
+/// Size of the RTC memory slot holding a panic record
+pub const PANIC_SLOT_LEN: usize = 320;
+
+/// Longest stored firmware version
+pub const MAX_VERSION_LEN: usize = 32;
+
+/// Size of the NVS blob holding the crash ring
+pub const MAX_RING_LEN: usize = 4096;
+
+/// Crash entries kept in NVS unless configured otherwise
+pub const DEFAULT_RING_CAPACITY: usize = 4;
+
+/// Largest encoded entry, with every field at its stored limit
+pub const MAX_ENTRY_LEN: usize = 4 + 1 + (1 + MAX_VERSION_LEN) + 1 + MAX_PANIC_LEN + 1 + MAX_CORE_DUMP_LEN;
+
+/// Most entries whose encoding is guaranteed to fit `MAX_RING_LEN`
+pub const MAX_RING_CAPACITY: usize = (MAX_RING_LEN - FRAME_LEN - 5) / MAX_ENTRY_LEN;
+
+/// Largest encoded panic record and core dump summary
+const MAX_PANIC_LEN: usize = (1 + MAX_MESSAGE_LEN) + (1 + MAX_FILE_LEN) + 4 + 4 + (1 + MAX_TASK_LEN) + 8;
+const MAX_CORE_DUMP_LEN: usize = (1 + MAX_TASK_LEN) + 4 + (1 + 4 * MAX_BACKTRACE_LEN) + 1;
+
+/// Magic, version, length and CRC around each payload
+const FRAME_LEN: usize = 9;
+
+const PANIC_MAGIC: [u8; 2] = *b"PR";
+const RING_MAGIC: [u8; 2] = *b"CL";
+
+/// Why the chip last reset, mirroring `esp_reset_reason_t`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetReason {
+    Unknown,
+    PowerOn,
+    External,
+    Software,
+    Panic,
+    InterruptWatchdog,
+    TaskWatchdog,
+    OtherWatchdog,
+    DeepSleep,
+    Brownout,
+    Sdio,
+    Usb,
+    Jtag,
+    Efuse,
+    PowerGlitch,
+    CpuLockup,
+}
+
+impl ResetReason {
+    /// Convert an `esp_reset_reason_t` value
+    pub fn from_raw(raw: u32) -> Self {
+        match raw {
+            1 => Self::PowerOn,
+            2 => Self::External,
+            3 => Self::Software,
+            4 => Self::Panic,
+            5 => Self::InterruptWatchdog,
+            6 => Self::TaskWatchdog,
+            7 => Self::OtherWatchdog,
+            8 => Self::DeepSleep,
+            9 => Self::Brownout,
+            10 => Self::Sdio,
+            11 => Self::Usb,
+            12 => Self::Jtag,
+            13 => Self::Efuse,
+            14 => Self::PowerGlitch,
+            15 => Self::CpuLockup,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Get the `esp_reset_reason_t` value
+    pub fn to_raw(self) -> u32 {
+        match self {
+            Self::Unknown => 0,
+            Self::PowerOn => 1,
+            Self::External => 2,
+            Self::Software => 3,
+            Self::Panic => 4,
+            Self::InterruptWatchdog => 5,
+            Self::TaskWatchdog => 6,
+            Self::OtherWatchdog => 7,
+            Self::DeepSleep => 8,
+            Self::Brownout => 9,
+            Self::Sdio => 10,
+            Self::Usb => 11,
+            Self::Jtag => 12,
+            Self::Efuse => 13,
+            Self::PowerGlitch => 14,
+            Self::CpuLockup => 15,
+        }
+    }
+
+    /// Check if the reset was caused by a fault rather than a request or power-up
+    pub fn is_unexpected(self) -> bool {
+        matches!(
+            self,
+            Self::Panic
+                | Self::InterruptWatchdog
+                | Self::TaskWatchdog
+                | Self::OtherWatchdog
+                | Self::Brownout
+                | Self::PowerGlitch
+                | Self::CpuLockup
+        )
+    }
+}
+
+/// Where and why the firmware panicked, written to RTC memory by the panic hook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PanicRecord {
+    pub message: String,
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    pub task: String,
+    pub uptime_ms: u64,
+}
+
+impl PanicRecord {
+    /// Create a record, truncating the text fields to their stored lengths
+    pub fn new(message: &str, file: &str, line: u32, column: u32, task: &str, uptime_ms: u64) -> Self {
+        Self {
+            message: truncate(message, MAX_MESSAGE_LEN).to_string(),
+            file: truncate_start(file, MAX_FILE_LEN).to_string(),
+            line,
+            column,
+            task: truncate(task, MAX_TASK_LEN).to_string(),
+            uptime_ms,
+        }
+    }
+
+    /// Encode with a header and CRC so a stale or garbled slot is detected
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        self.write_to(&mut payload);
+        frame(PANIC_MAGIC, &payload)
+    }
+
+    /// Decode a record produced by `encode`, ignoring trailing bytes
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let payload = unframe(PANIC_MAGIC, bytes)?;
+        let mut reader = Reader::new(payload);
+        Self::read_from(&mut reader)
+    }
+
+    fn write_to(&self, out: &mut Vec<u8>) {
+        write_str8(out, &self.message);
+        write_str8(out, &self.file);
+        out.extend_from_slice(&self.line.to_le_bytes());
+        out.extend_from_slice(&self.column.to_le_bytes());
+        write_str8(out, &self.task);
+        out.extend_from_slice(&self.uptime_ms.to_le_bytes());
+    }
+
+    fn read_from(reader: &mut Reader) -> Result<Self> {
+        Ok(Self {
+            message: reader.str8()?,
+            file: reader.str8()?,
+            line: reader.u32()?,
+            column: reader.u32()?,
+            task: reader.str8()?,
+            uptime_ms: reader.u64()?,
+        })
+    }
+}
+
+/// Exception details from a core dump stored in flash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CoreDumpSummary {
+    /// Task that caused the exception
+    pub task: String,
+    pub program_counter: u32,
+    pub backtrace: Vec<u32>,
+    pub backtrace_corrupted: bool,
+}
+
+impl CoreDumpSummary {
+    fn write_to(&self, out: &mut Vec<u8>) {
+        write_str8(out, &self.task);
+        out.extend_from_slice(&self.program_counter.to_le_bytes());
+        let backtrace = &self.backtrace[..self.backtrace.len().min(MAX_BACKTRACE_LEN)];
+        out.push(backtrace.len() as u8);
+        for address in backtrace {
+            out.extend_from_slice(&address.to_le_bytes());
+        }
+        out.push(self.backtrace_corrupted as u8);
+    }
+
+    fn read_from(reader: &mut Reader) -> Result<Self> {
+        let task = reader.str8()?;
+        let program_counter = reader.u32()?;
+        let depth = reader.u8()? as usize;
+        let backtrace = (0..depth).map(|_| reader.u32()).collect::<Result<Vec<_>>>()?;
+        let backtrace_corrupted = reader.u8()? != 0;
+        Ok(Self { task, program_counter, backtrace, backtrace_corrupted })
+    }
+}
+
+/// One unexpected reset with whatever evidence survived it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrashEntry {
+    /// Increases with every recorded crash, also across `clear`
+    pub sequence: u32,
+    pub reset_reason: ResetReason,
+    /// Firmware version that recorded the crash on the next boot
+    pub firmware_version: String,
+    pub panic: Option<PanicRecord>,
+    pub core_dump: Option<CoreDumpSummary>,
+}
+
+impl CrashEntry {
+    fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.sequence.to_le_bytes());
+        out.push(self.reset_reason.to_raw() as u8);
+        write_str8(out, &self.firmware_version);
+
+        match &self.panic {
+            Some(panic) => {
+                out.push(1);
+                panic.write_to(out);
+            }
+            None => out.push(0),
+        }
+        match &self.core_dump {
+            Some(core_dump) => {
+                out.push(1);
+                core_dump.write_to(out);
+            }
+            None => out.push(0),
+        }
+    }
+
+    fn read_from(reader: &mut Reader) -> Result<Self> {
+        let sequence = reader.u32()?;
+        let reset_reason = ResetReason::from_raw(reader.u8()? as u32);
+        let firmware_version = reader.str8()?;
+        let panic = match reader.u8()? {
+            0 => None,
+            _ => Some(PanicRecord::read_from(reader)?),
+        };
+        let core_dump = match reader.u8()? {
+            0 => None,
+            _ => Some(CoreDumpSummary::read_from(reader)?),
+        };
+        Ok(Self { sequence, reset_reason, firmware_version, panic, core_dump })
+    }
+}
+
+/// The most recent crashes, oldest dropped first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashRing {
+    capacity: usize,
+    next_sequence: u32,
+    entries: VecDeque<CrashEntry>,
+}
+
+impl CrashRing {
+    /// Create an empty ring keeping up to `capacity` entries
+    ///
+    /// The capacity is clamped to 1..=`MAX_RING_CAPACITY` so a full ring
+    /// always fits its NVS blob.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.clamp(1, MAX_RING_CAPACITY);
+        Self {
+            capacity,
+            next_sequence: 1,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Record a crash, dropping the oldest entry if the ring is full
+    ///
+    /// Text fields and backtraces are cut to their stored limits.
+    pub fn push(
+        &mut self,
+        reset_reason: ResetReason,
+        firmware_version: &str,
+        panic: Option<PanicRecord>,
+        core_dump: Option<CoreDumpSummary>,
+    ) -> &CrashEntry {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        let panic = panic.map(|p| PanicRecord::new(&p.message, &p.file, p.line, p.column, &p.task, p.uptime_ms));
+        let core_dump = core_dump.map(|mut summary| {
+            summary.task = truncate(&summary.task, MAX_TASK_LEN).to_string();
+            summary.backtrace.truncate(MAX_BACKTRACE_LEN);
+            summary
+        });
+        self.entries.push_back(CrashEntry {
+            sequence: self.next_sequence,
+            reset_reason,
+            firmware_version: truncate(firmware_version, MAX_VERSION_LEN).to_string(),
+            panic,
+            core_dump,
+        });
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.entries.back().unwrap()
+    }
+
+    /// Get the stored entries, newest first
+    pub fn entries(&self) -> Vec<CrashEntry> {
+        self.entries.iter().rev().cloned().collect()
+    }
+
+    /// Get the most recent crash
+    pub fn latest(&self) -> Option<&CrashEntry> {
+        self.entries.back()
+    }
+
+    /// Number of crashes recorded since the ring was created, including dropped ones
+    pub fn total_crashes(&self) -> u32 {
+        self.next_sequence.wrapping_sub(1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Forget the stored entries, keeping the sequence counter
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Encode the ring for storage in a single NVS blob of up to `MAX_RING_LEN` bytes
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&self.next_sequence.to_le_bytes());
+        payload.push(self.entries.len() as u8);
+        for entry in &self.entries {
+            entry.write_to(&mut payload);
+        }
+        let bytes = frame(RING_MAGIC, &payload)?;
+        if bytes.len() > MAX_RING_LEN {
+            return Err(Error::out_of_range("Crash log length", bytes.len() as f64, 0, MAX_RING_LEN as f64));
+        }
+        Ok(bytes)
+    }
+
+    /// Decode a stored ring, keeping only the newest `capacity` entries
+    pub fn decode(bytes: &[u8], capacity: usize) -> Result<Self> {
+        let payload = unframe(RING_MAGIC, bytes)?;
+        let mut reader = Reader::new(payload);
+
+        let mut ring = Self::new(capacity);
+        ring.next_sequence = reader.u32()?;
+        let count = reader.u8()?;
+        for _ in 0..count {
+            let entry = CrashEntry::read_from(&mut reader)?;
+            if ring.entries.len() == ring.capacity {
+                ring.entries.pop_front();
+            }
+            ring.entries.push_back(entry);
+        }
+        Ok(ring)
+    }
+}
+
+impl Default for CrashRing {
+    fn default() -> Self {
+        Self::new(DEFAULT_RING_CAPACITY)
+    }
+}
+
+/// CRC-32 (IEEE 802.3) of `bytes`
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Wrap a payload as magic, version, length, payload and CRC
+fn frame(magic: [u8; 2], payload: &[u8]) -> Result<Vec<u8>> {
+    let len = u16::try_from(payload.len())
+        .map_err(|_| Error::out_of_range("Crash record length", payload.len() as f64, 0, u16::MAX))?;
+
+    let mut out = Vec::with_capacity(payload.len() + FRAME_LEN);
+    out.extend_from_slice(&magic);
+    out.push(FORMAT_VERSION);
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(payload);
+    let crc = crc32(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    Ok(out)
+}
+
+/// Check the header and CRC written by `frame` and return the payload
+fn unframe(magic: [u8; 2], bytes: &[u8]) -> Result<&[u8]> {
+    if bytes.len() < FRAME_LEN || bytes[..2] != magic {
+        return Err(Error::InvalidArgument("No crash record".to_string()));
+    }
+    if bytes[2] != FORMAT_VERSION {
+        return Err(Error::InvalidArgument(format!("Unsupported crash record version {}", bytes[2])));
+    }
+
+    let len = u16::from_le_bytes([bytes[3], bytes[4]]) as usize;
+    let end = 5 + len;
+    if bytes.len() < end + 4 {
+        return Err(Error::InvalidArgument("Truncated crash record".to_string()));
+    }
+
+    let stored_crc = u32::from_le_bytes([bytes[end], bytes[end + 1], bytes[end + 2], bytes[end + 3]]);
+    if crc32(&bytes[..end]) != stored_crc {
+        return Err(Error::InvalidArgument("Crash record checksum mismatch".to_string()));
+    }
+    Ok(&bytes[5..end])
+}
+
+fn write_str8(out: &mut Vec<u8>, value: &str) {
+    let value = truncate(value, u8::MAX as usize);
+    out.push(value.len() as u8);
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Keep at most `max_len` bytes from the start, on a char boundary
+fn truncate(value: &str, max_len: usize) -> &str {
+    if value.len() <= max_len {
+        return value;
+    }
+    let mut end = max_len;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+/// Keep at most `max_len` bytes from the end, on a char boundary
+fn truncate_start(value: &str, max_len: usize) -> &str {
+    if value.len() <= max_len {
+        return value;
+    }
+    let mut start = value.len() - max_len;
+    while !value.is_char_boundary(start) {
+        start += 1;
+    }
+    &value[start..]
+}
+
+/// Little-endian cursor over an encoded payload
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos + len;
+        if end > self.bytes.len() {
+            return Err(Error::InvalidArgument("Truncated crash record".to_string()));
+        }
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn str8(&mut self) -> Result<String> {
+        let len = self.u8()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::InvalidArgument("Invalid text in crash record".to_string()))
+    }
+}
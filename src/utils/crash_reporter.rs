@@ -0,0 +1,237 @@
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use esp_idf_svc::sys::{self as sys, esp};
+use log::{info, warn, error};
+use std::ffi::CStr;
+use std::ptr::addr_of_mut;
+
+use crate::error::{Error, Result};
+use super::crash_log::{
+    CoreDumpSummary, CrashEntry, CrashRing, PanicRecord, ResetReason, DEFAULT_RING_CAPACITY,
+    MAX_RING_LEN, PANIC_SLOT_LEN,
+};
+use super::time_utils::get_uptime_ms;
+use super::watchdog::StallReport;
+
+/// NVS namespace and key of the crash ring
+const NVS_NAMESPACE: &str = "crash_log";
+const NVS_RING: &str = "ring";
+
+/// Survives panics and watchdog resets, but not power loss
+#[link_section = ".rtc_noinit"]
+static mut PANIC_SLOT: [u8; PANIC_SLOT_LEN] = [0; PANIC_SLOT_LEN];
+
+/// Record panics in RTC memory before the default handler aborts
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => info.payload().downcast_ref::<String>().cloned().unwrap_or_default(),
+        };
+        let (file, line, column) = info.location()
+            .map(|location| (location.file(), location.line(), location.column()))
+            .unwrap_or(("<unknown>", 0, 0));
+
+        let record = PanicRecord::new(&message, file, line, column, &current_task_name(), get_uptime_ms());
+        write_panic_slot(&record);
+        default_hook(info);
+    }));
+}
+
+/// Record a stalled task so the crash log names it after the watchdog reset
+pub fn record_watchdog_stall(stall: &StallReport) {
+    let message = format!("Task watchdog: not fed for {} ms (timeout {} ms)", stall.since_feed_ms, stall.timeout_ms);
+    let record = PanicRecord::new(&message, "<watchdog>", 0, 0, &stall.name, get_uptime_ms());
+    write_panic_slot(&record);
+}
+
+/// Drop a stall record after the task recovered
+pub fn clear_watchdog_stall() {
+    clear_panic_slot();
+}
+
+/// Get the reason for the last reset
+pub fn reset_reason() -> ResetReason {
+    ResetReason::from_raw(unsafe { sys::esp_reset_reason() } as u32)
+}
+
+/// Crash history kept in NVS, updated with the evidence of the last reset on boot
+pub struct CrashReporter {
+    nvs: EspNvs<NvsDefault>,
+    ring: CrashRing,
+    reset_reason: ResetReason,
+}
+
+impl CrashReporter {
+    /// Load the crash ring and record the previous run if it ended in a crash
+    pub fn on_boot(partition: EspDefaultNvsPartition) -> Result<Self> {
+        let reset_reason = reset_reason();
+        let panic = take_panic_slot();
+
+        let mut nvs = EspNvs::new(partition, NVS_NAMESPACE, true)
+            .map_err(|e| {
+                error!("Failed to open crash log namespace: {:?}", e);
+                Error::nvs("Crash log namespace open", e)
+            })?;
+        let mut ring = load_ring(&nvs);
+
+        info!("Reset reason: {:?}", reset_reason);
+        if reset_reason.is_unexpected() || panic.is_some() {
+            let core_dump = take_core_dump_summary();
+            let entry = ring.push(reset_reason, env!("CARGO_PKG_VERSION"), panic, core_dump);
+            log_entry(entry);
+            store_ring(&mut nvs, &ring)?;
+        }
+
+        Ok(Self { nvs, ring, reset_reason })
+    }
+
+    /// Get the reason for the reset that started this run
+    pub fn reset_reason(&self) -> ResetReason {
+        self.reset_reason
+    }
+
+    /// Get the stored crashes, newest first
+    pub fn entries(&self) -> Vec<CrashEntry> {
+        self.ring.entries()
+    }
+
+    /// Get the number of crashes recorded, including ones rotated out
+    pub fn total_crashes(&self) -> u32 {
+        self.ring.total_crashes()
+    }
+
+    /// Forget the stored crashes
+    pub fn clear(&mut self) -> Result<()> {
+        self.ring.clear();
+        store_ring(&mut self.nvs, &self.ring)?;
+        info!("Crash log cleared");
+        Ok(())
+    }
+}
+
+/// Name of the running thread, or of the FreeRTOS task if the thread is unnamed
+fn current_task_name() -> String {
+    if let Some(name) = std::thread::current().name() {
+        return name.to_string();
+    }
+    let name = unsafe { sys::pcTaskGetName(std::ptr::null_mut()) };
+    if name.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned()
+}
+
+fn write_panic_slot(record: &PanicRecord) {
+    // Records from `PanicRecord::new` always encode and fit the slot
+    let Ok(bytes) = record.encode() else {
+        return;
+    };
+    let len = bytes.len().min(PANIC_SLOT_LEN);
+    // Only the panicking task or the watchdog supervisor writes the slot, just before the chip resets
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), addr_of_mut!(PANIC_SLOT) as *mut u8, len);
+    }
+}
+
+fn clear_panic_slot() {
+    unsafe {
+        std::ptr::write_bytes(addr_of_mut!(PANIC_SLOT) as *mut u8, 0, PANIC_SLOT_LEN);
+    }
+}
+
+/// Read and invalidate the panic record left by the previous run
+fn take_panic_slot() -> Option<PanicRecord> {
+    let mut bytes = [0u8; PANIC_SLOT_LEN];
+    unsafe {
+        std::ptr::copy_nonoverlapping(addr_of_mut!(PANIC_SLOT) as *const u8, bytes.as_mut_ptr(), PANIC_SLOT_LEN);
+    }
+    clear_panic_slot();
+
+    // After power-on the slot holds random data, which fails the checksum
+    PanicRecord::decode(&bytes).ok()
+}
+
+/// Summarize the core dump in flash, then erase it so it is reported once
+fn take_core_dump_summary() -> Option<CoreDumpSummary> {
+    esp!(unsafe { sys::esp_core_dump_image_check() }).ok()?;
+
+    let mut summary: sys::esp_core_dump_summary_t = unsafe { std::mem::zeroed() };
+    if let Err(e) = esp!(unsafe { sys::esp_core_dump_get_summary(&mut summary) }) {
+        warn!("Failed to read core dump summary: {:?}", e);
+        return None;
+    }
+
+    let task = unsafe { CStr::from_ptr(summary.exc_task.as_ptr()) }.to_string_lossy().into_owned();
+    let (backtrace, backtrace_corrupted) = backtrace(&summary);
+
+    if let Err(e) = esp!(unsafe { sys::esp_core_dump_image_erase() }) {
+        warn!("Failed to erase core dump: {:?}", e);
+    }
+
+    Some(CoreDumpSummary {
+        task,
+        program_counter: summary.exc_pc,
+        backtrace,
+        backtrace_corrupted,
+    })
+}
+
+#[cfg(target_arch = "xtensa")]
+fn backtrace(summary: &sys::esp_core_dump_summary_t) -> (Vec<u32>, bool) {
+    use super::crash_log::MAX_BACKTRACE_LEN;
+
+    let info = &summary.exc_bt_info;
+    let depth = (info.depth as usize).min(MAX_BACKTRACE_LEN);
+    (info.bt[..depth].to_vec(), info.corrupted)
+}
+
+/// RISC-V summaries carry a raw stack dump instead of a decoded backtrace
+#[cfg(not(target_arch = "xtensa"))]
+fn backtrace(_summary: &sys::esp_core_dump_summary_t) -> (Vec<u32>, bool) {
+    (Vec::new(), false)
+}
+
+fn load_ring(nvs: &EspNvs<NvsDefault>) -> CrashRing {
+    let mut buf = vec![0u8; MAX_RING_LEN];
+    match nvs.get_blob(NVS_RING, &mut buf) {
+        Ok(Some(bytes)) => CrashRing::decode(bytes, DEFAULT_RING_CAPACITY).unwrap_or_else(|e| {
+            warn!("Discarding unreadable crash log: {:?}", e);
+            CrashRing::default()
+        }),
+        Ok(None) => CrashRing::default(),
+        Err(e) => {
+            warn!("Failed to read crash log: {:?}", e);
+            CrashRing::default()
+        }
+    }
+}
+
+fn store_ring(nvs: &mut EspNvs<NvsDefault>, ring: &CrashRing) -> Result<()> {
+    nvs.set_blob(NVS_RING, &ring.encode()?)
+        .map_err(|e| {
+            error!("Failed to store crash log: {:?}", e);
+            Error::nvs("Crash log storage", e)
+        })
+}
+
+/// Print a crash to the console
+fn log_entry(entry: &CrashEntry) {
+    error!("Crash #{}: {:?} running firmware {}", entry.sequence, entry.reset_reason, entry.firmware_version);
+    if let Some(panic) = &entry.panic {
+        error!(
+            "  panicked at {}:{}:{} in task '{}' after {} ms: {}",
+            panic.file, panic.line, panic.column, panic.task, panic.uptime_ms, panic.message
+        );
+    }
+    if let Some(core_dump) = &entry.core_dump {
+        let backtrace: Vec<String> = core_dump.backtrace.iter().map(|pc| format!("0x{:08x}", pc)).collect();
+        error!(
+            "  exception in task '{}' at 0x{:08x}, backtrace: {}{}",
+            core_dump.task,
+            core_dump.program_counter,
+            backtrace.join(" "),
+            if core_dump.backtrace_corrupted { " (corrupted)" } else { "" }
+        );
+    }
+}
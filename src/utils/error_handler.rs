@@ -1,8 +1,14 @@
-use anyhow::Result;
 use log::{error, warn, info};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{Error, Result, Retryable};
+use super::retry::{Clock, MonotonicClock, RetryPolicy};
 
 /// Handle errors with consistent logging and recovery strategies
-pub fn handle_error<T>(result: Result<T>, context: &str) -> Result<T> {
+pub fn handle_error<T, E: Debug>(result: Result<T, E>, context: &str) -> Result<T, E> {
     match result {
         Ok(value) => Ok(value),
         Err(e) => {
@@ -13,13 +19,14 @@ pub fn handle_error<T>(result: Result<T>, context: &str) -> Result<T> {
 }
 
 /// Handle errors with custom recovery strategy
-pub fn handle_error_with_recovery<T, F>(
-    result: Result<T>,
+pub fn handle_error_with_recovery<T, E, F>(
+    result: Result<T, E>,
     context: &str,
     recovery_fn: F,
-) -> Result<T>
+) -> Result<T, E>
 where
-    F: FnOnce() -> Result<T>,
+    E: Debug,
+    F: FnOnce() -> Result<T, E>,
 {
     match result {
         Ok(value) => Ok(value),
@@ -39,39 +46,28 @@ where
     }
 }
 
-/// Retry operation with exponential backoff
-pub fn retry_with_backoff<T, F>(
-    mut operation: F,
+/// Retry operation with exponential backoff, capped at `DEFAULT_MAX_DELAY_MS`.
+///
+/// Gives up early on errors that are not retryable; use `RetryPolicy` for
+/// jitter, deadlines or cancellation.
+pub fn retry_with_backoff<T, E, F>(
+    operation: F,
     max_attempts: u32,
     initial_delay_ms: u32,
-) -> Result<T>
+) -> Result<T, E>
 where
-    F: FnMut() -> Result<T>,
+    E: Retryable + Debug,
+    F: FnMut() -> Result<T, E>,
 {
-    let mut attempt = 0;
-    let mut delay_ms = initial_delay_ms;
-
-    loop {
-        match operation() {
-            Ok(value) => return Ok(value),
-            Err(e) => {
-                attempt += 1;
-                if attempt >= max_attempts {
-                    return Err(e);
-                }
-
-                warn!("Attempt {} failed, retrying in {}ms: {:?}", attempt, delay_ms, e);
-                esp_idf_hal::delay::FreeRtos::delay_ms(delay_ms);
-                delay_ms *= 2; // Exponential backoff
-            }
-        }
-    }
+    RetryPolicy::exponential(initial_delay_ms)
+        .max_attempts(max_attempts)
+        .run(operation)
 }
 
 /// Safe division with error handling
 pub fn safe_divide(numerator: f32, denominator: f32) -> Result<f32> {
     if denominator.abs() < f32::EPSILON {
-        Err(anyhow::anyhow!("Division by zero"))
+        Err(Error::InvalidArgument("Division by zero".to_string()))
     } else {
         Ok(numerator / denominator)
     }
@@ -80,14 +76,300 @@ pub fn safe_divide(numerator: f32, denominator: f32) -> Result<f32> {
 /// Validate range with error handling
 pub fn validate_range(value: f32, min: f32, max: f32, name: &str) -> Result<f32> {
     if value < min || value > max {
-        Err(anyhow::anyhow!(
-            "{} value {} is out of range [{}, {}]",
-            name,
-            value,
-            min,
-            max
-        ))
+        Err(Error::out_of_range(name, value, min, max))
     } else {
         Ok(value)
     }
-} 
\ No newline at end of file
+} 
+
+/// State of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls pass through and outcomes are recorded
+    Closed,
+    /// Calls are rejected until the cool-down ends
+    Open,
+    /// A limited number of trial calls decide whether to close again
+    HalfOpen,
+}
+
+/// When a circuit breaker opens and how it recovers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Failure rate over the window that opens the circuit, from 0.0 to 1.0
+    pub failure_rate_threshold: f32,
+    /// Number of most recent calls the failure rate is computed over
+    pub window_size: usize,
+    /// Calls needed in the window before the failure rate is considered
+    pub minimum_calls: usize,
+    /// How long the circuit stays open before allowing trial calls
+    pub open_duration_ms: u64,
+    /// Trial calls allowed while half-open; all of them must succeed to close
+    pub half_open_calls: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_rate_threshold: 0.5,
+            window_size: 10,
+            minimum_calls: 5,
+            open_duration_ms: 30_000,
+            half_open_calls: 1,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// Validate thresholds and window sizes
+    pub fn validate(&self) -> Result<()> {
+        if !(self.failure_rate_threshold > 0.0 && self.failure_rate_threshold <= 1.0) {
+            return Err(Error::out_of_range("Failure rate threshold", self.failure_rate_threshold, 0.0, 1.0));
+        }
+        if self.window_size == 0 || self.minimum_calls == 0 || self.minimum_calls > self.window_size {
+            return Err(Error::InvalidArgument("Minimum calls must be between 1 and the window size".to_string()));
+        }
+        if self.half_open_calls == 0 {
+            return Err(Error::InvalidArgument("At least one half-open trial call is required".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Counters and current state of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CircuitMetrics {
+    pub state: CircuitState,
+    /// Failure rate over the current window
+    pub failure_rate: f32,
+    pub successes: u64,
+    pub failures: u64,
+    /// Calls refused without running
+    pub rejected: u64,
+    /// How many times the circuit has opened
+    pub times_opened: u32,
+    pub ms_in_state: u64,
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    /// Outcomes of the most recent calls, `true` for a failure
+    window: VecDeque<bool>,
+    state_changed_ms: u64,
+    half_open_in_flight: u32,
+    half_open_successes: u32,
+    successes: u64,
+    failures: u64,
+    rejected: u64,
+    times_opened: u32,
+}
+
+impl BreakerState {
+    fn failure_rate(&self) -> f32 {
+        if self.window.is_empty() {
+            return 0.0;
+        }
+        let failures = self.window.iter().filter(|&&failed| failed).count();
+        failures as f32 / self.window.len() as f32
+    }
+}
+
+/// Stops calling a dependency that keeps failing, then probes it after a cool-down
+pub struct CircuitBreaker {
+    name: String,
+    config: CircuitBreakerConfig,
+    clock: Arc<dyn Clock>,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// Create a closed circuit breaker
+    pub fn new(name: &str, config: CircuitBreakerConfig) -> Result<Self> {
+        Self::with_clock(name, config, Arc::new(MonotonicClock::new()))
+    }
+
+    /// Create a closed circuit breaker timed by `clock`
+    pub fn with_clock(name: &str, config: CircuitBreakerConfig, clock: Arc<dyn Clock>) -> Result<Self> {
+        config.validate()?;
+        let now_ms = clock.now_ms();
+
+        Ok(Self {
+            name: name.to_string(),
+            config,
+            clock,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                window: VecDeque::with_capacity(config.window_size),
+                state_changed_ms: now_ms,
+                half_open_in_flight: 0,
+                half_open_successes: 0,
+                successes: 0,
+                failures: 0,
+                rejected: 0,
+                times_opened: 0,
+            }),
+        })
+    }
+
+    /// Get the breaker name used in logs and errors
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the current state, moving to half-open once the cool-down has ended
+    pub fn state(&self) -> CircuitState {
+        let mut state = self.state.lock().unwrap();
+        self.refresh(&mut state);
+        state.state
+    }
+
+    /// Run `operation` if the circuit allows it and record the outcome
+    pub fn call<T, E, F>(&self, operation: F) -> std::result::Result<T, E>
+    where
+        E: From<Error>,
+        F: FnOnce() -> std::result::Result<T, E>,
+    {
+        self.try_acquire()?;
+        let result = operation();
+        match &result {
+            Ok(_) => self.record_success(),
+            Err(_) => self.record_failure(),
+        }
+        result
+    }
+
+    /// Ask to make a call; report the outcome with `record_success` or `record_failure`
+    pub fn try_acquire(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let now_ms = self.refresh(&mut state);
+
+        match state.state {
+            CircuitState::Closed => Ok(()),
+            CircuitState::HalfOpen if state.half_open_in_flight + state.half_open_successes < self.config.half_open_calls => {
+                state.half_open_in_flight += 1;
+                Ok(())
+            }
+            CircuitState::HalfOpen => {
+                state.rejected += 1;
+                Err(Error::CircuitOpen { name: self.name.clone(), retry_in_ms: 0 })
+            }
+            CircuitState::Open => {
+                state.rejected += 1;
+                let open_ms = now_ms.saturating_sub(state.state_changed_ms);
+                Err(Error::CircuitOpen {
+                    name: self.name.clone(),
+                    retry_in_ms: self.config.open_duration_ms.saturating_sub(open_ms),
+                })
+            }
+        }
+    }
+
+    /// Record a successful call
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now_ms = self.refresh(&mut state);
+        state.successes += 1;
+
+        match state.state {
+            CircuitState::Closed => self.push_outcome(&mut state, false),
+            CircuitState::HalfOpen => {
+                state.half_open_in_flight = state.half_open_in_flight.saturating_sub(1);
+                state.half_open_successes += 1;
+                if state.half_open_successes >= self.config.half_open_calls {
+                    self.transition(&mut state, CircuitState::Closed, now_ms);
+                }
+            }
+            // A call that started before the circuit opened
+            CircuitState::Open => {}
+        }
+    }
+
+    /// Record a failed call
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now_ms = self.refresh(&mut state);
+        state.failures += 1;
+
+        match state.state {
+            CircuitState::Closed => {
+                self.push_outcome(&mut state, true);
+                if state.window.len() >= self.config.minimum_calls
+                    && state.failure_rate() >= self.config.failure_rate_threshold
+                {
+                    warn!(
+                        "{} failure rate {:.0}% over {} calls, opening circuit",
+                        self.name,
+                        state.failure_rate() * 100.0,
+                        state.window.len()
+                    );
+                    self.transition(&mut state, CircuitState::Open, now_ms);
+                }
+            }
+            CircuitState::HalfOpen => {
+                warn!("{} trial call failed, reopening circuit", self.name);
+                self.transition(&mut state, CircuitState::Open, now_ms);
+            }
+            CircuitState::Open => {}
+        }
+    }
+
+    /// Close the circuit and forget recorded outcomes, keeping the counters
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now_ms = self.clock.now_ms();
+        self.transition(&mut state, CircuitState::Closed, now_ms);
+    }
+
+    /// Get the counters and current state
+    pub fn metrics(&self) -> CircuitMetrics {
+        let mut state = self.state.lock().unwrap();
+        let now_ms = self.refresh(&mut state);
+
+        CircuitMetrics {
+            state: state.state,
+            failure_rate: state.failure_rate(),
+            successes: state.successes,
+            failures: state.failures,
+            rejected: state.rejected,
+            times_opened: state.times_opened,
+            ms_in_state: now_ms.saturating_sub(state.state_changed_ms),
+        }
+    }
+
+    /// Move from open to half-open once the cool-down has ended; returns the current time
+    fn refresh(&self, state: &mut BreakerState) -> u64 {
+        let now_ms = self.clock.now_ms();
+        if state.state == CircuitState::Open
+            && now_ms.saturating_sub(state.state_changed_ms) >= self.config.open_duration_ms
+        {
+            self.transition(state, CircuitState::HalfOpen, now_ms);
+        }
+        now_ms
+    }
+
+    fn push_outcome(&self, state: &mut BreakerState, failed: bool) {
+        if state.window.len() == self.config.window_size {
+            state.window.pop_front();
+        }
+        state.window.push_back(failed);
+    }
+
+    fn transition(&self, state: &mut BreakerState, to: CircuitState, now_ms: u64) {
+        if state.state != to {
+            info!("{} circuit {:?} -> {:?}", self.name, state.state, to);
+        }
+        if to == CircuitState::Open {
+            state.times_opened = state.times_opened.saturating_add(1);
+        }
+
+        state.state = to;
+        state.state_changed_ms = now_ms;
+        state.half_open_in_flight = 0;
+        state.half_open_successes = 0;
+        if to != CircuitState::Open {
+            state.window.clear();
+        }
+    }
+}
\ No newline at end of file
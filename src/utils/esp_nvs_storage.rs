@@ -0,0 +1,78 @@
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use esp_idf_svc::sys::{self, esp};
+use log::{error, warn};
+use std::ffi::CString;
+
+use crate::error::{Error, Result};
+use super::nvs_storage::NvsStorage;
+
+/// `NvsStorage` backed by a namespace of the default NVS partition
+pub struct EspNvsStorage {
+    nvs: EspNvs<NvsDefault>,
+    namespace: String,
+}
+
+impl EspNvsStorage {
+    /// Open `namespace` for reading and writing, creating it if needed
+    pub fn open(partition: EspDefaultNvsPartition, namespace: &str) -> Result<Self> {
+        let nvs = EspNvs::new(partition, namespace, true)
+            .map_err(|e| {
+                error!("Failed to open NVS namespace '{}': {:?}", namespace, e);
+                Error::nvs(format!("Namespace '{}' open", namespace), e)
+            })?;
+        Ok(Self { nvs, namespace: namespace.to_string() })
+    }
+}
+
+impl NvsStorage for EspNvsStorage {
+    fn get_blob(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let len = self.nvs.blob_len(key)
+            .map_err(|e| Error::nvs(format!("Length of '{}'", key), e))?;
+        let Some(len) = len else {
+            return Ok(None);
+        };
+
+        let mut buf = vec![0u8; len];
+        let blob = self.nvs.get_blob(key, &mut buf)
+            .map_err(|e| Error::nvs(format!("Read of '{}'", key), e))?;
+        Ok(blob.map(<[u8]>::to_vec))
+    }
+
+    fn set_blob(&mut self, key: &str, value: &[u8]) -> Result<()> {
+        self.nvs.set_blob(key, value)
+            .map_err(|e| {
+                error!("Failed to write NVS key '{}': {:?}", key, e);
+                Error::nvs(format!("Write of '{}'", key), e)
+            })
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool> {
+        self.nvs.remove(key)
+            .map_err(|e| Error::nvs(format!("Removal of '{}'", key), e))
+    }
+
+    fn erase_all(&mut self) -> Result<()> {
+        erase_namespace(&self.namespace)
+    }
+}
+
+/// Remove every key in `namespace` of the default NVS partition
+pub fn erase_namespace(namespace: &str) -> Result<()> {
+    let name = CString::new(namespace)
+        .map_err(|_| Error::invalid_argument(format!("NVS namespace '{}' contains a NUL byte", namespace)))?;
+    let context = format!("Erase of namespace '{}'", namespace);
+
+    let mut handle: sys::nvs_handle_t = 0;
+    esp!(unsafe { sys::nvs_open(name.as_ptr(), sys::nvs_open_mode_t_NVS_READWRITE, &mut handle) })
+        .map_err(|e| Error::nvs(context.clone(), e))?;
+    let result = esp!(unsafe { sys::nvs_erase_all(handle) })
+        .and_then(|_| esp!(unsafe { sys::nvs_commit(handle) }));
+    unsafe { sys::nvs_close(handle) };
+
+    result.map_err(|e| {
+        error!("Failed to erase NVS namespace '{}': {:?}", namespace, e);
+        Error::nvs(context, e)
+    })?;
+    warn!("Erased NVS namespace '{}'", namespace);
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use crate::error::{Error, Result};
+
+/// Longest module path accepted in a per-module override
+pub const MAX_MODULE_LEN: usize = 64;
+
+/// Log verbosity, from `Off` to `Trace`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// Get the equivalent `log` filter
+    pub fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(LogLevel::Off),
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            "trace" => Ok(LogLevel::Trace),
+            _ => Err(Error::invalid_argument(format!(
+                "Unknown log level '{}', expected off, error, warn, info, debug or trace",
+                s
+            ))),
+        }
+    }
+}
+
+/// Check a module path such as `tasks::wifi_task`
+pub fn validate_module(module: &str) -> Result<()> {
+    let valid_segments = module.split("::")
+        .all(|segment| !segment.is_empty() && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+    if module.len() > MAX_MODULE_LEN || !valid_segments {
+        return Err(Error::invalid_argument(format!(
+            "Module '{}' must be a path like tasks::wifi_task of at most {} bytes",
+            module, MAX_MODULE_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Check if `module` names the log target or one of its parents
+///
+/// Paths match on whole segments anywhere in the target, so `tasks::wifi_task`
+/// matches `esp32_template::tasks::wifi_task` and its submodules.
+pub fn module_matches(module: &str, target: &str) -> bool {
+    target.match_indices(module).any(|(start, _)| {
+        let end = start + module.len();
+        (start == 0 || target[..start].ends_with("::")) && (end == target.len() || target[end..].starts_with("::"))
+    })
+}
+
+#[derive(Debug, Default)]
+struct FilterState {
+    default: LogLevel,
+    /// Longest paths first, so the most specific override wins
+    modules: Vec<(String, LogLevel)>,
+}
+
+/// Log levels shared between `FilteredLogger` and the code that changes them
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    state: Arc<RwLock<FilterState>>,
+}
+
+impl LogFilter {
+    /// Create a filter logging `default` and above everywhere
+    pub fn new(default: LogLevel) -> Self {
+        Self { state: Arc::new(RwLock::new(FilterState { default, modules: Vec::new() })) }
+    }
+
+    /// Replace the global level and per-module overrides
+    ///
+    /// Also raises or lowers `log::max_level` so disabled records are skipped cheaply.
+    pub fn apply(&self, default: LogLevel, modules: &BTreeMap<String, LogLevel>) {
+        let mut modules: Vec<(String, LogLevel)> = modules.iter()
+            .map(|(module, level)| (module.clone(), *level))
+            .collect();
+        modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+
+        *self.state.write().unwrap() = FilterState { default, modules };
+        log::set_max_level(self.max_level().filter());
+    }
+
+    /// Get the global level
+    pub fn default_level(&self) -> LogLevel {
+        self.state.read().unwrap().default
+    }
+
+    /// Get the per-module overrides
+    pub fn modules(&self) -> BTreeMap<String, LogLevel> {
+        self.state.read().unwrap().modules.iter().cloned().collect()
+    }
+
+    /// Get the level that applies to a log target
+    pub fn level_for(&self, target: &str) -> LogLevel {
+        let state = self.state.read().unwrap();
+        state.modules.iter()
+            .find(|(module, _)| module_matches(module, target))
+            .map_or(state.default, |(_, level)| *level)
+    }
+
+    /// Get the most verbose level enabled anywhere
+    pub fn max_level(&self) -> LogLevel {
+        let state = self.state.read().unwrap();
+        state.modules.iter().map(|(_, level)| *level).fold(state.default, LogLevel::max)
+    }
+
+    /// Check if a record would be logged
+    pub fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target()).filter()
+    }
+}
+
+/// `log::Log` wrapper that drops records below the level of their module
+pub struct FilteredLogger<L: Log> {
+    filter: LogFilter,
+    inner: L,
+}
+
+impl<L: Log> FilteredLogger<L> {
+    pub fn new(filter: LogFilter, inner: L) -> Self {
+        Self { filter, inner }
+    }
+
+    /// Get the filter, to change levels at runtime
+    pub fn filter(&self) -> &LogFilter {
+        &self.filter
+    }
+}
+
+impl<L: Log> Log for FilteredLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata) && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
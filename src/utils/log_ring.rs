@@ -0,0 +1,366 @@
+use log::{Level, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{Error, Result};
+use super::log_filter::{module_matches, LogLevel};
+use super::retry::Clock;
+
+/// Default number of records kept in RAM
+pub const DEFAULT_LOG_CAPACITY: usize = 300;
+
+/// Longest message stored; longer ones are truncated
+pub const MAX_MESSAGE_LEN: usize = 200;
+
+/// Most entries returned by one query
+pub const MAX_PAGE_LEN: usize = 100;
+
+/// Marks an initialized retained log area
+const RETAINED_MAGIC: u32 = 0x4C4F_4752;
+
+/// Magic, write position and wrapped flag
+const RETAINED_HEADER_LEN: usize = 12;
+
+/// A captured log record
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogEntry {
+    /// Increases by one per record since boot, starting at 1
+    pub seq: u64,
+    pub uptime_ms: u64,
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+}
+
+/// Selects log entries; every field is optional
+///
+/// With `since`, the oldest matching entries after that sequence number are
+/// returned so clients can page forward; otherwise the newest ones.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LogQuery {
+    /// Only entries with a higher sequence number
+    pub since: Option<u64>,
+    /// Only entries at this level or more severe
+    pub level: Option<LogLevel>,
+    /// Only entries from this module path
+    pub module: Option<String>,
+    /// At most this many entries, capped at `MAX_PAGE_LEN`
+    pub limit: Option<usize>,
+    /// Read the entries retained from before the last reset instead
+    pub previous_boot: bool,
+}
+
+impl LogQuery {
+    /// Build a query from `key=value` pairs such as an HTTP query string
+    pub fn from_pairs<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self> {
+        let mut query = LogQuery::default();
+        for (key, value) in pairs {
+            let invalid = || Error::invalid_argument(format!("Invalid {} '{}'", key, value));
+            match key {
+                "since" => query.since = Some(value.parse().map_err(|_| invalid())?),
+                "level" => query.level = Some(value.parse()?),
+                "module" => query.module = Some(value.to_string()),
+                "limit" => query.limit = Some(value.parse().map_err(|_| invalid())?),
+                "previous_boot" => query.previous_boot = value.parse().map_err(|_| invalid())?,
+                _ => return Err(Error::invalid_argument(format!("Unknown log filter '{}'", key))),
+            }
+        }
+        Ok(query)
+    }
+
+    /// Check if an entry passes the level and module filters
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.since.map_or(true, |since| entry.seq > since)
+            && self.level.map_or(true, |level| entry.level <= level)
+            && self.module.as_deref().map_or(true, |module| module_matches(module, &entry.target))
+    }
+}
+
+/// Result of a log query
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogPage {
+    pub entries: Vec<LogEntry>,
+    /// Sequence number of the newest record, 0 if none
+    pub last_seq: u64,
+    /// Records overwritten before they could be read
+    pub dropped: u64,
+}
+
+/// Fixed-size ring of recent log records
+pub struct LogRing {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    last_seq: u64,
+    dropped: u64,
+    retained: Option<RetainedLog>,
+    previous_boot: Vec<LogEntry>,
+}
+
+impl LogRing {
+    /// Create a ring keeping the last `capacity` records in RAM
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            last_seq: 0,
+            dropped: 0,
+            retained: None,
+            previous_boot: Vec::new(),
+        }
+    }
+
+    /// Create a ring that also mirrors records into memory surviving soft resets
+    ///
+    /// Records left in `area` by the previous boot are available through
+    /// `LogQuery::previous_boot`.
+    pub fn with_retained(capacity: usize, area: &'static mut [u8]) -> Result<Self> {
+        let (retained, previous_boot) = RetainedLog::recover(area)?;
+        Ok(Self { retained: Some(retained), previous_boot, ..Self::new(capacity) })
+    }
+
+    /// Store a record, overwriting the oldest once full
+    pub fn push(&mut self, level: LogLevel, uptime_ms: u64, target: &str, message: &str) -> u64 {
+        self.last_seq += 1;
+        let entry = LogEntry {
+            seq: self.last_seq,
+            uptime_ms,
+            level,
+            target: target.to_string(),
+            message: truncate(message, MAX_MESSAGE_LEN).to_string(),
+        };
+
+        if let Some(retained) = &mut self.retained {
+            retained.append(&entry);
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(entry);
+        self.last_seq
+    }
+
+    /// Get the entries selected by `query`, oldest first
+    pub fn query(&self, query: &LogQuery) -> LogPage {
+        let source: Vec<&LogEntry> = if query.previous_boot {
+            self.previous_boot.iter().collect()
+        } else {
+            self.entries.iter().collect()
+        };
+        let matching: Vec<&LogEntry> = source.into_iter().filter(|entry| query.matches(entry)).collect();
+
+        let limit = query.limit.unwrap_or(MAX_PAGE_LEN).min(MAX_PAGE_LEN);
+        let selected = if query.since.is_some() {
+            &matching[..limit.min(matching.len())]
+        } else {
+            &matching[matching.len().saturating_sub(limit)..]
+        };
+
+        LogPage {
+            entries: selected.iter().map(|entry| (*entry).clone()).collect(),
+            last_seq: self.last_seq,
+            dropped: self.dropped,
+        }
+    }
+
+    /// Get the number of records held in RAM
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the number of records overwritten before they could be read
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Get the entries retained from before the last reset
+    pub fn previous_boot(&self) -> &[LogEntry] {
+        &self.previous_boot
+    }
+
+    /// Forget the records held in RAM; sequence numbers keep counting
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for LogRing {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_CAPACITY)
+    }
+}
+
+/// Shared handle to the ring filled by `RingLogger`
+pub type SharedLogRing = Arc<Mutex<LogRing>>;
+
+/// `log::Log` wrapper copying every record it receives into a `LogRing`
+pub struct RingLogger<L: Log> {
+    ring: SharedLogRing,
+    clock: Arc<dyn Clock>,
+    inner: L,
+}
+
+impl<L: Log> RingLogger<L> {
+    pub fn new(ring: SharedLogRing, clock: Arc<dyn Clock>, inner: L) -> Self {
+        Self { ring, clock, inner }
+    }
+}
+
+impl<L: Log> Log for RingLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.inner.log(record);
+
+        let level = match record.level() {
+            Level::Error => LogLevel::Error,
+            Level::Warn => LogLevel::Warn,
+            Level::Info => LogLevel::Info,
+            Level::Debug => LogLevel::Debug,
+            Level::Trace => LogLevel::Trace,
+        };
+        let message = record.args().to_string();
+        let mut ring = self.ring.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        ring.push(level, self.clock.now_ms(), record.target(), &message);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Log records kept as text lines in a byte ring, for memory that survives soft resets
+///
+/// Layout: magic, write position and wrapped flag (little-endian `u32`s), then
+/// the ring. Each record is one line: `seq uptime_ms level target message`.
+pub struct RetainedLog {
+    area: &'static mut [u8],
+}
+
+impl RetainedLog {
+    /// Take over `area`, returning the records it held before this boot
+    pub fn recover(area: &'static mut [u8]) -> Result<(Self, Vec<LogEntry>)> {
+        if area.len() <= RETAINED_HEADER_LEN + 1 {
+            return Err(Error::invalid_argument(format!(
+                "Retained log area must be larger than {} bytes",
+                RETAINED_HEADER_LEN + 1
+            )));
+        }
+        let mut log = Self { area };
+        let previous = if log.is_valid() { log.entries() } else { Vec::new() };
+        log.reset();
+        Ok((log, previous))
+    }
+
+    /// Append a record, overwriting the oldest bytes once full
+    pub fn append(&mut self, entry: &LogEntry) {
+        let line = format!(
+            "{} {} {} {} {}\n",
+            entry.seq,
+            entry.uptime_ms,
+            entry.level,
+            entry.target,
+            entry.message.replace(['\n', '\r'], " ")
+        );
+
+        let data_len = self.data_len();
+        let mut head = self.read_u32(4) as usize % data_len;
+        let mut wrapped = self.read_u32(8) != 0;
+        for &byte in line.as_bytes() {
+            self.area[RETAINED_HEADER_LEN + head] = byte;
+            head += 1;
+            if head == data_len {
+                head = 0;
+                wrapped = true;
+            }
+        }
+        self.write_u32(4, head as u32);
+        self.write_u32(8, wrapped as u32);
+    }
+
+    /// Give up the area, leaving its records for the next `recover`
+    pub fn into_area(self) -> &'static mut [u8] {
+        self.area
+    }
+
+    /// Decode the complete records in the area, oldest first
+    pub fn entries(&self) -> Vec<LogEntry> {
+        let head = (self.read_u32(4) as usize).min(self.data_len());
+        let data = &self.area[RETAINED_HEADER_LEN..];
+
+        let mut bytes = Vec::with_capacity(data.len());
+        let wrapped = self.read_u32(8) != 0;
+        if wrapped {
+            bytes.extend_from_slice(&data[head..]);
+        }
+        bytes.extend_from_slice(&data[..head]);
+
+        let text = String::from_utf8_lossy(&bytes);
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        // The last piece is unterminated; after a wrap the first is cut off too
+        lines.pop();
+        if wrapped && !lines.is_empty() {
+            lines.remove(0);
+        }
+        lines.into_iter().filter_map(parse_line).collect()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.read_u32(0) == RETAINED_MAGIC && (self.read_u32(4) as usize) < self.data_len()
+    }
+
+    fn reset(&mut self) {
+        self.area.fill(0);
+        self.write_u32(0, RETAINED_MAGIC);
+    }
+
+    fn data_len(&self) -> usize {
+        self.area.len() - RETAINED_HEADER_LEN
+    }
+
+    fn read_u32(&self, offset: usize) -> u32 {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&self.area[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn write_u32(&mut self, offset: usize, value: u32) {
+        self.area[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// Parse a line written by `RetainedLog::append`
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let mut fields = line.splitn(5, ' ');
+    Some(LogEntry {
+        seq: fields.next()?.parse().ok()?,
+        uptime_ms: fields.next()?.parse().ok()?,
+        level: fields.next()?.parse().ok()?,
+        target: fields.next()?.to_string(),
+        message: fields.next().unwrap_or_default().to_string(),
+    })
+}
+
+/// Cut `text` to at most `max_len` bytes on a character boundary
+fn truncate(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len {
+        return text;
+    }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
@@ -1,9 +1,39 @@
 // Utility functions and helpers module
+pub mod crash_log;
+#[cfg(target_os = "espidf")]
+pub mod crash_reporter;
 pub mod error_handler;
+#[cfg(target_os = "espidf")]
+pub mod esp_nvs_storage;
+pub mod log_filter;
+pub mod log_ring;
+pub mod retry;
+#[cfg(target_os = "espidf")]
+pub mod serial_logger;
+#[cfg(target_os = "espidf")]
 pub mod time_utils;
+pub mod wall_clock;
+pub mod watchdog;
+#[cfg(target_os = "espidf")]
+pub mod watchdog_supervisor;
 pub mod math_utils;
+pub mod nvs_storage;
 
 // Re-export commonly used utilities
-pub use error_handler::handle_error;
+pub use crash_log::{CrashEntry, ResetReason};
+#[cfg(target_os = "espidf")]
+pub use crash_reporter::CrashReporter;
+pub use error_handler::{handle_error, CircuitBreaker, CircuitBreakerConfig, CircuitState};
+pub use retry::{Backoff, CancellationToken, Clock, Jitter, RetryPolicy, Sleeper};
+#[cfg(target_os = "espidf")]
 pub use time_utils::get_uptime_ms;
-pub use math_utils::map_range; 
\ No newline at end of file
+pub use wall_clock::{DateTime, PosixTz, Timestamp};
+pub use watchdog::{Watchdog, WatchdogHandle};
+pub use math_utils::map_range;
+pub use nvs_storage::{MemoryNvs, NvsStorage};
+#[cfg(target_os = "espidf")]
+pub use esp_nvs_storage::EspNvsStorage;
+pub use log_filter::{FilteredLogger, LogFilter, LogLevel};
+pub use log_ring::{LogEntry, LogPage, LogQuery, LogRing, RingLogger, SharedLogRing};
+#[cfg(target_os = "espidf")]
+pub use serial_logger::{init_logging, SerialLogger}; 
\ No newline at end of file
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{Error, EspCode, Result};
+
+/// Longest key accepted by NVS
+pub const MAX_KEY_LEN: usize = 15;
+
+/// Blob storage in one NVS namespace, replaceable by `MemoryNvs` on the host
+pub trait NvsStorage: Send {
+    /// Read a blob, or `None` if the key is not set
+    fn get_blob(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Write a blob, replacing any previous value
+    fn set_blob(&mut self, key: &str, value: &[u8]) -> Result<()>;
+
+    /// Remove a key, returning whether it was set
+    fn remove(&mut self, key: &str) -> Result<bool>;
+
+    /// Remove every key in the namespace
+    fn erase_all(&mut self) -> Result<()>;
+}
+
+/// In-memory NVS namespace for host tests
+///
+/// Clones share their contents, so a test can keep one to inspect what was
+/// written or to load it again as if after a reboot.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryNvs {
+    entries: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+    fail_writes: Arc<Mutex<bool>>,
+}
+
+impl MemoryNvs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make writes fail like a full or worn-out partition
+    pub fn set_fail_writes(&self, fail: bool) {
+        *self.fail_writes.lock().unwrap() = fail;
+    }
+
+    /// Get the keys that are set, in order
+    pub fn keys(&self) -> Vec<String> {
+        self.entries.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn check_key(key: &str) -> Result<()> {
+        if key.is_empty() || key.len() > MAX_KEY_LEN {
+            return Err(Error::InvalidArgument(format!("NVS key '{}' must be 1-{} bytes", key, MAX_KEY_LEN)));
+        }
+        Ok(())
+    }
+}
+
+impl NvsStorage for MemoryNvs {
+    fn get_blob(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Self::check_key(key)?;
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    fn set_blob(&mut self, key: &str, value: &[u8]) -> Result<()> {
+        Self::check_key(key)?;
+        if *self.fail_writes.lock().unwrap() {
+            return Err(Error::nvs(format!("Write of '{}'", key), EspCode::NVS_NOT_ENOUGH_SPACE));
+        }
+        self.entries.lock().unwrap().insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool> {
+        Self::check_key(key)?;
+        if *self.fail_writes.lock().unwrap() {
+            return Err(Error::nvs(format!("Removal of '{}'", key), EspCode::NVS_NOT_ENOUGH_SPACE));
+        }
+        Ok(self.entries.lock().unwrap().remove(key).is_some())
+    }
+
+    fn erase_all(&mut self) -> Result<()> {
+        if *self.fail_writes.lock().unwrap() {
+            return Err(Error::nvs("Erase", EspCode::NVS_NOT_ENOUGH_SPACE));
+        }
+        self.entries.lock().unwrap().clear();
+        Ok(())
+    }
+}
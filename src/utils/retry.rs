@@ -0,0 +1,350 @@
+use log::warn;
+use std::collections::hash_map::RandomState;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::Retryable;
+
+/// Upper bound on a single delay unless set with `max_delay_ms`
+pub const DEFAULT_MAX_DELAY_MS: u32 = 60_000;
+
+/// Attempts made unless set with `max_attempts`
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Longest uninterrupted sleep while a cancellation token is attached
+const CANCEL_POLL_MS: u32 = 100;
+
+/// How the delay grows between attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Always wait the base delay
+    Fixed,
+    /// Wait the base delay times the retry number
+    Linear,
+    /// Double the delay after every retry
+    Exponential,
+}
+
+/// Randomization applied to the computed delay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Jitter {
+    #[default]
+    None,
+    /// Uniform between zero and the computed delay
+    Full,
+    /// Uniform between the base delay and three times the previous delay
+    Decorrelated,
+}
+
+/// Monotonic time source, replaceable by a mock in tests
+pub trait Clock: Send + Sync {
+    /// Milliseconds from an arbitrary starting point
+    fn now_ms(&self) -> u64;
+}
+
+/// Clock counting from its creation
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    epoch: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self { epoch: Instant::now() }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_ms(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+}
+
+/// Sleeps between attempts; its clock measures deadlines
+pub trait Sleeper: Clock {
+    fn sleep_ms(&self, ms: u32);
+}
+
+/// Sleeper blocking the current thread
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadSleeper {
+    clock: MonotonicClock,
+}
+
+impl ThreadSleeper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Clock for ThreadSleeper {
+    fn now_ms(&self) -> u64 {
+        self.clock.now_ms()
+    }
+}
+
+impl Sleeper for ThreadSleeper {
+    fn sleep_ms(&self, ms: u32) {
+        std::thread::sleep(Duration::from_millis(ms as u64));
+    }
+}
+
+/// Stops a running retry loop from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every retry loop holding a clone of this token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if `cancel` has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// How often and how long to retry a failing operation.
+///
+/// The first attempt always runs. When the policy gives up, because attempts
+/// ran out, the deadline would be missed, the error is not retryable or the
+/// token was cancelled, the last error is returned.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    backoff: Backoff,
+    base_delay_ms: u32,
+    max_delay_ms: u32,
+    max_attempts: u32,
+    jitter: Jitter,
+    deadline_ms: Option<u64>,
+    cancel: Option<CancellationToken>,
+    sleeper: Arc<dyn Sleeper>,
+    seed: Option<u64>,
+}
+
+impl RetryPolicy {
+    /// Create a policy with the given backoff and base delay
+    pub fn new(backoff: Backoff, base_delay_ms: u32) -> Self {
+        Self {
+            backoff,
+            base_delay_ms,
+            max_delay_ms: DEFAULT_MAX_DELAY_MS,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            jitter: Jitter::None,
+            deadline_ms: None,
+            cancel: None,
+            sleeper: Arc::new(ThreadSleeper::new()),
+            seed: None,
+        }
+    }
+
+    /// Wait the same delay between every attempt
+    pub fn fixed(delay_ms: u32) -> Self {
+        Self::new(Backoff::Fixed, delay_ms)
+    }
+
+    /// Wait `step_ms`, `2 * step_ms`, `3 * step_ms`, ...
+    pub fn linear(step_ms: u32) -> Self {
+        Self::new(Backoff::Linear, step_ms)
+    }
+
+    /// Wait `initial_delay_ms`, then double the delay after every retry
+    pub fn exponential(initial_delay_ms: u32) -> Self {
+        Self::new(Backoff::Exponential, initial_delay_ms)
+    }
+
+    /// Total number of attempts, including the first one
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Cap a single delay
+    pub fn max_delay_ms(mut self, max_delay_ms: u32) -> Self {
+        self.max_delay_ms = max_delay_ms;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Give up rather than start an attempt later than this after the first one
+    pub fn deadline_ms(mut self, deadline_ms: u64) -> Self {
+        self.deadline_ms = Some(deadline_ms);
+        self
+    }
+
+    /// Stop retrying once `token` is cancelled
+    pub fn cancel_on(mut self, token: &CancellationToken) -> Self {
+        self.cancel = Some(token.clone());
+        self
+    }
+
+    /// Replace the thread sleeper, e.g. with a mock in tests
+    pub fn sleeper(mut self, sleeper: Arc<dyn Sleeper>) -> Self {
+        self.sleeper = sleeper;
+        self
+    }
+
+    /// Make jitter deterministic
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Delay before retry number `retry` (starting at 1), without jitter
+    pub fn base_delay_for(&self, retry: u32) -> u32 {
+        let base = self.base_delay_ms as u64;
+        let delay = match self.backoff {
+            Backoff::Fixed => base,
+            Backoff::Linear => base.saturating_mul(retry.max(1) as u64),
+            Backoff::Exponential => {
+                let doublings = retry.saturating_sub(1).min(63);
+                base.saturating_mul(1u64 << doublings)
+            }
+        };
+        delay.min(self.max_delay_ms as u64) as u32
+    }
+
+    /// Run `operation`, retrying errors that report themselves as retryable
+    pub fn run<T, E, F>(&self, operation: F) -> Result<T, E>
+    where
+        E: Retryable + Debug,
+        F: FnMut() -> Result<T, E>,
+    {
+        self.run_if(operation, |e: &E| e.is_retryable())
+    }
+
+    /// Run `operation`, retrying errors accepted by `should_retry`
+    pub fn run_if<T, E, F, P>(&self, mut operation: F, should_retry: P) -> Result<T, E>
+    where
+        E: Debug,
+        F: FnMut() -> Result<T, E>,
+        P: Fn(&E) -> bool,
+    {
+        let started_ms = self.sleeper.now_ms();
+        let mut rng = JitterRng::new(self.seed.unwrap_or_else(random_seed));
+        let mut previous_delay_ms = self.base_delay_ms;
+        let mut attempt = 0;
+
+        loop {
+            let e = match operation() {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            attempt += 1;
+
+            if attempt >= self.max_attempts {
+                warn!("Giving up after {} attempt(s): {:?}", attempt, e);
+                return Err(e);
+            }
+            if !should_retry(&e) {
+                warn!("Attempt {} failed with a permanent error: {:?}", attempt, e);
+                return Err(e);
+            }
+            if self.is_cancelled() {
+                return Err(e);
+            }
+
+            let delay_ms = self.jittered_delay(attempt, previous_delay_ms, &mut rng);
+            previous_delay_ms = delay_ms;
+
+            if let Some(deadline_ms) = self.deadline_ms {
+                let elapsed_ms = self.sleeper.now_ms().saturating_sub(started_ms);
+                if elapsed_ms + delay_ms as u64 > deadline_ms {
+                    warn!("Retry deadline of {}ms reached after {} attempt(s): {:?}", deadline_ms, attempt, e);
+                    return Err(e);
+                }
+            }
+
+            warn!("Attempt {} failed, retrying in {}ms: {:?}", attempt, delay_ms, e);
+            if !self.sleep(delay_ms) {
+                return Err(e);
+            }
+        }
+    }
+
+    /// Apply jitter to the delay before retry number `retry`
+    fn jittered_delay(&self, retry: u32, previous_delay_ms: u32, rng: &mut JitterRng) -> u32 {
+        match self.jitter {
+            Jitter::None => self.base_delay_for(retry),
+            Jitter::Full => rng.between(0, self.base_delay_for(retry)),
+            Jitter::Decorrelated => {
+                let upper = previous_delay_ms.saturating_mul(3).max(self.base_delay_ms);
+                rng.between(self.base_delay_ms, upper).min(self.max_delay_ms)
+            }
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Sleep, waking periodically to check for cancellation; false if cancelled
+    fn sleep(&self, delay_ms: u32) -> bool {
+        if self.cancel.is_none() {
+            self.sleeper.sleep_ms(delay_ms);
+            return true;
+        }
+
+        let mut remaining_ms = delay_ms;
+        while remaining_ms > 0 {
+            if self.is_cancelled() {
+                return false;
+            }
+            let slice_ms = remaining_ms.min(CANCEL_POLL_MS);
+            self.sleeper.sleep_ms(slice_ms);
+            remaining_ms -= slice_ms;
+        }
+        !self.is_cancelled()
+    }
+}
+
+/// xorshift64* generator; jitter only needs to spread retries out
+struct JitterRng(u64);
+
+impl JitterRng {
+    fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift
+        Self(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform value in `[low, high]`
+    fn between(&mut self, low: u32, high: u32) -> u32 {
+        if high <= low {
+            return low;
+        }
+        let span = (high - low) as u64 + 1;
+        low + (self.next() % span) as u32
+    }
+}
+
+/// Seed from the randomly keyed std hasher, backed by the hardware RNG on the device
+fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
@@ -0,0 +1,89 @@
+use log::{info, Level, Log, Metadata, Record};
+use std::io::Write;
+use std::ptr::addr_of_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{Error, Result};
+use super::log_filter::{FilteredLogger, LogFilter};
+use super::log_ring::{LogRing, RingLogger, SharedLogRing, DEFAULT_LOG_CAPACITY};
+use super::retry::Clock;
+use super::time_utils::get_uptime_ms;
+
+/// Bytes of log text kept in RTC memory across soft resets
+const RETAINED_LOG_LEN: usize = 2048;
+
+#[link_section = ".rtc_noinit"]
+static mut RETAINED_LOG: [u8; RETAINED_LOG_LEN] = [0; RETAINED_LOG_LEN];
+
+/// Set once the retained log area has been handed out
+static RETAINED_LOG_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Writes records to the console UART in the ESP-IDF format `I (1234) target: message`
+pub struct SerialLogger;
+
+impl Log for SerialLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let letter = match record.level() {
+            Level::Error => 'E',
+            Level::Warn => 'W',
+            Level::Info => 'I',
+            Level::Debug => 'D',
+            Level::Trace => 'V',
+        };
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{} ({}) {}: {}", letter, get_uptime_ms(), record.target(), record.args());
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Milliseconds since boot, for log timestamps
+struct UptimeClock;
+
+impl Clock for UptimeClock {
+    fn now_ms(&self) -> u64 {
+        get_uptime_ms()
+    }
+}
+
+/// Install the serial logger behind `filter`, replacing `EspLogger`
+///
+/// Records that pass the filter are also kept in the returned ring, which
+/// mirrors them into RTC memory so the tail survives a soft reset.
+/// Components written in C keep logging through ESP-IDF at `CONFIG_LOG_DEFAULT_LEVEL`.
+pub fn init_logging(filter: LogFilter) -> Result<SharedLogRing> {
+    let ring = Arc::new(Mutex::new(retained_log_ring()));
+    let max_level = filter.max_level();
+    let logger = Box::leak(Box::new(FilteredLogger::new(
+        filter,
+        RingLogger::new(ring.clone(), Arc::new(UptimeClock), SerialLogger),
+    )));
+    log::set_logger(logger)
+        .map_err(|_| Error::InvalidState("A logger is already installed".to_string()))?;
+    log::set_max_level(max_level.filter());
+
+    let previous = ring.lock().unwrap().previous_boot().len();
+    if previous > 0 {
+        info!("Recovered {} log records from before the last reset", previous);
+    }
+    Ok(ring)
+}
+
+/// Create the log ring over the RTC area, or RAM only if it was already taken
+fn retained_log_ring() -> LogRing {
+    if RETAINED_LOG_TAKEN.swap(true, Ordering::AcqRel) {
+        return LogRing::new(DEFAULT_LOG_CAPACITY);
+    }
+    // SAFETY: the flag above hands the area out once, so this is the only reference
+    let area: &'static mut [u8] = unsafe { &mut *addr_of_mut!(RETAINED_LOG) };
+    // Only fails for areas too small to hold a record, which `RETAINED_LOG_LEN` is not
+    LogRing::with_retained(DEFAULT_LOG_CAPACITY, area)
+        .unwrap_or_else(|_| LogRing::new(DEFAULT_LOG_CAPACITY))
+}
@@ -1,4 +1,13 @@
 use anyhow::Result;
+use std::sync::Mutex;
+
+use super::wall_clock::{DateTime, PosixTz, SyncReport, Timestamp, WallClock};
+
+/// Offset between uptime and UTC, learned from SNTP
+static WALL_CLOCK: Mutex<WallClock> = Mutex::new(WallClock::new());
+
+/// Time zone used by `now_local`, UTC if unset
+static TIME_ZONE: Mutex<Option<PosixTz>> = Mutex::new(None);
 
 /// Get system uptime in milliseconds
 pub fn get_uptime_ms() -> u64 {
@@ -86,4 +95,43 @@ impl Timer {
         self.duration_ms = duration_ms;
         self.start_time = get_uptime_ms();
     }
-} 
\ No newline at end of file
+}
+
+/// Record a time sync reporting the current UTC time
+pub fn record_time_sync(utc_ms: i64) -> SyncReport {
+    WALL_CLOCK.lock().unwrap().record_sync(utc_ms, get_uptime_ms())
+}
+
+/// Get a copy of the wall clock bookkeeping
+pub fn wall_clock() -> WallClock {
+    *WALL_CLOCK.lock().unwrap()
+}
+
+/// Check if the wall clock has been synchronized
+pub fn is_time_synced() -> bool {
+    wall_clock().is_synced()
+}
+
+/// Set the time zone from a POSIX TZ string, e.g. `CET-1CEST,M3.5.0,M10.5.0/3`
+pub fn set_time_zone(tz: &str) -> Result<()> {
+    let zone = PosixTz::parse(tz)?;
+    *TIME_ZONE.lock().unwrap() = Some(zone);
+    Ok(())
+}
+
+/// Get the current UTC time, if synchronized
+pub fn now_utc() -> Option<DateTime> {
+    wall_clock().utc_ms(get_uptime_ms()).map(|utc_ms| DateTime::from_unix_ms(utc_ms, 0))
+}
+
+/// Get the current local time, if synchronized
+pub fn now_local() -> Option<DateTime> {
+    let utc_ms = wall_clock().utc_ms(get_uptime_ms())?;
+    let offset_s = TIME_ZONE.lock().unwrap().as_ref().map_or(0, |zone| zone.utc_offset_s(utc_ms));
+    Some(DateTime::from_unix_ms(utc_ms, offset_s))
+}
+
+/// Timestamp for a measurement taken now, UTC once synchronized
+pub fn timestamp_now() -> Timestamp {
+    wall_clock().timestamp(get_uptime_ms())
+}
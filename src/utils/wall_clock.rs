@@ -0,0 +1,478 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+
+const MS_PER_SECOND: i64 = 1000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// When a measurement was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Timestamp {
+    /// Milliseconds since boot, before the clock was synchronized
+    Uptime(u64),
+    /// Milliseconds since the Unix epoch
+    Utc(i64),
+}
+
+/// Outcome of recording a time synchronization
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncReport {
+    pub utc_ms: i64,
+    pub uptime_ms: u64,
+    /// How far the previous estimate was off, if the clock was already synced
+    pub correction_ms: Option<i64>,
+}
+
+/// Tracks the offset between uptime and UTC learned from time syncs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WallClock {
+    /// UTC milliseconds at uptime zero
+    offset_ms: Option<i64>,
+    last_sync_uptime_ms: Option<u64>,
+    sync_count: u32,
+}
+
+impl WallClock {
+    /// Create an unsynchronized clock
+    pub const fn new() -> Self {
+        Self {
+            offset_ms: None,
+            last_sync_uptime_ms: None,
+            sync_count: 0,
+        }
+    }
+
+    /// Record that the current UTC time is `utc_ms` at `uptime_ms`
+    pub fn record_sync(&mut self, utc_ms: i64, uptime_ms: u64) -> SyncReport {
+        let correction_ms = self.utc_ms(uptime_ms).map(|estimate| utc_ms - estimate);
+
+        self.offset_ms = Some(utc_ms - uptime_ms as i64);
+        self.last_sync_uptime_ms = Some(uptime_ms);
+        self.sync_count = self.sync_count.saturating_add(1);
+
+        SyncReport { utc_ms, uptime_ms, correction_ms }
+    }
+
+    /// Check if the clock has been synchronized at least once
+    pub fn is_synced(&self) -> bool {
+        self.offset_ms.is_some()
+    }
+
+    /// Get the number of syncs recorded
+    pub fn sync_count(&self) -> u32 {
+        self.sync_count
+    }
+
+    /// Get the time since the last sync
+    pub fn last_sync_age_ms(&self, uptime_ms: u64) -> Option<u64> {
+        self.last_sync_uptime_ms.map(|last| uptime_ms.saturating_sub(last))
+    }
+
+    /// Convert an uptime to UTC milliseconds, if synced
+    pub fn utc_ms(&self, uptime_ms: u64) -> Option<i64> {
+        self.offset_ms.map(|offset| offset + uptime_ms as i64)
+    }
+
+    /// Timestamp for a measurement taken at `uptime_ms`
+    pub fn timestamp(&self, uptime_ms: u64) -> Timestamp {
+        match self.utc_ms(uptime_ms) {
+            Some(utc_ms) => Timestamp::Utc(utc_ms),
+            None => Timestamp::Uptime(uptime_ms),
+        }
+    }
+
+    /// Convert an earlier timestamp to UTC milliseconds, if possible
+    pub fn to_utc_ms(&self, timestamp: Timestamp) -> Option<i64> {
+        match timestamp {
+            Timestamp::Utc(utc_ms) => Some(utc_ms),
+            Timestamp::Uptime(uptime_ms) => self.utc_ms(uptime_ms),
+        }
+    }
+}
+
+/// A calendar date and time at a fixed UTC offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millisecond: u16,
+    /// Seconds east of UTC
+    pub utc_offset_s: i32,
+}
+
+impl DateTime {
+    /// Convert Unix milliseconds to a date and time at `utc_offset_s`
+    pub fn from_unix_ms(utc_ms: i64, utc_offset_s: i32) -> Self {
+        let local_ms = utc_ms + utc_offset_s as i64 * MS_PER_SECOND;
+        let days = local_ms.div_euclid(SECONDS_PER_DAY * MS_PER_SECOND);
+        let ms_of_day = local_ms.rem_euclid(SECONDS_PER_DAY * MS_PER_SECOND);
+        let (year, month, day) = civil_from_days(days);
+        let seconds = ms_of_day / MS_PER_SECOND;
+
+        Self {
+            year: year as i32,
+            month: month as u8,
+            day: day as u8,
+            hour: (seconds / 3600) as u8,
+            minute: (seconds % 3600 / 60) as u8,
+            second: (seconds % 60) as u8,
+            millisecond: (ms_of_day % MS_PER_SECOND) as u16,
+            utc_offset_s,
+        }
+    }
+
+    /// Convert back to Unix milliseconds
+    pub fn to_unix_ms(&self) -> i64 {
+        let days = days_from_civil(self.year as i64, self.month as u32, self.day as u32);
+        let seconds = days * SECONDS_PER_DAY
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+            - self.utc_offset_s as i64;
+        seconds * MS_PER_SECOND + self.millisecond as i64
+    }
+
+    /// Day of the week, 0 = Sunday
+    pub fn weekday(&self) -> u8 {
+        weekday(days_from_civil(self.year as i64, self.month as u32, self.day as u32)) as u8
+    }
+}
+
+/// Formats as RFC 3339, e.g. `2024-03-31T02:30:00.000+02:00`
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second, self.millisecond
+        )?;
+
+        if self.utc_offset_s == 0 {
+            return write!(f, "Z");
+        }
+        let sign = if self.utc_offset_s < 0 { '-' } else { '+' };
+        let offset = self.utc_offset_s.unsigned_abs();
+        write!(f, "{}{:02}:{:02}", sign, offset / 3600, offset % 3600 / 60)
+    }
+}
+
+/// Day of a daylight saving transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransitionDay {
+    /// `Jn`: day 1-365, February 29 is never counted
+    Julian(u16),
+    /// `n`: zero-based day 0-365, counting February 29
+    ZeroBased(u16),
+    /// `Mm.w.d`: weekday `d` (0 = Sunday) of week `w` (5 = last) of month `m`
+    MonthWeekDay { month: u8, week: u8, weekday: u8 },
+}
+
+/// Daylight saving transition, at local time `time_s` on `day`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Transition {
+    day: TransitionDay,
+    time_s: i32,
+}
+
+impl Transition {
+    /// Seconds since the epoch of the transition in `year`, in local time
+    fn local_seconds(&self, year: i64) -> i64 {
+        let leap = is_leap_year(year);
+        let days = match self.day {
+            TransitionDay::Julian(n) => {
+                let day_of_year = n as i64 - 1 + if leap && n >= 60 { 1 } else { 0 };
+                days_from_civil(year, 1, 1) + day_of_year
+            }
+            TransitionDay::ZeroBased(n) => days_from_civil(year, 1, 1) + n as i64,
+            TransitionDay::MonthWeekDay { month, week, weekday: target } => {
+                let first = days_from_civil(year, month as u32, 1);
+                let first_match = first + (target as i64 - weekday(first)).rem_euclid(7);
+                let mut day = first_match + (week as i64 - 1) * 7;
+                if day >= first + days_in_month(year, month as u32) as i64 {
+                    day -= 7;
+                }
+                day
+            }
+        };
+        days * SECONDS_PER_DAY + self.time_s as i64
+    }
+}
+
+/// Daylight saving part of a time zone
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DaylightSaving {
+    name: String,
+    utc_offset_s: i32,
+    start: Transition,
+    end: Transition,
+}
+
+/// A time zone described by a POSIX TZ string, e.g. `CET-1CEST,M3.5.0,M10.5.0/3`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PosixTz {
+    std_name: String,
+    /// Seconds east of UTC
+    std_offset_s: i32,
+    dst: Option<DaylightSaving>,
+}
+
+impl PosixTz {
+    /// Coordinated Universal Time
+    pub fn utc() -> Self {
+        Self {
+            std_name: "UTC".to_string(),
+            std_offset_s: 0,
+            dst: None,
+        }
+    }
+
+    /// Parse a POSIX TZ string
+    pub fn parse(tz: &str) -> Result<Self> {
+        let mut parser = TzParser { input: tz.trim().as_bytes(), pos: 0, tz };
+
+        let std_name = parser.name()?;
+        // POSIX offsets are west of UTC
+        let std_offset_s = -parser.offset()?;
+
+        let dst = if parser.at_end() {
+            None
+        } else {
+            let name = parser.name()?;
+            let utc_offset_s = if parser.peek().is_some_and(|c| c != b',') {
+                -parser.offset()?
+            } else {
+                std_offset_s + 3600
+            };
+
+            // Without rules, fall back to the US rules like glibc
+            let (start, end) = if parser.at_end() {
+                (
+                    Transition { day: TransitionDay::MonthWeekDay { month: 3, week: 2, weekday: 0 }, time_s: 7200 },
+                    Transition { day: TransitionDay::MonthWeekDay { month: 11, week: 1, weekday: 0 }, time_s: 7200 },
+                )
+            } else {
+                parser.expect(b',')?;
+                let start = parser.transition()?;
+                parser.expect(b',')?;
+                (start, parser.transition()?)
+            };
+
+            Some(DaylightSaving { name, utc_offset_s, start, end })
+        };
+
+        if !parser.at_end() {
+            return Err(parser.error());
+        }
+
+        Ok(Self { std_name, std_offset_s, dst })
+    }
+
+    /// Get the UTC offset in seconds in effect at `utc_ms`
+    pub fn utc_offset_s(&self, utc_ms: i64) -> i32 {
+        match &self.dst {
+            Some(dst) if self.is_dst(dst, utc_ms) => dst.utc_offset_s,
+            _ => self.std_offset_s,
+        }
+    }
+
+    /// Get the zone abbreviation in effect at `utc_ms`
+    pub fn name_at(&self, utc_ms: i64) -> &str {
+        match &self.dst {
+            Some(dst) if self.is_dst(dst, utc_ms) => &dst.name,
+            _ => &self.std_name,
+        }
+    }
+
+    /// Convert Unix milliseconds to local date and time
+    pub fn to_local(&self, utc_ms: i64) -> DateTime {
+        DateTime::from_unix_ms(utc_ms, self.utc_offset_s(utc_ms))
+    }
+
+    fn is_dst(&self, dst: &DaylightSaving, utc_ms: i64) -> bool {
+        let utc_s = utc_ms.div_euclid(MS_PER_SECOND);
+        let local_s = utc_s + self.std_offset_s as i64;
+        let year = civil_from_days(local_s.div_euclid(SECONDS_PER_DAY)).0;
+
+        // The start is given in standard time and the end in daylight time
+        let start = dst.start.local_seconds(year) - self.std_offset_s as i64;
+        let end = dst.end.local_seconds(year) - dst.utc_offset_s as i64;
+
+        if start < end {
+            utc_s >= start && utc_s < end
+        } else {
+            // Southern hemisphere: daylight time spans the new year
+            utc_s >= start || utc_s < end
+        }
+    }
+}
+
+/// Cursor over a POSIX TZ string
+struct TzParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    tz: &'a str,
+}
+
+impl TzParser<'_> {
+    fn error(&self) -> anyhow::Error {
+        anyhow::anyhow!("Invalid POSIX TZ string '{}' at position {}", self.tz, self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.peek() != Some(c) {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Zone abbreviation, alphabetic or quoted like `<+03>`
+    fn name(&mut self) -> Result<String> {
+        let (start, end) = if self.peek() == Some(b'<') {
+            let start = self.pos + 1;
+            let len = self.input[start..].iter().position(|&c| c == b'>').ok_or_else(|| self.error())?;
+            self.pos = start + len + 1;
+            (start, start + len)
+        } else {
+            let start = self.pos;
+            while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                self.pos += 1;
+            }
+            (start, self.pos)
+        };
+
+        if end - start < 3 {
+            return Err(self.error());
+        }
+        Ok(String::from_utf8_lossy(&self.input[start..end]).into_owned())
+    }
+
+    fn number(&mut self, max: u32) -> Result<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+
+        std::str::from_utf8(&self.input[start..self.pos])
+            .ok()
+            .and_then(|digits| digits.parse::<u32>().ok())
+            .filter(|value| *value <= max)
+            .ok_or_else(|| self.error())
+    }
+
+    /// Signed `[+-]hh[:mm[:ss]]` in seconds
+    fn time(&mut self, max_hours: u32) -> Result<i32> {
+        let sign = if self.peek() == Some(b'-') { -1 } else { 1 };
+        if matches!(self.peek(), Some(b'-' | b'+')) {
+            self.pos += 1;
+        }
+
+        let mut seconds = self.number(max_hours)? * 3600;
+        if self.peek() == Some(b':') {
+            self.pos += 1;
+            seconds += self.number(59)? * 60;
+            if self.peek() == Some(b':') {
+                self.pos += 1;
+                seconds += self.number(59)?;
+            }
+        }
+        Ok(sign * seconds as i32)
+    }
+
+    fn offset(&mut self) -> Result<i32> {
+        self.time(24)
+    }
+
+    fn transition(&mut self) -> Result<Transition> {
+        let day = match self.peek() {
+            Some(b'J') => {
+                self.pos += 1;
+                let day = self.number(365)?;
+                if day == 0 {
+                    return Err(self.error());
+                }
+                TransitionDay::Julian(day as u16)
+            }
+            Some(b'M') => {
+                self.pos += 1;
+                let month = self.number(12)?;
+                self.expect(b'.')?;
+                let week = self.number(5)?;
+                self.expect(b'.')?;
+                let weekday = self.number(6)?;
+                if month == 0 || week == 0 {
+                    return Err(self.error());
+                }
+                TransitionDay::MonthWeekDay { month: month as u8, week: week as u8, weekday: weekday as u8 }
+            }
+            _ => TransitionDay::ZeroBased(self.number(365)? as u16),
+        };
+
+        let time_s = if self.peek() == Some(b'/') {
+            self.pos += 1;
+            // RFC 8536 allows transition times of -167 to 167 hours
+            self.time(167)?
+        } else {
+            7200
+        };
+
+        Ok(Transition { day, time_s })
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Day of the week for days since the epoch, 0 = Sunday
+fn weekday(days: i64) -> i64 {
+    // 1970-01-01 was a Thursday
+    (days + 4).rem_euclid(7)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian date for days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,190 @@
+use log::{info, error};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{Error, Result};
+use super::retry::{Clock, MonotonicClock};
+
+/// Longest task name kept, matching the FreeRTOS limit
+pub const MAX_TASK_NAME_LEN: usize = 16;
+
+/// A task that has not fed the watchdog within its timeout
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StallReport {
+    pub name: String,
+    pub timeout_ms: u32,
+    pub since_feed_ms: u64,
+}
+
+/// Feeding state of a registered task
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskHealth {
+    pub name: String,
+    pub timeout_ms: u32,
+    pub since_feed_ms: u64,
+    pub feeds: u64,
+    pub stalled: bool,
+}
+
+struct TaskSlot {
+    id: u32,
+    name: String,
+    timeout_ms: u32,
+    last_feed_ms: u64,
+    feeds: u64,
+}
+
+struct Registry {
+    clock: Arc<dyn Clock>,
+    tasks: Mutex<Vec<TaskSlot>>,
+    next_id: AtomicU32,
+}
+
+impl Registry {
+    fn feed(&self, id: u32) {
+        let now_ms = self.clock.now_ms();
+        if let Some(slot) = self.tasks.lock().unwrap().iter_mut().find(|slot| slot.id == id) {
+            slot.last_feed_ms = now_ms;
+            slot.feeds += 1;
+        }
+    }
+
+    fn remove(&self, id: u32) {
+        self.tasks.lock().unwrap().retain(|slot| slot.id != id);
+    }
+}
+
+/// Software watchdog tracking when each registered task last checked in
+///
+/// Clones share the same registry, so one can be handed to the supervisor
+/// while tasks register through another.
+#[derive(Clone)]
+pub struct Watchdog {
+    registry: Arc<Registry>,
+}
+
+impl Watchdog {
+    /// Create an empty watchdog
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(MonotonicClock::new()))
+    }
+
+    /// Create an empty watchdog timed by `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            registry: Arc::new(Registry {
+                clock,
+                tasks: Mutex::new(Vec::new()),
+                next_id: AtomicU32::new(1),
+            }),
+        }
+    }
+
+    /// Register a task that must feed the returned handle at least every `timeout_ms`
+    ///
+    /// The task counts as fed on registration and is removed when the handle is dropped.
+    pub fn register(&self, name: &str, timeout_ms: u32) -> Result<WatchdogHandle> {
+        if name.is_empty() || name.len() > MAX_TASK_NAME_LEN {
+            error!("Invalid watchdog task name: '{}'", name);
+            return Err(Error::InvalidArgument(format!(
+                "Watchdog task name must be 1-{} bytes",
+                MAX_TASK_NAME_LEN
+            )));
+        }
+        if timeout_ms == 0 {
+            error!("Invalid watchdog timeout for task '{}'", name);
+            return Err(Error::InvalidArgument("Watchdog timeout must be positive".to_string()));
+        }
+
+        let now_ms = self.registry.clock.now_ms();
+        let mut tasks = self.registry.tasks.lock().unwrap();
+        if tasks.iter().any(|slot| slot.name == name) {
+            error!("Task '{}' is already registered with the watchdog", name);
+            return Err(Error::InvalidState(format!("Watchdog task '{}' already registered", name)));
+        }
+
+        let id = self.registry.next_id.fetch_add(1, Ordering::Relaxed);
+        tasks.push(TaskSlot {
+            id,
+            name: name.to_string(),
+            timeout_ms,
+            last_feed_ms: now_ms,
+            feeds: 0,
+        });
+        info!("Task '{}' registered with the watchdog ({} ms)", name, timeout_ms);
+
+        Ok(WatchdogHandle {
+            id,
+            name: name.to_string(),
+            registry: self.registry.clone(),
+        })
+    }
+
+    /// Get the tasks that have not been fed within their timeout
+    pub fn check(&self) -> Vec<StallReport> {
+        self.health()
+            .into_iter()
+            .filter(|task| task.stalled)
+            .map(|task| StallReport {
+                name: task.name,
+                timeout_ms: task.timeout_ms,
+                since_feed_ms: task.since_feed_ms,
+            })
+            .collect()
+    }
+
+    /// Get the feeding state of every registered task, in registration order
+    pub fn health(&self) -> Vec<TaskHealth> {
+        let now_ms = self.registry.clock.now_ms();
+        self.registry.tasks.lock().unwrap()
+            .iter()
+            .map(|slot| {
+                let since_feed_ms = now_ms.saturating_sub(slot.last_feed_ms);
+                TaskHealth {
+                    name: slot.name.clone(),
+                    timeout_ms: slot.timeout_ms,
+                    since_feed_ms,
+                    feeds: slot.feeds,
+                    stalled: since_feed_ms > slot.timeout_ms as u64,
+                }
+            })
+            .collect()
+    }
+
+    /// Get the number of registered tasks
+    pub fn task_count(&self) -> usize {
+        self.registry.tasks.lock().unwrap().len()
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Registration of one task; feed it from the task's loop
+pub struct WatchdogHandle {
+    id: u32,
+    name: String,
+    registry: Arc<Registry>,
+}
+
+impl WatchdogHandle {
+    /// Signal that the task is making progress
+    pub fn feed(&self) {
+        self.registry.feed(self.id);
+    }
+
+    /// Get the name the task registered with
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for WatchdogHandle {
+    fn drop(&mut self) {
+        self.registry.remove(self.id);
+    }
+}
@@ -0,0 +1,68 @@
+use esp_idf_hal::delay::FreeRtos;
+use esp_idf_svc::sys::{self as sys, esp};
+use log::{info, warn, error};
+use std::thread::JoinHandle;
+
+use crate::error::{Error, Result};
+use super::crash_reporter::{clear_watchdog_stall, record_watchdog_stall};
+use super::watchdog::Watchdog;
+
+/// Hardware task watchdog timeout set in sdkconfig
+pub const HARDWARE_TIMEOUT_MS: u32 = sys::CONFIG_ESP_TASK_WDT_TIMEOUT_S * 1000;
+
+/// How often the supervisor checks the registered tasks
+pub const DEFAULT_CHECK_INTERVAL_MS: u32 = 1000;
+
+/// Check the registered tasks from a thread that feeds the hardware task
+/// watchdog only while none of them is stalled
+///
+/// A stalled task is logged and recorded for the crash log. Unless it recovers,
+/// the hardware watchdog resets the chip `HARDWARE_TIMEOUT_MS` later.
+pub fn spawn_supervisor(watchdog: Watchdog, check_interval_ms: u32) -> Result<JoinHandle<()>> {
+    if check_interval_ms == 0 || check_interval_ms >= HARDWARE_TIMEOUT_MS {
+        error!("Watchdog check interval {} ms must be below the hardware timeout", check_interval_ms);
+        return Err(Error::out_of_range("Watchdog check interval", check_interval_ms, 1, HARDWARE_TIMEOUT_MS - 1));
+    }
+
+    let handle = std::thread::Builder::new()
+        .name("wdt_supervisor".to_string())
+        .stack_size(4096)
+        .spawn(move || {
+            if let Err(e) = run_supervisor(&watchdog, check_interval_ms) {
+                error!("Watchdog supervisor stopped: {:?}", e);
+            }
+        })?;
+
+    info!("Watchdog supervisor started (hardware timeout {} ms)", HARDWARE_TIMEOUT_MS);
+    Ok(handle)
+}
+
+fn run_supervisor(watchdog: &Watchdog, check_interval_ms: u32) -> Result<()> {
+    // Subscribe this thread's FreeRTOS task to the hardware watchdog
+    esp!(unsafe { sys::esp_task_wdt_add(std::ptr::null_mut()) })
+        .map_err(|e| Error::InvalidState(format!("Task watchdog subscription failed: {}", e)))?;
+
+    let mut stalled = false;
+    loop {
+        let stalls = watchdog.check();
+        if stalls.is_empty() {
+            if stalled {
+                warn!("All watched tasks recovered");
+                clear_watchdog_stall();
+                stalled = false;
+            }
+            unsafe { sys::esp_task_wdt_reset() };
+        } else if !stalled {
+            for stall in &stalls {
+                error!(
+                    "Task '{}' stalled: not fed for {} ms (timeout {} ms), reset in {} ms",
+                    stall.name, stall.since_feed_ms, stall.timeout_ms, HARDWARE_TIMEOUT_MS
+                );
+            }
+            record_watchdog_stall(&stalls[0]);
+            stalled = true;
+        }
+
+        FreeRtos::delay_ms(check_interval_ms);
+    }
+}
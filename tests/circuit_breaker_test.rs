@@ -0,0 +1,170 @@
+// Host tests for the circuit breaker state machine
+// These tests do not require hardware; time is driven by a mock clock
+
+mod common;
+
+use esp32_template::error::Error;
+use esp32_template::utils::error_handler::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+use std::sync::Arc;
+use common::MockClock;
+
+fn config() -> CircuitBreakerConfig {
+    CircuitBreakerConfig {
+        failure_rate_threshold: 0.5,
+        window_size: 4,
+        minimum_calls: 4,
+        open_duration_ms: 10_000,
+        half_open_calls: 2,
+    }
+}
+
+fn breaker(clock: &Arc<MockClock>) -> CircuitBreaker {
+    CircuitBreaker::with_clock("Sensor", config(), clock.clone()).unwrap()
+}
+
+fn succeed(breaker: &CircuitBreaker) -> Result<(), Error> {
+    breaker.call(|| Ok(()))
+}
+
+fn fail(breaker: &CircuitBreaker) -> Result<(), Error> {
+    breaker.call(|| Err(Error::timeout("Sensor read", 100)))
+}
+
+/// Drive a fresh breaker into the open state
+fn trip(breaker: &CircuitBreaker) {
+    for _ in 0..4 {
+        let _ = fail(breaker);
+    }
+    assert_eq!(breaker.state(), CircuitState::Open);
+}
+
+#[test]
+fn test_config_validation() {
+    assert!(CircuitBreakerConfig::default().validate().is_ok());
+    assert!(CircuitBreakerConfig { failure_rate_threshold: 0.0, ..config() }.validate().is_err());
+    assert!(CircuitBreakerConfig { failure_rate_threshold: 1.5, ..config() }.validate().is_err());
+    assert!(CircuitBreakerConfig { minimum_calls: 5, ..config() }.validate().is_err());
+    assert!(CircuitBreakerConfig { half_open_calls: 0, ..config() }.validate().is_err());
+    assert!(CircuitBreaker::new("Bad", CircuitBreakerConfig { window_size: 0, ..config() }).is_err());
+}
+
+#[test]
+fn test_stays_closed_below_threshold() {
+    let clock = Arc::new(MockClock::default());
+    let breaker = breaker(&clock);
+
+    // Needs the minimum number of calls before the rate counts
+    let _ = fail(&breaker);
+    let _ = fail(&breaker);
+    let _ = fail(&breaker);
+    assert_eq!(breaker.state(), CircuitState::Closed);
+
+    // The window slides, so old failures age out
+    for _ in 0..4 {
+        succeed(&breaker).unwrap();
+    }
+    let _ = fail(&breaker);
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    assert_eq!(breaker.metrics().failure_rate, 0.25);
+}
+
+#[test]
+fn test_opens_and_rejects_calls() {
+    let clock = Arc::new(MockClock::default());
+    let breaker = breaker(&clock);
+
+    succeed(&breaker).unwrap();
+    succeed(&breaker).unwrap();
+    let _ = fail(&breaker);
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    let _ = fail(&breaker);
+    assert_eq!(breaker.state(), CircuitState::Open);
+
+    clock.advance(4_000);
+    let mut ran = false;
+    let result: Result<(), Error> = breaker.call(|| {
+        ran = true;
+        Ok(())
+    });
+
+    assert!(!ran);
+    match result {
+        Err(Error::CircuitOpen { name, retry_in_ms }) => {
+            assert_eq!(name, "Sensor");
+            assert_eq!(retry_in_ms, 6_000);
+        }
+        other => panic!("expected an open circuit, got {:?}", other),
+    }
+
+    let metrics = breaker.metrics();
+    assert_eq!(metrics.rejected, 1);
+    assert_eq!(metrics.times_opened, 1);
+    assert_eq!(metrics.ms_in_state, 4_000);
+}
+
+#[test]
+fn test_half_open_closes_after_successful_trials() {
+    let clock = Arc::new(MockClock::default());
+    let breaker = breaker(&clock);
+    trip(&breaker);
+
+    clock.advance(9_999);
+    assert_eq!(breaker.state(), CircuitState::Open);
+    clock.advance(1);
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+    // Only the configured number of trial calls may be in flight
+    breaker.try_acquire().unwrap();
+    breaker.try_acquire().unwrap();
+    assert!(matches!(breaker.try_acquire(), Err(Error::CircuitOpen { .. })));
+
+    breaker.record_success();
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    breaker.record_success();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+
+    // The old failures are forgotten after closing
+    let _ = fail(&breaker);
+    assert_eq!(breaker.state(), CircuitState::Closed);
+}
+
+#[test]
+fn test_half_open_failure_reopens() {
+    let clock = Arc::new(MockClock::default());
+    let breaker = breaker(&clock);
+    trip(&breaker);
+
+    clock.advance(10_000);
+    succeed(&breaker).unwrap();
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    assert!(fail(&breaker).is_err());
+    assert_eq!(breaker.state(), CircuitState::Open);
+    assert_eq!(breaker.metrics().times_opened, 2);
+
+    // The cool-down starts over from the failed trial
+    clock.advance(5_000);
+    assert_eq!(breaker.state(), CircuitState::Open);
+    clock.advance(5_000);
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+}
+
+#[test]
+fn test_anyhow_errors_and_reset() {
+    let clock = Arc::new(MockClock::default());
+    let breaker = breaker(&clock);
+    trip(&breaker);
+
+    let result: anyhow::Result<()> = breaker.call(|| Ok(()));
+    let error = result.unwrap_err();
+    assert!(matches!(error.downcast_ref::<Error>(), Some(Error::CircuitOpen { .. })));
+    assert!(!Error::CircuitOpen { name: "Sensor".to_string(), retry_in_ms: 0 }.is_retryable());
+
+    breaker.reset();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    succeed(&breaker).unwrap();
+
+    let metrics = breaker.metrics();
+    assert_eq!(metrics.successes, 1);
+    assert_eq!(metrics.failures, 4);
+    assert_eq!(metrics.rejected, 1);
+}
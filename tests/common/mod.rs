@@ -0,0 +1,87 @@
+// Test doubles shared by the host tests
+// Each test crate uses only some of them
+#![allow(dead_code)]
+
+use esp32_template::error::Error;
+use esp32_template::tasks::http_api::{HttpMethod, HttpRequest};
+use esp32_template::utils::retry::Clock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Clock that only moves when a test says so
+#[derive(Default)]
+pub struct MockClock {
+    now_ms: AtomicU64,
+}
+
+impl MockClock {
+    pub fn advance(&self, ms: u64) {
+        self.now_ms.fetch_add(ms, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, ms: u64) {
+        self.now_ms.store(ms, Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::Relaxed)
+    }
+}
+
+/// Fake request with headers and an in-memory body
+pub struct FakeRequest {
+    method: HttpMethod,
+    uri: String,
+    headers: Vec<(&'static str, &'static str)>,
+    body: Vec<u8>,
+    fail_read: bool,
+}
+
+impl FakeRequest {
+    pub fn new(method: HttpMethod, uri: &str, body: &str) -> Self {
+        Self {
+            method,
+            uri: uri.to_string(),
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+            fail_read: false,
+        }
+    }
+
+    /// A GET request with the given headers
+    pub fn get(uri: &str, headers: &[(&'static str, &'static str)]) -> Self {
+        Self { headers: headers.to_vec(), ..Self::new(HttpMethod::Get, uri, "") }
+    }
+
+    /// A request whose body can't be read, like a dropped connection
+    pub fn failing(method: HttpMethod, uri: &str) -> Self {
+        Self { fail_read: true, ..Self::new(method, uri, "") }
+    }
+}
+
+impl HttpRequest for FakeRequest {
+    fn method(&self) -> HttpMethod {
+        self.method
+    }
+
+    fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+
+    fn read_body(&mut self, max_len: usize) -> anyhow::Result<Vec<u8>> {
+        if self.fail_read {
+            return Err(std::io::Error::from(std::io::ErrorKind::ConnectionReset).into());
+        }
+        if self.body.len() > max_len {
+            return Err(Error::out_of_range("Request body length", self.body.len() as f64, 0, max_len as f64).into());
+        }
+        Ok(self.body.clone())
+    }
+}
@@ -0,0 +1,297 @@
+// Host tests for the serial console parser, dispatcher and line editor
+// These tests feed bytes directly instead of reading the UART
+
+use esp32_template::tasks::console::{
+    config_field_patch, format_crashes, parse_arg, register_config_command, register_log_command, register_logs_command,
+    tokenize, Console, LineEditor,
+    HISTORY_LEN, MAX_LINE_LEN,
+};
+use esp32_template::tasks::device_config::ConfigStore;
+use esp32_template::utils::crash_log::{CoreDumpSummary, CrashRing, PanicRecord, ResetReason};
+use esp32_template::utils::log_filter::LogLevel;
+use esp32_template::utils::log_ring::LogRing;
+use esp32_template::utils::nvs_storage::MemoryNvs;
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+const UP: &[u8] = b"\x1b[A";
+const DOWN: &[u8] = b"\x1b[B";
+const LEFT: &[u8] = b"\x1b[D";
+
+fn test_console() -> Console {
+    let mut console = Console::new();
+    console.register("uptime", "Show the time since boot", |_| Ok("00:00:05".to_string()));
+    console.register_with_subcommands("wifi", "wifi status | scan", &["status", "scan"], |args| {
+        Ok(format!("wifi {}", args.join(" ")))
+    });
+    console.register("echo", "Print the arguments", |args| Ok(args.join("|")));
+    console
+}
+
+/// Feed bytes, returning the lines that were entered
+fn type_bytes(editor: &mut LineEditor, console: &Console, bytes: &[u8]) -> Vec<String> {
+    bytes.iter().filter_map(|&byte| editor.feed(byte, console)).collect()
+}
+
+#[test]
+fn test_tokenize() {
+    assert_eq!(tokenize("  led 1   on ").unwrap(), ["led", "1", "on"]);
+    assert_eq!(tokenize(r#"wifi connect "My Network" 'p@ss "word"'"#).unwrap(), ["wifi", "connect", "My Network", r#"p@ss "word""#]);
+    assert_eq!(tokenize(r#"echo a\ b "c\"d" 'e\f' """#).unwrap(), ["echo", "a b", "c\"d", r"e\f", ""]);
+    assert!(tokenize("").unwrap().is_empty());
+    assert!(tokenize("echo \"open").is_err());
+    assert!(tokenize("echo trailing\\").is_err());
+
+    let args = tokenize("led 2 on").unwrap();
+    assert_eq!(parse_arg::<u8>(&args, 1, "LED id").unwrap(), 2);
+    assert_eq!(parse_arg::<u8>(&args, 2, "LED id").unwrap_err().to_string(), "Invalid LED id 'on'");
+    assert_eq!(parse_arg::<u8>(&args, 3, "level").unwrap_err().to_string(), "Missing level");
+}
+
+#[test]
+fn test_dispatch_and_help() {
+    let mut console = test_console();
+
+    assert_eq!(console.execute("uptime").unwrap(), "00:00:05");
+    assert_eq!(console.execute("echo 'a b' c").unwrap(), "a b|c");
+    assert_eq!(console.execute("   ").unwrap(), "");
+    assert!(console.execute("reboot").unwrap_err().to_string().contains("Unknown command 'reboot'"));
+    assert!(console.has_command("help"));
+    assert!(!console.has_command("reboot"));
+
+    let help = console.execute("help").unwrap();
+    let names: Vec<&str> = help.lines().map(|line| line.split_whitespace().next().unwrap()).collect();
+    assert_eq!(names, ["help", "echo", "uptime", "wifi"]);
+    assert!(console.execute("help wifi").unwrap().contains("wifi status | scan"));
+    assert!(console.execute("help nothing").is_err());
+
+    // Handlers can be replaced and their errors are returned
+    console.register("uptime", "Broken", |_| Err(anyhow::anyhow!("clock stopped")));
+    assert_eq!(console.execute("uptime").unwrap_err().to_string(), "clock stopped");
+}
+
+#[test]
+fn test_completion_candidates() {
+    let console = test_console();
+
+    assert_eq!(console.complete(""), ["echo", "help", "uptime", "wifi"]);
+    assert_eq!(console.complete("u"), ["uptime"]);
+    assert_eq!(console.complete("wifi "), ["scan", "status"]);
+    assert_eq!(console.complete("wifi st"), ["status"]);
+    assert_eq!(console.complete("help w"), ["wifi"]);
+    assert!(console.complete("wifi status x").is_empty());
+    assert!(console.complete("uptime ").is_empty());
+}
+
+#[test]
+fn test_line_editing() {
+    let console = test_console();
+    let mut editor = LineEditor::new();
+
+    // Typed characters are echoed
+    assert!(type_bytes(&mut editor, &console, b"uptme").is_empty());
+    assert_eq!(editor.take_output(), b"uptme");
+
+    // Insert in the middle, delete and backspace
+    type_bytes(&mut editor, &console, LEFT);
+    type_bytes(&mut editor, &console, LEFT);
+    type_bytes(&mut editor, &console, b"i");
+    assert_eq!(editor.line(), "uptime");
+    assert_eq!(editor.cursor(), 4);
+    type_bytes(&mut editor, &console, b"\x1b[3~\x7f");
+    assert_eq!(editor.line(), "upte");
+    type_bytes(&mut editor, &console, b"\x05\x01x\x1b[F");
+    assert_eq!((editor.line(), editor.cursor()), ("xupte", 5));
+
+    // Ctrl-U clears, Ctrl-C cancels and prints a new prompt
+    type_bytes(&mut editor, &console, b"\x15");
+    assert_eq!(editor.line(), "");
+    type_bytes(&mut editor, &console, b"abc\x03");
+    assert_eq!(editor.line(), "");
+    editor.take_output();
+    type_bytes(&mut editor, &console, b"\x03");
+    assert_eq!(editor.take_output(), b"^C\r\nesp32> ");
+
+    // CR LF submits once
+    assert_eq!(type_bytes(&mut editor, &console, b"echo hi\r\n"), ["echo hi"]);
+    assert_eq!(type_bytes(&mut editor, &console, b"\n"), [""]);
+
+    // Overlong lines are cut off with a bell
+    let long = vec![b'x'; MAX_LINE_LEN + 10];
+    type_bytes(&mut editor, &console, &long);
+    assert_eq!(editor.line().len(), MAX_LINE_LEN);
+    assert_eq!(editor.take_output().last(), Some(&0x07));
+}
+
+#[test]
+fn test_history() {
+    let console = test_console();
+    let mut editor = LineEditor::new();
+
+    for line in ["uptime\r", "wifi status\r", "wifi status\r", " \r", "help\r"] {
+        type_bytes(&mut editor, &console, line.as_bytes());
+    }
+    // Blank lines and repeats are not recorded
+    assert_eq!(editor.history(), &["uptime", "wifi status", "help"]);
+
+    type_bytes(&mut editor, &console, b"ech");
+    type_bytes(&mut editor, &console, UP);
+    assert_eq!(editor.line(), "help");
+    type_bytes(&mut editor, &console, UP);
+    type_bytes(&mut editor, &console, UP);
+    type_bytes(&mut editor, &console, UP);
+    assert_eq!(editor.line(), "uptime");
+    type_bytes(&mut editor, &console, DOWN);
+    assert_eq!(editor.line(), "wifi status");
+    // Going past the newest entry restores the draft
+    type_bytes(&mut editor, &console, DOWN);
+    type_bytes(&mut editor, &console, DOWN);
+    assert_eq!(editor.line(), "ech");
+    assert_eq!(editor.cursor(), 3);
+
+    for i in 0..HISTORY_LEN + 5 {
+        type_bytes(&mut editor, &console, format!("echo {}\r", i).as_bytes());
+    }
+    assert_eq!(editor.history().len(), HISTORY_LEN);
+    assert_eq!(editor.history().back().unwrap(), &format!("echo {}", HISTORY_LEN + 4));
+}
+
+#[test]
+fn test_tab_completion() {
+    let console = test_console();
+    let mut editor = LineEditor::new();
+
+    type_bytes(&mut editor, &console, b"up\t");
+    assert_eq!(editor.line(), "uptime ");
+
+    // Several matches extend to the common prefix, then list the candidates
+    type_bytes(&mut editor, &console, b"\x15wifi s\t");
+    assert_eq!(editor.line(), "wifi s");
+    editor.take_output();
+    type_bytes(&mut editor, &console, b"\t");
+    let output = String::from_utf8(editor.take_output()).unwrap();
+    assert!(output.contains("scan  status"));
+    type_bytes(&mut editor, &console, b"t\t");
+    assert_eq!(editor.line(), "wifi status ");
+
+    // No match rings the bell
+    type_bytes(&mut editor, &console, b"\x15zz\t");
+    assert_eq!(editor.line(), "zz");
+    assert_eq!(editor.take_output().last(), Some(&0x07));
+}
+
+#[test]
+fn test_config_command() {
+    let nvs = MemoryNvs::new();
+    let store = Arc::new(Mutex::new(ConfigStore::load(nvs)));
+    let reset = Arc::new(AtomicBool::new(false));
+    let mut console = Console::new();
+    let reset_flag = reset.clone();
+    register_config_command(&mut console, store.clone(), move || {
+        reset_flag.store(true, Ordering::Relaxed);
+        Ok(())
+    });
+
+    assert_eq!(config_field_patch("telemetry.sample_interval_ms", "5000"), json!({ "telemetry": { "sample_interval_ms": 5000 } }));
+    assert_eq!(config_field_patch("mqtt.client_id", "line-3"), json!({ "mqtt": { "client_id": "line-3" } }));
+    assert_eq!(config_field_patch("mqtt.username", "null"), json!({ "mqtt": { "username": null } }));
+
+    assert_eq!(console.execute("config get pins.led1").unwrap(), "2");
+    assert!(console.execute("config").unwrap().contains("\"version\": 1"));
+    assert!(console.execute("config get pins.led9").is_err());
+
+    assert_eq!(console.execute("config set telemetry.sample_interval_ms 5000").unwrap(), "Changed: telemetry");
+    assert_eq!(console.execute("config set telemetry.sample_interval_ms 5000").unwrap(), "No changes");
+    assert_eq!(store.lock().unwrap().config().telemetry.sample_interval_ms, 5000);
+
+    let error = console.execute("config set pins.led1 4").unwrap_err().to_string();
+    assert!(error.contains("pins.led2"), "{}", error);
+    assert!(console.execute("config set telemetry.sample_interval_ms").is_err());
+
+    let output = console.execute(r#"config import '{"button": {"debounce_ms": 20}, "mqtt": {"password": "x"}}'"#).unwrap();
+    assert_eq!(output, "Changed: button, mqtt");
+    assert_eq!(console.execute("config get mqtt.password").unwrap(), "\"********\"");
+
+    // Resetting needs confirmation
+    assert!(console.execute("config reset").unwrap().contains("confirm"));
+    assert!(!reset.load(Ordering::Relaxed));
+    console.execute("config reset confirm").unwrap();
+    assert!(reset.load(Ordering::Relaxed));
+    assert!(console.execute("config frobnicate").is_err());
+}
+
+#[test]
+fn test_log_command() {
+    let store = Arc::new(Mutex::new(ConfigStore::load(MemoryNvs::new())));
+    let changes = store.lock().unwrap().subscribe();
+    let mut console = Console::new();
+    register_log_command(&mut console, store.clone());
+
+    assert_eq!(console.execute("log").unwrap(), "default: info");
+    assert_eq!(console.execute("log level DEBUG").unwrap(), "Changed: logging");
+    assert_eq!(console.execute("log level tasks::wifi_task trace").unwrap(), "Changed: logging");
+    assert_eq!(console.execute("log show").unwrap(), "default: debug\ntasks::wifi_task: trace");
+    assert!(changes.try_iter().last().unwrap().config.logging.modules.contains_key("tasks::wifi_task"));
+
+    console.execute("log level tasks::wifi_task default").unwrap();
+    assert!(store.lock().unwrap().config().logging.modules.is_empty());
+
+    assert_eq!(console.execute("log level loud").unwrap_err().to_string(), "Invalid level 'loud'");
+    assert!(console.execute("log level tasks.wifi debug").is_err());
+    assert!(console.execute("log level").is_err());
+}
+
+#[test]
+fn test_logs_command() {
+    let ring = Arc::new(Mutex::new(LogRing::new(10)));
+    let mut console = Console::new();
+    register_logs_command(&mut console, ring.clone());
+
+    assert_eq!(console.execute("logs").unwrap(), "No log records");
+    {
+        let mut ring = ring.lock().unwrap();
+        ring.push(LogLevel::Info, 100, "esp32_template::tasks::wifi_task", "Connected");
+        ring.push(LogLevel::Warn, 250, "esp32_template::tasks::mqtt_task", "Broker unreachable");
+    }
+
+    assert_eq!(
+        console.execute("logs").unwrap(),
+        "1 info (100) esp32_template::tasks::wifi_task: Connected\n2 warn (250) esp32_template::tasks::mqtt_task: Broker unreachable"
+    );
+    assert_eq!(console.execute("logs level=warn").unwrap(), "2 warn (250) esp32_template::tasks::mqtt_task: Broker unreachable");
+    assert_eq!(console.execute("logs since=1 module=tasks::wifi_task").unwrap(), "No log records");
+    assert!(console.execute("logs warn").is_err());
+    assert!(console.execute("logs limit=many").is_err());
+
+    assert_eq!(console.execute("logs clear").unwrap(), "Log cleared");
+    assert!(ring.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_format_crashes() {
+    let mut ring = CrashRing::new(4);
+    assert_eq!(format_crashes(&ring.entries(), 0), "No crashes stored (0 recorded in total)");
+
+    let panic = PanicRecord::new("index out of bounds", "src/app.rs", 120, 9, "telemetry", 93_512);
+    let core_dump = CoreDumpSummary {
+        task: "telemetry".to_string(),
+        program_counter: 0x4200_1234,
+        backtrace: vec![0x4200_1234, 0x4200_5678],
+        backtrace_corrupted: true,
+    };
+    ring.push(ResetReason::Brownout, "1.2.0", None, None);
+    ring.push(ResetReason::Panic, "1.2.1", Some(panic), Some(core_dump));
+
+    assert_eq!(
+        format_crashes(&ring.entries(), ring.total_crashes()),
+        "#2 panic, firmware 1.2.1\n\
+         \x20 panicked at src/app.rs:120:9 in task 'telemetry' after 93512 ms: index out of bounds\n\
+         \x20 exception in task 'telemetry' at 0x42001234, backtrace: 0x42001234 0x42005678 (corrupted)\n\
+         #1 brownout, firmware 1.2.0\n\
+         2 crash(es) recorded in total"
+    );
+
+    ring.clear();
+    assert_eq!(format_crashes(&ring.entries(), ring.total_crashes()), "No crashes stored (2 recorded in total)");
+}
@@ -0,0 +1,197 @@
+// Host tests for the crash record format and ring rotation
+// These tests do not require hardware
+
+use esp32_template::error::Error;
+use esp32_template::utils::crash_log::{
+    crc32, CoreDumpSummary, CrashRing, PanicRecord, ResetReason, MAX_BACKTRACE_LEN, MAX_FILE_LEN,
+    MAX_MESSAGE_LEN, MAX_RING_CAPACITY, MAX_RING_LEN, MAX_TASK_LEN, MAX_VERSION_LEN, PANIC_SLOT_LEN,
+};
+
+fn sample_panic() -> PanicRecord {
+    PanicRecord::new("called `Option::unwrap()` on a `None` value", "src/tasks/sensor_task.rs", 68, 21, "telemetry", 93_512)
+}
+
+fn sample_core_dump() -> CoreDumpSummary {
+    CoreDumpSummary {
+        task: "telemetry".to_string(),
+        program_counter: 0x4200_1234,
+        backtrace: vec![0x4200_1234, 0x4200_5678, 0x4037_9abc],
+        backtrace_corrupted: false,
+    }
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+}
+
+#[test]
+fn test_reset_reason_mapping() {
+    for raw in 0..=15 {
+        assert_eq!(ResetReason::from_raw(raw).to_raw(), raw);
+    }
+    assert_eq!(ResetReason::from_raw(99), ResetReason::Unknown);
+
+    assert!(ResetReason::Panic.is_unexpected());
+    assert!(ResetReason::TaskWatchdog.is_unexpected());
+    assert!(ResetReason::Brownout.is_unexpected());
+    assert!(!ResetReason::PowerOn.is_unexpected());
+    assert!(!ResetReason::Software.is_unexpected());
+    assert!(!ResetReason::DeepSleep.is_unexpected());
+}
+
+#[test]
+fn test_panic_record_round_trip() {
+    let record = sample_panic();
+    let encoded = record.encode().unwrap();
+    assert_eq!(PanicRecord::decode(&encoded).unwrap(), record);
+
+    // The RTC slot is larger than the record; trailing bytes are ignored
+    let mut slot = vec![0xAA; PANIC_SLOT_LEN];
+    slot[..encoded.len()].copy_from_slice(&encoded);
+    assert_eq!(PanicRecord::decode(&slot).unwrap(), record);
+}
+
+#[test]
+fn test_panic_record_truncation() {
+    let message = "é".repeat(MAX_MESSAGE_LEN);
+    let file = format!("/home/builder/{}/src/main.rs", "deep/".repeat(20));
+    let record = PanicRecord::new(&message, &file, 1, 1, "a-very-long-task-name", 0);
+
+    assert!(record.message.len() <= MAX_MESSAGE_LEN);
+    assert!(record.message.chars().all(|c| c == 'é'));
+    assert!(record.file.len() <= MAX_FILE_LEN);
+    assert!(record.file.ends_with("/src/main.rs"));
+    assert_eq!(record.task.len(), MAX_TASK_LEN);
+
+    // Even a record with every field at its limit fits the RTC slot
+    assert!(record.encode().unwrap().len() <= PANIC_SLOT_LEN);
+    assert_eq!(PanicRecord::decode(&record.encode().unwrap()).unwrap(), record);
+}
+
+#[test]
+fn test_corrupted_records_are_rejected() {
+    let encoded = sample_panic().encode().unwrap();
+
+    // Random RTC memory after power-on
+    assert!(PanicRecord::decode(&[0x5A; PANIC_SLOT_LEN]).is_err());
+    assert!(PanicRecord::decode(&[0; PANIC_SLOT_LEN]).is_err());
+
+    let mut flipped = encoded.clone();
+    flipped[10] ^= 0x01;
+    assert!(PanicRecord::decode(&flipped).is_err());
+
+    assert!(PanicRecord::decode(&encoded[..encoded.len() - 1]).is_err());
+
+    let mut future = encoded.clone();
+    future[2] = 99;
+    assert!(PanicRecord::decode(&future).is_err());
+
+    // A crash ring is not a panic record
+    assert!(PanicRecord::decode(&CrashRing::default().encode().unwrap()).is_err());
+}
+
+#[test]
+fn test_ring_rotation() {
+    let mut ring = CrashRing::new(3);
+    assert!(ring.is_empty());
+    assert_eq!(ring.total_crashes(), 0);
+
+    for reason in [ResetReason::Panic, ResetReason::TaskWatchdog, ResetReason::Brownout, ResetReason::Panic] {
+        ring.push(reason, "1.2.0", None, None);
+    }
+
+    assert_eq!(ring.len(), 3);
+    assert_eq!(ring.total_crashes(), 4);
+    let sequences: Vec<u32> = ring.entries().iter().map(|entry| entry.sequence).collect();
+    assert_eq!(sequences, vec![4, 3, 2]);
+    assert_eq!(ring.latest().unwrap().reset_reason, ResetReason::Panic);
+
+    // Clearing keeps the sequence counter
+    ring.clear();
+    assert!(ring.is_empty());
+    assert_eq!(ring.push(ResetReason::Panic, "1.2.0", None, None).sequence, 5);
+}
+
+#[test]
+fn test_ring_round_trip() {
+    let mut ring = CrashRing::new(4);
+    ring.push(ResetReason::Panic, "1.2.0", Some(sample_panic()), Some(sample_core_dump()));
+    ring.push(ResetReason::InterruptWatchdog, "1.2.1", None, Some(sample_core_dump()));
+    ring.push(ResetReason::Brownout, "1.2.1", None, None);
+
+    let encoded = ring.encode().unwrap();
+    let decoded = CrashRing::decode(&encoded, 4).unwrap();
+    assert_eq!(decoded, ring);
+
+    let newest = &decoded.entries()[2];
+    assert_eq!(newest.panic.as_ref().unwrap().line, 68);
+    assert_eq!(newest.core_dump.as_ref().unwrap().backtrace.len(), 3);
+
+    // Loading with a smaller capacity keeps the newest entries
+    let smaller = CrashRing::decode(&encoded, 2).unwrap();
+    assert_eq!(smaller.len(), 2);
+    assert_eq!(smaller.entries()[0].reset_reason, ResetReason::Brownout);
+    assert_eq!(smaller.total_crashes(), 3);
+
+    let mut damaged = encoded;
+    let last = damaged.len() - 5;
+    damaged[last] ^= 0xFF;
+    assert!(CrashRing::decode(&damaged, 4).is_err());
+}
+
+#[test]
+fn test_full_ring_fits_its_blob() {
+    assert_eq!(CrashRing::new(0).capacity(), 1);
+    assert_eq!(CrashRing::new(1000).capacity(), MAX_RING_CAPACITY);
+
+    // Oversized fields set directly on the public structs are cut on push
+    let panic = PanicRecord {
+        message: "m".repeat(255),
+        file: "f".repeat(255),
+        line: u32::MAX,
+        column: u32::MAX,
+        task: "t".repeat(255),
+        uptime_ms: u64::MAX,
+    };
+    let core_dump = CoreDumpSummary {
+        task: "t".repeat(255),
+        program_counter: u32::MAX,
+        backtrace: vec![u32::MAX; 255],
+        backtrace_corrupted: true,
+    };
+
+    let mut ring = CrashRing::new(1000);
+    for _ in 0..MAX_RING_CAPACITY * 2 {
+        ring.push(ResetReason::Panic, &"v".repeat(300), Some(panic.clone()), Some(core_dump.clone()));
+    }
+
+    let entry = ring.latest().unwrap();
+    assert_eq!(entry.firmware_version.len(), MAX_VERSION_LEN);
+    assert_eq!(entry.panic.as_ref().unwrap().message.len(), MAX_MESSAGE_LEN);
+    assert_eq!(entry.core_dump.as_ref().unwrap().task.len(), MAX_TASK_LEN);
+    assert_eq!(entry.core_dump.as_ref().unwrap().backtrace.len(), MAX_BACKTRACE_LEN);
+
+    let encoded = ring.encode().unwrap();
+    assert!(encoded.len() <= MAX_RING_LEN);
+    assert_eq!(CrashRing::decode(&encoded, 1000).unwrap(), ring);
+}
+
+#[test]
+fn test_decode_errors_are_typed() {
+    assert!(matches!(PanicRecord::decode(&[0; 4]), Err(Error::InvalidArgument(_))));
+    assert!(matches!(CrashRing::decode(&[0x5A; 64], 4), Err(Error::InvalidArgument(_))));
+}
+
+#[test]
+fn test_entries_serialize_for_the_api() {
+    let mut ring = CrashRing::default();
+    ring.push(ResetReason::Panic, "1.2.0", Some(sample_panic()), None);
+
+    let json = serde_json::to_value(ring.entries()).unwrap();
+    assert_eq!(json[0]["reset_reason"], "panic");
+    assert_eq!(json[0]["panic"]["file"], "src/tasks/sensor_task.rs");
+    assert_eq!(json[0]["panic"]["uptime_ms"], 93_512);
+    assert!(json[0]["core_dump"].is_null());
+}
@@ -0,0 +1,83 @@
+// Host tests for serving the embedded web dashboard
+// These tests use hand-written assets instead of the generated ones
+
+mod common;
+
+use esp32_template::tasks::dashboard::{find_asset, register_dashboard_routes, WebAsset};
+use esp32_template::tasks::http_api::ApiRouter;
+use std::borrow::Cow;
+use common::FakeRequest;
+
+static ASSETS: &[WebAsset] = &[
+    WebAsset { path: "/app.js", content_type: "application/javascript", etag: "\"0000000000000001\"", body: b"\x1f\x8bjs" },
+    WebAsset { path: "/index.html", content_type: "text/html; charset=utf-8", etag: "\"0000000000000002\"", body: b"\x1f\x8bhtml" },
+];
+
+fn header<'a>(headers: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(key, _)| *key == name).map(|(_, value)| value.as_str())
+}
+
+#[test]
+fn test_find_asset() {
+    assert_eq!(find_asset(ASSETS, "/").unwrap().path, "/index.html");
+    assert_eq!(find_asset(ASSETS, "/app.js").unwrap().content_type, "application/javascript");
+    assert!(find_asset(ASSETS, "/missing.css").is_none());
+}
+
+#[test]
+fn test_serve_gzipped_assets() {
+    let mut router = ApiRouter::new();
+    register_dashboard_routes(&mut router, ASSETS);
+
+    let response = router.handle(&mut FakeRequest::get("/", &[("Accept-Encoding", "gzip, deflate, br")]));
+    assert_eq!(response.status, 200);
+    assert_eq!(response.content_type, "text/html; charset=utf-8");
+    assert_eq!(&*response.body, b"\x1f\x8bhtml");
+    // Served straight from the embedded asset
+    assert!(matches!(response.body, Cow::Borrowed(_)));
+    assert_eq!(header(&response.headers, "Content-Encoding"), Some("gzip"));
+    assert_eq!(header(&response.headers, "ETag"), Some("\"0000000000000002\""));
+
+    let response = router.handle(&mut FakeRequest::get("/app.js", &[("accept-encoding", "br;q=1, gzip;q=0")]));
+    assert_eq!(response.status, 406);
+    let response = router.handle(&mut FakeRequest::get("/app.js", &[]));
+    assert_eq!(response.status, 406);
+
+    assert_eq!(router.handle(&mut FakeRequest::get("/missing.css", &[])).status, 404);
+}
+
+#[test]
+fn test_etag_revalidation() {
+    let mut router = ApiRouter::new();
+    register_dashboard_routes(&mut router, ASSETS);
+
+    let response = router.handle(&mut FakeRequest::get("/app.js", &[("If-None-Match", "\"0000000000000001\"")]));
+    assert_eq!(response.status, 304);
+    assert!(response.body.is_empty());
+    assert_eq!(header(&response.headers, "ETag"), Some("\"0000000000000001\""));
+
+    let response = router.handle(&mut FakeRequest::get(
+        "/app.js",
+        &[("If-None-Match", "W/\"stale\", \"0000000000000001\"")],
+    ));
+    assert_eq!(response.status, 304);
+
+    let response = router.handle(&mut FakeRequest::get(
+        "/app.js",
+        &[("If-None-Match", "\"stale\""), ("Accept-Encoding", "gzip")],
+    ));
+    assert_eq!(response.status, 200);
+}
+
+#[test]
+fn test_generated_assets() {
+    use esp32_template::tasks::web_assets::WEB_ASSETS;
+
+    let index = find_asset(WEB_ASSETS, "/").expect("web/index.html is embedded");
+    assert_eq!(index.content_type, "text/html; charset=utf-8");
+
+    for asset in WEB_ASSETS {
+        assert_eq!(&asset.body[..2], b"\x1f\x8b", "{} is gzipped", asset.path);
+        assert!(asset.etag.starts_with('"') && asset.etag.ends_with('"'));
+    }
+}
@@ -0,0 +1,422 @@
+// Host tests for the device configuration and its NVS persistence
+// These tests do not require hardware; NVS is replaced by an in-memory mock
+
+use esp32_template::error::{Error, FieldError};
+use esp32_template::tasks::device_config::{
+    merge_patch, migrate, validate_pin, ConfigSection, ConfigStore, DeviceConfig, Migration, CONFIG_VERSION,
+    MAX_GPIO, REDACTED,
+};
+use esp32_template::tasks::{IpMode, NetworkConfig, SntpConfig, StaticIpConfig};
+use esp32_template::utils::nvs_storage::{MemoryNvs, NvsStorage};
+use serde_json::{json, Value};
+use std::net::Ipv4Addr;
+
+fn stored(nvs: &MemoryNvs) -> Value {
+    serde_json::from_slice(&nvs.get_blob("config").unwrap().unwrap()).unwrap()
+}
+
+#[test]
+fn test_defaults_are_valid() {
+    let config = DeviceConfig::default();
+    assert!(config.validate().is_ok());
+    assert_eq!(config.pins.led1, 2);
+    assert_eq!(config.telemetry.sample_interval_ms, 10_000);
+}
+
+#[test]
+fn test_validation() {
+    let invalid = |change: fn(&mut DeviceConfig)| {
+        let mut config = DeviceConfig::default();
+        change(&mut config);
+        config.validate().unwrap_err()
+    };
+
+    match invalid(|c| c.telemetry.sample_interval_ms = 10) {
+        Error::OutOfRange { name, .. } => assert_eq!(name, "telemetry.sample_interval_ms"),
+        other => panic!("expected an out-of-range error, got {:?}", other),
+    }
+    assert!(matches!(invalid(|c| c.pins.button = 49), Error::OutOfRange { .. }));
+    assert!(matches!(invalid(|c| c.pins.led2 = 2), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.pins.led1 = 6), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.pins.button = 1), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.pins.led2 = 34), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.pins.button = 24), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.wifi.connect_attempts = 0), Error::OutOfRange { .. }));
+    assert!(matches!(invalid(|c| c.wifi.retry_max_delay_ms = 1000), Error::OutOfRange { .. }));
+    assert!(matches!(invalid(|c| c.mqtt.broker_url = "http://broker".to_string()), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.mqtt.broker_url = "mqtt://".to_string()), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.mqtt.client_id = "x".repeat(24)), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.mqtt.topic_prefix = "home/#".to_string()), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.mqtt.topic_prefix = "home/".to_string()), Error::InvalidArgument(_)));
+    assert!(matches!(invalid(|c| c.mqtt.qos = 3), Error::OutOfRange { .. }));
+    assert!(matches!(invalid(|c| c.thresholds.low_humidity_pct = 120.0), Error::OutOfRange { .. }));
+
+    // Above 2^24 an f32 comparison would round these onto the limit
+    assert!(matches!(invalid(|c| c.sntp.sync_interval_ms = 86_400_001), Error::OutOfRange { .. }));
+    assert!(matches!(invalid(|c| c.sntp.sync_interval_ms = 86_400_004), Error::OutOfRange { .. }));
+    let mut config = DeviceConfig::default();
+    config.sntp.sync_interval_ms = 86_400_000;
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_missing_config_uses_defaults() {
+    let nvs = MemoryNvs::new();
+    let store = ConfigStore::load(nvs.clone());
+
+    assert_eq!(*store.config(), DeviceConfig::default());
+    // Nothing is written until something changes
+    assert!(nvs.keys().is_empty());
+}
+
+#[test]
+fn test_update_persists_and_reloads() {
+    let nvs = MemoryNvs::new();
+    let mut store = ConfigStore::load(nvs.clone());
+
+    let sections = store.modify(|c| {
+        c.telemetry.sample_interval_ms = 30_000;
+        c.mqtt.password = Some("secret".to_string());
+    }).unwrap();
+    assert_eq!(sections, vec![ConfigSection::Mqtt, ConfigSection::Telemetry]);
+
+    let document = stored(&nvs);
+    assert_eq!(document["version"], CONFIG_VERSION);
+    assert_eq!(document["telemetry"]["sample_interval_ms"], 30_000);
+
+    // As after a reboot
+    let reloaded = ConfigStore::load(nvs.clone());
+    assert_eq!(reloaded.config(), store.config());
+}
+
+#[test]
+fn test_invalid_update_is_rejected() {
+    let nvs = MemoryNvs::new();
+    let mut store = ConfigStore::load(nvs.clone());
+
+    assert!(store.modify(|c| c.pins.led1 = 60).is_err());
+    assert_eq!(*store.config(), DeviceConfig::default());
+    assert!(nvs.keys().is_empty());
+
+    // A failed write leaves the running configuration unchanged
+    nvs.set_fail_writes(true);
+    assert!(matches!(store.modify(|c| c.button.debounce_ms = 20), Err(Error::Nvs { .. })));
+    assert_eq!(store.config().button.debounce_ms, 50);
+}
+
+#[test]
+fn test_subscribers_are_notified() {
+    let mut store = ConfigStore::load(MemoryNvs::new());
+    let changes = store.subscribe();
+    let dropped = store.subscribe();
+    drop(dropped);
+
+    // Unchanged values are not announced
+    assert!(store.modify(|_| {}).unwrap().is_empty());
+    assert!(changes.try_recv().is_err());
+
+    store.modify(|c| c.thresholds.high_temperature_c = 35.0).unwrap();
+    let change = changes.try_recv().unwrap();
+    assert!(change.affects(ConfigSection::Thresholds));
+    assert!(!change.affects(ConfigSection::Telemetry));
+    assert_eq!(change.config.thresholds.high_temperature_c, 35.0);
+}
+
+#[test]
+fn test_unusable_config_falls_back_to_defaults() {
+    for bytes in [&b"not json"[..], br#"{"pins": {"led1": 2}}"#, br#"{"version": 1, "pins": {"led1": 99}}"#] {
+        let mut nvs = MemoryNvs::new();
+        nvs.set_blob("config", bytes).unwrap();
+        assert_eq!(*ConfigStore::load(nvs).config(), DeviceConfig::default());
+    }
+}
+
+#[test]
+fn test_partial_and_newer_documents() {
+    // Settings missing from the document keep their defaults
+    let mut nvs = MemoryNvs::new();
+    let document = json!({ "version": 1, "telemetry": { "sample_interval_ms": 5000 } });
+    nvs.set_blob("config", document.to_string().as_bytes()).unwrap();
+    let store = ConfigStore::load(nvs);
+    assert_eq!(store.config().telemetry.sample_interval_ms, 5000);
+    assert_eq!(store.config().pins, DeviceConfig::default().pins);
+
+    // Settings from newer firmware are ignored, and the document is not rewritten
+    let mut nvs = MemoryNvs::new();
+    let document = json!({ "version": CONFIG_VERSION + 1, "button": { "debounce_ms": 20 }, "display": { "brightness": 80 } });
+    nvs.set_blob("config", document.to_string().as_bytes()).unwrap();
+    let store = ConfigStore::load(nvs.clone());
+    assert_eq!(store.config().button.debounce_ms, 20);
+    assert_eq!(stored(&nvs)["display"]["brightness"], 80);
+}
+
+#[test]
+fn test_migrations() {
+    // Version 1 stored the interval in seconds at the top level
+    fn nest_interval(document: &mut Value) -> esp32_template::error::Result<()> {
+        let seconds = document["interval_s"].as_u64().unwrap_or(10);
+        document.as_object_mut().unwrap().remove("interval_s");
+        document["telemetry"] = json!({ "sample_interval_ms": seconds * 1000 });
+        Ok(())
+    }
+    // Version 2 had a single LED
+    fn add_second_led(document: &mut Value) -> esp32_template::error::Result<()> {
+        document["pins"]["led2"] = json!(4);
+        Ok(())
+    }
+    let migrations: [Migration; 2] = [nest_interval, add_second_led];
+
+    let mut document = json!({ "version": 1, "interval_s": 30, "pins": { "led1": 12 } });
+    assert_eq!(migrate(&mut document, &migrations).unwrap(), 3);
+    assert_eq!(document, json!({
+        "version": 3,
+        "telemetry": { "sample_interval_ms": 30_000 },
+        "pins": { "led1": 12, "led2": 4 },
+    }));
+
+    // Only the missing steps run
+    let mut document = json!({ "version": 2, "pins": {} });
+    assert_eq!(migrate(&mut document, &migrations).unwrap(), 3);
+    assert_eq!(document["pins"]["led2"], 4);
+    assert!(document.get("telemetry").is_none());
+
+    // Current and newer documents are untouched
+    let mut document = json!({ "version": 5 });
+    assert_eq!(migrate(&mut document, &migrations).unwrap(), 5);
+    assert_eq!(document, json!({ "version": 5 }));
+
+    // A failing step stops the upgrade
+    fn fail(_: &mut Value) -> esp32_template::error::Result<()> {
+        Err(Error::InvalidState("unsupported layout".to_string()))
+    }
+    let mut document = json!({ "version": 1 });
+    assert!(migrate(&mut document, &[fail]).is_err());
+    assert!(migrate(&mut json!({ "pins": {} }), &migrations).is_err());
+}
+
+fn field_errors(result: esp32_template::error::Result<DeviceConfig>) -> Vec<FieldError> {
+    match result {
+        Err(Error::InvalidFields(fields)) => fields,
+        other => panic!("expected field errors, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_merge_patch() {
+    let mut document = json!({ "a": { "b": 1, "c": 2 }, "d": [1, 2], "e": "x" });
+    merge_patch(&mut document, &json!({ "a": { "b": 5, "c": null }, "d": [3], "f": { "g": true } }));
+    assert_eq!(document, json!({ "a": { "b": 5 }, "d": [3], "e": "x", "f": { "g": true } }));
+}
+
+#[test]
+fn test_export_redacts_secrets() {
+    let mut config = DeviceConfig::default();
+    let exported = config.export();
+    assert_eq!(exported["version"], CONFIG_VERSION);
+    assert!(exported["mqtt"]["password"].is_null());
+
+    config.mqtt.password = Some("secret".to_string());
+    let exported = config.export();
+    assert_eq!(exported["mqtt"]["password"], REDACTED);
+    assert!(!exported.to_string().contains("secret"));
+
+    // An export imported on the same device changes nothing, including the secret
+    assert_eq!(config.merged(&exported).unwrap(), config);
+    // On a fresh unit the secret stays unset
+    assert_eq!(DeviceConfig::default().merged(&exported).unwrap().mqtt.password, None);
+}
+
+#[test]
+fn test_import_merges_partial_documents() {
+    let mut config = DeviceConfig::default();
+    config.mqtt.username = Some("line-3".to_string());
+    config.button.debounce_ms = 20;
+
+    let merged = config.merged(&json!({
+        "telemetry": { "sample_interval_ms": 60_000 },
+        "mqtt": { "username": null, "password": "new" },
+        "button": null,
+    })).unwrap();
+    assert_eq!(merged.telemetry.sample_interval_ms, 60_000);
+    assert_eq!(merged.mqtt.username, None);
+    assert_eq!(merged.mqtt.password.as_deref(), Some("new"));
+    // `null` restores defaults, untouched settings are kept
+    assert_eq!(merged.button, DeviceConfig::default().button);
+    assert_eq!(merged.mqtt.broker_url, config.mqtt.broker_url);
+
+    // Documents from an older schema are migrated first
+    assert!(config.merged(&json!({ "version": 1, "pins": { "led1": 12 } })).is_ok());
+    assert_eq!(field_errors(config.merged(&json!({ "version": 0 })))[0].field, "version");
+}
+
+#[test]
+fn test_import_reports_each_field() {
+    let config = DeviceConfig::default();
+
+    let fields = field_errors(config.merged(&json!({ "display": {}, "pins": { "led9": 1 }, "wifi": 3, "telemetry": { "sample_interval_ms": {} } })));
+    let names: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+    assert_eq!(names, ["display", "pins.led9", "telemetry.sample_interval_ms", "wifi"]);
+    assert_eq!(fields[0].message, "unknown setting");
+
+    let fields = field_errors(config.merged(&json!({
+        "pins": { "led1": 4, "button": 300 },
+        "wifi": { "connect_attempts": 0 },
+        "mqtt": { "broker_url": "http://broker" },
+    })));
+    let names: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+    assert_eq!(names, ["pins.button", "wifi.connect_attempts", "pins.led2", "mqtt.broker_url"]);
+    assert!(fields[2].message.contains("GPIO4"));
+
+    assert_eq!(field_errors(config.merged(&json!("pins"))).len(), 1);
+    let error = Error::InvalidFields(vec![FieldError::new("pins.led1", "taken"), FieldError::new("wifi", "bad")]);
+    assert_eq!(error.to_string(), "Invalid settings: pins.led1: taken; wifi: bad");
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_store_import_and_factory_reset() {
+    let nvs = MemoryNvs::new();
+    let mut store = ConfigStore::load(nvs.clone());
+    let changes = store.subscribe();
+
+    let sections = store.import(&json!({ "thresholds": { "high_temperature_c": 40.0 } })).unwrap();
+    assert_eq!(sections, vec![ConfigSection::Thresholds]);
+    assert_eq!(stored(&nvs)["thresholds"]["high_temperature_c"], 40.0);
+    assert!(changes.try_recv().unwrap().affects(ConfigSection::Thresholds));
+
+    // A rejected import changes nothing
+    assert!(store.import(&json!({ "thresholds": { "high_temperature_c": 400.0 } })).is_err());
+    assert_eq!(store.export()["thresholds"]["high_temperature_c"], 40.0);
+
+    store.factory_reset().unwrap();
+    assert_eq!(*store.config(), DeviceConfig::default());
+    assert!(nvs.keys().is_empty());
+    assert!(changes.try_recv().unwrap().affects(ConfigSection::Thresholds));
+    assert_eq!(*ConfigStore::load(nvs).config(), DeviceConfig::default());
+}
+
+#[test]
+fn test_logging_section() {
+    use esp32_template::utils::log_filter::LogLevel;
+
+    let config = DeviceConfig::default();
+    assert_eq!(config.logging.level, LogLevel::Info);
+
+    // Modules merge key by key and `null` removes an override
+    let config = config.merged(&json!({ "logging": { "modules": { "tasks::wifi_task": "trace", "tasks": "debug" } } })).unwrap();
+    let config = config.merged(&json!({ "logging": { "level": "warn", "modules": { "tasks": null } } })).unwrap();
+    assert_eq!(config.logging.level, LogLevel::Warn);
+    assert_eq!(config.logging.modules.len(), 1);
+    assert_eq!(config.logging.modules["tasks::wifi_task"], LogLevel::Trace);
+    assert_eq!(config.changed_sections(&DeviceConfig::default()), vec![ConfigSection::Logging]);
+
+    let fields = field_errors(config.merged(&json!({ "logging": { "level": "loud", "modules": { "peripherals::button": "verbose" } } })));
+    let names: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+    assert_eq!(names, ["logging.level", "logging.modules.peripherals::button"]);
+    let fields = field_errors(config.merged(&json!({ "logging": { "modules": { "bad name": "info" } } })));
+    assert_eq!(fields[0].field, "logging.modules");
+    let fields = field_errors(config.merged(&json!({ "logging": { "modules": "debug" } })));
+    assert_eq!(fields[0].message, "expected an object");
+}
+
+#[test]
+fn test_sntp_section() {
+    let config = DeviceConfig::default();
+    assert_eq!(config.mdns.instance_name, "ESP32 Template");
+    assert_eq!(config.sntp.servers, ["pool.ntp.org", "time.google.com"]);
+    assert_eq!(config.sntp.time_zone, "UTC0");
+    assert_eq!(config.sntp, SntpConfig::default());
+    assert!(config.sntp.validate().is_ok());
+
+    let config = config.merged(&json!({
+        "mdns": { "instance_name": "Greenhouse", "mqtt_port": 1883 },
+        "sntp": { "servers": ["ntp.lan"], "time_zone": "CET-1CEST,M3.5.0,M10.5.0/3" },
+    })).unwrap();
+    assert_eq!(config.mdns.instance_name, "Greenhouse");
+    assert_eq!(config.mdns.mqtt_port, Some(1883));
+    assert_eq!(config.sntp.servers, ["ntp.lan"]);
+    assert_eq!(
+        config.changed_sections(&DeviceConfig::default()),
+        vec![ConfigSection::Mdns, ConfigSection::Sntp]
+    );
+
+    let fields = field_errors(config.merged(&json!({
+        "mdns": { "hostname": "-bad" },
+        "sntp": { "servers": ["a", "b", "c", "d"], "time_zone": "not a zone", "sync_interval_ms": 1000 },
+    })));
+    let mut names: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+    names.sort();
+    assert_eq!(names, ["mdns", "sntp.servers", "sntp.sync_interval_ms", "sntp.time_zone"]);
+
+    // The section is what `SntpTask::start` validates
+    let sntp = SntpConfig { time_zone: "not a zone".to_string(), ..SntpConfig::default() };
+    assert!(sntp.validate().is_err());
+    let sntp = SntpConfig { servers: vec![" ".to_string()], ..SntpConfig::default() };
+    assert!(sntp.validate().is_err());
+}
+
+#[test]
+fn test_pin_capabilities() {
+    assert!(validate_pin(2, true).is_ok());
+    assert!(validate_pin(34, false).is_ok());
+    assert!(validate_pin(34, true).is_err());
+    assert!(validate_pin(9, false).is_err());
+    assert!(validate_pin(3, false).is_err());
+    assert!(matches!(validate_pin(MAX_GPIO + 1, false), Err(Error::OutOfRange { .. })));
+
+    let fields = field_errors(DeviceConfig::default().merged(&json!({
+        "pins": { "led1": 7, "led2": 36, "button": 39 },
+    })));
+    let names: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+    assert_eq!(names, ["pins.led1", "pins.led2"]);
+}
+
+#[test]
+fn test_network_section() {
+    let mut nvs = MemoryNvs::new();
+    let document = json!({
+        "version": 1,
+        "network": {
+            "hostname": "greenhouse-1",
+            "mode": "dhcp_with_fallback",
+            "address": "192.168.1.50",
+            "gateway": "192.168.1.1",
+            "dns": "1.1.1.1",
+        },
+    });
+    nvs.set_blob("config", document.to_string().as_bytes()).unwrap();
+    let store = ConfigStore::load(nvs.clone());
+
+    let network = &store.config().network;
+    assert_eq!(network.hostname, "greenhouse-1");
+    assert_eq!(network.dhcp_timeout_ms, NetworkConfig::default().dhcp_timeout_ms);
+    let expected = StaticIpConfig {
+        address: Ipv4Addr::new(192, 168, 1, 50),
+        prefix_len: 24,
+        gateway: Ipv4Addr::new(192, 168, 1, 1),
+        dns: Some(Ipv4Addr::new(1, 1, 1, 1)),
+        secondary_dns: None,
+    };
+    assert_eq!(network.ip_mode, IpMode::DhcpWithFallback(expected));
+
+    // Switching the mode keeps the static settings given earlier
+    let config = store.config().merged(&json!({ "network": { "mode": "static" } })).unwrap();
+    assert_eq!(config.network.ip_mode, IpMode::Static(expected));
+    assert_eq!(config.changed_sections(store.config()), vec![ConfigSection::Network]);
+
+    let config = config.merged(&json!({ "network": { "mode": "dhcp" } })).unwrap();
+    assert_eq!(config.network.ip_mode, IpMode::Dhcp);
+    assert_eq!(config.to_document()["network"]["address"], "0.0.0.0");
+
+    let fields = field_errors(store.config().merged(&json!({
+        "network": { "hostname": "-bad", "gateway": "10.0.0.1", "dhcp_timeout_ms": 10 },
+    })));
+    let mut names: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+    names.sort();
+    assert_eq!(names, ["network", "network.dhcp_timeout_ms", "network.hostname"]);
+
+    // A stored static address outside its subnet is not loaded
+    let document = json!({ "version": 1, "network": { "mode": "static", "address": "192.168.1.255" } });
+    nvs.set_blob("config", document.to_string().as_bytes()).unwrap();
+    assert_eq!(ConfigStore::load(nvs).config().network, NetworkConfig::default());
+}
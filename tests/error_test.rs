@@ -0,0 +1,71 @@
+// Host tests for the typed driver and task errors
+// These tests do not require hardware
+
+use esp32_template::error::{Error, EspCode, Retryable};
+use std::error::Error as _;
+use std::io;
+
+#[test]
+fn test_display_messages() {
+    assert_eq!(Error::gpio("LED1 pin configuration", EspCode::INVALID_ARG).to_string(), "LED1 pin configuration failed");
+    assert_eq!(Error::timeout("WiFi connection", 15_000).to_string(), "WiFi connection timed out after 15000 ms");
+    assert_eq!(Error::NotInitialized("WiFi").to_string(), "WiFi not initialized");
+    assert_eq!(Error::out_of_range("LED", 3, 1, 2).to_string(), "LED value 3 is out of range [1, 2]");
+    assert_eq!(Error::invalid_argument("SSID too long").to_string(), "SSID too long");
+}
+
+#[test]
+fn test_esp_code_is_preserved() {
+    let error = Error::wifi("WiFi start", EspCode(0x3002));
+    assert_eq!(error.esp_code(), Some(EspCode::WIFI_NOT_STARTED));
+
+    let source = error.source().expect("driver errors chain to the ESP code");
+    assert_eq!(source.to_string(), "ESP_ERR_WIFI_NOT_STARTED (0x3002)");
+    assert_eq!(EspCode(0x7777).to_string(), "esp_err_t 0x7777");
+    assert_eq!(EspCode::FAIL.name(), Some("ESP_FAIL"));
+
+    assert_eq!(Error::NotInitialized("Sensor task").esp_code(), None);
+    assert!(Error::NotInitialized("Sensor task").source().is_none());
+}
+
+#[test]
+fn test_io_errors_chain() {
+    let error: Error = io::Error::new(io::ErrorKind::TimedOut, "read timed out").into();
+    assert!(matches!(error, Error::Io(_)));
+    assert_eq!(error.source().unwrap().to_string(), "read timed out");
+    assert!(error.is_retryable());
+
+    let error: Error = io::Error::new(io::ErrorKind::PermissionDenied, "denied").into();
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_retryable_classification() {
+    assert!(Error::timeout("Sensor read", 100).is_retryable());
+    assert!(Error::wifi("WiFi connection", EspCode::WIFI_CONN).is_retryable());
+    assert!(Error::nvs("NVS write", EspCode::NO_MEM).is_retryable());
+    assert!(Error::i2c("Sensor read", EspCode::FAIL).is_retryable());
+
+    assert!(!Error::gpio("LED1 set", EspCode::INVALID_ARG).is_retryable());
+    assert!(!Error::nvs("NVS read", EspCode::NVS_NOT_FOUND).is_retryable());
+    assert!(!Error::uart("Console UART driver install", EspCode::INVALID_STATE).is_retryable());
+    assert!(!Error::NotInitialized("WiFi").is_retryable());
+    assert!(!Error::InvalidState("No WiFi networks configured".to_string()).is_retryable());
+    assert!(!Error::out_of_range("LED", 0, 1, 2).is_retryable());
+}
+
+#[test]
+fn test_anyhow_interop() {
+    let error: anyhow::Error = Error::gpio("Button read", EspCode::INVALID_STATE).into();
+    assert!(!Retryable::is_retryable(&error));
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Gpio { code: EspCode::INVALID_STATE, .. })
+    ));
+
+    let wrapped = anyhow::Error::from(Error::timeout("WiFi connection", 15_000)).context("Provisioning failed");
+    assert!(Retryable::is_retryable(&wrapped));
+
+    // Errors without a typed cause are assumed to be transient
+    assert!(Retryable::is_retryable(&anyhow::anyhow!("Broker unreachable")));
+}
@@ -0,0 +1,76 @@
+// Snapshot tests for Home Assistant MQTT discovery configs
+// Regenerate snapshots with: UPDATE_SNAPSHOTS=1 cargo test --test ha_discovery_test
+
+use esp32_template::peripherals::button::ButtonEvent;
+use esp32_template::tasks::ha_discovery::{
+    button_event_payload, led_state_payload, parse_led_state, DeviceInfo, DiscoveryRegistry,
+    LedKind,
+};
+use esp32_template::tasks::mqtt_task::MqttConfig;
+
+fn registry() -> DiscoveryRegistry {
+    let mut device = DeviceInfo::new("Greenhouse 1", "ESP32-S3");
+    device.sw_version = "1.2.3".to_string();
+
+    let mut registry = DiscoveryRegistry::new(device);
+    registry
+        .add_led(1, "Status LED", LedKind::Light)
+        .add_led(2, "Pump", LedKind::Switch)
+        .add_button(1, "Button")
+        .add_sensor_task_measurements();
+    registry
+}
+
+/// Render messages as topic + pretty JSON blocks for stable diffs
+fn render(registry: &DiscoveryRegistry, config: &MqttConfig) -> String {
+    registry.messages(config)
+        .iter()
+        .map(|message| {
+            let json: serde_json::Value = serde_json::from_str(&message.payload).unwrap();
+            format!("{}\n{}\n\n", message.topic, serde_json::to_string_pretty(&json).unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn test_discovery_snapshot() {
+    let config = MqttConfig::new("mqtt://localhost", "dev1");
+    let actual = render(&registry(), &config);
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots/ha_discovery.snap");
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(path, &actual).unwrap();
+    }
+
+    let expected = std::fs::read_to_string(path).unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_discovery_topics() {
+    let config = MqttConfig::new("mqtt://localhost", "dev1");
+    let mut registry = registry();
+    registry.set_discovery_prefix("ha");
+
+    let topics: Vec<String> = registry.messages(&config).into_iter().map(|m| m.topic).collect();
+    assert_eq!(topics, vec![
+        "ha/light/dev1/led1/config",
+        "ha/switch/dev1/led2/config",
+        "ha/binary_sensor/dev1/button1/config",
+        "ha/device_automation/dev1/button1_press/config",
+        "ha/sensor/dev1/temperature/config",
+        "ha/sensor/dev1/humidity/config",
+        "ha/sensor/dev1/pressure/config",
+    ]);
+    assert_eq!(registry.status_topic(), "ha/status");
+}
+
+#[test]
+fn test_state_payloads() {
+    assert_eq!(led_state_payload(true), r#"{"state":"ON"}"#);
+    assert_eq!(button_event_payload(ButtonEvent::Pressed), r#"{"event":"pressed"}"#);
+
+    assert!(parse_led_state(&serde_json::json!({ "state": "ON" })).unwrap());
+    assert!(!parse_led_state(&serde_json::json!({ "state": "OFF" })).unwrap());
+    assert!(parse_led_state(&serde_json::json!({ "state": "on" })).is_err());
+}
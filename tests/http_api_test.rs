@@ -0,0 +1,362 @@
+// Host tests for the REST API router
+// These tests use a fake request type instead of the ESP-IDF HTTP server
+
+mod common;
+
+use esp32_template::tasks::device_config::{ConfigSection, DeviceConfig};
+use esp32_template::tasks::http_api::{
+    register_device_routes, ApiRouter, DeviceApi, DeviceStatus, HttpMethod, HttpResponse, WifiInfo,
+};
+use esp32_template::tasks::ota::{OtaState, OtaStatus};
+use esp32_template::tasks::SensorReadings;
+use esp32_template::utils::crash_log::{CrashEntry, CrashRing, PanicRecord, ResetReason};
+use esp32_template::utils::log_ring::{LogPage, LogQuery, LogRing};
+use esp32_template::utils::LogLevel;
+use esp32_template::utils::Timestamp;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use common::FakeRequest;
+
+/// Fake device with two LEDs
+#[derive(Default)]
+struct FakeDevice {
+    leds: Mutex<[bool; 2]>,
+    readings: Option<SensorReadings>,
+    rebooted: AtomicBool,
+    ota: Mutex<OtaStatus>,
+    ota_url: Mutex<Option<String>>,
+    crashes: Mutex<CrashRing>,
+    config: Mutex<DeviceConfig>,
+    logs: Mutex<LogRing>,
+}
+
+impl DeviceApi for FakeDevice {
+    fn status(&self) -> anyhow::Result<DeviceStatus> {
+        Ok(DeviceStatus {
+            uptime: "00:01:05".to_string(),
+            uptime_ms: 65_000,
+            free_heap: 120_000,
+            min_free_heap: 90_000,
+            wifi: WifiInfo {
+                connected: true,
+                ssid: Some("lab".to_string()),
+                ip: Some("192.168.1.20".to_string()),
+                rssi: Some(-55),
+                access_point_active: false,
+            },
+            reset_reason: ResetReason::Panic,
+            crash_count: self.crashes.lock().unwrap().total_crashes(),
+        })
+    }
+
+    fn sensors(&self) -> anyhow::Result<Option<SensorReadings>> {
+        Ok(self.readings)
+    }
+
+    fn led_count(&self) -> u8 {
+        2
+    }
+
+    fn led_state(&self, id: u8) -> anyhow::Result<bool> {
+        Ok(self.leds.lock().unwrap()[id as usize - 1])
+    }
+
+    fn set_led(&self, id: u8, state: bool) -> anyhow::Result<()> {
+        self.leds.lock().unwrap()[id as usize - 1] = state;
+        Ok(())
+    }
+
+    fn reboot(&self) -> anyhow::Result<()> {
+        self.rebooted.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn ota_status(&self) -> OtaStatus {
+        self.ota.lock().unwrap().clone()
+    }
+
+    fn start_ota(&self, manifest_url: &str) -> anyhow::Result<()> {
+        self.ota.lock().unwrap().state = OtaState::Checking;
+        *self.ota_url.lock().unwrap() = Some(manifest_url.to_string());
+        Ok(())
+    }
+
+    fn crash_log(&self) -> anyhow::Result<Vec<CrashEntry>> {
+        Ok(self.crashes.lock().unwrap().entries())
+    }
+
+    fn clear_crash_log(&self) -> anyhow::Result<()> {
+        self.crashes.lock().unwrap().clear();
+        Ok(())
+    }
+
+    fn config(&self) -> anyhow::Result<Value> {
+        Ok(self.config.lock().unwrap().export())
+    }
+
+    fn import_config(&self, patch: &Value) -> anyhow::Result<Vec<ConfigSection>> {
+        let mut config = self.config.lock().unwrap();
+        let merged = config.merged(patch)?;
+        let changed = merged.changed_sections(&config);
+        *config = merged;
+        Ok(changed)
+    }
+
+    fn factory_reset(&self) -> anyhow::Result<()> {
+        *self.config.lock().unwrap() = DeviceConfig::default();
+        self.rebooted.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn logs(&self, query: &LogQuery) -> anyhow::Result<LogPage> {
+        Ok(self.logs.lock().unwrap().query(query))
+    }
+}
+
+fn router_for(device: Arc<FakeDevice>) -> ApiRouter {
+    let mut router = ApiRouter::new();
+    register_device_routes(&mut router, device);
+    router
+}
+
+fn send(router: &ApiRouter, method: HttpMethod, uri: &str, body: &str) -> (u16, Value) {
+    let response = router.handle(&mut FakeRequest::new(method, uri, body));
+    assert_eq!(response.content_type, "application/json");
+    (response.status, serde_json::from_slice(&response.body).unwrap())
+}
+
+#[test]
+fn test_status_and_sensors() {
+    let device = Arc::new(FakeDevice::default());
+    let router = router_for(device);
+
+    let (status, json) = send(&router, HttpMethod::Get, "/api/status", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["uptime"], "00:01:05");
+    assert_eq!(json["wifi"]["ssid"], "lab");
+    assert_eq!(json["wifi"]["rssi"], -55);
+
+    // No readings yet
+    let (status, _) = send(&router, HttpMethod::Get, "/api/sensors", "");
+    assert_eq!(status, 503);
+
+    let device = Arc::new(FakeDevice {
+        readings: Some(SensorReadings {
+            temperature: 21.5,
+            humidity: 40.0,
+            pressure: 1000.0,
+            uptime_ms: 5,
+            timestamp: Timestamp::Uptime(5),
+        }),
+        ..Default::default()
+    });
+    let (status, json) = send(&router_for(device), HttpMethod::Get, "/api/sensors?fresh=1", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["temperature"], 21.5);
+}
+
+#[test]
+fn test_led_endpoints() {
+    let device = Arc::new(FakeDevice::default());
+    let router = router_for(device.clone());
+
+    let (status, json) = send(&router, HttpMethod::Put, "/api/leds/2", r#"{"state": true}"#);
+    assert_eq!(status, 200);
+    assert_eq!(json["state"], true);
+    assert_eq!(*device.leds.lock().unwrap(), [false, true]);
+
+    let (status, json) = send(&router, HttpMethod::Get, "/api/leds/2", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["id"], 2);
+    assert_eq!(json["state"], true);
+
+    assert_eq!(send(&router, HttpMethod::Get, "/api/leds/3", "").0, 404);
+    assert_eq!(send(&router, HttpMethod::Get, "/api/leds/abc", "").0, 400);
+    assert_eq!(send(&router, HttpMethod::Put, "/api/leds/1", "{\"state\": 1}").0, 400);
+}
+
+#[test]
+fn test_routing_errors_and_reboot() {
+    let device = Arc::new(FakeDevice::default());
+    let router = router_for(device.clone());
+
+    assert_eq!(send(&router, HttpMethod::Get, "/api/missing", "").0, 404);
+    assert_eq!(send(&router, HttpMethod::Delete, "/api/status", "").0, 405);
+
+    let large = "x".repeat(4096);
+    assert_eq!(send(&router, HttpMethod::Put, "/api/leds/1", &large).0, 413);
+
+    let response = router.handle(&mut FakeRequest::failing(HttpMethod::Put, "/api/leds/1"));
+    assert_eq!(response.status, 400);
+
+    let (status, _) = send(&router, HttpMethod::Post, "/api/reboot", "");
+    assert_eq!(status, 202);
+    assert!(device.rebooted.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_custom_routes() {
+    let mut router = ApiRouter::new();
+    router.route(HttpMethod::Get, "/api/echo/{name}", |request| {
+        let shout = request.query_param("shout").is_some();
+        let name = request.param("name").unwrap_or_default();
+        let body = if shout { name.to_uppercase() } else { name.to_string() };
+        Ok(HttpResponse::json(200, &Value::String(body)))
+    });
+    router.route(HttpMethod::Get, "/api/fail", |_| Err(anyhow::anyhow!("boom")));
+
+    assert_eq!(send(&router, HttpMethod::Get, "/api/echo/bob?shout", "").1, "BOB");
+    assert_eq!(send(&router, HttpMethod::Get, "/api/echo/bob/", "").1, "bob");
+
+    let (status, json) = send(&router, HttpMethod::Get, "/api/fail", "");
+    assert_eq!(status, 500);
+    assert_eq!(json["error"], "boom");
+}
+
+#[test]
+fn test_ota_endpoints() {
+    let device = Arc::new(FakeDevice::default());
+    let router = router_for(device.clone());
+
+    let (status, json) = send(&router, HttpMethod::Get, "/api/ota", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["state"], "idle");
+
+    assert_eq!(send(&router, HttpMethod::Post, "/api/ota", "{}").0, 400);
+
+    let body = r#"{"url": "https://updates.example.com/manifest.json"}"#;
+    let (status, json) = send(&router, HttpMethod::Post, "/api/ota", body);
+    assert_eq!(status, 202);
+    assert_eq!(json["state"], "checking");
+    assert_eq!(device.ota_url.lock().unwrap().as_deref(), Some("https://updates.example.com/manifest.json"));
+
+    // A second update is rejected while the first is running
+    assert_eq!(send(&router, HttpMethod::Post, "/api/ota", body).0, 409);
+}
+
+#[test]
+fn test_crash_endpoints() {
+    let device = Arc::new(FakeDevice::default());
+    {
+        let mut crashes = device.crashes.lock().unwrap();
+        crashes.push(ResetReason::TaskWatchdog, "1.0.0", None, None);
+        let panic = PanicRecord::new("index out of bounds", "src/main.rs", 42, 9, "telemetry", 1234);
+        crashes.push(ResetReason::Panic, "1.0.0", Some(panic), None);
+    }
+    let router = router_for(device.clone());
+
+    let (status, json) = send(&router, HttpMethod::Get, "/api/status", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["reset_reason"], "panic");
+    assert_eq!(json["crash_count"], 2);
+
+    let (status, json) = send(&router, HttpMethod::Get, "/api/crashes", "");
+    assert_eq!(status, 200);
+    assert_eq!(json[0]["sequence"], 2);
+    assert_eq!(json[0]["panic"]["task"], "telemetry");
+    assert_eq!(json[0]["panic"]["line"], 42);
+    assert_eq!(json[1]["reset_reason"], "task_watchdog");
+    assert!(json[1]["panic"].is_null());
+
+    let (status, _) = send(&router, HttpMethod::Delete, "/api/crashes", "");
+    assert_eq!(status, 200);
+    assert_eq!(send(&router, HttpMethod::Get, "/api/crashes", "").1, Value::Array(Vec::new()));
+    // The total keeps counting after the entries are cleared
+    assert_eq!(send(&router, HttpMethod::Get, "/api/status", "").1["crash_count"], 2);
+}
+
+#[test]
+fn test_config_endpoints() {
+    let device = Arc::new(FakeDevice::default());
+    device.config.lock().unwrap().mqtt.password = Some("secret".to_string());
+    let router = router_for(device.clone());
+
+    let (status, json) = send(&router, HttpMethod::Get, "/api/config", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["version"], 1);
+    assert_eq!(json["mqtt"]["password"], "********");
+
+    let body = r#"{"telemetry": {"sample_interval_ms": 20000}, "mqtt": {"password": "********"}}"#;
+    let (status, json) = send(&router, HttpMethod::Patch, "/api/config", body);
+    assert_eq!(status, 200);
+    assert_eq!(json["changed"], json!(["telemetry"]));
+    assert_eq!(json["config"]["telemetry"]["sample_interval_ms"], 20_000);
+    assert_eq!(device.config.lock().unwrap().mqtt.password.as_deref(), Some("secret"));
+
+    // Every rejected field is listed and nothing is applied
+    let body = r#"{"pins": {"led1": 99}, "button": {"debounce_ms": "fast"}, "display": {}}"#;
+    let (status, json) = send(&router, HttpMethod::Patch, "/api/config", body);
+    assert_eq!(status, 400);
+    assert_eq!(json["fields"][0]["field"], "display");
+    assert_eq!(json["fields"][0]["message"], "unknown setting");
+    let (status, json) = send(&router, HttpMethod::Patch, "/api/config", r#"{"pins": {"led1": 99}, "button": {"debounce_ms": "fast"}}"#);
+    assert_eq!(status, 400);
+    assert_eq!(json["fields"][0]["field"], "button.debounce_ms");
+    assert_eq!(json["fields"][1]["field"], "pins.led1");
+    assert_eq!(send(&router, HttpMethod::Patch, "/api/config", "[1]").0, 400);
+    assert_eq!(send(&router, HttpMethod::Patch, "/api/config", "{").0, 400);
+    assert_eq!(device.config.lock().unwrap().telemetry.sample_interval_ms, 20_000);
+
+    let (status, _) = send(&router, HttpMethod::Post, "/api/factory-reset", "");
+    assert_eq!(status, 202);
+    assert_eq!(*device.config.lock().unwrap(), DeviceConfig::default());
+    assert!(device.rebooted.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_logging_endpoints() {
+    let device = Arc::new(FakeDevice::default());
+    let router = router_for(device.clone());
+
+    let (status, json) = send(&router, HttpMethod::Get, "/api/logging", "");
+    assert_eq!(status, 200);
+    assert_eq!(json, json!({ "level": "info", "modules": {} }));
+
+    let body = r#"{"level": "warn", "modules": {"tasks::wifi_task": "debug"}}"#;
+    let (status, json) = send(&router, HttpMethod::Patch, "/api/logging", body);
+    assert_eq!(status, 200);
+    assert_eq!(json, json!({ "level": "warn", "modules": { "tasks::wifi_task": "debug" } }));
+
+    let (status, json) = send(&router, HttpMethod::Patch, "/api/logging", r#"{"modules": {"tasks::wifi_task": null}, "level": "noisy"}"#);
+    assert_eq!(status, 400);
+    assert_eq!(json["fields"][0]["field"], "logging.level");
+    // Nothing was applied
+    assert_eq!(device.config.lock().unwrap().logging.modules.len(), 1);
+}
+
+#[test]
+fn test_logs_endpoint() {
+    let device = Arc::new(FakeDevice::default());
+    {
+        let mut logs = device.logs.lock().unwrap();
+        logs.push(LogLevel::Info, 100, "esp32_template::tasks::wifi_task", "Connected");
+        logs.push(LogLevel::Warn, 200, "esp32_template::tasks::mqtt_task", "Broker unreachable");
+        logs.push(LogLevel::Error, 300, "esp32_template::tasks::wifi_task", "Connection lost");
+    }
+    let router = router_for(device);
+
+    let (status, json) = send(&router, HttpMethod::Get, "/api/logs", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["last_seq"], 3);
+    assert_eq!(json["entries"].as_array().unwrap().len(), 3);
+    assert_eq!(json["entries"][0], json!({
+        "seq": 1,
+        "uptime_ms": 100,
+        "level": "info",
+        "target": "esp32_template::tasks::wifi_task",
+        "message": "Connected",
+    }));
+
+    let (_, json) = send(&router, HttpMethod::Get, "/api/logs?since=1&module=tasks::wifi_task", "");
+    let seqs: Vec<&Value> = json["entries"].as_array().unwrap().iter().map(|entry| &entry["seq"]).collect();
+    assert_eq!(seqs, [&json!(3)]);
+
+    let (_, json) = send(&router, HttpMethod::Get, "/api/logs?level=warn&limit=1", "");
+    assert_eq!(json["entries"][0]["message"], "Connection lost");
+
+    let (status, _) = send(&router, HttpMethod::Get, "/api/logs?since=latest", "");
+    assert_eq!(status, 400);
+    let (status, _) = send(&router, HttpMethod::Get, "/api/logs?colour=red", "");
+    assert_eq!(status, 400);
+}
@@ -1,8 +1,15 @@
 // Integration tests for ESP32 template
 // These tests can be run with: cargo test --target xtensa-esp32s3-espidf
+// Tests that don't need hardware also run on the host with: cargo test
 
+#[cfg(target_os = "espidf")]
+#[allow(unused_imports)]
 use esp32_template::peripherals::{LedController, ButtonController};
-use esp32_template::utils::{get_uptime_ms, map_range};
+use esp32_template::tasks::network_config::{prefix_to_netmask, validate_hostname, StaticIpConfig};
+use esp32_template::tasks::wifi_scan::{dedup_by_ssid, filter_min_rssi, AuthMode, ScanResult};
+#[cfg(target_os = "espidf")]
+use esp32_template::utils::get_uptime_ms;
+use esp32_template::utils::map_range;
 
 #[test]
 fn test_led_controller() {
@@ -12,6 +19,7 @@ fn test_led_controller() {
     assert_eq!(map_range(25.0, 0.0, 100.0, 0.0, 10.0), 2.5);
 }
 
+#[cfg(target_os = "espidf")]
 #[test]
 fn test_time_utilities() {
     let uptime = get_uptime_ms();
@@ -40,8 +48,73 @@ fn test_error_handling() {
     assert!(error_result.is_err());
 }
 
+fn scan_result(ssid: &str, rssi: i8) -> ScanResult {
+    ScanResult {
+        ssid: ssid.to_string(),
+        bssid: [0; 6],
+        channel: 1,
+        rssi,
+        auth_mode: AuthMode::Wpa2Personal,
+        hidden: ssid.is_empty(),
+    }
+}
+
+#[test]
+fn test_wifi_scan_helpers() {
+    let results = vec![
+        scan_result("mesh", -70),
+        scan_result("office", -60),
+        scan_result("mesh", -50),
+        scan_result("", -40),
+    ];
+
+    // Mesh APs collapse to the strongest one, hidden APs are kept
+    let deduped = dedup_by_ssid(results);
+    assert_eq!(deduped.len(), 3);
+    assert_eq!(deduped[0].ssid, "");
+    assert_eq!(deduped[1].ssid, "mesh");
+    assert_eq!(deduped[1].rssi, -50);
+
+    let strong = filter_min_rssi(deduped, -55);
+    assert_eq!(strong.len(), 2);
+}
+
+#[test]
+fn test_static_ip_validation() {
+    use std::net::Ipv4Addr;
+
+    assert_eq!(prefix_to_netmask(24), Ipv4Addr::new(255, 255, 255, 0));
+    assert_eq!(prefix_to_netmask(32), Ipv4Addr::BROADCAST);
+
+    let mut settings = StaticIpConfig {
+        address: Ipv4Addr::new(192, 168, 1, 50),
+        prefix_len: 24,
+        gateway: Ipv4Addr::new(192, 168, 1, 1),
+        dns: None,
+        secondary_dns: None,
+    };
+    assert!(settings.validate().is_ok());
+
+    settings.gateway = Ipv4Addr::new(10, 0, 0, 1);
+    assert!(settings.validate().is_err());
+
+    settings.gateway = settings.address;
+    assert!(settings.validate().is_err());
+
+    settings.gateway = Ipv4Addr::new(192, 168, 1, 255);
+    assert!(settings.validate().is_err());
+
+    settings.gateway = Ipv4Addr::new(192, 168, 1, 1);
+    settings.address = Ipv4Addr::new(192, 168, 1, 0);
+    assert!(settings.validate().is_err());
+
+    assert!(validate_hostname("greenhouse-1").is_ok());
+    assert!(validate_hostname("-bad").is_err());
+    assert!(validate_hostname("bad_name").is_err());
+}
+
 // Mock tests for hardware-dependent functionality
-#[cfg(test)]
+#[cfg(all(test, target_os = "espidf"))]
 mod mock_tests {
     use super::*;
     
@@ -0,0 +1,108 @@
+// Host tests for runtime log level filtering
+// These tests capture records in memory instead of writing to the UART
+
+use esp32_template::utils::log_filter::{module_matches, validate_module, FilteredLogger, LogFilter, LogLevel};
+use log::{Level, Log, Metadata, Record};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Logger that keeps the targets and messages it receives
+#[derive(Clone, Default)]
+struct CaptureLogger {
+    records: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.records.lock().unwrap().push((record.target().to_string(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+fn log_at(logger: &dyn Log, level: Level, target: &str, message: &str) {
+    logger.log(&Record::builder().level(level).target(target).args(format_args!("{}", message)).build());
+}
+
+fn modules(entries: &[(&str, LogLevel)]) -> BTreeMap<String, LogLevel> {
+    entries.iter().map(|(module, level)| (module.to_string(), *level)).collect()
+}
+
+#[test]
+fn test_level_parsing() {
+    assert_eq!("DEBUG".parse::<LogLevel>().unwrap(), LogLevel::Debug);
+    assert_eq!("warning".parse::<LogLevel>().unwrap(), LogLevel::Warn);
+    assert!("loud".parse::<LogLevel>().is_err());
+    assert_eq!(LogLevel::Trace.to_string(), "trace");
+    assert_eq!(LogLevel::Off.filter(), log::LevelFilter::Off);
+    assert_eq!(serde_json::to_value(LogLevel::Warn).unwrap(), "warn");
+    assert_eq!(LogLevel::default(), LogLevel::Info);
+}
+
+#[test]
+fn test_module_matching() {
+    assert!(module_matches("tasks::wifi_task", "esp32_template::tasks::wifi_task"));
+    assert!(module_matches("tasks", "esp32_template::tasks::wifi_task"));
+    assert!(module_matches("esp32_template", "esp32_template::tasks::wifi_task"));
+    assert!(module_matches("wifi_task", "wifi_task"));
+    // Only whole segments match
+    assert!(!module_matches("tasks::wifi", "esp32_template::tasks::wifi_task"));
+    assert!(!module_matches("task", "esp32_template::tasks::wifi_task"));
+    assert!(!module_matches("peripherals::button", "esp32_template::tasks::wifi_task"));
+
+    assert!(validate_module("peripherals::button").is_ok());
+    for invalid in ["", "tasks::", "::tasks", "tasks.wifi", "a b", &"x".repeat(65)] {
+        assert!(validate_module(invalid).is_err(), "{:?}", invalid);
+    }
+}
+
+#[test]
+fn test_levels_per_module() {
+    let filter = LogFilter::new(LogLevel::Info);
+    assert_eq!(filter.level_for("esp32_template::tasks::wifi_task"), LogLevel::Info);
+
+    filter.apply(LogLevel::Warn, &modules(&[
+        ("tasks", LogLevel::Debug),
+        ("tasks::wifi_task", LogLevel::Trace),
+        ("peripherals::button", LogLevel::Off),
+    ]));
+    // The most specific override wins
+    assert_eq!(filter.level_for("esp32_template::tasks::wifi_task"), LogLevel::Trace);
+    assert_eq!(filter.level_for("esp32_template::tasks::mqtt_task"), LogLevel::Debug);
+    assert_eq!(filter.level_for("esp32_template::peripherals::button"), LogLevel::Off);
+    assert_eq!(filter.level_for("esp32_template::peripherals::led"), LogLevel::Warn);
+    assert_eq!(filter.max_level(), LogLevel::Trace);
+    assert_eq!(filter.default_level(), LogLevel::Warn);
+    assert_eq!(filter.modules().len(), 3);
+
+    filter.apply(LogLevel::Error, &BTreeMap::new());
+    assert_eq!(filter.max_level(), LogLevel::Error);
+    assert!(filter.modules().is_empty());
+}
+
+#[test]
+fn test_filtered_logger() {
+    let capture = CaptureLogger::default();
+    let filter = LogFilter::new(LogLevel::Info);
+    let logger = FilteredLogger::new(filter.clone(), capture.clone());
+
+    log_at(&logger, Level::Info, "esp32_template::tasks::wifi_task", "connected");
+    log_at(&logger, Level::Debug, "esp32_template::tasks::wifi_task", "scan details");
+    log_at(&logger, Level::Warn, "esp32_template::peripherals::button", "bounce");
+
+    // Changes apply to the installed logger immediately
+    logger.filter().apply(LogLevel::Info, &modules(&[("tasks::wifi_task", LogLevel::Debug), ("peripherals", LogLevel::Error)]));
+    log_at(&logger, Level::Debug, "esp32_template::tasks::wifi_task", "retrying");
+    log_at(&logger, Level::Debug, "esp32_template::tasks::mqtt_task", "publish");
+    log_at(&logger, Level::Warn, "esp32_template::peripherals::button", "bounce again");
+
+    let messages: Vec<String> = capture.records.lock().unwrap().iter().map(|(_, message)| message.clone()).collect();
+    assert_eq!(messages, ["connected", "bounce", "retrying"]);
+
+    let metadata = Metadata::builder().level(Level::Trace).target("esp32_template::main").build();
+    assert!(!logger.enabled(&metadata));
+}
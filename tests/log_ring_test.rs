@@ -0,0 +1,192 @@
+// Host tests for the in-memory log ring and the RTC-retained copy
+// These tests use leaked buffers in place of RTC memory and a mock clock for timestamps
+
+mod common;
+
+use esp32_template::error::Error;
+use esp32_template::utils::log_filter::{FilteredLogger, LogFilter, LogLevel};
+use esp32_template::utils::log_ring::{LogQuery, LogRing, RetainedLog, RingLogger, MAX_MESSAGE_LEN, MAX_PAGE_LEN};
+use log::{Level, Log, Metadata, Record};
+use std::sync::{Arc, Mutex};
+use common::MockClock;
+
+/// Logger that discards everything, standing in for the serial output
+struct NullLogger;
+
+impl Log for NullLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, _record: &Record) {}
+
+    fn flush(&self) {}
+}
+
+fn area(len: usize) -> &'static mut [u8] {
+    Box::leak(vec![0u8; len].into_boxed_slice())
+}
+
+fn messages(ring: &LogRing, query: &LogQuery) -> Vec<String> {
+    ring.query(query).entries.into_iter().map(|entry| entry.message).collect()
+}
+
+#[test]
+fn test_ring_wraps() {
+    let mut ring = LogRing::new(3);
+    for i in 1..=5 {
+        assert_eq!(ring.push(LogLevel::Info, i * 10, "app", &format!("record {}", i)), i);
+    }
+
+    assert_eq!(ring.len(), 3);
+    assert_eq!(ring.dropped(), 2);
+    let page = ring.query(&LogQuery::default());
+    assert_eq!(page.last_seq, 5);
+    assert_eq!(page.dropped, 2);
+    let seqs: Vec<u64> = page.entries.iter().map(|entry| entry.seq).collect();
+    assert_eq!(seqs, [3, 4, 5]);
+    assert_eq!(page.entries[0].uptime_ms, 30);
+
+    // Sequence numbers keep counting after a clear
+    ring.clear();
+    assert!(ring.is_empty());
+    assert_eq!(ring.push(LogLevel::Info, 60, "app", "after clear"), 6);
+}
+
+#[test]
+fn test_query_filters() {
+    let mut ring = LogRing::new(10);
+    ring.push(LogLevel::Info, 1, "esp32_template::tasks::wifi_task", "connected");
+    ring.push(LogLevel::Debug, 2, "esp32_template::tasks::wifi_task::scan", "scanning");
+    ring.push(LogLevel::Warn, 3, "esp32_template::tasks::mqtt_task", "broker slow");
+    ring.push(LogLevel::Error, 4, "esp32_template::tasks::wifi_task_ext", "unrelated");
+
+    let wifi = LogQuery { module: Some("tasks::wifi_task".to_string()), ..Default::default() };
+    assert_eq!(messages(&ring, &wifi), ["connected", "scanning"]);
+
+    let warnings = LogQuery { level: Some(LogLevel::Warn), ..Default::default() };
+    assert_eq!(messages(&ring, &warnings), ["broker slow", "unrelated"]);
+
+    let since = LogQuery { since: Some(2), ..Default::default() };
+    assert_eq!(messages(&ring, &since), ["broker slow", "unrelated"]);
+
+    // Without `since` the newest records win, with it the oldest after `since`
+    let newest = LogQuery { limit: Some(2), ..Default::default() };
+    assert_eq!(messages(&ring, &newest), ["broker slow", "unrelated"]);
+    let paged = LogQuery { since: Some(0), limit: Some(2), ..Default::default() };
+    assert_eq!(messages(&ring, &paged), ["connected", "scanning"]);
+}
+
+#[test]
+fn test_page_and_message_limits() {
+    let mut ring = LogRing::new(MAX_PAGE_LEN * 2);
+    for i in 0..MAX_PAGE_LEN * 2 {
+        ring.push(LogLevel::Info, 0, "app", &i.to_string());
+    }
+    let unlimited = LogQuery { limit: Some(usize::MAX), ..Default::default() };
+    assert_eq!(ring.query(&unlimited).entries.len(), MAX_PAGE_LEN);
+
+    ring.push(LogLevel::Info, 0, "app", &"é".repeat(MAX_MESSAGE_LEN));
+    let last = ring.query(&LogQuery { limit: Some(1), ..Default::default() }).entries.remove(0);
+    assert_eq!(last.message.len(), MAX_MESSAGE_LEN);
+}
+
+#[test]
+fn test_query_from_pairs() {
+    let query = LogQuery::from_pairs([
+        ("since", "12"),
+        ("level", "WARNING"),
+        ("module", "tasks::wifi_task"),
+        ("limit", "5"),
+        ("previous_boot", "true"),
+    ])
+    .unwrap();
+    assert_eq!(query, LogQuery {
+        since: Some(12),
+        level: Some(LogLevel::Warn),
+        module: Some("tasks::wifi_task".to_string()),
+        limit: Some(5),
+        previous_boot: true,
+    });
+
+    assert!(matches!(LogQuery::from_pairs([("since", "soon")]), Err(Error::InvalidArgument(_))));
+    assert!(matches!(LogQuery::from_pairs([("level", "loud")]), Err(Error::InvalidArgument(_))));
+    assert!(matches!(LogQuery::from_pairs([("colour", "red")]), Err(Error::InvalidArgument(_))));
+
+    let json: LogQuery = serde_json::from_str(r#"{"level": "error", "correlation_id": "7"}"#).unwrap();
+    assert_eq!(json, LogQuery { level: Some(LogLevel::Error), ..Default::default() });
+}
+
+#[test]
+fn test_ring_logger_captures_filtered_records() {
+    let ring = Arc::new(Mutex::new(LogRing::new(10)));
+    let clock = Arc::new(MockClock::default());
+    let filter = LogFilter::new(LogLevel::Info);
+    let logger = FilteredLogger::new(filter, RingLogger::new(ring.clone(), clock.clone(), NullLogger));
+
+    clock.set(1500);
+    logger.log(&Record::builder().level(Level::Warn).target("app").args(format_args!("low battery {}%", 9)).build());
+    logger.log(&Record::builder().level(Level::Debug).target("app").args(format_args!("hidden")).build());
+
+    let entries = ring.lock().unwrap().query(&LogQuery::default()).entries;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].level, LogLevel::Warn);
+    assert_eq!(entries[0].uptime_ms, 1500);
+    assert_eq!(entries[0].target, "app");
+    assert_eq!(entries[0].message, "low battery 9%");
+}
+
+#[test]
+fn test_retained_log_recovery() {
+    // A fresh area holds nothing from a previous boot
+    let (mut retained, previous) = RetainedLog::recover(area(256)).unwrap();
+    assert!(previous.is_empty());
+
+    let mut ring = LogRing::new(10);
+    ring.push(LogLevel::Info, 10, "app", "booted");
+    ring.push(LogLevel::Error, 20, "esp32_template::tasks::ota", "line one\nline two");
+    for entry in ring.query(&LogQuery::default()).entries {
+        retained.append(&entry);
+    }
+
+    let ring = LogRing::with_retained(10, retained.into_area()).unwrap();
+    assert!(ring.is_empty());
+    let previous = ring.previous_boot();
+    assert_eq!(previous.len(), 2);
+    assert_eq!(previous[1].seq, 2);
+    assert_eq!(previous[1].uptime_ms, 20);
+    assert_eq!(previous[1].level, LogLevel::Error);
+    assert_eq!(previous[1].target, "esp32_template::tasks::ota");
+    assert_eq!(previous[1].message, "line one line two");
+
+    let query = LogQuery { previous_boot: true, level: Some(LogLevel::Error), ..Default::default() };
+    assert_eq!(messages(&ring, &query), ["line one line two"]);
+
+    assert!(matches!(RetainedLog::recover(area(8)), Err(Error::InvalidArgument(_))));
+}
+
+#[test]
+fn test_retained_log_wraps() {
+    let (mut retained, _) = RetainedLog::recover(area(12 + 100)).unwrap();
+    let mut ring = LogRing::new(50);
+    for i in 1..=20 {
+        ring.push(LogLevel::Info, i, "app", &format!("record {}", i));
+    }
+    for entry in ring.query(&LogQuery::default()).entries {
+        retained.append(&entry);
+    }
+
+    // Only whole lines survive and they end with the newest record
+    let entries = retained.entries();
+    assert!(!entries.is_empty() && entries.len() < 20);
+    assert_eq!(entries.last().unwrap().message, "record 20");
+    let seqs: Vec<u64> = entries.iter().map(|entry| entry.seq).collect();
+    let expected: Vec<u64> = (21 - entries.len() as u64..=20).collect();
+    assert_eq!(seqs, expected);
+
+    // A corrupted header is treated as an empty area
+    let area = retained.into_area();
+    area[0] ^= 0xff;
+    let (_, previous) = RetainedLog::recover(area).unwrap();
+    assert!(previous.is_empty());
+}
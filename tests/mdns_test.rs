@@ -0,0 +1,105 @@
+// Host tests for mDNS service adverts and discovery results
+// These tests do not require hardware
+
+use esp32_template::tasks::mdns_config::{
+    unique_nodes, validate_service_type, validate_txt, DiscoveredService, MdnsConfig, ServiceAdvert,
+    ServiceProtocol, APP_SERVICE_TYPE,
+};
+use std::net::{IpAddr, Ipv4Addr};
+
+fn node(id: Option<&str>, last_octet: u8) -> DiscoveredService {
+    let mut txt = vec![("FW".to_string(), "1.2.0".to_string())];
+    if let Some(id) = id {
+        txt.push(("id".to_string(), id.to_string()));
+    }
+
+    DiscoveredService {
+        instance_name: Some(format!("Node {}", last_octet)),
+        hostname: Some(format!("node-{}", last_octet)),
+        port: 80,
+        addresses: vec![IpAddr::V4(Ipv4Addr::new(192, 168, 1, last_octet))],
+        txt,
+    }
+}
+
+#[test]
+fn test_default_services() {
+    let config = MdnsConfig::default();
+    config.validate().unwrap();
+    assert_eq!(config.hostname("esp32-device"), "esp32-device");
+
+    let services = config.services("dev1", "1.2.0");
+    let types: Vec<&str> = services.iter().map(|s| s.service_type.as_str()).collect();
+    assert_eq!(types, vec!["_http", APP_SERVICE_TYPE]);
+
+    let app = &services[1];
+    assert_eq!(app.protocol.label(), "_tcp");
+    assert_eq!(app.port, 80);
+    assert_eq!(app.txt, vec![
+        ("id".to_string(), "dev1".to_string()),
+        ("fw".to_string(), "1.2.0".to_string()),
+    ]);
+    for service in &services {
+        service.validate().unwrap();
+    }
+}
+
+#[test]
+fn test_configured_services() {
+    let config = MdnsConfig {
+        hostname: Some("greenhouse".to_string()),
+        http_port: None,
+        mqtt_port: Some(1883),
+        advertise_app: false,
+        ..Default::default()
+    };
+    config.validate().unwrap();
+    assert_eq!(config.hostname("esp32-device"), "greenhouse");
+
+    let services = config.services("dev1", "1.2.0");
+    assert_eq!(services, vec![ServiceAdvert::new("_mqtt", ServiceProtocol::Tcp, 1883)]);
+
+    let invalid = MdnsConfig { hostname: Some("bad host".to_string()), ..Default::default() };
+    assert!(invalid.validate().is_err());
+    assert!(MdnsConfig { http_port: Some(0), ..Default::default() }.validate().is_err());
+    assert!(MdnsConfig { instance_name: String::new(), ..Default::default() }.validate().is_err());
+}
+
+#[test]
+fn test_service_validation() {
+    assert!(validate_service_type("_http").is_ok());
+    assert!(validate_service_type("_esp32-template").is_ok());
+    assert!(validate_service_type("http").is_err());
+    assert!(validate_service_type("_").is_err());
+    assert!(validate_service_type("_a-very-long-service").is_err());
+    assert!(validate_service_type("_my--app").is_err());
+    assert!(validate_service_type("_1234").is_err());
+
+    assert!(validate_txt("fw", "1.2.0").is_ok());
+    assert!(validate_txt("", "x").is_err());
+    assert!(validate_txt("a=b", "x").is_err());
+    assert!(validate_txt("fw", &"x".repeat(253)).is_err());
+
+    let advert = ServiceAdvert::new("_http", ServiceProtocol::Udp, 0);
+    assert!(advert.validate().is_err());
+}
+
+#[test]
+fn test_discovered_nodes() {
+    let found = node(Some("dev2"), 20);
+    assert_eq!(found.device_id(), Some("dev2"));
+    // TXT keys are case-insensitive
+    assert_eq!(found.firmware_version(), Some("1.2.0"));
+
+    let services = vec![
+        node(Some("dev1"), 10),
+        node(Some("dev2"), 20),
+        node(None, 30),
+        node(Some("dev2"), 21),
+        node(Some("dev3"), 40),
+    ];
+    let nodes = unique_nodes(services, "dev1");
+    let ids: Vec<&str> = nodes.iter().filter_map(|n| n.device_id()).collect();
+    assert_eq!(ids, vec!["dev2", "dev3"]);
+    assert_eq!(nodes[0].port, 80);
+}
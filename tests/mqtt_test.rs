@@ -0,0 +1,226 @@
+// Host tests for MQTT telemetry publishing
+// These tests use an in-process mock transport instead of a broker
+
+mod common;
+
+use esp32_template::tasks::mqtt_task::{MqttConfig, MqttTask, MqttTransport, QoS, ReceivedMessage};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use esp32_template::tasks::SensorReadings;
+use esp32_template::utils::error_handler::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+use esp32_template::utils::Timestamp;
+use common::MockClock;
+
+/// Mock transport recording published messages
+#[derive(Default)]
+struct MockTransport {
+    connected: bool,
+    /// Reject publishes while connected, like a broker refusing them
+    failing: bool,
+    attempts: usize,
+    published: Vec<(String, Vec<u8>, bool)>,
+    subscribed: Vec<String>,
+    inbox: VecDeque<ReceivedMessage>,
+}
+
+impl MqttTransport for MockTransport {
+    fn publish(&mut self, topic: &str, _qos: QoS, retain: bool, payload: &[u8]) -> anyhow::Result<()> {
+        self.attempts += 1;
+        if !self.connected || self.failing {
+            return Err(anyhow::anyhow!("not connected"));
+        }
+        self.published.push((topic.to_string(), payload.to_vec(), retain));
+        Ok(())
+    }
+
+    fn subscribe(&mut self, topic: &str, _qos: QoS) -> anyhow::Result<()> {
+        self.subscribed.push(topic.to_string());
+        Ok(())
+    }
+
+    fn poll_message(&mut self) -> Option<ReceivedMessage> {
+        self.inbox.pop_front()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+fn readings() -> SensorReadings {
+    SensorReadings {
+        temperature: 21.5,
+        humidity: 40.0,
+        pressure: 1000.0,
+        uptime_ms: 1234,
+        timestamp: Timestamp::Utc(1_700_000_000_000),
+    }
+}
+
+#[test]
+fn test_publish_readings_topics() {
+    let transport = MockTransport { connected: true, ..Default::default() };
+    let mut mqtt = MqttTask::new(transport, MqttConfig::new("mqtt://localhost", "dev1"));
+
+    mqtt.publish_readings(&readings()).unwrap();
+
+    let topics: Vec<&str> = mqtt.transport().published.iter().map(|(t, _, _)| t.as_str()).collect();
+    assert_eq!(topics, vec![
+        "devices/dev1/status",
+        "devices/dev1/sensors/temperature",
+        "devices/dev1/sensors/humidity",
+        "devices/dev1/sensors/pressure",
+        "devices/dev1/telemetry",
+    ]);
+
+    let (_, payload, _) = &mqtt.transport().published[4];
+    let json: serde_json::Value = serde_json::from_slice(payload).unwrap();
+    assert_eq!(json["temperature"], 21.5);
+    assert_eq!(json["uptime_ms"], 1234);
+    assert_eq!(json["timestamp"]["utc"], 1_700_000_000_000i64);
+}
+
+#[test]
+fn test_offline_buffering_and_flush() {
+    let mut config = MqttConfig::new("mqtt://localhost", "dev1");
+    config.offline_buffer_size = 2;
+    let mut mqtt = MqttTask::new(MockTransport::default(), config);
+
+    mqtt.publish("a", b"1").unwrap();
+    mqtt.publish("b", b"2").unwrap();
+    mqtt.publish("c", b"3").unwrap();
+    assert_eq!(mqtt.pending_count(), 2);
+    assert_eq!(mqtt.dropped_count(), 1);
+
+    mqtt.transport_mut().connected = true;
+    mqtt.flush().unwrap();
+
+    let topics: Vec<&str> = mqtt.transport().published.iter().map(|(t, _, _)| t.as_str()).collect();
+    assert_eq!(topics, vec!["devices/dev1/status", "b", "c"]);
+    assert_eq!(mqtt.pending_count(), 0);
+}
+
+#[test]
+fn test_circuit_breaker_buffers_publishes() {
+    let clock = Arc::new(MockClock::default());
+    let config = CircuitBreakerConfig {
+        failure_rate_threshold: 0.5,
+        window_size: 2,
+        minimum_calls: 2,
+        open_duration_ms: 10_000,
+        half_open_calls: 1,
+    };
+    let transport = MockTransport { connected: true, failing: true, ..Default::default() };
+    let mut mqtt = MqttTask::new(transport, MqttConfig::new("mqtt://localhost", "dev1"));
+    mqtt.set_circuit_breaker(CircuitBreaker::with_clock("MQTT", config, clock.clone()).unwrap());
+
+    mqtt.flush().unwrap();
+    mqtt.publish("a", b"1").unwrap();
+    mqtt.publish("b", b"2").unwrap();
+    assert_eq!(mqtt.circuit_breaker().unwrap().state(), CircuitState::Open);
+
+    // Buffered without trying the transport while the circuit is open
+    let attempts = mqtt.transport().attempts;
+    mqtt.transport_mut().failing = false;
+    mqtt.publish("c", b"3").unwrap();
+    mqtt.flush().unwrap();
+    assert_eq!(mqtt.transport().attempts, attempts);
+    assert_eq!(mqtt.pending_count(), 3);
+
+    clock.advance(10_000);
+    mqtt.flush().unwrap();
+    let topics: Vec<&str> = mqtt.transport().published.iter().map(|(t, _, _)| t.as_str()).collect();
+    assert_eq!(topics, vec!["a", "b", "c"]);
+    assert_eq!(mqtt.circuit_breaker().unwrap().state(), CircuitState::Closed);
+}
+
+#[test]
+fn test_qos_levels() {
+    assert_eq!(QoS::from_level(0), Some(QoS::AtMostOnce));
+    assert_eq!(QoS::from_level(1), Some(QoS::AtLeastOnce));
+    assert_eq!(QoS::from_level(2), Some(QoS::ExactlyOnce));
+    assert_eq!(QoS::from_level(3), None);
+}
+
+#[test]
+fn test_mqtt_config() {
+    let config = MqttConfig::new("mqtts://broker.example.com:8883", "dev1");
+    assert!(config.uses_tls());
+    assert!(config.validate().is_ok());
+
+    let will = config.last_will().unwrap();
+    assert_eq!(will.topic, "devices/dev1/status");
+    assert!(will.retain);
+
+    assert!(MqttConfig::new("http://broker", "dev1").validate().is_err());
+    assert!(MqttConfig::new("mqtt://broker", "dev/1").validate().is_err());
+
+    let mut config = MqttConfig::new("mqtts://broker.example.com:8883", "dev1");
+    config.ca_certificate = Some("-----BEGIN CERTIFICATE-----\n");
+    assert!(config.validate().is_err());
+    config.ca_certificate = Some("-----BEGIN CERTIFICATE-----\n\0");
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_parse_commands() {
+    use esp32_template::tasks::mqtt_commands::{parse_command, Command};
+
+    let request = parse_command("set_led", br#"{"led": 1, "state": true, "correlation_id": "abc"}"#).unwrap();
+    assert_eq!(request.command, Command::SetLed { led: 1, state: true });
+    assert_eq!(request.correlation_id.as_deref(), Some("abc"));
+
+    assert_eq!(parse_command("reboot", b"").unwrap().command, Command::Reboot);
+    assert!(parse_command("set_led", br#"{"led": 1}"#).is_err());
+    assert!(parse_command("set_sample_interval", br#"{"interval_ms": 10}"#).is_err());
+    assert!(parse_command("toggle_led", b"not json").is_err());
+
+    let request = parse_command("identify", br#"{"seconds": 5}"#).unwrap();
+    assert_eq!(request.command.name(), "identify");
+}
+
+#[test]
+fn test_command_dispatch_and_ack() {
+    use esp32_template::tasks::mqtt_commands::{Command, CommandDispatcher};
+
+    let mut dispatcher = CommandDispatcher::new();
+    dispatcher.register("toggle_led", |command| match command {
+        Command::ToggleLed { led } if *led <= 2 => Ok(Some(serde_json::json!({ "led": led }))),
+        _ => Err(anyhow::anyhow!("LED does not exist")),
+    });
+
+    let transport = MockTransport { connected: true, ..Default::default() };
+    let mut mqtt = MqttTask::new(transport, MqttConfig::new("mqtt://localhost", "dev1"));
+    dispatcher.subscribe(&mut mqtt).unwrap();
+    assert_eq!(mqtt.transport().subscribed, vec!["devices/dev1/cmd/#"]);
+
+    let inbox = &mut mqtt.transport_mut().inbox;
+    for (name, payload) in [
+        ("toggle_led", r#"{"led": 2, "correlation_id": "c1"}"#),
+        ("toggle_led", r#"{"led": 7, "correlation_id": "c2"}"#),
+        ("unknown", r#"{"correlation_id": "c3"}"#),
+    ] {
+        inbox.push_back(ReceivedMessage {
+            topic: format!("devices/dev1/cmd/{}", name),
+            payload: payload.as_bytes().to_vec(),
+        });
+    }
+    inbox.push_back(ReceivedMessage {
+        topic: "devices/other/cmd/toggle_led".to_string(),
+        payload: Vec::new(),
+    });
+
+    assert_eq!(dispatcher.process(&mut mqtt).unwrap(), 3);
+
+    let acks: Vec<serde_json::Value> = mqtt.transport().published.iter()
+        .filter(|(topic, _, _)| topic == "devices/dev1/ack")
+        .map(|(_, payload, _)| serde_json::from_slice(payload).unwrap())
+        .collect();
+    assert_eq!(acks.len(), 3);
+    assert_eq!(acks[0]["correlation_id"], "c1");
+    assert_eq!(acks[0]["status"], "ok");
+    assert_eq!(acks[0]["result"]["led"], 2);
+    assert_eq!(acks[1]["status"], "error");
+    assert_eq!(acks[2]["correlation_id"], "c3");
+    assert_eq!(acks[2]["error"], "Unknown command 'unknown'");
+}
@@ -0,0 +1,91 @@
+// Host tests for Ed25519 signing and verification of OTA images
+// These tests use the RFC 8032 test keys and do not require hardware
+
+use esp32_template::tasks::ota::{parse_sha256, OtaManifest};
+use esp32_template::tasks::ota_signature::{signed_message, OtaPublicKey, OtaSigningKey};
+
+/// RFC 8032 section 7.1, test 1
+const TEST_SECRET_KEY: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+const TEST_PUBLIC_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+/// RFC 8032 section 7.1, test 2
+const OTHER_SECRET_KEY: &str = "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb";
+
+/// SHA-256 of `hello world`
+const HELLO_SHA256: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+fn signed_manifest(key: &OtaSigningKey) -> OtaManifest {
+    OtaManifest {
+        version: "1.1.0".to_string(),
+        url: "https://updates.example.com/fw.bin".to_string(),
+        sha256: HELLO_SHA256.to_string(),
+        size: 11,
+        signature: key.sign_image("1.1.0", 11, &parse_sha256(HELLO_SHA256).unwrap()),
+    }
+}
+
+#[test]
+fn test_key_encoding() {
+    let secret = OtaSigningKey::from_hex(TEST_SECRET_KEY).unwrap();
+    assert_eq!(secret.to_hex(), TEST_SECRET_KEY);
+    assert_eq!(secret.public_key().to_hex(), TEST_PUBLIC_KEY);
+
+    let public = OtaPublicKey::from_hex(TEST_PUBLIC_KEY).unwrap();
+    assert_eq!(public, secret.public_key());
+
+    assert!(OtaPublicKey::from_hex("d75a98").is_err());
+    assert!(OtaSigningKey::from_hex(&"x".repeat(64)).is_err());
+}
+
+#[test]
+fn test_sign_message() {
+    let secret = OtaSigningKey::from_hex(TEST_SECRET_KEY).unwrap();
+    let digest = parse_sha256(HELLO_SHA256).unwrap();
+    let message = signed_message("1.1.0", 11, &digest);
+
+    // Context, version length and version, size, digest
+    assert!(message.starts_with(b"esp32-template-ota-v1\0\x05\x001.1.0\x0b\x00\x00\x00"));
+    assert!(message.ends_with(&digest));
+
+    let signature = secret.sign_image("1.1.0", 11, &digest);
+    assert_eq!(signature.len(), 128);
+
+    // Ed25519 signatures are deterministic
+    assert_eq!(secret.sign(&message), signature);
+    assert!(secret.public_key().verify(&message, &signature).is_ok());
+    assert!(secret.public_key().verify(&digest, &signature).is_err());
+}
+
+#[test]
+fn test_verify_image() {
+    let secret = OtaSigningKey::from_hex(TEST_SECRET_KEY).unwrap();
+    let public = OtaPublicKey::from_hex(TEST_PUBLIC_KEY).unwrap();
+    let manifest = signed_manifest(&secret);
+    manifest.validate().unwrap();
+
+    let digest = parse_sha256(HELLO_SHA256).unwrap();
+    assert!(public.verify_image(&manifest, &digest).is_ok());
+
+    // Different image content
+    let mut tampered = digest;
+    tampered[0] ^= 1;
+    assert!(public.verify_image(&manifest, &tampered).is_err());
+
+    // Corrupted signature
+    let mut corrupted = manifest.clone();
+    let flipped = if corrupted.signature.starts_with('0') { "1" } else { "0" };
+    corrupted.signature.replace_range(..1, flipped);
+    assert!(public.verify_image(&corrupted, &digest).is_err());
+
+    // Same image offered under another version or size
+    let mut replayed = manifest.clone();
+    replayed.version = "9.9.9".to_string();
+    assert!(public.verify_image(&replayed, &digest).is_err());
+    let mut resized = manifest.clone();
+    resized.size = 12;
+    assert!(public.verify_image(&resized, &digest).is_err());
+
+    // Signed by an untrusted key
+    let other = OtaSigningKey::from_hex(OTHER_SECRET_KEY).unwrap();
+    assert!(public.verify_image(&signed_manifest(&other), &digest).is_err());
+}
@@ -0,0 +1,185 @@
+// Host tests for OTA manifest parsing, version checks and rollback decisions
+// These tests do not require hardware
+
+use esp32_template::tasks::ota::{
+    boot_action, check_update, parse_sha256, BootAction, FirmwareVersion, ImageState,
+    ImageVerifier, OtaManifest, OtaState, OtaStatus, SelfTest, SelfTestConfig, SelfTestVerdict,
+    UpdateDecision,
+};
+use esp32_template::utils::watchdog::TaskHealth;
+
+/// SHA-256 of `hello world`
+const HELLO_SHA256: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+/// Any well-formed signature; signatures are checked in `ota_signature_test`
+const SIGNATURE: &str = concat!(
+    "00000000000000000000000000000000000000000000000000000000000000000",
+    "000000000000000000000000000000000000000000000000000000000000000"
+);
+
+fn task(name: &str, stalled: bool) -> TaskHealth {
+    TaskHealth {
+        name: name.to_string(),
+        timeout_ms: 5000,
+        since_feed_ms: if stalled { 6000 } else { 100 },
+        feeds: 1,
+        stalled,
+    }
+}
+
+fn self_test() -> SelfTest {
+    SelfTest::new(SelfTestConfig {
+        tasks: vec!["main".to_string(), "telemetry".to_string()],
+        healthy_ms: 30_000,
+        connectivity_timeout_ms: Some(300_000),
+    }, 1000)
+}
+
+fn manifest(version: &str, size: u32) -> OtaManifest {
+    OtaManifest {
+        version: version.to_string(),
+        url: "https://updates.example.com/fw.bin".to_string(),
+        sha256: HELLO_SHA256.to_string(),
+        size,
+        signature: SIGNATURE.to_string(),
+    }
+}
+
+#[test]
+fn test_firmware_version() {
+    let version = FirmwareVersion::parse("v1.2.3").unwrap();
+    assert_eq!(version, FirmwareVersion { major: 1, minor: 2, patch: 3 });
+    assert_eq!(version.to_string(), "1.2.3");
+    assert_eq!(FirmwareVersion::parse("1.2.3-rc.1+build5").unwrap(), version);
+
+    assert!(FirmwareVersion::parse("1.10.0").unwrap() > FirmwareVersion::parse("1.9.9").unwrap());
+    assert!(FirmwareVersion::parse("2.0.0").unwrap() > FirmwareVersion::parse("1.99.99").unwrap());
+
+    assert!(FirmwareVersion::parse("1.2").is_err());
+    assert!(FirmwareVersion::parse("1.x.3").is_err());
+}
+
+#[test]
+fn test_manifest_parsing() {
+    let json = format!(
+        r#"{{"version": "1.4.0", "url": "https://updates.example.com/fw.bin", "sha256": "{}", "size": 11, "signature": "{}"}}"#,
+        HELLO_SHA256, SIGNATURE
+    );
+    let manifest = OtaManifest::parse(json.as_bytes()).unwrap();
+    assert_eq!(manifest.size, 11);
+
+    let insecure = json.replace("https://", "http://");
+    assert!(OtaManifest::parse(insecure.as_bytes()).is_err());
+    assert!(OtaManifest::parse(json.replace(HELLO_SHA256, "abcd").as_bytes()).is_err());
+    assert!(OtaManifest::parse(json.replace(SIGNATURE, "abcd").as_bytes()).is_err());
+    assert!(OtaManifest::parse(br#"{"version": "1.4.0"}"#).is_err());
+
+    assert_eq!(parse_sha256(HELLO_SHA256).unwrap()[0], 0xb9);
+    assert!(parse_sha256(&"zz".repeat(32)).is_err());
+}
+
+#[test]
+fn test_update_decision() {
+    let current = FirmwareVersion::parse("1.2.0").unwrap();
+
+    assert_eq!(
+        check_update(current, &manifest("1.3.0", 1), false).unwrap(),
+        UpdateDecision::Update(FirmwareVersion::parse("1.3.0").unwrap())
+    );
+    assert_eq!(check_update(current, &manifest("1.2.0", 1), false).unwrap(), UpdateDecision::UpToDate);
+    assert_eq!(check_update(current, &manifest("1.1.0", 1), false).unwrap(), UpdateDecision::UpToDate);
+    assert_eq!(
+        check_update(current, &manifest("1.1.0", 1), true).unwrap(),
+        UpdateDecision::Downgrade(FirmwareVersion::parse("1.1.0").unwrap())
+    );
+}
+
+#[test]
+fn test_image_verifier() {
+    let mut verifier = ImageVerifier::new(&manifest("1.0.0", 11)).unwrap();
+    verifier.update(b"hello ").unwrap();
+    verifier.update(b"world").unwrap();
+    assert_eq!(verifier.received(), 11);
+    assert_eq!(verifier.finish().unwrap()[..2], [0xb9, 0x4d]);
+
+    // Corrupted content
+    let mut verifier = ImageVerifier::new(&manifest("1.0.0", 11)).unwrap();
+    verifier.update(b"hello w0rld").unwrap();
+    assert!(verifier.finish().is_err());
+
+    // Truncated and oversized images
+    let mut verifier = ImageVerifier::new(&manifest("1.0.0", 11)).unwrap();
+    verifier.update(b"hello").unwrap();
+    assert!(verifier.finish().is_err());
+
+    let mut verifier = ImageVerifier::new(&manifest("1.0.0", 11)).unwrap();
+    assert!(verifier.update(b"hello world!").is_err());
+}
+
+#[test]
+fn test_boot_action_and_status() {
+    assert_eq!(boot_action(ImageState::PendingVerify, true), BootAction::MarkValid);
+    assert_eq!(boot_action(ImageState::PendingVerify, false), BootAction::Rollback);
+    assert_eq!(boot_action(ImageState::Valid, false), BootAction::None);
+    assert_eq!(boot_action(ImageState::Untracked, true), BootAction::None);
+
+    let mut status = OtaStatus::new();
+    assert!(!status.is_busy());
+    assert_eq!(status.percent(), None);
+
+    status.state = OtaState::Downloading;
+    status.total = Some(2000);
+    status.downloaded = 500;
+    assert!(status.is_busy());
+    assert_eq!(status.percent(), Some(25));
+}
+
+#[test]
+fn test_self_test_waits_for_healthy_tasks_and_connectivity() {
+    let running = [task("main", false), task("telemetry", false)];
+    let mut test = self_test();
+
+    // Telemetry registers late; the healthy period starts once it has
+    assert_eq!(test.update(2000, &running[..1], false), SelfTestVerdict::Pending);
+    assert_eq!(test.update(5000, &running, false), SelfTestVerdict::Pending);
+    assert_eq!(test.update(35_000, &running, false), SelfTestVerdict::Pending);
+
+    // A late access point defers the verdict instead of rolling back
+    assert_eq!(test.update(120_000, &running, true), SelfTestVerdict::Passed);
+
+    let mut test = self_test();
+    test.update(5000, &running, true);
+    assert_eq!(test.update(20_000, &running, false), SelfTestVerdict::Pending);
+    // Connectivity seen once is enough
+    assert_eq!(test.update(35_000, &running, false), SelfTestVerdict::Passed);
+}
+
+#[test]
+fn test_self_test_failures() {
+    let running = [task("main", false), task("telemetry", false)];
+
+    let mut test = self_test();
+    let stalled = [task("main", false), task("telemetry", true)];
+    assert!(matches!(test.update(5000, &stalled, true), SelfTestVerdict::Failed(_)));
+
+    let mut test = self_test();
+    test.update(5000, &running, true);
+    assert!(matches!(test.update(6000, &running[..1], true), SelfTestVerdict::Failed(_)));
+
+    let mut test = self_test();
+    assert_eq!(test.update(5000, &running[..1], true), SelfTestVerdict::Pending);
+    assert!(matches!(test.update(31_000, &running[..1], true), SelfTestVerdict::Failed(_)));
+
+    let mut test = self_test();
+    assert_eq!(test.update(299_000, &running, false), SelfTestVerdict::Pending);
+    assert!(matches!(test.update(301_000, &running, false), SelfTestVerdict::Failed(_)));
+
+    // Without a connectivity requirement only the tasks count
+    let mut test = SelfTest::new(SelfTestConfig {
+        tasks: vec!["main".to_string()],
+        connectivity_timeout_ms: None,
+        ..SelfTestConfig::default()
+    }, 0);
+    assert_eq!(test.update(0, &running, false), SelfTestVerdict::Pending);
+    assert_eq!(test.update(60_000, &running, false), SelfTestVerdict::Passed);
+}
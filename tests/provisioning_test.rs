@@ -0,0 +1,58 @@
+// Host tests for the provisioning portal form handling
+// These tests do not require hardware
+
+use esp32_template::tasks::provisioning_portal::{
+    build_dns_response, html_escape, parse_credentials_form, url_decode, DEFAULT_PRIORITY,
+};
+use std::net::Ipv4Addr;
+
+#[test]
+fn test_url_decode() {
+    assert_eq!(url_decode("my+network").unwrap(), "my network");
+    assert_eq!(url_decode("caf%C3%A9%21").unwrap(), "café!");
+    assert!(url_decode("bad%2").is_err());
+    assert!(url_decode("bad%zz").is_err());
+    assert!(url_decode("bad%+f").is_err());
+}
+
+#[test]
+fn test_parse_credentials_form() {
+    let network = parse_credentials_form("ssid=Office+WiFi&password=hunter2%21%21").unwrap();
+    assert_eq!(network.ssid, "Office WiFi");
+    assert_eq!(network.password, "hunter2!!");
+    assert_eq!(network.priority, DEFAULT_PRIORITY);
+
+    let network = parse_credentials_form("ssid=open&password=&priority=3").unwrap();
+    assert_eq!(network.password, "");
+    assert_eq!(network.priority, 3);
+
+    // Missing SSID, short password and bad priority are rejected
+    assert!(parse_credentials_form("password=12345678").is_err());
+    assert!(parse_credentials_form("ssid=x&password=short").is_err());
+    assert!(parse_credentials_form("ssid=x&priority=999").is_err());
+}
+
+#[test]
+fn test_html_escape() {
+    assert_eq!(html_escape("<a href=\"x\">&'"), "&lt;a href=&quot;x&quot;&gt;&amp;&#39;");
+}
+
+#[test]
+fn test_dns_response_points_to_portal() {
+    // Query for "a.io" type A, class IN
+    let query = [
+        0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x01, b'a', 0x02, b'i', b'o', 0x00, 0x00, 0x01, 0x00, 0x01,
+    ];
+    let response = build_dns_response(&query, Ipv4Addr::new(192, 168, 71, 1)).unwrap();
+
+    assert_eq!(&response[0..2], &[0x12, 0x34]);
+    assert_eq!(&response[6..8], &[0x00, 0x01]);
+    assert_eq!(&response[response.len() - 4..], &[192, 168, 71, 1]);
+
+    // Responses and truncated packets are ignored
+    let mut not_query = query;
+    not_query[2] |= 0x80;
+    assert!(build_dns_response(&not_query, Ipv4Addr::LOCALHOST).is_none());
+    assert!(build_dns_response(&query[..15], Ipv4Addr::LOCALHOST).is_none());
+}
@@ -0,0 +1,222 @@
+// Host tests for retry policies
+// These tests do not require hardware and never actually sleep
+
+use esp32_template::error::{Error, EspCode};
+use esp32_template::utils::retry::{
+    Backoff, CancellationToken, Clock, Jitter, RetryPolicy, Sleeper, DEFAULT_MAX_DELAY_MS,
+};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Records requested sleeps and advances a virtual clock instead of blocking
+#[derive(Default)]
+struct MockSleeper {
+    now_ms: AtomicU64,
+    sleeps: Mutex<Vec<u32>>,
+}
+
+impl MockSleeper {
+    fn sleeps(&self) -> Vec<u32> {
+        self.sleeps.lock().unwrap().clone()
+    }
+
+    fn total_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::Relaxed)
+    }
+}
+
+impl Clock for MockSleeper {
+    fn now_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::Relaxed)
+    }
+}
+
+impl Sleeper for MockSleeper {
+    fn sleep_ms(&self, ms: u32) {
+        self.sleeps.lock().unwrap().push(ms);
+        self.now_ms.fetch_add(ms as u64, Ordering::Relaxed);
+    }
+}
+
+fn transient() -> Error {
+    Error::timeout("Sensor read", 100)
+}
+
+/// Fail `failures` times with a transient error, then succeed
+fn flaky(failures: u32) -> impl FnMut() -> Result<u32, Error> {
+    let mut calls = 0;
+    move || {
+        calls += 1;
+        if calls <= failures {
+            Err(transient())
+        } else {
+            Ok(calls)
+        }
+    }
+}
+
+#[test]
+fn test_backoff_delays() {
+    let fixed = RetryPolicy::fixed(500);
+    assert_eq!((1..=3).map(|n| fixed.base_delay_for(n)).collect::<Vec<_>>(), vec![500, 500, 500]);
+
+    let linear = RetryPolicy::linear(100);
+    assert_eq!((1..=3).map(|n| linear.base_delay_for(n)).collect::<Vec<_>>(), vec![100, 200, 300]);
+
+    let exponential = RetryPolicy::exponential(100).max_delay_ms(1000);
+    assert_eq!(
+        (1..=6).map(|n| exponential.base_delay_for(n)).collect::<Vec<_>>(),
+        vec![100, 200, 400, 800, 1000, 1000]
+    );
+
+    // Large retry numbers saturate instead of overflowing
+    let unbounded = RetryPolicy::new(Backoff::Exponential, u32::MAX).max_delay_ms(u32::MAX);
+    assert_eq!(unbounded.base_delay_for(200), u32::MAX);
+    assert_eq!(RetryPolicy::exponential(1000).base_delay_for(40), DEFAULT_MAX_DELAY_MS);
+}
+
+#[test]
+fn test_retries_until_success() {
+    let sleeper = Arc::new(MockSleeper::default());
+    let policy = RetryPolicy::exponential(100).max_attempts(5).sleeper(sleeper.clone());
+
+    assert_eq!(policy.run(flaky(3)).unwrap(), 4);
+    assert_eq!(sleeper.sleeps(), vec![100, 200, 400]);
+}
+
+#[test]
+fn test_gives_up_after_max_attempts() {
+    let sleeper = Arc::new(MockSleeper::default());
+    let policy = RetryPolicy::fixed(50).max_attempts(3).sleeper(sleeper.clone());
+
+    let calls = Cell::new(0);
+    let result: Result<(), Error> = policy.run(|| {
+        calls.set(calls.get() + 1);
+        Err(transient())
+    });
+
+    assert!(matches!(result, Err(Error::Timeout { .. })));
+    assert_eq!(calls.get(), 3);
+    assert_eq!(sleeper.sleeps(), vec![50, 50]);
+}
+
+#[test]
+fn test_permanent_errors_are_not_retried() {
+    let sleeper = Arc::new(MockSleeper::default());
+    let policy = RetryPolicy::fixed(50).max_attempts(5).sleeper(sleeper.clone());
+
+    let calls = Cell::new(0);
+    let result: Result<(), Error> = policy.run(|| {
+        calls.set(calls.get() + 1);
+        Err(Error::gpio("LED1 set", EspCode::INVALID_ARG))
+    });
+
+    assert!(result.is_err());
+    assert_eq!(calls.get(), 1);
+    assert!(sleeper.sleeps().is_empty());
+}
+
+#[test]
+fn test_custom_predicate() {
+    let sleeper = Arc::new(MockSleeper::default());
+    let policy = RetryPolicy::fixed(10).max_attempts(5).sleeper(sleeper.clone());
+
+    let calls = Cell::new(0);
+    let result: Result<(), &str> = policy.run_if(
+        || {
+            calls.set(calls.get() + 1);
+            Err(if calls.get() < 3 { "busy" } else { "rejected" })
+        },
+        |e| *e == "busy",
+    );
+
+    assert_eq!(result, Err("rejected"));
+    assert_eq!(calls.get(), 3);
+}
+
+#[test]
+fn test_deadline_stops_retrying() {
+    let sleeper = Arc::new(MockSleeper::default());
+    let policy = RetryPolicy::exponential(100)
+        .max_attempts(10)
+        .deadline_ms(1000)
+        .sleeper(sleeper.clone());
+
+    let result = policy.run(flaky(u32::MAX));
+
+    assert!(result.is_err());
+    // 100 + 200 + 400 fit, the next 800 ms would end past the deadline
+    assert_eq!(sleeper.sleeps(), vec![100, 200, 400]);
+    assert!(sleeper.total_ms() <= 1000);
+}
+
+#[test]
+fn test_cancellation() {
+    let sleeper = Arc::new(MockSleeper::default());
+    let token = CancellationToken::new();
+    let policy = RetryPolicy::fixed(1000)
+        .max_attempts(10)
+        .cancel_on(&token)
+        .sleeper(sleeper.clone());
+
+    let calls = Cell::new(0);
+    let result: Result<(), Error> = policy.run(|| {
+        calls.set(calls.get() + 1);
+        if calls.get() == 2 {
+            token.cancel();
+        }
+        Err(transient())
+    });
+
+    assert!(result.is_err());
+    assert_eq!(calls.get(), 2);
+    assert!(token.is_cancelled());
+    // Sleeps are split up so cancellation is noticed promptly
+    assert_eq!(sleeper.total_ms(), 1000);
+    assert!(sleeper.sleeps().iter().all(|&ms| ms <= 100));
+}
+
+#[test]
+fn test_full_jitter_stays_within_bounds() {
+    let sleeper = Arc::new(MockSleeper::default());
+    let policy = RetryPolicy::exponential(100)
+        .max_attempts(8)
+        .max_delay_ms(2000)
+        .jitter(Jitter::Full)
+        .seed(42)
+        .sleeper(sleeper.clone());
+
+    assert!(policy.run(flaky(u32::MAX)).is_err());
+
+    let sleeps = sleeper.sleeps();
+    assert_eq!(sleeps.len(), 7);
+    for (retry, &ms) in sleeps.iter().enumerate() {
+        assert!(ms <= policy.base_delay_for(retry as u32 + 1));
+    }
+
+    // The same seed gives the same delays
+    let replay = Arc::new(MockSleeper::default());
+    assert!(policy.clone().sleeper(replay.clone()).run(flaky(u32::MAX)).is_err());
+    assert_eq!(replay.sleeps(), sleeps);
+}
+
+#[test]
+fn test_decorrelated_jitter_stays_within_bounds() {
+    let sleeper = Arc::new(MockSleeper::default());
+    let policy = RetryPolicy::exponential(100)
+        .max_attempts(20)
+        .max_delay_ms(3000)
+        .jitter(Jitter::Decorrelated)
+        .seed(7)
+        .sleeper(sleeper.clone());
+
+    assert!(policy.run(flaky(u32::MAX)).is_err());
+
+    let mut previous = 100;
+    for &ms in &sleeper.sleeps() {
+        assert!((100..=3000).contains(&ms));
+        assert!(ms <= previous * 3);
+        previous = ms;
+    }
+}
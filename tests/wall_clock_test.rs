@@ -0,0 +1,96 @@
+// Host tests for wall-clock bookkeeping, date conversion and POSIX time zones
+// These tests do not require hardware
+
+use esp32_template::utils::wall_clock::{DateTime, PosixTz, Timestamp, WallClock};
+
+fn utc(year: i32, month: u8, day: u8, hour: u8, minute: u8) -> i64 {
+    DateTime { year, month, day, hour, minute, second: 0, millisecond: 0, utc_offset_s: 0 }.to_unix_ms()
+}
+
+#[test]
+fn test_wall_clock_offset() {
+    let mut clock = WallClock::new();
+    assert!(!clock.is_synced());
+    assert_eq!(clock.timestamp(5_000), Timestamp::Uptime(5_000));
+    assert_eq!(clock.utc_ms(5_000), None);
+
+    let report = clock.record_sync(1_700_000_000_000, 10_000);
+    assert_eq!(report.correction_ms, None);
+    assert!(clock.is_synced());
+    assert_eq!(clock.timestamp(12_500), Timestamp::Utc(1_700_000_002_500));
+
+    // Measurements taken before the sync can be converted afterwards
+    assert_eq!(clock.to_utc_ms(Timestamp::Uptime(5_000)), Some(1_699_999_995_000));
+    assert_eq!(clock.to_utc_ms(Timestamp::Utc(42)), Some(42));
+
+    // The local clock ran 30 ms slow since the last sync
+    let report = clock.record_sync(1_700_000_060_030, 70_000);
+    assert_eq!(report.correction_ms, Some(30));
+    assert_eq!(clock.sync_count(), 2);
+    assert_eq!(clock.last_sync_age_ms(75_000), Some(5_000));
+    assert_eq!(clock.utc_ms(70_000), Some(1_700_000_060_030));
+}
+
+#[test]
+fn test_date_time_conversion() {
+    assert_eq!(DateTime::from_unix_ms(0, 0).to_string(), "1970-01-01T00:00:00.000Z");
+    assert_eq!(DateTime::from_unix_ms(1_700_000_000_123, 0).to_string(), "2023-11-14T22:13:20.123Z");
+    assert_eq!(DateTime::from_unix_ms(-1, 0).to_string(), "1969-12-31T23:59:59.999Z");
+    assert_eq!(DateTime::from_unix_ms(utc(2024, 2, 29, 12, 0), 0).to_string(), "2024-02-29T12:00:00.000Z");
+
+    let india = DateTime::from_unix_ms(utc(2024, 1, 1, 0, 0), 19_800);
+    assert_eq!(india.to_string(), "2024-01-01T05:30:00.000+05:30");
+    assert_eq!(india.to_unix_ms(), utc(2024, 1, 1, 0, 0));
+
+    let new_york = DateTime::from_unix_ms(utc(2024, 1, 1, 3, 0), -18_000);
+    assert_eq!(new_york.to_string(), "2023-12-31T22:00:00.000-05:00");
+    assert_eq!(new_york.weekday(), 0);
+}
+
+#[test]
+fn test_posix_tz_northern() {
+    let berlin = PosixTz::parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+
+    // Last Sunday of March, 02:00 CET
+    assert_eq!(berlin.utc_offset_s(utc(2024, 3, 31, 0, 59)), 3600);
+    assert_eq!(berlin.utc_offset_s(utc(2024, 3, 31, 1, 0)), 7200);
+    assert_eq!(berlin.name_at(utc(2024, 7, 1, 0, 0)), "CEST");
+
+    // Last Sunday of October, 03:00 CEST
+    assert_eq!(berlin.utc_offset_s(utc(2024, 10, 27, 0, 59)), 7200);
+    assert_eq!(berlin.utc_offset_s(utc(2024, 10, 27, 1, 0)), 3600);
+    assert_eq!(berlin.name_at(utc(2024, 12, 1, 0, 0)), "CET");
+
+    assert_eq!(berlin.to_local(utc(2024, 7, 1, 10, 0)).to_string(), "2024-07-01T12:00:00.000+02:00");
+
+    // Second Sunday of March, 02:00 EST
+    let new_york = PosixTz::parse("EST5EDT,M3.2.0,M11.1.0").unwrap();
+    assert_eq!(new_york.utc_offset_s(utc(2024, 3, 10, 6, 59)), -18_000);
+    assert_eq!(new_york.utc_offset_s(utc(2024, 3, 10, 7, 0)), -14_400);
+}
+
+#[test]
+fn test_posix_tz_southern_and_fixed() {
+    let sydney = PosixTz::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+    assert_eq!(sydney.utc_offset_s(utc(2024, 1, 15, 0, 0)), 39_600);
+    assert_eq!(sydney.utc_offset_s(utc(2024, 7, 1, 0, 0)), 36_000);
+    assert_eq!(sydney.utc_offset_s(utc(2024, 12, 31, 12, 0)), 39_600);
+
+    let india = PosixTz::parse("<+0530>-5:30").unwrap();
+    assert_eq!(india.utc_offset_s(utc(2024, 7, 1, 0, 0)), 19_800);
+    assert_eq!(india.name_at(0), "+0530");
+
+    assert_eq!(PosixTz::parse("UTC0").unwrap(), PosixTz::utc());
+
+    // Julian days never count February 29
+    let julian = PosixTz::parse("XST0XDT,J60/0,J61/0").unwrap();
+    assert_eq!(julian.utc_offset_s(utc(2024, 2, 29, 12, 0)), 0);
+    assert_eq!(julian.utc_offset_s(utc(2024, 3, 1, 12, 0)), 3600);
+}
+
+#[test]
+fn test_posix_tz_errors() {
+    for tz in ["", "X5", "CET", "CET-1CEST,M13.1.0,M10.5.0", "CET-1CEST,M3.5.0", "CET-1CEST,M3.5.0,M10.5.0/x", "<+03-3"] {
+        assert!(PosixTz::parse(tz).is_err(), "{} should be rejected", tz);
+    }
+}
@@ -0,0 +1,115 @@
+// Host tests for the software watchdog
+// These tests do not require hardware; time is driven by a mock clock
+
+mod common;
+
+use esp32_template::error::Error;
+use esp32_template::utils::watchdog::{StallReport, Watchdog};
+use std::sync::Arc;
+use common::MockClock;
+
+fn watchdog() -> (Arc<MockClock>, Watchdog) {
+    let clock = Arc::new(MockClock::default());
+    (clock.clone(), Watchdog::with_clock(clock))
+}
+
+#[test]
+fn test_registration_validation() {
+    let (_, watchdog) = watchdog();
+
+    assert!(matches!(watchdog.register("", 1000), Err(Error::InvalidArgument(_))));
+    assert!(matches!(watchdog.register("a-very-long-task-name", 1000), Err(Error::InvalidArgument(_))));
+    assert!(matches!(watchdog.register("sensors", 0), Err(Error::InvalidArgument(_))));
+
+    let _sensors = watchdog.register("sensors", 1000).unwrap();
+    assert!(matches!(watchdog.register("sensors", 2000), Err(Error::InvalidState(_))));
+    assert_eq!(watchdog.task_count(), 1);
+}
+
+#[test]
+fn test_fed_tasks_are_healthy() {
+    let (clock, watchdog) = watchdog();
+    let sensors = watchdog.register("sensors", 1000).unwrap();
+
+    for _ in 0..10 {
+        clock.advance(900);
+        sensors.feed();
+        assert!(watchdog.check().is_empty());
+    }
+
+    // Exactly at the timeout still counts as fed
+    clock.advance(1000);
+    assert!(watchdog.check().is_empty());
+    assert_eq!(watchdog.health()[0].feeds, 10);
+}
+
+#[test]
+fn test_reports_the_stalled_task() {
+    let (clock, watchdog) = watchdog();
+    let main = watchdog.register("main", 2000).unwrap();
+    let _telemetry = watchdog.register("telemetry", 30_000).unwrap();
+
+    // Only the main loop keeps feeding
+    for _ in 0..20 {
+        clock.advance(1000);
+        main.feed();
+    }
+    assert!(watchdog.check().is_empty());
+
+    clock.advance(10_001);
+    main.feed();
+    assert_eq!(
+        watchdog.check(),
+        vec![StallReport { name: "telemetry".to_string(), timeout_ms: 30_000, since_feed_ms: 30_001 }]
+    );
+
+    let health = watchdog.health();
+    assert_eq!(health.len(), 2);
+    assert!(!health[0].stalled);
+    assert!(health[1].stalled);
+}
+
+#[test]
+fn test_stalled_task_recovers_when_fed() {
+    let (clock, watchdog) = watchdog();
+    let sensors = watchdog.register("sensors", 1000).unwrap();
+
+    clock.advance(1500);
+    assert_eq!(watchdog.check().len(), 1);
+
+    sensors.feed();
+    assert!(watchdog.check().is_empty());
+}
+
+#[test]
+fn test_dropped_handles_are_unregistered() {
+    let (clock, watchdog) = watchdog();
+    let sensors = watchdog.register("sensors", 1000).unwrap();
+
+    // A clone shares the registry, as the supervisor's copy does
+    let supervisor = watchdog.clone();
+    assert_eq!(supervisor.task_count(), 1);
+
+    drop(sensors);
+    clock.advance(5000);
+    assert!(supervisor.check().is_empty());
+    assert_eq!(supervisor.task_count(), 0);
+
+    // The name can be registered again
+    let sensors = watchdog.register("sensors", 1000).unwrap();
+    assert_eq!(sensors.name(), "sensors");
+}
+
+#[test]
+fn test_handles_feed_from_other_threads() {
+    let (clock, watchdog) = watchdog();
+    let worker = watchdog.register("worker", 1000).unwrap();
+
+    clock.advance(5000);
+    std::thread::scope(|s| {
+        s.spawn(|| worker.feed());
+    });
+
+    assert!(watchdog.check().is_empty());
+    assert_eq!(watchdog.health()[0].feeds, 1);
+}
@@ -0,0 +1,103 @@
+// Host tests for the WiFi credential list and connection ordering
+// These tests do not require hardware; scans are hand-written results
+
+use esp32_template::error::Error;
+use esp32_template::tasks::wifi_credentials::{
+    connection_order, upsert_network, WifiNetwork, MAX_NETWORKS, MAX_SSID_LEN,
+};
+use esp32_template::tasks::wifi_scan::{AuthMode, ScanResult};
+
+fn network(ssid: &str, priority: u8) -> WifiNetwork {
+    WifiNetwork::new(ssid.to_string(), "password".to_string(), priority)
+}
+
+fn seen(ssid: &str, bssid: u8, rssi: i8) -> ScanResult {
+    ScanResult {
+        ssid: ssid.to_string(),
+        bssid: [0, 0, 0, 0, 0, bssid],
+        channel: 6,
+        rssi,
+        auth_mode: AuthMode::Wpa2Personal,
+        hidden: ssid.is_empty(),
+    }
+}
+
+fn ssids(networks: &[WifiNetwork]) -> Vec<&str> {
+    networks.iter().map(|n| n.ssid.as_str()).collect()
+}
+
+#[test]
+fn test_priority_before_rssi() {
+    let networks = [network("weak-preferred", 5), network("strong", 1)];
+    let visible = [seen("strong", 1, -40), seen("weak-preferred", 2, -85)];
+
+    let order = connection_order(&networks, &visible);
+    assert_eq!(ssids(&order), ["weak-preferred", "strong"]);
+}
+
+#[test]
+fn test_rssi_breaks_priority_ties() {
+    let networks = [network("far", 3), network("near", 3)];
+    let visible = [seen("far", 1, -80), seen("near", 2, -50)];
+
+    let order = connection_order(&networks, &visible);
+    assert_eq!(ssids(&order), ["near", "far"]);
+}
+
+#[test]
+fn test_unseen_and_hidden_networks_go_last() {
+    let networks = [network("hidden-office", 9), network("home", 1), network("away", 4)];
+    // A hidden AP broadcasts an empty SSID, so it doesn't match a stored network
+    let visible = [seen("", 1, -30), seen("home", 2, -70)];
+
+    let order = connection_order(&networks, &visible);
+    assert_eq!(ssids(&order), ["home", "hidden-office", "away"]);
+}
+
+#[test]
+fn test_duplicate_bssids_use_best_rssi() {
+    let networks = [network("mesh", 2), network("cafe", 2)];
+    // The same AP reported twice, plus a second AP of the mesh
+    let visible = [
+        seen("mesh", 1, -88),
+        seen("mesh", 1, -60),
+        seen("mesh", 2, -75),
+        seen("cafe", 3, -65),
+    ];
+
+    let order = connection_order(&networks, &visible);
+    assert_eq!(ssids(&order), ["mesh", "cafe"]);
+    assert_eq!(order.len(), 2);
+}
+
+#[test]
+fn test_upsert_replaces_existing_ssid() {
+    let mut networks = Vec::new();
+    upsert_network(&mut networks, network("home", 1)).unwrap();
+    upsert_network(&mut networks, WifiNetwork::new("home".to_string(), "new-pass".to_string(), 7)).unwrap();
+
+    assert_eq!(networks.len(), 1);
+    assert_eq!(networks[0].password, "new-pass");
+    assert_eq!(networks[0].priority, 7);
+}
+
+#[test]
+fn test_upsert_enforces_limits() {
+    let mut networks = Vec::new();
+    for i in 0..MAX_NETWORKS {
+        upsert_network(&mut networks, network(&format!("net{}", i), 0)).unwrap();
+    }
+
+    assert!(matches!(upsert_network(&mut networks, network("one-too-many", 0)), Err(Error::InvalidState(_))));
+    // Replacing an entry still works when full
+    upsert_network(&mut networks, network("net0", 9)).unwrap();
+    assert_eq!(networks.len(), MAX_NETWORKS);
+
+    let mut networks = Vec::new();
+    assert!(matches!(upsert_network(&mut networks, network("", 0)), Err(Error::OutOfRange { .. })));
+    let long_ssid = "x".repeat(MAX_SSID_LEN + 1);
+    assert!(matches!(upsert_network(&mut networks, network(&long_ssid, 0)), Err(Error::OutOfRange { .. })));
+    let long_password = WifiNetwork::new("home".to_string(), "p".repeat(65), 0);
+    assert!(matches!(upsert_network(&mut networks, long_password), Err(Error::OutOfRange { .. })));
+    assert!(networks.is_empty());
+}
@@ -0,0 +1,173 @@
+// Host tests for WebSocket stream fan-out
+// These tests use a recording sink instead of real WebSocket sessions
+
+use esp32_template::peripherals::button::ButtonEvent;
+use esp32_template::tasks::ws_stream::{FrameSink, StreamEvent, StreamHub};
+use esp32_template::tasks::SensorReadings;
+use esp32_template::utils::Timestamp;
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Sink recording frames, optionally failing every send
+#[derive(Default)]
+struct RecordingSink {
+    frames: Vec<String>,
+    fail: bool,
+    closed: Rc<Cell<bool>>,
+}
+
+impl FrameSink for RecordingSink {
+    fn send_text(&mut self, frame: &str) -> anyhow::Result<()> {
+        if self.fail {
+            return Err(anyhow::anyhow!("socket closed"));
+        }
+        self.frames.push(frame.to_string());
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.closed.set(true);
+    }
+}
+
+fn sensors(uptime_ms: u64) -> StreamEvent {
+    StreamEvent::Sensors(SensorReadings {
+        temperature: 22.0,
+        humidity: 45.0,
+        pressure: 1010.0,
+        uptime_ms,
+        timestamp: Timestamp::Uptime(uptime_ms),
+    })
+}
+
+fn button() -> StreamEvent {
+    StreamEvent::Button { id: 1, event: ButtonEvent::Pressed, uptime_ms: 10 }
+}
+
+#[test]
+fn test_frames_and_filters() {
+    let mut hub = StreamHub::new();
+    hub.add_client(1, RecordingSink::default());
+    hub.add_client(2, RecordingSink::default());
+    hub.handle_client_message(2, r#"{"subscribe": ["buttons"]}"#).unwrap();
+
+    hub.publish(&sensors(0), 0).unwrap();
+    hub.publish(&button(), 10).unwrap();
+    hub.flush();
+
+    let frames = &hub.sink(1).unwrap().frames;
+    assert_eq!(frames.len(), 2);
+    let json: Value = serde_json::from_str(&frames[0]).unwrap();
+    assert_eq!(json["type"], "sensors");
+    assert_eq!(json["temperature"], 22.0);
+    let json: Value = serde_json::from_str(&frames[1]).unwrap();
+    assert_eq!(json["type"], "button");
+    assert_eq!(json["event"], "pressed");
+
+    // Client 2 only receives button events
+    assert_eq!(hub.sink(2).unwrap().frames.len(), 1);
+
+    assert!(hub.handle_client_message(2, r#"{"subscribe": ["bogus"]}"#).is_err());
+    assert!(hub.handle_client_message(9, "{}").is_err());
+}
+
+#[test]
+fn test_rate_limiting() {
+    let mut hub = StreamHub::new();
+    hub.add_client(1, RecordingSink::default());
+    hub.handle_client_message(1, r#"{"min_interval_ms": 1000}"#).unwrap();
+
+    for now_ms in [0, 300, 600, 1000, 1500, 2100] {
+        hub.publish(&sensors(now_ms), now_ms).unwrap();
+    }
+    // Button events bypass the rate limit
+    hub.publish(&button(), 2200).unwrap();
+    hub.flush();
+    hub.flush();
+
+    assert_eq!(hub.sink(1).unwrap().frames.len(), 4);
+    assert_eq!(hub.stats().frames_rate_limited, 3);
+}
+
+#[test]
+fn test_slow_and_failing_clients_are_dropped() {
+    let mut hub = StreamHub::with_limits(2, 1);
+    let failing_closed = Rc::new(Cell::new(false));
+    hub.add_client(1, RecordingSink { fail: true, closed: failing_closed.clone(), ..Default::default() });
+    hub.publish(&button(), 0).unwrap();
+    hub.flush();
+
+    assert!(hub.client_ids().is_empty());
+    assert!(failing_closed.get());
+
+    // Events arriving faster than flushes overflow the queue
+    let slow_closed = Rc::new(Cell::new(false));
+    hub.add_client(2, RecordingSink { closed: slow_closed.clone(), ..Default::default() });
+    hub.publish(&button(), 0).unwrap();
+    hub.publish(&button(), 0).unwrap();
+    assert_eq!(hub.queued(2), 2);
+    assert!(!slow_closed.get());
+
+    hub.publish(&button(), 0).unwrap();
+    assert!(hub.client_ids().is_empty());
+    // Closing sends on the socket, so it waits for the next flush
+    assert!(!slow_closed.get());
+    hub.flush();
+    assert!(slow_closed.get());
+    assert_eq!(hub.stats().clients_dropped, 2);
+}
+
+/// Sink that can be cloned out of the hub, recording into shared storage
+#[derive(Clone, Default)]
+struct SharedSink {
+    frames: Rc<RefCell<Vec<String>>>,
+    fail: bool,
+    closed: Rc<Cell<bool>>,
+}
+
+impl FrameSink for SharedSink {
+    fn send_text(&mut self, frame: &str) -> anyhow::Result<()> {
+        if self.fail {
+            return Err(anyhow::anyhow!("socket closed"));
+        }
+        self.frames.borrow_mut().push(frame.to_string());
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.closed.set(true);
+    }
+}
+
+#[test]
+fn test_outgoing_frames_are_sent_outside_the_hub() {
+    let mut hub = StreamHub::with_limits(8, 2);
+    let sink = SharedSink::default();
+    let failing = SharedSink { fail: true, ..Default::default() };
+    hub.add_client(1, sink.clone());
+    hub.add_client(2, failing.clone());
+    for _ in 0..3 {
+        hub.publish(&button(), 0).unwrap();
+    }
+
+    // One batch per client is taken; the rest stays queued
+    let mut outgoing = hub.take_outgoing();
+    assert_eq!(outgoing.len(), 4);
+    assert_eq!(hub.queued(1), 1);
+    assert!(sink.frames.borrow().is_empty());
+
+    outgoing.send();
+    hub.finish(outgoing);
+    assert_eq!(sink.frames.borrow().len(), 2);
+    assert_eq!(hub.stats().frames_sent, 2);
+    assert_eq!(hub.client_ids(), vec![1]);
+
+    // The failed client is closed with the next batch
+    assert!(!failing.closed.get());
+    let mut outgoing = hub.take_outgoing();
+    outgoing.send();
+    hub.finish(outgoing);
+    assert!(failing.closed.get());
+    assert_eq!(sink.frames.borrow().len(), 3);
+}
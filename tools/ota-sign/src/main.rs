@@ -0,0 +1,178 @@
+//! Host tool for signing OTA firmware images
+//!
+//! Shares the manifest and signature code with the firmware so both sides
+//! always agree on the format.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+#[allow(dead_code)]
+#[path = "../../../src/tasks/ota.rs"]
+mod ota;
+#[allow(dead_code)]
+#[path = "../../../src/tasks/ota_signature.rs"]
+mod ota_signature;
+
+use ota::{to_hex, OtaManifest};
+use ota_signature::{OtaPublicKey, OtaSigningKey};
+
+const USAGE: &str = "\
+Usage:
+  ota-sign keygen <secret-key-file>
+  ota-sign pubkey <secret-key-file>
+  ota-sign sign --key <secret-key-file> --version <version> --url <image-url> [--out <manifest>] <image>
+  ota-sign verify --public-key <hex> --manifest <manifest> <image>
+
+Build the firmware with OTA_PUBLIC_KEY=<hex> to trust the printed public key.";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<()> {
+    let (command, rest) = args.split_first().ok_or_else(|| anyhow::anyhow!("{}", USAGE))?;
+    let options = Options::parse(rest)?;
+
+    match command.as_str() {
+        "keygen" => keygen(&options.positional()?),
+        "pubkey" => {
+            let key = read_signing_key(&options.positional()?)?;
+            println!("{}", key.public_key().to_hex());
+            Ok(())
+        }
+        "sign" => sign(&options),
+        "verify" => verify(&options),
+        "-h" | "--help" | "help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(anyhow::anyhow!("Unknown command '{}'\n\n{}", command, USAGE)),
+    }
+}
+
+/// `--name value` pairs plus a single positional argument
+struct Options {
+    named: Vec<(String, String)>,
+    positional: Option<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut named = Vec::new();
+        let mut positional = None;
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                let value = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --{}", name))?;
+                named.push((name.to_string(), value.clone()));
+            } else if positional.replace(arg.clone()).is_some() {
+                return Err(anyhow::anyhow!("Unexpected argument '{}'", arg));
+            }
+        }
+
+        Ok(Self { named, positional })
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.named.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn required(&self, name: &str) -> Result<&str> {
+        self.get(name).ok_or_else(|| anyhow::anyhow!("Missing --{}\n\n{}", name, USAGE))
+    }
+
+    fn positional(&self) -> Result<String> {
+        self.positional.clone().ok_or_else(|| anyhow::anyhow!("Missing file argument\n\n{}", USAGE))
+    }
+}
+
+/// Generate a secret key file and print its public key
+fn keygen(path: &str) -> Result<()> {
+    if Path::new(path).exists() {
+        return Err(anyhow::anyhow!("{} already exists", path));
+    }
+
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed).map_err(|e| anyhow::anyhow!("No system randomness: {}", e))?;
+    let key = OtaSigningKey::from_bytes(&seed);
+
+    write_secret(path, &format!("{}\n", key.to_hex()))?;
+    eprintln!("Secret key written to {}", path);
+    println!("{}", key.public_key().to_hex());
+    Ok(())
+}
+
+/// Hash and sign an image, writing its manifest
+fn sign(options: &Options) -> Result<()> {
+    let key = read_signing_key(options.required("key")?)?;
+    let image_path = options.positional()?;
+    let image = std::fs::read(&image_path).with_context(|| format!("Reading {}", image_path))?;
+    let digest: [u8; 32] = Sha256::digest(&image).into();
+
+    let version = options.required("version")?;
+    let size = u32::try_from(image.len()).context("Image is too large")?;
+
+    let manifest = OtaManifest {
+        version: version.to_string(),
+        url: options.required("url")?.to_string(),
+        sha256: to_hex(&digest),
+        size,
+        signature: key.sign_image(version, size, &digest),
+    };
+    manifest.validate()?;
+
+    let json = serde_json::to_string_pretty(&manifest)?;
+    match options.get("out") {
+        Some(path) => {
+            std::fs::write(path, format!("{}\n", json)).with_context(|| format!("Writing {}", path))?;
+            eprintln!("Manifest for {} bytes written to {}", manifest.size, path);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// Check an image against its manifest and a public key
+fn verify(options: &Options) -> Result<()> {
+    let public_key = OtaPublicKey::from_hex(options.required("public-key")?)?;
+    let manifest_path = options.required("manifest")?;
+    let manifest = OtaManifest::parse(&std::fs::read(manifest_path).with_context(|| format!("Reading {}", manifest_path))?)?;
+    let image_path = options.positional()?;
+    let image = std::fs::read(&image_path).with_context(|| format!("Reading {}", image_path))?;
+
+    let mut verifier = ota::ImageVerifier::new(&manifest)?;
+    verifier.update(&image)?;
+    let digest = verifier.finish()?;
+    public_key.verify_image(&manifest, &digest)?;
+
+    println!("OK: {} {} bytes, signature valid", manifest.version, manifest.size);
+    Ok(())
+}
+
+fn read_signing_key(path: &str) -> Result<OtaSigningKey> {
+    let hex = std::fs::read_to_string(path).with_context(|| format!("Reading {}", path))?;
+    OtaSigningKey::from_hex(&hex)
+}
+
+/// Write a file readable only by the owner
+fn write_secret(path: &str, contents: &str) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path).with_context(|| format!("Creating {}", path))?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
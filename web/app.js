@@ -0,0 +1,229 @@
+'use strict';
+
+const HISTORY_LEN = 60;
+const STATUS_POLL_MS = 5000;
+const LOG_POLL_MS = 3000;
+const LOG_PAGE_LEN = 100;
+const DEVICE_LOG_LINES = 500;
+
+const history = { temperature: [], humidity: [], pressure: [] };
+const colors = { temperature: '#f04438', humidity: '#2e90fa', pressure: '#7a5af8' };
+
+// Sequence number of the newest device log record shown, null before the first page
+let lastLogSeq = null;
+let logPollBusy = false;
+
+function log(message) {
+  const el = document.getElementById('log');
+  const time = new Date().toLocaleTimeString();
+  el.textContent += `[${time}] ${message}\n`;
+  el.scrollTop = el.scrollHeight;
+}
+
+function formatUptime(ms) {
+  return (ms / 1000).toFixed(3).padStart(10);
+}
+
+function showDeviceLogs(entries) {
+  const el = document.getElementById('device-log');
+  const atBottom = el.scrollTop + el.clientHeight >= el.scrollHeight - 4;
+  const lines = entries.map((entry) =>
+    `[${formatUptime(entry.uptime_ms)}] ${entry.level.toUpperCase().padEnd(5)} ${entry.target}: ${entry.message}`);
+  const text = (el.textContent + lines.join('\n') + '\n').split('\n');
+  // Keep the last lines only; the final element is the empty string after the trailing newline
+  el.textContent = text.slice(-DEVICE_LOG_LINES - 1).join('\n');
+  if (atBottom) {
+    el.scrollTop = el.scrollHeight;
+  }
+}
+
+async function pollDeviceLogs() {
+  if (logPollBusy) {
+    return;
+  }
+  logPollBusy = true;
+  try {
+    for (;;) {
+      const query = lastLogSeq === null ? '' : `?since=${lastLogSeq}`;
+      const page = await api('GET', `/api/logs${query}`);
+      if (lastLogSeq !== null && page.last_seq < lastLogSeq) {
+        // The device restarted and its sequence numbers began again
+        log('Device restarted, reloading its log');
+        document.getElementById('device-log').textContent = '';
+        lastLogSeq = null;
+        continue;
+      }
+      if (page.entries.length > 0) {
+        showDeviceLogs(page.entries);
+        lastLogSeq = page.entries[page.entries.length - 1].seq;
+      } else if (lastLogSeq === null) {
+        lastLogSeq = page.last_seq;
+      }
+      // A full page means more records are waiting
+      if (page.entries.length < LOG_PAGE_LEN) {
+        break;
+      }
+    }
+  } catch (e) {
+    log(`Log request failed: ${e.message}`);
+  } finally {
+    logPollBusy = false;
+  }
+}
+
+async function api(method, path, body) {
+  const options = { method, headers: {} };
+  if (body !== undefined) {
+    options.headers['Content-Type'] = 'application/json';
+    options.body = JSON.stringify(body);
+  }
+  const response = await fetch(path, options);
+  const json = await response.json();
+  if (!response.ok) {
+    throw new Error(json.error || response.statusText);
+  }
+  return json;
+}
+
+function drawChart(key) {
+  const canvas = document.getElementById(`chart-${key}`);
+  const ctx = canvas.getContext('2d');
+  const width = canvas.width = canvas.clientWidth;
+  const height = canvas.height;
+  const values = history[key];
+
+  ctx.clearRect(0, 0, width, height);
+  if (values.length < 2) {
+    return;
+  }
+
+  const min = Math.min(...values);
+  const max = Math.max(...values);
+  const span = max - min || 1;
+
+  ctx.strokeStyle = colors[key];
+  ctx.lineWidth = 2;
+  ctx.beginPath();
+  values.forEach((value, i) => {
+    const x = (i / (HISTORY_LEN - 1)) * width;
+    const y = height - 8 - ((value - min) / span) * (height - 16);
+    if (i === 0) {
+      ctx.moveTo(x, y);
+    } else {
+      ctx.lineTo(x, y);
+    }
+  });
+  ctx.stroke();
+
+  ctx.fillStyle = '#667085';
+  ctx.font = '11px system-ui';
+  ctx.fillText(`${key} ${min.toFixed(1)} – ${max.toFixed(1)}`, 4, 12);
+}
+
+function showReadings(readings) {
+  for (const key of Object.keys(history)) {
+    document.getElementById(key).textContent = readings[key].toFixed(1);
+    history[key].push(readings[key]);
+    if (history[key].length > HISTORY_LEN) {
+      history[key].shift();
+    }
+    drawChart(key);
+  }
+}
+
+async function refreshStatus() {
+  try {
+    const status = await api('GET', '/api/status');
+    document.getElementById('uptime').textContent = status.uptime;
+    document.getElementById('heap').textContent = `${Math.round(status.free_heap / 1024)} KiB`;
+    document.getElementById('wifi').textContent = status.wifi.connected ? status.wifi.ssid : 'disconnected';
+    document.getElementById('ip').textContent = status.wifi.ip || '--';
+    document.getElementById('rssi').textContent = status.wifi.rssi !== null ? `${status.wifi.rssi} dBm` : '--';
+  } catch (e) {
+    log(`Status request failed: ${e.message}`);
+  }
+}
+
+async function refreshLeds() {
+  for (const input of document.querySelectorAll('[data-led]')) {
+    try {
+      const led = await api('GET', `/api/leds/${input.dataset.led}`);
+      input.checked = led.state;
+    } catch (e) {
+      log(`LED ${input.dataset.led} request failed: ${e.message}`);
+    }
+  }
+}
+
+function setupLeds() {
+  for (const input of document.querySelectorAll('[data-led]')) {
+    input.addEventListener('change', async () => {
+      try {
+        await api('PUT', `/api/leds/${input.dataset.led}`, { state: input.checked });
+        log(`LED ${input.dataset.led} ${input.checked ? 'on' : 'off'}`);
+      } catch (e) {
+        input.checked = !input.checked;
+        log(`Failed to set LED ${input.dataset.led}: ${e.message}`);
+      }
+    });
+  }
+}
+
+function setupReboot() {
+  document.getElementById('reboot').addEventListener('click', async () => {
+    if (!confirm('Reboot the device?')) {
+      return;
+    }
+    try {
+      await api('POST', '/api/reboot');
+      log('Rebooting...');
+    } catch (e) {
+      log(`Reboot failed: ${e.message}`);
+    }
+  });
+}
+
+function connectStream() {
+  const badge = document.getElementById('connection');
+  const ws = new WebSocket(`ws://${location.host}/ws`);
+
+  ws.onopen = () => {
+    badge.textContent = 'live';
+    badge.className = 'badge online';
+    log('Event stream connected');
+  };
+
+  ws.onmessage = (message) => {
+    const event = JSON.parse(message.data);
+    if (event.type === 'sensors') {
+      showReadings(event);
+    } else if (event.type === 'button') {
+      log(`Button ${event.id} ${event.event}`);
+    }
+  };
+
+  ws.onclose = () => {
+    badge.textContent = 'offline';
+    badge.className = 'badge offline';
+    log('Event stream disconnected, retrying in 3s');
+    setTimeout(connectStream, 3000);
+  };
+}
+
+async function loadInitialReadings() {
+  try {
+    showReadings(await api('GET', '/api/sensors'));
+  } catch (e) {
+    log(`No sensor readings yet: ${e.message}`);
+  }
+}
+
+setupLeds();
+setupReboot();
+refreshLeds();
+refreshStatus();
+loadInitialReadings();
+connectStream();
+pollDeviceLogs();
+setInterval(refreshStatus, STATUS_POLL_MS);
+setInterval(pollDeviceLogs, LOG_POLL_MS);